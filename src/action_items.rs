@@ -0,0 +1,71 @@
+// Action items extracted by `--mode action-items`.
+//
+// The combine reply is parsed strictly: only bullet lines count, owners come from an `Owner:`
+// prefix or a minutes-style `[Owner]` tag, and everything is rewritten in the one checklist
+// format `- [ ] owner: task (due date)`, so scripts and task trackers can rely on it.
+
+use regex::Regex;
+
+/// Owner written for items whose owner the transcript doesn't name
+pub const UNASSIGNED: &str = "Unassigned";
+
+/// One follow-up task
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ActionItem {
+    pub owner: Option<String>,
+    /// What to do, including a due date when one was mentioned
+    pub task: String,
+}
+
+/// Extract the action items of a model reply, dropping preambles, headings, placeholders like
+/// `- None` and duplicates
+pub fn parse(reply: &str) -> Vec<ActionItem> {
+    let marker = Regex::new(r"^(?:[-*•]|\d+[.)])\s+(?:\[[ xX]?\]\s*)?").unwrap();
+    let tagged = Regex::new(r"^\[([^\]]+)\]\s*(.+)$").unwrap();
+
+    let mut items: Vec<ActionItem> = Vec::new();
+    for line in reply.lines() {
+        let line = line.trim();
+        let Some(found) = marker.find(line) else {
+            continue;
+        };
+        let rest = line[found.end()..].trim();
+        let (owner, task) = match tagged.captures(rest) {
+            Some(captures) => (Some(captures[1].trim().to_string()), captures[2].to_string()),
+            None => match rest.split_once(':') {
+                Some((owner, task)) if is_owner(owner) => (Some(owner.trim().to_string()), task.to_string()),
+                _ => (None, rest.to_string()),
+            },
+        };
+        let task = task.trim().to_string();
+        if task.is_empty() || task.eq_ignore_ascii_case("none") {
+            continue;
+        }
+        let owner = owner.filter(|owner| !owner.eq_ignore_ascii_case(UNASSIGNED));
+        let item = ActionItem { owner, task };
+        let key = |item: &ActionItem| (item.owner.as_deref().map(str::to_lowercase), item.task.to_lowercase());
+        let duplicate = items.iter().any(|i| key(i) == key(&item));
+        if !duplicate {
+            items.push(item);
+        }
+    }
+    items
+}
+
+/// Whether the text before a `:` names someone rather than starting a sentence
+fn is_owner(text: &str) -> bool {
+    let text = text.trim();
+    !text.is_empty() && text.split_whitespace().count() <= 4 && !text.ends_with(['.', '!', '?'])
+}
+
+/// One `- [ ] owner: task` line per item, or `- None` without items
+pub fn render(items: &[ActionItem]) -> String {
+    if items.is_empty() {
+        return "- None".to_string();
+    }
+    items
+        .iter()
+        .map(|item| format!("- [ ] {}: {}", item.owner.as_deref().unwrap_or(UNASSIGNED), item.task))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
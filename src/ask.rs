@@ -0,0 +1,87 @@
+// Answers to questions about a transcript, for `ask`.
+//
+// The transcript is chunked as for a summary, and the chunks and the question are embedded with
+// the embedding model. The chunks closest to the question by cosine similarity go to the model
+// as excerpts in transcript order, each labelled with the time range it covers (or its number,
+// for plain text), and the answer cites those labels.
+
+use std::time::Duration;
+
+use crate::{chunker, parser};
+
+pub const ASK_TEMPLATE: &str = r#"Answer the question using only these excerpts of a transcript, each labelled with the part of the transcript it comes from.
+Cite the label of every excerpt your answer relies on in square brackets, e.g. [00:12:10–00:14:02], right after the statement it supports.
+If the excerpts don't answer the question, say so rather than guessing.
+Question: {question}
+
+Excerpts:
+{text}
+
+ANSWER:"#;
+
+/// Chunks retrieved for a question unless `--top-k` says otherwise
+pub const DEFAULT_TOP_K: usize = 4;
+
+/// A chunk retrieved for the question
+#[derive(Debug, Clone, PartialEq)]
+pub struct Passage {
+    /// Index of the chunk in the transcript
+    pub index: usize,
+    /// Time range of subtitle chunks
+    pub start: Option<Duration>,
+    pub end: Option<Duration>,
+    /// Cosine similarity to the question
+    pub score: f32,
+    pub text: String,
+}
+
+impl Passage {
+    /// `00:12:10–00:14:02`, or `Excerpt 3` without timings
+    pub fn label(&self) -> String {
+        match (self.start, self.end) {
+            (Some(start), Some(end)) => format!("{}–{}", parser::format_clock(start), parser::format_clock(end)),
+            _ => format!("Excerpt {}", self.index + 1),
+        }
+    }
+}
+
+/// The model's answer and the passages it was given, in transcript order
+#[derive(Debug, Clone, PartialEq)]
+pub struct Answer {
+    pub question: String,
+    pub answer: String,
+    pub passages: Vec<Passage>,
+}
+
+/// Indices and similarities of the `k` vectors closest to `query`, the closest first
+pub fn nearest(query: &[f32], vectors: &[Vec<f32>], k: usize) -> Vec<(usize, f32)> {
+    let mut scored: Vec<(usize, f32)> = vectors
+        .iter()
+        .enumerate()
+        .map(|(i, vector)| (i, chunker::similarity(query, vector)))
+        .collect();
+    scored.sort_by(|a, b| b.1.total_cmp(&a.1));
+    scored.truncate(k);
+    scored
+}
+
+/// The ask prompt for `question` over `passages`
+pub fn prompt(question: &str, passages: &[Passage]) -> String {
+    let excerpts: Vec<String> = passages
+        .iter()
+        .map(|passage| format!("[{}] {}", passage.label(), passage.text))
+        .collect();
+    ASK_TEMPLATE
+        .replace("{question}", question.trim())
+        .replace("{text}", &excerpts.join("\n\n"))
+}
+
+/// The answer, then the excerpts it was given as `Sources:`
+pub fn render(answer: &Answer) -> String {
+    let sources: Vec<String> = answer
+        .passages
+        .iter()
+        .map(|passage| format!("- [{}] similarity {:.2}", passage.label(), passage.score))
+        .collect();
+    format!("{}\n\nSources:\n{}\n", answer.answer.trim(), sources.join("\n"))
+}
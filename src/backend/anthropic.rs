@@ -0,0 +1,180 @@
+use async_trait::async_trait;
+use serde::Deserialize;
+use serde_json::json;
+
+use super::{read_lines, send_error, BackendError, Completion, LlmBackend, OnToken, Sampling};
+use crate::{config::BackendKind, error::SummarizerError, usage::TokenUsage};
+
+const API_VERSION: &str = "2023-06-01";
+/// The Messages API requires an explicit completion budget; this one is used without --max-tokens
+const DEFAULT_MAX_TOKENS: u32 = 4096;
+
+/// Anthropic Messages API (`/v1/messages`)
+#[derive(Clone)]
+pub struct AnthropicBackend {
+    client: reqwest::Client,
+    api_base: String,
+    api_key: String,
+    model: String,
+    sampling: Sampling,
+}
+
+#[derive(Deserialize)]
+struct MessagesResponse {
+    content: Vec<ContentBlock>,
+    #[serde(default)]
+    usage: Option<Usage>,
+}
+
+/// `GET /v1/models`
+#[derive(Deserialize)]
+struct ModelList {
+    data: Vec<ModelEntry>,
+}
+
+#[derive(Deserialize)]
+struct ModelEntry {
+    id: String,
+}
+
+#[derive(Deserialize)]
+struct Usage {
+    input_tokens: u64,
+    output_tokens: u64,
+}
+
+#[derive(Deserialize)]
+struct ContentBlock {
+    #[serde(rename = "type")]
+    kind: String,
+    #[serde(default)]
+    text: String,
+}
+
+impl AnthropicBackend {
+    pub fn new(api_base: &str, api_key: &str, model: &str) -> Self {
+        Self {
+            client: reqwest::Client::new(),
+            api_base: api_base.trim_end_matches('/').to_string(),
+            api_key: api_key.to_string(),
+            model: model.to_string(),
+            sampling: Sampling::default(),
+        }
+    }
+
+    /// Send `sampling`'s parameters with every request; the Messages API has no seed
+    pub fn with_sampling(mut self, sampling: Sampling) -> Self {
+        self.sampling = sampling;
+        self
+    }
+
+    async fn messages(&self, prompt: &str, stream: bool) -> Result<reqwest::Response, BackendError> {
+        let mut body = json!({
+            "model": self.model,
+            "max_tokens": self.sampling.max_tokens.unwrap_or(DEFAULT_MAX_TOKENS),
+            "messages": [{ "role": "user", "content": prompt }],
+            "stream": stream,
+        });
+        if let Some(temperature) = self.sampling.temperature {
+            body["temperature"] = json!(temperature);
+        }
+        if let Some(top_p) = self.sampling.top_p {
+            body["top_p"] = json!(top_p);
+        }
+
+        let response = self
+            .client
+            .post(format!("{}/v1/messages", self.api_base))
+            .header("x-api-key", &self.api_key)
+            .header("anthropic-version", API_VERSION)
+            .json(&body)
+            .send()
+            .await
+            .map_err(|e| send_error(BackendKind::Anthropic, &self.api_base, e))?;
+        self.check(response).await
+    }
+
+    /// Turn non-2xx responses into errors carrying the API's error message
+    async fn check(&self, response: reqwest::Response) -> Result<reqwest::Response, BackendError> {
+        let status = response.status();
+        if status.is_success() {
+            return Ok(response);
+        }
+        let body = response.text().await.unwrap_or_default();
+        let message = serde_json::from_str::<serde_json::Value>(&body)
+            .ok()
+            .and_then(|v| v["error"]["message"].as_str().map(str::to_string))
+            .unwrap_or(body);
+        Err(SummarizerError::from_status(BackendKind::Anthropic, &self.model, status.as_u16(), message).into())
+    }
+}
+
+#[async_trait]
+impl LlmBackend for AnthropicBackend {
+    async fn models(&self) -> Result<Option<Vec<String>>, BackendError> {
+        let response = self
+            .client
+            .get(format!("{}/v1/models?limit=1000", self.api_base))
+            .header("x-api-key", &self.api_key)
+            .header("anthropic-version", API_VERSION)
+            .send()
+            .await
+            .map_err(|e| send_error(BackendKind::Anthropic, &self.api_base, e))?;
+        let list: ModelList = self.check(response).await?.json().await?;
+        Ok(Some(list.data.into_iter().map(|m| m.id).collect()))
+    }
+
+    async fn complete(&self, prompt: &str) -> Result<String, BackendError> {
+        Ok(self.complete_with_usage(prompt).await?.text)
+    }
+
+    async fn complete_with_usage(&self, prompt: &str) -> Result<Completion, BackendError> {
+        let messages: MessagesResponse = self.messages(prompt, false).await?.json().await?;
+        Ok(Completion {
+            text: messages
+                .content
+                .into_iter()
+                .filter(|block| block.kind == "text")
+                .map(|block| block.text)
+                .collect::<Vec<_>>()
+                .join(""),
+            usage: messages.usage.map(|usage| TokenUsage {
+                prompt_tokens: usage.input_tokens,
+                completion_tokens: usage.output_tokens,
+                estimated: false,
+            }),
+        })
+    }
+
+    async fn complete_streaming(
+        &self,
+        prompt: &str,
+        on_token: &OnToken,
+    ) -> Result<String, BackendError> {
+        let response = self.messages(prompt, true).await?;
+        // Server-sent events; only `data:` lines carry a payload
+        let mut reply = String::new();
+        read_lines(response, |line| {
+            let Some(data) = line.strip_prefix("data:") else {
+                return Ok(());
+            };
+            let event: serde_json::Value = serde_json::from_str(data.trim())?;
+            match event["type"].as_str() {
+                Some("content_block_delta") => {
+                    if let Some(text) = event["delta"]["text"].as_str() {
+                        on_token(text);
+                        reply.push_str(text);
+                    }
+                }
+                Some("error") => {
+                    let message = event["error"]["message"].as_str().unwrap_or("unknown error");
+                    return Err(format!("Anthropic stream failed: {}", message).into());
+                }
+                _ => {}
+            }
+            Ok(())
+        })
+        .await?;
+        Ok(reply)
+    }
+}
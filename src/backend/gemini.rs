@@ -0,0 +1,339 @@
+use async_trait::async_trait;
+use serde::Deserialize;
+use serde_json::json;
+use tracing::warn;
+
+use super::{read_lines, send_error, BackendError, Completion, Embeddings, LlmBackend, OnToken, Sampling};
+use crate::{config::BackendKind, error::SummarizerError, usage::TokenUsage};
+
+/// Categories whose thresholds are lowered when a prompt was blocked
+const HARM_CATEGORIES: &[&str] = &[
+    "HARM_CATEGORY_HARASSMENT",
+    "HARM_CATEGORY_HATE_SPEECH",
+    "HARM_CATEGORY_SEXUALLY_EXPLICIT",
+    "HARM_CATEGORY_DANGEROUS_CONTENT",
+];
+
+/// Finish reasons of a reply stopped by Gemini's filters
+const BLOCKED_REASONS: &[&str] = &["SAFETY", "BLOCKLIST", "PROHIBITED_CONTENT", "SPII"];
+
+/// Google Gemini API (`/v1beta/models/{model}:generateContent`)
+#[derive(Clone)]
+pub struct GeminiBackend {
+    client: reqwest::Client,
+    api_base: String,
+    api_key: String,
+    model: String,
+    sampling: Sampling,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct GenerateResponse {
+    #[serde(default)]
+    candidates: Vec<Candidate>,
+    #[serde(default)]
+    prompt_feedback: Option<PromptFeedback>,
+    #[serde(default)]
+    usage_metadata: Option<UsageMetadata>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Candidate {
+    #[serde(default)]
+    content: Option<Content>,
+    #[serde(default)]
+    finish_reason: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct Content {
+    #[serde(default)]
+    parts: Vec<Part>,
+}
+
+#[derive(Deserialize)]
+struct Part {
+    #[serde(default)]
+    text: String,
+    /// Thinking models' reasoning, which isn't part of the answer
+    #[serde(default)]
+    thought: bool,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct PromptFeedback {
+    #[serde(default)]
+    block_reason: Option<String>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct UsageMetadata {
+    #[serde(default)]
+    prompt_token_count: u64,
+    #[serde(default)]
+    candidates_token_count: u64,
+}
+
+/// `GET /v1beta/models`
+#[derive(Deserialize)]
+struct ModelList {
+    #[serde(default)]
+    models: Vec<ModelEntry>,
+}
+
+#[derive(Deserialize)]
+struct ModelEntry {
+    name: String,
+}
+
+/// `GET /v1beta/models/{model}`
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ModelInfo {
+    #[serde(default)]
+    input_token_limit: Option<usize>,
+}
+
+/// `POST /v1beta/models/{model}:batchEmbedContents`
+#[derive(Deserialize)]
+struct EmbedResponse {
+    #[serde(default)]
+    embeddings: Vec<EmbeddingValues>,
+}
+
+#[derive(Deserialize)]
+struct EmbeddingValues {
+    values: Vec<f32>,
+}
+
+impl GenerateResponse {
+    /// Why the prompt or its reply was blocked, if it was
+    fn blocked(&self) -> Option<String> {
+        let prompt = self.prompt_feedback.as_ref().and_then(|feedback| feedback.block_reason.clone());
+        prompt.or_else(|| {
+            self.candidates
+                .iter()
+                .filter_map(|candidate| candidate.finish_reason.as_deref())
+                .find(|reason| BLOCKED_REASONS.contains(reason))
+                .map(str::to_string)
+        })
+    }
+
+    fn text(&self) -> String {
+        self.candidates
+            .iter()
+            .take(1)
+            .filter_map(|candidate| candidate.content.as_ref())
+            .flat_map(|content| &content.parts)
+            .filter(|part| !part.thought)
+            .map(|part| part.text.as_str())
+            .collect()
+    }
+}
+
+impl GeminiBackend {
+    pub fn new(api_base: &str, api_key: &str, model: &str) -> Self {
+        Self {
+            client: reqwest::Client::new(),
+            api_base: api_base.trim_end_matches('/').to_string(),
+            api_key: api_key.to_string(),
+            // `models/gemini-2.0-flash`, as the model list names them, works too
+            model: model.trim_start_matches("models/").to_string(),
+            sampling: Sampling::default(),
+        }
+    }
+
+    /// Send `sampling`'s parameters as the generation config of every request
+    pub fn with_sampling(mut self, sampling: Sampling) -> Self {
+        self.sampling = sampling;
+        self
+    }
+
+    /// Send `prompt`; `relaxed` lowers the safety thresholds to block nothing, for prompts the
+    /// default thresholds blocked
+    async fn generate(&self, prompt: &str, stream: bool, relaxed: bool) -> Result<reqwest::Response, BackendError> {
+        let mut body = json!({
+            "contents": [{ "role": "user", "parts": [{ "text": prompt }] }],
+        });
+        let sampling = &self.sampling;
+        let mut config = serde_json::Map::new();
+        if let Some(temperature) = sampling.temperature {
+            config.insert("temperature".into(), json!(temperature));
+        }
+        if let Some(top_p) = sampling.top_p {
+            config.insert("topP".into(), json!(top_p));
+        }
+        if let Some(max_tokens) = sampling.max_tokens {
+            config.insert("maxOutputTokens".into(), json!(max_tokens));
+        }
+        if let Some(seed) = sampling.seed {
+            config.insert("seed".into(), json!(seed));
+        }
+        if !config.is_empty() {
+            body["generationConfig"] = config.into();
+        }
+        if relaxed {
+            body["safetySettings"] = HARM_CATEGORIES
+                .iter()
+                .map(|category| json!({ "category": category, "threshold": "BLOCK_NONE" }))
+                .collect();
+        }
+
+        let url = if stream {
+            format!("{}/v1beta/models/{}:streamGenerateContent?alt=sse", self.api_base, self.model)
+        } else {
+            format!("{}/v1beta/models/{}:generateContent", self.api_base, self.model)
+        };
+        let response = self
+            .client
+            .post(url)
+            .header("x-goog-api-key", &self.api_key)
+            .json(&body)
+            .send()
+            .await
+            .map_err(|e| send_error(BackendKind::Gemini, &self.api_base, e))?;
+        self.check(response).await
+    }
+
+    /// Stream the reply to `prompt`, stopping with the block reason if there is one
+    async fn stream(&self, prompt: &str, relaxed: bool, on_token: &OnToken) -> Result<(String, Option<String>), BackendError> {
+        let response = self.generate(prompt, true, relaxed).await?;
+        // Server-sent events, each carrying a partial response
+        let mut reply = String::new();
+        let mut blocked = None;
+        read_lines(response, |line| {
+            let Some(data) = line.strip_prefix("data:") else {
+                return Ok(());
+            };
+            let chunk: GenerateResponse = serde_json::from_str(data.trim())?;
+            let text = chunk.text();
+            on_token(&text);
+            reply.push_str(&text);
+            if blocked.is_none() {
+                blocked = chunk.blocked();
+            }
+            Ok(())
+        })
+        .await?;
+        Ok((reply, blocked))
+    }
+
+    /// Turn non-2xx responses into errors carrying Gemini's `error.message`
+    async fn check(&self, response: reqwest::Response) -> Result<reqwest::Response, BackendError> {
+        let status = response.status();
+        if status.is_success() {
+            return Ok(response);
+        }
+        let body = response.text().await.unwrap_or_default();
+        let message = serde_json::from_str::<serde_json::Value>(&body)
+            .ok()
+            .and_then(|v| v["error"]["message"].as_str().map(str::to_string))
+            .unwrap_or(body);
+        // A wrong key is a 400 rather than a 401
+        let status = if message.contains("API key not valid") { 401 } else { status.as_u16() };
+        Err(SummarizerError::from_status(BackendKind::Gemini, &self.model, status, message).into())
+    }
+}
+
+fn blocked_error(reason: String) -> BackendError {
+    Box::new(SummarizerError::Blocked {
+        backend: BackendKind::Gemini,
+        reason,
+    })
+}
+
+#[async_trait]
+impl LlmBackend for GeminiBackend {
+    async fn models(&self) -> Result<Option<Vec<String>>, BackendError> {
+        let response = self
+            .client
+            .get(format!("{}/v1beta/models?pageSize=1000", self.api_base))
+            .header("x-goog-api-key", &self.api_key)
+            .send()
+            .await
+            .map_err(|e| send_error(BackendKind::Gemini, &self.api_base, e))?;
+        let list: ModelList = self.check(response).await?.json().await?;
+        let names = list.models.into_iter().map(|m| m.name.trim_start_matches("models/").to_string());
+        Ok(Some(names.collect()))
+    }
+
+    async fn context_window(&self) -> Result<Option<usize>, BackendError> {
+        let response = self
+            .client
+            .get(format!("{}/v1beta/models/{}", self.api_base, self.model))
+            .header("x-goog-api-key", &self.api_key)
+            .send()
+            .await
+            .map_err(|e| send_error(BackendKind::Gemini, &self.api_base, e))?;
+        let info: ModelInfo = self.check(response).await?.json().await?;
+        Ok(info.input_token_limit)
+    }
+
+    async fn complete(&self, prompt: &str) -> Result<String, BackendError> {
+        Ok(self.complete_with_usage(prompt).await?.text)
+    }
+
+    async fn complete_with_usage(&self, prompt: &str) -> Result<Completion, BackendError> {
+        let mut response: GenerateResponse = self.generate(prompt, false, false).await?.json().await?;
+        if let Some(reason) = response.blocked() {
+            warn!("Gemini blocked a request ({}); asking again with relaxed safety settings", reason);
+            response = self.generate(prompt, false, true).await?.json().await?;
+            if let Some(reason) = response.blocked() {
+                return Err(blocked_error(reason));
+            }
+        }
+        Ok(Completion {
+            text: response.text(),
+            usage: response.usage_metadata.map(|usage| TokenUsage {
+                prompt_tokens: usage.prompt_token_count,
+                completion_tokens: usage.candidates_token_count,
+                estimated: false,
+            }),
+        })
+    }
+
+    async fn complete_streaming(
+        &self,
+        prompt: &str,
+        on_token: &OnToken,
+    ) -> Result<String, BackendError> {
+        match self.stream(prompt, false, on_token).await? {
+            (reply, None) => Ok(reply),
+            // Only ask again while nothing of the reply has been shown
+            (reply, Some(reason)) if reply.is_empty() => {
+                warn!("Gemini blocked a request ({}); asking again with relaxed safety settings", reason);
+                match self.stream(prompt, true, on_token).await? {
+                    (reply, None) => Ok(reply),
+                    (_, Some(reason)) => Err(blocked_error(reason)),
+                }
+            }
+            (_, Some(reason)) => Err(blocked_error(reason)),
+        }
+    }
+
+    async fn embed(&self, texts: &[String]) -> Result<Embeddings, BackendError> {
+        let model = format!("models/{}", self.model);
+        let requests: Vec<_> = texts
+            .iter()
+            .map(|text| json!({ "model": model, "content": { "parts": [{ "text": text }] } }))
+            .collect();
+        let response = self
+            .client
+            .post(format!("{}/v1beta/{}:batchEmbedContents", self.api_base, model))
+            .header("x-goog-api-key", &self.api_key)
+            .json(&json!({ "requests": requests }))
+            .send()
+            .await
+            .map_err(|e| send_error(BackendKind::Gemini, &self.api_base, e))?;
+        let embeddings: EmbedResponse = self.check(response).await?.json().await?;
+        // The API doesn't report the tokens of embeddings
+        Ok(Embeddings {
+            vectors: embeddings.embeddings.into_iter().map(|embedding| embedding.values).collect(),
+            usage: None,
+        })
+    }
+}
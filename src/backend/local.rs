@@ -0,0 +1,388 @@
+// In-process inference of GGUF models with candle (`--backend local`, behind the `local` feature).
+//
+// No server is involved: the quantized weights are loaded from the GGUF file once per process
+// and shared by every backend that names the same file, and replies are generated on the CPU on
+// a blocking thread. The tokenizer is rebuilt from the vocabulary stored in the file, and the
+// chat format is picked from its special tokens, so the GGUF file alone is enough for Llama 3 and
+// Qwen 2 models.
+
+use async_trait::async_trait;
+use candle_core::{quantized::gguf_file, Device, Tensor};
+use candle_transformers::{
+    generation::LogitsProcessor,
+    models::{quantized_llama, quantized_qwen2},
+};
+use std::{
+    collections::HashMap,
+    fs::File,
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex, OnceLock},
+    time::Instant,
+};
+use tokenizers::{
+    decoders::byte_level::ByteLevel,
+    models::bpe::{Vocab, BPE},
+    pre_tokenizers::{
+        sequence::Sequence,
+        split::{Split, SplitPattern},
+    },
+    AddedToken, SplitDelimiterBehavior, Tokenizer,
+};
+use tokio::sync::mpsc;
+use tracing::info;
+
+use super::{BackendError, Completion, LlmBackend, OnToken, Sampling};
+use crate::{error::SummarizerError, usage::TokenUsage};
+
+/// Most tokens of a reply when `--max-tokens` isn't set
+const DEFAULT_MAX_TOKENS: u32 = 1024;
+
+/// llama.cpp's default, as greedy decoding tends to repeat itself
+const DEFAULT_TEMPERATURE: f64 = 0.8;
+
+/// Pre-tokenizer of the Llama 3 vocabulary (`tokenizer.ggml.pre = "llama-bpe"`)
+const LLAMA3_PATTERN: &str = r"(?i:'s|'t|'re|'ve|'m|'ll|'d)|[^\r\n\p{L}\p{N}]?\p{L}+|\p{N}{1,3}| ?[^\s\p{L}\p{N}]+[\r\n]*|\s*[\r\n]+|\s+(?!\S)|\s+";
+
+/// Pre-tokenizer of the Qwen 2 vocabulary, which splits numbers into single digits
+const QWEN2_PATTERN: &str = r"(?i:'s|'t|'re|'ve|'m|'ll|'d)|[^\r\n\p{L}\p{N}]?\p{L}+|\p{N}| ?[^\s\p{L}\p{N}]+[\r\n]*|\s*[\r\n]+|\s+(?!\S)|\s+";
+
+/// Tokens that end a reply, besides the file's `eos_token_id`
+const STOP_TOKENS: &[&str] = &["<|eot_id|>", "<|end_of_text|>", "<|im_end|>", "<|endoftext|>", "<|end|>", "</s>"];
+
+/// A GGUF model run in-process
+#[derive(Clone)]
+pub struct LocalBackend {
+    path: PathBuf,
+    tokenizer: Option<PathBuf>,
+    model: String,
+    sampling: Sampling,
+}
+
+/// A model loaded from its GGUF file
+struct Loaded {
+    /// Locked for a whole reply, as the weights hold the key-value cache of the prompt
+    weights: Mutex<Weights>,
+    tokenizer: Tokenizer,
+    chat: ChatFormat,
+    stop: Vec<u32>,
+    /// Most tokens of prompt and reply together
+    context: usize,
+}
+
+enum Weights {
+    Llama(quantized_llama::ModelWeights),
+    Qwen2(quantized_qwen2::ModelWeights),
+}
+
+impl Weights {
+    fn forward(&mut self, input: &Tensor, position: usize) -> candle_core::Result<Tensor> {
+        match self {
+            Weights::Llama(weights) => weights.forward(input, position),
+            Weights::Qwen2(weights) => weights.forward(input, position),
+        }
+    }
+}
+
+/// How a prompt is wrapped as a user turn, told apart by the special tokens of the vocabulary
+#[derive(Debug, Clone, Copy)]
+enum ChatFormat {
+    Llama3,
+    ChatMl,
+    Mistral,
+    /// Base models without a chat format get the bare prompt
+    Plain,
+}
+
+impl ChatFormat {
+    fn detect(tokenizer: &Tokenizer) -> Self {
+        let has = |token: &str| tokenizer.token_to_id(token).is_some();
+        if has("<|start_header_id|>") {
+            ChatFormat::Llama3
+        } else if has("<|im_start|>") {
+            ChatFormat::ChatMl
+        } else if has("[INST]") {
+            ChatFormat::Mistral
+        } else {
+            ChatFormat::Plain
+        }
+    }
+
+    fn render(self, prompt: &str) -> String {
+        match self {
+            ChatFormat::Llama3 => format!(
+                "<|begin_of_text|><|start_header_id|>user<|end_header_id|>\n\n{}<|eot_id|><|start_header_id|>assistant<|end_header_id|>\n\n",
+                prompt
+            ),
+            ChatFormat::ChatMl => format!("<|im_start|>user\n{}<|im_end|>\n<|im_start|>assistant\n", prompt),
+            ChatFormat::Mistral => format!("<s>[INST] {} [/INST]", prompt),
+            ChatFormat::Plain => prompt.to_string(),
+        }
+    }
+}
+
+impl LocalBackend {
+    /// Run the GGUF model at `path`, tokenized by the `tokenizer.json` at `tokenizer` if given;
+    /// `model` names it in messages
+    pub fn new(path: PathBuf, tokenizer: Option<PathBuf>, model: &str) -> Self {
+        Self {
+            path,
+            tokenizer,
+            model: model.to_string(),
+            sampling: Sampling::default(),
+        }
+    }
+
+    /// Sample replies with `sampling`'s parameters
+    pub fn with_sampling(mut self, sampling: Sampling) -> Self {
+        self.sampling = sampling;
+        self
+    }
+
+    /// Generate the reply to `prompt` on a blocking thread, handing each piece to `on_token`
+    async fn generate(&self, prompt: &str, on_token: Option<&OnToken>) -> Result<Completion, BackendError> {
+        let (pieces, mut received) = mpsc::unbounded_channel();
+        let backend = self.clone();
+        let prompt = prompt.to_string();
+        let task = tokio::task::spawn_blocking(move || {
+            let loaded = load(&backend.path, backend.tokenizer.as_deref())?;
+            loaded.generate(&backend.model, &prompt, &backend.sampling, &pieces)
+        });
+        while let Some(piece) = received.recv().await {
+            if let Some(on_token) = on_token {
+                on_token(&piece);
+            }
+        }
+        task.await?
+    }
+}
+
+/// The model in the GGUF file at `path`, loaded on first use
+fn load(path: &Path, tokenizer: Option<&Path>) -> Result<Arc<Loaded>, BackendError> {
+    static LOADED: OnceLock<Mutex<HashMap<PathBuf, Arc<Loaded>>>> = OnceLock::new();
+    // Held while loading, so concurrent requests wait for one copy instead of loading their own
+    let mut loaded = LOADED.get_or_init(Default::default).lock().unwrap();
+    if let Some(model) = loaded.get(path) {
+        return Ok(model.clone());
+    }
+    let model = Arc::new(Loaded::read(path, tokenizer)?);
+    loaded.insert(path.to_path_buf(), model.clone());
+    Ok(model)
+}
+
+impl Loaded {
+    fn read(path: &Path, tokenizer: Option<&Path>) -> Result<Self, BackendError> {
+        info!("Loading {}", path.display());
+        let start = Instant::now();
+        let mut file = File::open(path)?;
+        let content = gguf_file::Content::read(&mut file).map_err(|e| e.with_path(path))?;
+        let metadata = &content.metadata;
+        let architecture = metadata
+            .get("general.architecture")
+            .and_then(|value| value.to_string().ok())
+            .cloned()
+            .unwrap_or_default();
+
+        // A tokenizer.json next to the model wins over the vocabulary in the file
+        let tokenizer_file = tokenizer
+            .map(Path::to_path_buf)
+            .or_else(|| Some(path.with_file_name("tokenizer.json")).filter(|file| file.is_file()));
+        let tokenizer = match tokenizer_file {
+            Some(file) => Tokenizer::from_file(&file).map_err(|e| format!("Cannot read {}: {}", file.display(), e))?,
+            None => gguf_tokenizer(metadata)?,
+        };
+        let mut stop: Vec<u32> = STOP_TOKENS.iter().filter_map(|token| tokenizer.token_to_id(token)).collect();
+        stop.extend(metadata.get("tokenizer.ggml.eos_token_id").and_then(|id| id.to_u32().ok()));
+        let context = context_window(path, metadata)?;
+
+        let device = Device::Cpu;
+        let weights = match architecture.as_str() {
+            "qwen2" => Weights::Qwen2(quantized_qwen2::ModelWeights::from_gguf(content, &mut file, &device)?),
+            _ => Weights::Llama(quantized_llama::ModelWeights::from_gguf(content, &mut file, &device)?),
+        };
+        let chat = ChatFormat::detect(&tokenizer);
+        info!(
+            "Loaded {} ({} model, {:?} chat format, {} tokens of context) in {:.1}s",
+            path.display(),
+            architecture,
+            chat,
+            context,
+            start.elapsed().as_secs_f64()
+        );
+        Ok(Self {
+            weights: Mutex::new(weights),
+            tokenizer,
+            chat,
+            stop,
+            context,
+        })
+    }
+
+    /// Generate the reply to `prompt`, sending each piece to `pieces` and stopping early once
+    /// nobody receives them
+    fn generate(
+        &self,
+        model: &str,
+        prompt: &str,
+        sampling: &Sampling,
+        pieces: &mpsc::UnboundedSender<String>,
+    ) -> Result<Completion, BackendError> {
+        let prompt = self.tokenizer.encode(self.chat.render(prompt), false)?.get_ids().to_vec();
+        if prompt.len() >= self.context {
+            return Err(Box::new(SummarizerError::ContextOverflow {
+                model: model.to_string(),
+                message: format!("the prompt has {} tokens, the context window {}", prompt.len(), self.context),
+            }));
+        }
+        let budget = (sampling.max_tokens.unwrap_or(DEFAULT_MAX_TOKENS) as usize).min(self.context - prompt.len());
+        let mut sampler = LogitsProcessor::new(
+            sampling.seed.unwrap_or_else(rand::random),
+            Some(sampling.temperature.unwrap_or(DEFAULT_TEMPERATURE)),
+            sampling.top_p,
+        );
+
+        let device = Device::Cpu;
+        let mut weights = self.weights.lock().unwrap();
+        let mut logits = weights.forward(&Tensor::new(prompt.as_slice(), &device)?.unsqueeze(0)?, 0)?;
+        let mut reply = Vec::new();
+        let mut shown = 0;
+        while reply.len() < budget && !pieces.is_closed() {
+            let token = sampler.sample(&logits.squeeze(0)?)?;
+            if self.stop.contains(&token) {
+                break;
+            }
+            reply.push(token);
+            let text = self.tokenizer.decode(&reply, true)?;
+            // A character split across tokens waits for its last byte
+            if !text.ends_with('\u{fffd}') && text.len() > shown {
+                let _ = pieces.send(text[shown..].to_string());
+                shown = text.len();
+            }
+            let input = Tensor::new(&[token], &device)?.unsqueeze(0)?;
+            logits = weights.forward(&input, prompt.len() + reply.len() - 1)?;
+        }
+
+        Ok(Completion {
+            text: self.tokenizer.decode(&reply, true)?,
+            usage: Some(TokenUsage {
+                prompt_tokens: prompt.len() as u64,
+                completion_tokens: reply.len() as u64,
+                estimated: false,
+            }),
+        })
+    }
+}
+
+/// Tokens of context the model in the GGUF file at `path` runs with, failing for architectures
+/// the backend can't run
+fn context_window(path: &Path, metadata: &HashMap<String, gguf_file::Value>) -> Result<usize, BackendError> {
+    let architecture = metadata
+        .get("general.architecture")
+        .and_then(|value| value.to_string().ok())
+        .map_or("", String::as_str);
+    let context_length = metadata
+        .get(&format!("{}.context_length", architecture))
+        .and_then(|length| length.to_u32().ok())
+        .map(|length| length as usize);
+    match architecture {
+        // Mistral models use the llama architecture too; candle's rotary embeddings stop at
+        // MAX_SEQ_LEN
+        "llama" => Ok(context_length
+            .unwrap_or(quantized_llama::MAX_SEQ_LEN)
+            .min(quantized_llama::MAX_SEQ_LEN)),
+        "qwen2" => Ok(context_length.unwrap_or(quantized_llama::MAX_SEQ_LEN)),
+        other => Err(format!(
+            "{} holds a {:?} model; the local backend runs llama (Llama, Mistral) and qwen2 models",
+            path.display(),
+            other
+        )
+        .into()),
+    }
+}
+
+/// Rebuild the byte-level BPE tokenizer (Llama 3, Qwen 2) from the vocabulary in the GGUF file
+fn gguf_tokenizer(metadata: &HashMap<String, gguf_file::Value>) -> Result<Tokenizer, BackendError> {
+    let get = |key: &str| metadata.get(key).ok_or_else(|| format!("the GGUF file has no {}", key));
+    let strings = |key: &str| -> Result<Vec<String>, BackendError> {
+        get(key)?
+            .to_vec()?
+            .iter()
+            .map(|value| Ok(value.to_string()?.clone()))
+            .collect()
+    };
+    let kind = get("tokenizer.ggml.model")?.to_string()?;
+    if kind != "gpt2" {
+        return Err(format!(
+            "the model has a {:?} vocabulary, which can't be read from the GGUF file; pass the tokenizer.json of the model with --tokenizer",
+            kind
+        )
+        .into());
+    }
+
+    let tokens = strings("tokenizer.ggml.tokens")?;
+    let vocab: Vocab = tokens.iter().cloned().zip(0..).collect();
+    let merges = strings("tokenizer.ggml.merges")?
+        .into_iter()
+        .filter_map(|merge| merge.split_once(' ').map(|(left, right)| (left.to_string(), right.to_string())))
+        .collect();
+    let mut tokenizer = Tokenizer::new(BPE::builder().vocab_and_merges(vocab, merges).build()?);
+
+    let pattern = match metadata.get("tokenizer.ggml.pre").and_then(|pre| pre.to_string().ok()) {
+        Some(pre) if pre == "llama-bpe" || pre == "llama3" => Some(LLAMA3_PATTERN),
+        Some(pre) if pre == "qwen2" => Some(QWEN2_PATTERN),
+        _ => None,
+    };
+    match pattern {
+        Some(pattern) => {
+            let split = Split::new(SplitPattern::Regex(pattern.to_string()), SplitDelimiterBehavior::Isolated, false)?;
+            tokenizer.with_pre_tokenizer(Some(Sequence::new(vec![split.into(), ByteLevel::new(false, true, false).into()])));
+        }
+        // GPT-2's own split
+        None => {
+            tokenizer.with_pre_tokenizer(Some(ByteLevel::new(false, true, true)));
+        }
+    }
+    tokenizer.with_decoder(Some(ByteLevel::default()));
+
+    // Control tokens such as <|eot_id|> must stay whole when the chat format writes them
+    if let Some(types) = metadata.get("tokenizer.ggml.token_type").and_then(|types| types.to_vec().ok()) {
+        let special: Vec<AddedToken> = tokens
+            .iter()
+            .zip(types)
+            .filter(|(_, kind)| matches!(kind.to_i32(), Ok(3) | Ok(4)))
+            .map(|(token, _)| AddedToken::from(token.clone(), true))
+            .collect();
+        tokenizer.add_special_tokens(special)?;
+    }
+    Ok(tokenizer)
+}
+
+#[async_trait]
+impl LlmBackend for LocalBackend {
+    /// Load the model before the first chunk, so a broken file fails the run at once
+    async fn prepare(&self) -> Result<(), BackendError> {
+        let (path, tokenizer) = (self.path.clone(), self.tokenizer.clone());
+        tokio::task::spawn_blocking(move || load(&path, tokenizer.as_deref()).map(|_| ())).await?
+    }
+
+    /// Read from the header of the file, without loading the weights
+    async fn context_window(&self) -> Result<Option<usize>, BackendError> {
+        let path = self.path.clone();
+        tokio::task::spawn_blocking(move || {
+            let content = gguf_file::Content::read(&mut File::open(&path)?).map_err(|e| e.with_path(&path))?;
+            context_window(&path, &content.metadata).map(Some)
+        })
+        .await?
+    }
+
+    async fn complete(&self, prompt: &str) -> Result<String, BackendError> {
+        Ok(self.generate(prompt, None).await?.text)
+    }
+
+    async fn complete_with_usage(&self, prompt: &str) -> Result<Completion, BackendError> {
+        self.generate(prompt, None).await
+    }
+
+    async fn complete_streaming(&self, prompt: &str, on_token: &OnToken) -> Result<String, BackendError> {
+        Ok(self.generate(prompt, Some(on_token)).await?.text)
+    }
+}
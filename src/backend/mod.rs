@@ -0,0 +1,284 @@
+// LLM backends.
+//
+// The summarizer only needs "send a prompt, get text back", so every provider is reached through
+// the `LlmBackend` trait. New providers get their own submodule and a branch in `from_settings`.
+
+mod anthropic;
+mod gemini;
+#[cfg(feature = "local")]
+mod local;
+mod ollama;
+mod openai;
+
+use async_trait::async_trait;
+use std::{error::Error, sync::Arc, time::Duration};
+use tracing::{debug, warn};
+
+use crate::{
+    config::{BackendKind, Settings},
+    error::SummarizerError,
+    usage::TokenUsage,
+};
+
+pub use anthropic::AnthropicBackend;
+pub use gemini::GeminiBackend;
+#[cfg(feature = "local")]
+pub use local::LocalBackend;
+pub use ollama::OllamaBackend;
+pub use openai::OpenAiBackend;
+
+/// Error type returned by backends; `Send + Sync` so calls can run concurrently
+pub type BackendError = Box<dyn Error + Send + Sync>;
+
+/// A model reply with the tokens it took, when the API reports them
+#[derive(Debug, Clone)]
+pub struct Completion {
+    pub text: String,
+    pub usage: Option<TokenUsage>,
+}
+
+/// Vectors of embedded texts, one per text in their order, with the tokens they took when the
+/// API reports them
+#[derive(Debug, Clone)]
+pub struct Embeddings {
+    pub vectors: Vec<Vec<f32>>,
+    pub usage: Option<TokenUsage>,
+}
+
+/// Sampling parameters sent with every request; `None` leaves the backend's default
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct Sampling {
+    pub temperature: Option<f64>,
+    pub top_p: Option<f64>,
+    /// Most tokens of one reply
+    pub max_tokens: Option<u32>,
+    /// Seed for reproducible replies, on backends that support one
+    pub seed: Option<u64>,
+}
+
+/// Callback receiving each piece of a streamed reply
+pub type OnToken = dyn for<'a> Fn(&'a str) + Send + Sync;
+
+#[async_trait]
+pub trait LlmBackend: Send + Sync {
+    /// Check the backend is usable (e.g. the model is installed) before a long run
+    async fn prepare(&self) -> Result<(), BackendError> {
+        Ok(())
+    }
+
+    /// Names of the models the API offers, or `None` when it has no way to list them
+    async fn models(&self) -> Result<Option<Vec<String>>, BackendError> {
+        Ok(None)
+    }
+
+    /// Tokens of context the model is served with, or `None` when the API doesn't say
+    async fn context_window(&self) -> Result<Option<usize>, BackendError> {
+        Ok(None)
+    }
+
+    /// Send one fully rendered prompt and return the model's reply
+    async fn complete(&self, prompt: &str) -> Result<String, BackendError>;
+
+    /// Like `complete`, but also return the token usage the API reported. Backends without
+    /// usage fields leave it out, and the summarizer estimates it.
+    async fn complete_with_usage(&self, prompt: &str) -> Result<Completion, BackendError> {
+        Ok(Completion {
+            text: self.complete(prompt).await?,
+            usage: None,
+        })
+    }
+
+    /// Like `complete`, but hand each piece of the reply to `on_token` as it arrives.
+    /// Backends without a streaming API deliver the whole reply as one piece.
+    async fn complete_streaming(
+        &self,
+        prompt: &str,
+        on_token: &OnToken,
+    ) -> Result<String, BackendError> {
+        let reply = self.complete(prompt).await?;
+        on_token(&reply);
+        Ok(reply)
+    }
+
+    /// Embed `texts` with the backend's model, for `ask` to retrieve the chunks a question is
+    /// about and for splitting where the topic changes. Backends without an embeddings API
+    /// refuse.
+    async fn embed(&self, _texts: &[String]) -> Result<Embeddings, BackendError> {
+        Err(SummarizerError::NoEmbeddings.into())
+    }
+}
+
+/// Turn a failed request into `SummarizerError::BackendUnreachable` when nothing answered at
+/// `api_base`, keeping other errors as they are
+fn send_error(backend: BackendKind, api_base: &str, e: reqwest::Error) -> BackendError {
+    if e.is_connect() || e.is_timeout() {
+        Box::new(SummarizerError::BackendUnreachable {
+            backend,
+            api_base: api_base.to_string(),
+            message: e.to_string(),
+        })
+    } else {
+        Box::new(e)
+    }
+}
+
+/// Context windows of hosted models whose APIs don't report one, by model name prefix; the
+/// first matching prefix wins
+const KNOWN_CONTEXT_WINDOWS: &[(&str, usize)] = &[
+    ("gpt-4.1", 1_047_576),
+    ("gpt-4o", 128_000),
+    ("gpt-4-turbo", 128_000),
+    ("gpt-4", 8_192),
+    ("gpt-3.5-turbo", 16_385),
+    ("o1-mini", 128_000),
+    ("o1", 200_000),
+    ("o3", 200_000),
+    ("o4-mini", 200_000),
+    ("claude", 200_000),
+    ("gemini-1.5-pro", 2_097_152),
+    ("gemini", 1_048_576),
+];
+
+/// How long asking for a context window may take before the fixed defaults are used instead
+const CONTEXT_WINDOW_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// The context window of the models `settings` requests, the smaller one when the steps use
+/// different models, from their API or else the table of known models; `None` when one of them
+/// can't be found out
+pub async fn context_window(settings: &Settings) -> Option<usize> {
+    let mut models = vec![settings.chunk_model()];
+    if settings.final_model() != settings.chunk_model() {
+        models.push(settings.final_model());
+    }
+    let mut smallest: Option<usize> = None;
+    for model in models {
+        let backend = for_model(settings, model).ok()?;
+        let reported = match tokio::time::timeout(CONTEXT_WINDOW_TIMEOUT, backend.context_window()).await {
+            Ok(Ok(window)) => window,
+            Ok(Err(e)) => {
+                debug!("Cannot ask the {} API for the context window of {}: {}", settings.backend, model, e);
+                None
+            }
+            Err(_) => None,
+        };
+        let window = reported.or_else(|| known_context_window(model))?;
+        smallest = Some(smallest.map_or(window, |smallest| smallest.min(window)));
+    }
+    smallest
+}
+
+/// The context window of `model` from the table of known models
+fn known_context_window(model: &str) -> Option<usize> {
+    // Hosted through a router, e.g. `openai/gpt-4o`
+    let name = model.rsplit('/').next().unwrap_or(model);
+    KNOWN_CONTEXT_WINDOWS
+        .iter()
+        .find(|(prefix, _)| name.starts_with(prefix))
+        .map(|&(_, window)| window)
+}
+
+/// Whether `model` is one of `models`; `llama3.2` matches Ollama's `llama3.2:latest` tag
+pub fn has_model(models: &[String], model: &str) -> bool {
+    let latest = format!("{}:latest", model);
+    models.iter().any(|m| *m == model || *m == latest)
+}
+
+/// Feed each line of a streamed response body to `on_line` as soon as it is complete
+async fn read_lines(
+    mut response: reqwest::Response,
+    mut on_line: impl FnMut(&str) -> Result<(), BackendError> + Send,
+) -> Result<(), BackendError> {
+    // Buffer raw bytes so a multi-byte character split across network chunks stays intact
+    let mut buffer = Vec::new();
+    while let Some(bytes) = response.chunk().await? {
+        buffer.extend_from_slice(&bytes);
+        while let Some(end) = buffer.iter().position(|&b| b == b'\n') {
+            let line: Vec<u8> = buffer.drain(..=end).collect();
+            on_line(String::from_utf8_lossy(&line).trim_end())?;
+        }
+    }
+    if !buffer.is_empty() {
+        on_line(String::from_utf8_lossy(&buffer).trim_end())?;
+    }
+    Ok(())
+}
+
+/// Build the backend described by `settings`
+pub fn from_settings(settings: &Settings) -> Result<Arc<dyn LlmBackend>, Box<dyn Error>> {
+    for_model(settings, &settings.model)
+}
+
+/// Like `from_settings`, but requesting `model` instead of `settings.model`
+pub fn for_model(settings: &Settings, model: &str) -> Result<Arc<dyn LlmBackend>, Box<dyn Error>> {
+    let sampling = settings.sampling();
+    Ok(match settings.backend {
+        BackendKind::Openai => Arc::new(
+            OpenAiBackend::new(&settings.api_base, settings.api_key.as_deref(), model)
+                .with_sampling(sampling),
+        ),
+        BackendKind::Ollama => Arc::new(
+            OllamaBackend::new(
+                &settings.api_base,
+                model,
+                settings.keep_alive.as_deref(),
+                settings.auto_pull,
+            )
+            .with_sampling(sampling),
+        ),
+        BackendKind::Anthropic => {
+            let api_key = settings
+                .api_key
+                .as_deref()
+                .ok_or("The anthropic backend needs an API key; set ANTHROPIC_API_KEY or pass --api-key")?;
+            if sampling.seed.is_some() {
+                warn!("The anthropic backend doesn't support --seed; it is ignored");
+            }
+            Arc::new(AnthropicBackend::new(&settings.api_base, api_key, model).with_sampling(sampling))
+        }
+        BackendKind::Gemini => {
+            let api_key = settings
+                .api_key
+                .as_deref()
+                .ok_or("The gemini backend needs an API key; set GEMINI_API_KEY or pass --api-key")?;
+            Arc::new(GeminiBackend::new(&settings.api_base, api_key, model).with_sampling(sampling))
+        }
+        BackendKind::Azure => {
+            if settings.api_base.is_empty() {
+                return Err("The azure backend needs the endpoint of your resource; pass --api-base https://<resource>.openai.azure.com".into());
+            }
+            let api_key = settings
+                .api_key
+                .as_deref()
+                .ok_or("The azure backend needs an API key; set AZURE_OPENAI_API_KEY or pass --api-key")?;
+            // --deployment names the deployment of --model; other models are deployment names
+            let deployment = settings.deployment.as_deref().filter(|_| model == settings.model).unwrap_or(model);
+            Arc::new(
+                OpenAiBackend::azure(&settings.api_base, api_key, deployment, &settings.api_version)
+                    .with_sampling(sampling),
+            )
+        }
+        BackendKind::Local => local_backend(settings, model)?,
+    })
+}
+
+#[cfg(feature = "local")]
+fn local_backend(settings: &Settings, model: &str) -> Result<Arc<dyn LlmBackend>, Box<dyn Error>> {
+    // A model ending in .gguf is a file of its own, e.g. a small one for --map-model
+    let path = match std::path::Path::new(model) {
+        path if path.extension().is_some_and(|extension| extension == "gguf") => path.to_path_buf(),
+        _ => settings
+            .model_path
+            .clone()
+            .ok_or("The local backend needs a GGUF model file; pass --model-path ./model.gguf")?,
+    };
+    // Different spellings of one path share the loaded model
+    let path = path.canonicalize().map_err(|_| SummarizerError::FileNotFound(path))?;
+    Ok(Arc::new(
+        LocalBackend::new(path, settings.tokenizer.clone(), model).with_sampling(settings.sampling()),
+    ))
+}
+
+#[cfg(not(feature = "local"))]
+fn local_backend(_settings: &Settings, _model: &str) -> Result<Arc<dyn LlmBackend>, Box<dyn Error>> {
+    Err("This build can't run models in-process; rebuild it with `cargo build --release --features local`".into())
+}
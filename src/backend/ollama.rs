@@ -0,0 +1,286 @@
+use async_trait::async_trait;
+use serde::Deserialize;
+use serde_json::json;
+use tracing::info;
+
+use super::{read_lines, send_error, BackendError, Completion, Embeddings, LlmBackend, OnToken, Sampling};
+use crate::{config::BackendKind, error::SummarizerError, usage::TokenUsage};
+
+/// Tokens of context Ollama serves a model with when its Modelfile sets no `num_ctx` (and the
+/// server no `OLLAMA_CONTEXT_LENGTH`)
+const DEFAULT_NUM_CTX: usize = 4096;
+
+/// Ollama's native API (`/api/chat`), which unlike its OpenAI-compatible layer supports
+/// `keep_alive` and model management
+#[derive(Clone)]
+pub struct OllamaBackend {
+    client: reqwest::Client,
+    api_base: String,
+    model: String,
+    keep_alive: Option<String>,
+    auto_pull: bool,
+    sampling: Sampling,
+}
+
+#[derive(Deserialize)]
+struct ChatResponse {
+    message: ChatMessage,
+    /// Tokens of the prompt, left out when Ollama reused its prompt cache
+    #[serde(default)]
+    prompt_eval_count: Option<u64>,
+    #[serde(default)]
+    eval_count: Option<u64>,
+}
+
+/// One line of a streamed `/api/chat` reply
+#[derive(Deserialize)]
+struct ChatChunk {
+    message: Option<ChatMessage>,
+    error: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct ChatMessage {
+    content: String,
+}
+
+/// `POST /api/embed`
+#[derive(Deserialize)]
+struct EmbedResponse {
+    embeddings: Vec<Vec<f32>>,
+    #[serde(default)]
+    prompt_eval_count: Option<u64>,
+}
+
+/// `POST /api/show`
+#[derive(Deserialize)]
+struct ShowResponse {
+    /// Modelfile parameters, one `name value` per line
+    #[serde(default)]
+    parameters: String,
+    /// GGUF metadata, e.g. `llama.context_length`
+    #[serde(default)]
+    model_info: serde_json::Map<String, serde_json::Value>,
+}
+
+#[derive(Deserialize)]
+struct TagsResponse {
+    models: Vec<ModelTag>,
+}
+
+#[derive(Deserialize)]
+struct ModelTag {
+    name: String,
+}
+
+impl OllamaBackend {
+    pub fn new(api_base: &str, model: &str, keep_alive: Option<&str>, auto_pull: bool) -> Self {
+        Self {
+            client: reqwest::Client::new(),
+            api_base: api_base.trim_end_matches('/').to_string(),
+            model: model.to_string(),
+            keep_alive: keep_alive.map(str::to_string),
+            auto_pull,
+            sampling: Sampling::default(),
+        }
+    }
+
+    /// Send `sampling`'s parameters as model options with every request
+    pub fn with_sampling(mut self, sampling: Sampling) -> Self {
+        self.sampling = sampling;
+        self
+    }
+
+    /// Tags of the installed models
+    async fn tags(&self) -> Result<Vec<String>, BackendError> {
+        let response = self
+            .client
+            .get(format!("{}/api/tags", self.api_base))
+            .send()
+            .await
+            .map_err(|e| send_error(BackendKind::Ollama, &self.api_base, e))?;
+        let tags: TagsResponse = self.check(response).await?.json().await?;
+        Ok(tags.models.into_iter().map(|m| m.name).collect())
+    }
+
+    /// Whether the model is installed
+    async fn has_model(&self) -> Result<bool, BackendError> {
+        Ok(super::has_model(&self.tags().await?, &self.model))
+    }
+
+    async fn chat(&self, prompt: &str, stream: bool) -> Result<reqwest::Response, BackendError> {
+        let mut body = json!({
+            "model": self.model,
+            "messages": [{ "role": "user", "content": prompt }],
+            "stream": stream,
+        });
+        if let Some(keep_alive) = &self.keep_alive {
+            body["keep_alive"] = json!(keep_alive);
+        }
+        let sampling = &self.sampling;
+        let mut options = serde_json::Map::new();
+        if let Some(temperature) = sampling.temperature {
+            options.insert("temperature".into(), json!(temperature));
+        }
+        if let Some(top_p) = sampling.top_p {
+            options.insert("top_p".into(), json!(top_p));
+        }
+        if let Some(max_tokens) = sampling.max_tokens {
+            options.insert("num_predict".into(), json!(max_tokens));
+        }
+        if let Some(seed) = sampling.seed {
+            options.insert("seed".into(), json!(seed));
+        }
+        if !options.is_empty() {
+            body["options"] = options.into();
+        }
+
+        let response = self
+            .client
+            .post(format!("{}/api/chat", self.api_base))
+            .json(&body)
+            .send()
+            .await
+            .map_err(|e| send_error(BackendKind::Ollama, &self.api_base, e))?;
+        self.check(response).await
+    }
+
+    async fn pull(&self) -> Result<(), BackendError> {
+        info!("Pulling model {} (this may take a while)...", self.model);
+        let response = self
+            .client
+            .post(format!("{}/api/pull", self.api_base))
+            .json(&json!({ "model": self.model, "stream": false }))
+            .send()
+            .await
+            .map_err(|e| send_error(BackendKind::Ollama, &self.api_base, e))?;
+        self.check(response).await?;
+        info!("Model {} is ready", self.model);
+        Ok(())
+    }
+
+    /// Turn non-2xx responses into errors carrying Ollama's `error` message
+    async fn check(&self, response: reqwest::Response) -> Result<reqwest::Response, BackendError> {
+        let status = response.status();
+        if status.is_success() {
+            return Ok(response);
+        }
+        let body = response.text().await.unwrap_or_default();
+        let message = serde_json::from_str::<serde_json::Value>(&body)
+            .ok()
+            .and_then(|v| v["error"].as_str().map(str::to_string))
+            .unwrap_or(body);
+        Err(SummarizerError::from_status(BackendKind::Ollama, &self.model, status.as_u16(), message).into())
+    }
+}
+
+#[async_trait]
+impl LlmBackend for OllamaBackend {
+    async fn prepare(&self) -> Result<(), BackendError> {
+        if self.has_model().await? {
+            return Ok(());
+        }
+        if self.auto_pull {
+            return self.pull().await;
+        }
+        Err(format!(
+            "Model {:?} is not installed in Ollama; run `ollama pull {}` or pass --auto-pull",
+            self.model, self.model
+        )
+        .into())
+    }
+
+    async fn models(&self) -> Result<Option<Vec<String>>, BackendError> {
+        Ok(Some(self.tags().await?))
+    }
+
+    async fn context_window(&self) -> Result<Option<usize>, BackendError> {
+        let response = self
+            .client
+            .post(format!("{}/api/show", self.api_base))
+            .json(&json!({ "model": self.model }))
+            .send()
+            .await
+            .map_err(|e| send_error(BackendKind::Ollama, &self.api_base, e))?;
+        let show: ShowResponse = self.check(response).await?.json().await?;
+        let trained = show
+            .model_info
+            .iter()
+            .find(|(key, _)| key.ends_with(".context_length"))
+            .and_then(|(_, length)| length.as_u64())
+            .map(|length| length as usize);
+        // Ollama cuts prompts to num_ctx, whatever the model was trained on
+        let num_ctx = show
+            .parameters
+            .lines()
+            .find_map(|line| line.strip_prefix("num_ctx"))
+            .and_then(|value| value.trim().parse().ok())
+            .unwrap_or(DEFAULT_NUM_CTX);
+        Ok(Some(trained.map_or(num_ctx, |trained| trained.min(num_ctx))))
+    }
+
+    async fn complete(&self, prompt: &str) -> Result<String, BackendError> {
+        Ok(self.complete_with_usage(prompt).await?.text)
+    }
+
+    async fn complete_with_usage(&self, prompt: &str) -> Result<Completion, BackendError> {
+        let chat: ChatResponse = self.chat(prompt, false).await?.json().await?;
+        Ok(Completion {
+            text: chat.message.content,
+            usage: chat.eval_count.map(|completion_tokens| TokenUsage {
+                prompt_tokens: chat.prompt_eval_count.unwrap_or(0),
+                completion_tokens,
+                estimated: false,
+            }),
+        })
+    }
+
+    async fn complete_streaming(
+        &self,
+        prompt: &str,
+        on_token: &OnToken,
+    ) -> Result<String, BackendError> {
+        let response = self.chat(prompt, true).await?;
+        // The reply arrives as newline-delimited JSON objects
+        let mut reply = String::new();
+        read_lines(response, |line| {
+            if line.is_empty() {
+                return Ok(());
+            }
+            let chunk: ChatChunk = serde_json::from_str(line)?;
+            if let Some(error) = chunk.error {
+                return Err(format!("Ollama stream failed: {}", error).into());
+            }
+            if let Some(message) = chunk.message {
+                on_token(&message.content);
+                reply.push_str(&message.content);
+            }
+            Ok(())
+        })
+        .await?;
+        Ok(reply)
+    }
+
+    async fn embed(&self, texts: &[String]) -> Result<Embeddings, BackendError> {
+        let mut body = json!({ "model": self.model, "input": texts });
+        if let Some(keep_alive) = &self.keep_alive {
+            body["keep_alive"] = json!(keep_alive);
+        }
+        let response = self
+            .client
+            .post(format!("{}/api/embed", self.api_base))
+            .json(&body)
+            .send()
+            .await
+            .map_err(|e| send_error(BackendKind::Ollama, &self.api_base, e))?;
+        let embeddings: EmbedResponse = self.check(response).await?.json().await?;
+        Ok(Embeddings {
+            vectors: embeddings.embeddings,
+            usage: embeddings.prompt_eval_count.map(|prompt_tokens| TokenUsage {
+                prompt_tokens,
+                completion_tokens: 0,
+                estimated: false,
+            }),
+        })
+    }
+}
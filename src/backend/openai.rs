@@ -0,0 +1,321 @@
+use async_trait::async_trait;
+use serde::Deserialize;
+use serde_json::json;
+
+use super::{read_lines, send_error, BackendError, Completion, Embeddings, LlmBackend, OllamaBackend, OnToken, Sampling};
+use crate::{config::BackendKind, error::SummarizerError, usage::TokenUsage};
+
+/// Any OpenAI-compatible chat completions endpoint (OpenAI, Ollama's `/v1`, vLLM, LM Studio, ...),
+/// or a deployment of Azure OpenAI
+#[derive(Clone)]
+pub struct OpenAiBackend {
+    client: reqwest::Client,
+    api_base: String,
+    api_key: Option<String>,
+    model: String,
+    sampling: Sampling,
+    azure: Option<AzureDeployment>,
+}
+
+/// Azure OpenAI serves each model as a named deployment, versioned by a query parameter
+#[derive(Clone)]
+struct AzureDeployment {
+    deployment: String,
+    api_version: String,
+}
+
+#[derive(Deserialize)]
+struct ChatResponse {
+    choices: Vec<Choice>,
+    #[serde(default)]
+    usage: Option<Usage>,
+}
+
+#[derive(Deserialize)]
+struct Choice {
+    message: ChoiceMessage,
+}
+
+#[derive(Deserialize)]
+struct ChoiceMessage {
+    #[serde(default)]
+    content: Option<String>,
+}
+
+/// `POST /embeddings`
+#[derive(Deserialize)]
+struct EmbeddingResponse {
+    data: Vec<EmbeddingEntry>,
+    #[serde(default)]
+    usage: Option<EmbeddingUsage>,
+}
+
+#[derive(Deserialize)]
+struct EmbeddingEntry {
+    #[serde(default)]
+    index: usize,
+    embedding: Vec<f32>,
+}
+
+#[derive(Deserialize)]
+struct EmbeddingUsage {
+    prompt_tokens: u64,
+}
+
+/// `GET /models`
+#[derive(Deserialize)]
+struct ModelList {
+    data: Vec<ModelEntry>,
+}
+
+#[derive(Deserialize)]
+struct ModelEntry {
+    id: String,
+}
+
+#[derive(Deserialize)]
+struct Usage {
+    prompt_tokens: u64,
+    completion_tokens: u64,
+}
+
+impl OpenAiBackend {
+    pub fn new(api_base: &str, api_key: Option<&str>, model: &str) -> Self {
+        Self {
+            client: reqwest::Client::new(),
+            api_base: api_base.trim_end_matches('/').to_string(),
+            api_key: api_key.map(str::to_string),
+            model: model.to_string(),
+            sampling: Sampling::default(),
+            azure: None,
+        }
+    }
+
+    /// Azure OpenAI's `deployment` of the resource at `api_base`
+    /// (`https://<resource>.openai.azure.com`)
+    pub fn azure(api_base: &str, api_key: &str, deployment: &str, api_version: &str) -> Self {
+        Self {
+            azure: Some(AzureDeployment {
+                deployment: deployment.to_string(),
+                api_version: api_version.to_string(),
+            }),
+            ..Self::new(api_base, Some(api_key), deployment)
+        }
+    }
+
+    /// Send `sampling`'s parameters with every request
+    pub fn with_sampling(mut self, sampling: Sampling) -> Self {
+        self.sampling = sampling;
+        self
+    }
+
+    async fn chat(&self, prompt: &str, stream: bool) -> Result<reqwest::Response, BackendError> {
+        let mut body = json!({
+            "model": self.model,
+            "messages": [{ "role": "user", "content": prompt }],
+            "stream": stream,
+        });
+        let sampling = &self.sampling;
+        if let Some(temperature) = sampling.temperature {
+            body["temperature"] = json!(temperature);
+        }
+        if let Some(top_p) = sampling.top_p {
+            body["top_p"] = json!(top_p);
+        }
+        if let Some(max_tokens) = sampling.max_tokens {
+            body["max_tokens"] = json!(max_tokens);
+        }
+        if let Some(seed) = sampling.seed {
+            body["seed"] = json!(seed);
+        }
+
+        let response = self
+            .authorize(self.client.post(self.url("chat/completions")).json(&body))
+            .send()
+            .await
+            .map_err(|e| send_error(self.kind(), &self.api_base, e))?;
+
+        self.check(response).await
+    }
+
+    /// Turn non-2xx responses into errors carrying the API's `error.message`
+    async fn check(&self, response: reqwest::Response) -> Result<reqwest::Response, BackendError> {
+        let status = response.status();
+        if status.is_success() {
+            return Ok(response);
+        }
+        let body = response.text().await.unwrap_or_default();
+        let message = serde_json::from_str::<serde_json::Value>(&body)
+            .ok()
+            .and_then(|v| v["error"]["message"].as_str().map(str::to_string))
+            .unwrap_or(body);
+        Err(SummarizerError::from_status(self.kind(), &self.model, status.as_u16(), message).into())
+    }
+
+    fn kind(&self) -> BackendKind {
+        match self.azure {
+            Some(_) => BackendKind::Azure,
+            None => BackendKind::Openai,
+        }
+    }
+
+    /// URL of the endpoint at `path`
+    fn url(&self, path: &str) -> String {
+        match &self.azure {
+            Some(azure) => format!(
+                "{}/openai/deployments/{}/{}?api-version={}",
+                self.api_base, azure.deployment, path, azure.api_version
+            ),
+            None => format!("{}/{}", self.api_base, path),
+        }
+    }
+
+    /// The JSON body of a successful GET of `url`, for optional lookups that may fail quietly
+    async fn get_json(&self, url: String) -> Option<serde_json::Value> {
+        let response = self.authorize(self.client.get(url)).send().await.ok()?;
+        if !response.status().is_success() {
+            return None;
+        }
+        response.json().await.ok()
+    }
+
+    /// Add the API key: a bearer token, or Azure's `api-key` header
+    fn authorize(&self, request: reqwest::RequestBuilder) -> reqwest::RequestBuilder {
+        match (&self.api_key, &self.azure) {
+            (Some(api_key), Some(_)) => request.header("api-key", api_key),
+            (Some(api_key), None) => request.bearer_auth(api_key),
+            (None, _) => request,
+        }
+    }
+}
+
+#[async_trait]
+impl LlmBackend for OpenAiBackend {
+    async fn models(&self) -> Result<Option<Vec<String>>, BackendError> {
+        // Azure lists the models of the resource, but only its management API lists deployments
+        let url = match &self.azure {
+            Some(azure) => format!("{}/openai/models?api-version={}", self.api_base, azure.api_version),
+            None => self.url("models"),
+        };
+        let response = self
+            .authorize(self.client.get(url))
+            .send()
+            .await
+            .map_err(|e| send_error(self.kind(), &self.api_base, e))?;
+        let status = response.status();
+        // Not every OpenAI-compatible server implements the endpoint
+        if status.as_u16() == 404 || status.as_u16() == 405 {
+            return Ok(None);
+        }
+        if !status.is_success() {
+            let message = response.text().await.unwrap_or_default();
+            return Err(SummarizerError::from_status(self.kind(), &self.model, status.as_u16(), message).into());
+        }
+        if self.azure.is_some() {
+            return Ok(None);
+        }
+        let list: ModelList = response.json().await?;
+        Ok(Some(list.data.into_iter().map(|m| m.id).collect()))
+    }
+
+    async fn context_window(&self) -> Result<Option<usize>, BackendError> {
+        // Azure names deployments, not models, so the table of known models has to do
+        if self.azure.is_some() {
+            return Ok(None);
+        }
+        // vLLM (max_model_len), LM Studio, OpenRouter and Groq list it with the model
+        if let Some(models) = self.get_json(format!("{}/models", self.api_base)).await {
+            let entry = models["data"]
+                .as_array()
+                .and_then(|models| models.iter().find(|entry| entry["id"] == self.model.as_str()));
+            let window = entry.and_then(|entry| {
+                ["max_model_len", "context_length", "max_context_length", "context_window"]
+                    .iter()
+                    .find_map(|field| entry[field].as_u64())
+            });
+            if let Some(window) = window {
+                return Ok(Some(window as usize));
+            }
+        }
+        // llama.cpp's server reports the context of its slots at /props, and Ollama's
+        // OpenAI-compatible layer has the native API next to it
+        let root = self.api_base.trim_end_matches("/v1");
+        if let Some(props) = self.get_json(format!("{}/props", root)).await {
+            let settings = &props["default_generation_settings"];
+            if let Some(n_ctx) = settings["n_ctx"].as_u64().or(props["n_ctx"].as_u64()) {
+                return Ok(Some(n_ctx as usize));
+            }
+        }
+        Ok(OllamaBackend::new(root, &self.model, None, false).context_window().await.ok().flatten())
+    }
+
+    async fn complete(&self, prompt: &str) -> Result<String, BackendError> {
+        Ok(self.complete_with_usage(prompt).await?.text)
+    }
+
+    async fn complete_with_usage(&self, prompt: &str) -> Result<Completion, BackendError> {
+        let chat: ChatResponse = self.chat(prompt, false).await?.json().await?;
+        let text = chat
+            .choices
+            .into_iter()
+            .next()
+            .and_then(|choice| choice.message.content)
+            .unwrap_or_default();
+        Ok(Completion {
+            text,
+            usage: chat.usage.map(|usage| TokenUsage {
+                prompt_tokens: usage.prompt_tokens,
+                completion_tokens: usage.completion_tokens,
+                estimated: false,
+            }),
+        })
+    }
+
+    async fn complete_streaming(
+        &self,
+        prompt: &str,
+        on_token: &OnToken,
+    ) -> Result<String, BackendError> {
+        let response = self.chat(prompt, true).await?;
+        // Server-sent events, ending with `data: [DONE]`
+        let mut reply = String::new();
+        read_lines(response, |line| {
+            let Some(data) = line.strip_prefix("data:").map(str::trim) else {
+                return Ok(());
+            };
+            if data == "[DONE]" {
+                return Ok(());
+            }
+            let event: serde_json::Value = serde_json::from_str(data)?;
+            if let Some(message) = event["error"]["message"].as_str() {
+                return Err(format!("OpenAI stream failed: {}", message).into());
+            }
+            if let Some(text) = event["choices"][0]["delta"]["content"].as_str() {
+                on_token(text);
+                reply.push_str(text);
+            }
+            Ok(())
+        })
+        .await?;
+        Ok(reply)
+    }
+
+    async fn embed(&self, texts: &[String]) -> Result<Embeddings, BackendError> {
+        let body = json!({ "model": self.model, "input": texts });
+        let response = self
+            .authorize(self.client.post(self.url("embeddings")).json(&body))
+            .send()
+            .await
+            .map_err(|e| send_error(self.kind(), &self.api_base, e))?;
+        let mut embeddings: EmbeddingResponse = self.check(response).await?.json().await?;
+        embeddings.data.sort_by_key(|entry| entry.index);
+        Ok(Embeddings {
+            vectors: embeddings.data.into_iter().map(|entry| entry.embedding).collect(),
+            usage: embeddings.usage.map(|usage| TokenUsage {
+                prompt_tokens: usage.prompt_tokens,
+                completion_tokens: 0,
+                estimated: false,
+            }),
+        })
+    }
+}
@@ -0,0 +1,122 @@
+// Batch support: expanding directories and glob patterns into input files, and reporting
+// per-file outcomes when more than one file is summarized.
+
+use std::{
+    collections::HashSet,
+    error::Error,
+    fs,
+    path::{Path, PathBuf},
+};
+use tracing::{info, warn};
+
+use crate::{error::SummarizerError, parser};
+
+/// Extensions picked up when scanning a directory, besides recordings. Plain `.txt` and Whisper
+/// `.json` are left out on purpose so previously written `_summary.txt`/`_summary.json` files are
+/// not summarized again.
+const SUBTITLE_EXTENSIONS: &[&str] = &["srt", "vtt", "ass", "ssa", "pdf", "epub"];
+
+/// Expand the command-line inputs into a sorted, de-duplicated list of files.
+///
+/// Files are taken as-is, directories are scanned for subtitle files (descending into
+/// subdirectories when `recursive` is set), and anything else containing `*`, `?` or `[`
+/// is treated as a glob pattern, for shells that don't expand them. With `in_order`, files keep
+/// the order of the inputs naming them instead, only the files of each directory or pattern
+/// being sorted, as `--merge` reads them one after the other.
+pub fn collect_inputs(inputs: &[PathBuf], recursive: bool, in_order: bool) -> Result<Vec<PathBuf>, Box<dyn Error>> {
+    let mut files = Vec::new();
+
+    for input in inputs {
+        if input.is_dir() {
+            let mut found = Vec::new();
+            scan_dir(input, recursive, &mut found)?;
+            if found.is_empty() {
+                warn!("No subtitle, PDF, EPUB or audio files found in {:?}", input);
+            }
+            found.sort();
+            files.extend(found);
+        } else if input.exists() {
+            files.push(input.clone());
+        } else if is_glob(input) {
+            let pattern = input.to_string_lossy();
+            let mut matches = glob::glob(&pattern)
+                .map_err(|e| format!("Invalid pattern {:?}: {}", pattern, e))?
+                .filter_map(Result::ok)
+                .filter(|p| p.is_file())
+                .collect::<Vec<_>>();
+            if matches.is_empty() {
+                return Err(format!("No files match {:?}", pattern).into());
+            }
+            matches.sort();
+            files.extend(matches);
+        } else {
+            return Err(SummarizerError::FileNotFound(input.clone()).into());
+        }
+    }
+
+    if in_order {
+        // Keep the first mention of a file named twice
+        let mut seen = HashSet::new();
+        files.retain(|file| seen.insert(file.clone()));
+    } else {
+        files.sort();
+        files.dedup();
+    }
+    if files.is_empty() {
+        return Err("No input files to summarize".into());
+    }
+    Ok(files)
+}
+
+fn scan_dir(dir: &Path, recursive: bool, files: &mut Vec<PathBuf>) -> Result<(), Box<dyn Error>> {
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+        if path.is_dir() {
+            if recursive {
+                scan_dir(&path, recursive, files)?;
+            }
+        } else if is_subtitle(&path) {
+            files.push(path);
+        }
+    }
+    Ok(())
+}
+
+fn is_subtitle(path: &Path) -> bool {
+    path.extension()
+        .and_then(|e| e.to_str())
+        .is_some_and(|e| SUBTITLE_EXTENSIONS.contains(&e.to_lowercase().as_str()) || parser::is_audio(e))
+}
+
+fn is_glob(path: &Path) -> bool {
+    path.to_string_lossy().contains(['*', '?', '['])
+}
+
+/// Outcome of a multi-file run
+#[derive(Debug, Default)]
+pub struct BatchReport {
+    pub succeeded: Vec<(PathBuf, PathBuf)>,
+    pub failed: Vec<(PathBuf, String)>,
+    /// Inputs left alone because their summary already exists
+    pub skipped: Vec<(PathBuf, PathBuf)>,
+}
+
+impl BatchReport {
+    pub fn print(&self) {
+        info!(
+            "Batch finished: {} succeeded, {} failed{}",
+            self.succeeded.len(),
+            self.failed.len(),
+            if self.skipped.is_empty() { String::new() } else { format!(", {} skipped", self.skipped.len()) }
+        );
+        for (input, output) in &self.succeeded {
+            info!("  ✅ {} -> {}", input.display(), output.display());
+        }
+        for (input, output) in &self.skipped {
+            info!("  ⏭️ {}: {} already exists", input.display(), output.display());
+        }
+        for (input, error) in &self.failed {
+            info!("  ❌ {}: {}", input.display(), error);
+        }
+    }
+}
@@ -0,0 +1,235 @@
+// Bullet-list helpers for checking and cleaning up the shape of model replies.
+//
+// Only top-level items count as bullets: lines starting (unindented) with `-`, `*`, `•` or a
+// number like `1.`. Indented sub-points and wrapped lines belong to the bullet above them.
+// `normalize` rewrites a reply into the one format the prompts ask for: `- ` bullets, no
+// "Here is the summary:" preamble or closing remarks, and no stray blank lines.
+// `dedup_neighbours` drops the bullets a chunk summary repeats from the one before it, which the
+// overlap between chunks makes common.
+
+use regex::Regex;
+
+/// Number of top-level bullets in `text`
+pub fn count(text: &str) -> usize {
+    let bullet = bullet_re();
+    text.lines().filter(|line| bullet.is_match(line)).count()
+}
+
+/// Keep the first `max` bullets of `text` with their sub-points, dropping everything after them
+pub fn truncate(text: &str, max: usize) -> String {
+    let bullet = bullet_re();
+    let mut seen = 0;
+    let mut kept = Vec::new();
+    for line in text.lines() {
+        if bullet.is_match(line) {
+            seen += 1;
+            if seen > max {
+                break;
+            }
+        }
+        kept.push(line);
+    }
+    kept.join("\n").trim_end().to_string()
+}
+
+/// Normalized Levenshtein similarity above which two bullets count as the same point
+pub const DUPLICATE_SIMILARITY: f64 = 0.85;
+
+/// Drop every bullet of a summary, with its sub-points, that is a near copy of a bullet of the
+/// summary before it, returning the summaries left with bullets and the number dropped
+pub fn dedup_neighbours(summaries: &[String]) -> (Vec<String>, usize) {
+    let bullet = bullet_re();
+    let points = |summary: &str| -> Vec<String> {
+        summary.lines().filter(|line| bullet.is_match(line)).map(comparable).collect()
+    };
+    let mut dropped = 0;
+    let mut kept = Vec::with_capacity(summaries.len());
+    for (i, summary) in summaries.iter().enumerate() {
+        let previous = match i {
+            0 => Vec::new(),
+            _ => points(&summaries[i - 1]),
+        };
+        let mut lines = Vec::new();
+        let mut skipping = false;
+        for line in summary.lines() {
+            if bullet.is_match(line) {
+                let point = comparable(line);
+                skipping = previous.iter().any(|known| similarity(known, &point) >= DUPLICATE_SIMILARITY);
+                dropped += usize::from(skipping);
+            } else if !line.starts_with(char::is_whitespace) {
+                // A heading or paragraph ends the sub-points of a dropped bullet
+                skipping = false;
+            }
+            if !skipping {
+                lines.push(line);
+            }
+        }
+        // A summary repeated in full has nothing left to combine
+        if count(summary) == 0 || lines.iter().any(|line| bullet.is_match(line)) {
+            kept.push(lines.join("\n").trim_end().to_string());
+        }
+    }
+    (kept, dropped)
+}
+
+/// 1 minus the Levenshtein distance of `a` and `b` over the length of the longer, by characters;
+/// 0 without computing it when their lengths alone rule out `DUPLICATE_SIMILARITY`
+fn similarity(a: &str, b: &str) -> f64 {
+    let a: Vec<char> = a.chars().collect();
+    let b: Vec<char> = b.chars().collect();
+    let longer = a.len().max(b.len());
+    if longer == 0 {
+        return 1.0;
+    }
+    // The distance is at least the difference in length
+    if (longer - a.len().min(b.len())) as f64 / longer as f64 > 1.0 - DUPLICATE_SIMILARITY {
+        return 0.0;
+    }
+    let mut row: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.iter().enumerate() {
+        let mut diagonal = row[0];
+        row[0] = i + 1;
+        for (j, cb) in b.iter().enumerate() {
+            let substitution = diagonal + usize::from(ca != cb);
+            diagonal = row[j + 1];
+            row[j + 1] = substitution.min(row[j] + 1).min(row[j + 1] + 1);
+        }
+    }
+    1.0 - row[b.len()] as f64 / longer as f64
+}
+
+/// A bullet without its marker, a leading `[00:14:10–00:18:42]` time range, case and
+/// punctuation, for comparing
+fn comparable(line: &str) -> String {
+    let text = bullet_re().replace(line, "");
+    let text = text.trim_start();
+    let text = match text.strip_prefix('[').and_then(|rest| rest.split_once(']')) {
+        Some((_, rest)) => rest,
+        None => text,
+    };
+    let words: Vec<String> = text
+        .split_whitespace()
+        .map(|word| word.chars().filter(|c| c.is_alphanumeric()).collect::<String>().to_lowercase())
+        .filter(|word| !word.is_empty())
+        .collect();
+    words.join(" ")
+}
+
+/// Phrases models open a reply with before the content
+const PREAMBLES: &[&str] = &[
+    "here is",
+    "here are",
+    "here's",
+    "sure",
+    "certainly",
+    "of course",
+    "below is",
+    "below are",
+    "the following",
+];
+
+/// Phrases models close a reply with after the content
+const CLOSINGS: &[&str] = &["let me know", "i hope this", "hope this helps", "feel free to"];
+
+/// Rewrite a reply with `- ` for every bullet marker (`*`, `•`, `+`, `–`, `1.`), keeping the
+/// indentation of sub-points, and without preamble, closing remarks, trailing spaces or blank
+/// lines other than one before each `#` heading
+pub fn normalize(text: &str) -> String {
+    let marker = Regex::new(r"^(\s*)(?:[-*•+–]|\d+[.)])\s+").unwrap();
+    let lines: Vec<String> = text
+        .lines()
+        .map(|line| marker.replace(line.trim_end(), "$1- ").into_owned())
+        .collect();
+
+    let start = lines
+        .iter()
+        .position(|line| !line.trim().is_empty() && !is_remark(line, PREAMBLES))
+        .unwrap_or(lines.len());
+    let mut end = lines.len();
+    while end > start && (lines[end - 1].trim().is_empty() || is_remark(&lines[end - 1], CLOSINGS)) {
+        end -= 1;
+    }
+
+    let mut kept: Vec<&str> = Vec::new();
+    for line in &lines[start..end] {
+        if line.trim().is_empty() {
+            continue;
+        }
+        if line.starts_with('#') && !kept.is_empty() {
+            kept.push("");
+        }
+        kept.push(line);
+    }
+    kept.join("\n")
+}
+
+/// Whether `line` is chatter starting with one of `phrases`, or a lone label like `**Summary:**`
+fn is_remark(line: &str, phrases: &[&str]) -> bool {
+    let line = line.trim().trim_matches('*').trim().to_lowercase();
+    let label = line.ends_with(':') && line.split_whitespace().count() <= 6 && !line.starts_with(['-', '#']);
+    label
+        || phrases.iter().any(|phrase| {
+            line.strip_prefix(phrase)
+                .is_some_and(|rest| !rest.starts_with(char::is_alphanumeric))
+        })
+}
+
+fn bullet_re() -> Regex {
+    Regex::new(r"^(?:[-*•]|\d+[.)])(?:\s|$)").unwrap()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn summaries(texts: &[&str]) -> Vec<String> {
+        texts.iter().map(|text| text.to_string()).collect()
+    }
+
+    #[test]
+    fn bullets_at_the_threshold_are_dropped_and_below_it_kept() {
+        let previous = "- Pricing moves to per seat billing in May";
+        // 6 of 40 characters differ: a similarity of exactly 0.85
+        assert_eq!(similarity(&comparable(previous), &comparable("- Pricing moves to per user billing in Jun")), 0.85);
+        // 8 of 40: 0.8
+        assert_eq!(similarity(&comparable(previous), &comparable("- Pricing moves to per user billing by Jun")), 0.8);
+
+        let (kept, dropped) = dedup_neighbours(&summaries(&[
+            previous,
+            "- Pricing moves to per user billing in Jun\n- Pricing moves to per user billing by Jun\n- Hiring starts in April",
+        ]));
+        assert_eq!(dropped, 1);
+        assert_eq!(kept[1], "- Pricing moves to per user billing by Jun\n- Hiring starts in April");
+    }
+
+    #[test]
+    fn sub_points_go_with_their_dropped_bullet() {
+        let (kept, dropped) = dedup_neighbours(&summaries(&[
+            "- The launch slips to June\n- QA found two blockers",
+            "- The launch slips to June.\n  - Marketing moves the campaign\n  - Sales is told on Friday\n- Budget review next week\n  - Finance joins",
+        ]));
+        assert_eq!(dropped, 1);
+        assert_eq!(kept[1], "- Budget review next week\n  - Finance joins");
+    }
+
+    #[test]
+    fn leading_time_ranges_are_ignored() {
+        let (kept, dropped) = dedup_neighbours(&summaries(&[
+            "- [00:14:10–00:18:42] The launch slips to June",
+            "- [00:17:55–00:22:30] The launch slips to June\n- [00:19:02–00:22:30] Budget review next week",
+        ]));
+        assert_eq!(dropped, 1);
+        assert_eq!(kept[1], "- [00:19:02–00:22:30] Budget review next week");
+    }
+
+    #[test]
+    fn a_summary_repeated_in_full_is_removed() {
+        let (kept, dropped) = dedup_neighbours(&summaries(&[
+            "- The launch slips to June\n- QA found two blockers",
+            "- QA found two blockers\n- The launch slips to June!",
+            "- Budget review next week",
+        ]));
+        assert_eq!(dropped, 2);
+        assert_eq!(kept, ["- The launch slips to June\n- QA found two blockers", "- Budget review next week"]);
+    }
+}
@@ -0,0 +1,197 @@
+// Disk cache of model replies.
+//
+// Every request is stored under a sha256 of the model (with its sampling parameters when they
+// differ from the backend's defaults) and the full prompt (template, text and language
+// instruction), so re-running a file with the same prompts is answered from disk: the chunk
+// summaries after a failed combine step or a change to only the combine prompt, and every step
+// of a run repeated as is. The replies sampled for `--samples` are left out, as they are meant
+// to differ. Unlike checkpoints, cached replies outlive a successful run and are shared between
+// inputs. `cache_ttl_days` and `cache_max_mb` bound the cache, which is pruned when a run starts.
+
+use sha2::{Digest, Sha256};
+use std::{
+    env, fs, io,
+    path::{Path, PathBuf},
+    time::{Duration, SystemTime},
+};
+use tracing::{info, warn};
+
+/// How long replies are kept and how large the cache may grow; unbounded by default
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct CacheLimits {
+    /// Replies older than this are ignored and pruned
+    pub ttl: Option<Duration>,
+    /// The oldest replies are pruned until the cache is at most this size
+    pub max_bytes: Option<u64>,
+}
+
+/// Cache under `dir`, or a disabled cache that misses every lookup
+#[derive(Debug, Clone)]
+pub struct ResponseCache {
+    dir: Option<PathBuf>,
+    ttl: Option<Duration>,
+}
+
+impl ResponseCache {
+    /// The cache in `responses_dir()` when `enabled`, pruned to `limits`, or a disabled one
+    pub fn new(enabled: bool, limits: CacheLimits) -> Self {
+        let dir = enabled.then(responses_dir).flatten();
+        if let Some(dir) = &dir {
+            match prune(dir, limits) {
+                Ok((0, _)) => {}
+                Ok((removed, bytes)) => info!(
+                    "Pruned {} cached replies ({}) past cache_ttl_days or cache_max_mb",
+                    removed,
+                    format_size(bytes)
+                ),
+                Err(e) => warn!("Cannot prune the cache {:?}: {}", dir, e),
+            }
+        }
+        Self { dir, ttl: limits.ttl }
+    }
+
+    /// The reply `model` gave to `prompt` in an earlier run, unless it has expired
+    pub fn get(&self, model: &str, prompt: &str) -> Option<String> {
+        let path = self.path(model, prompt)?;
+        if let Some(ttl) = self.ttl {
+            let modified = fs::metadata(&path).and_then(|metadata| metadata.modified()).ok()?;
+            if is_expired(modified, ttl) {
+                return None;
+            }
+        }
+        fs::read_to_string(path).ok()
+    }
+
+    /// Remember a reply; a cache that cannot be written only costs a warning
+    pub fn put(&self, model: &str, prompt: &str, reply: &str) {
+        let Some(path) = self.path(model, prompt) else {
+            return;
+        };
+        let tmp = path.with_extension("txt.tmp");
+        let written = path
+            .parent()
+            .map_or(Ok(()), fs::create_dir_all)
+            .and_then(|()| fs::write(&tmp, reply))
+            .and_then(|()| fs::rename(&tmp, &path));
+        if let Err(e) = written {
+            warn!("Cannot write cache {:?}: {}", path, e);
+        }
+    }
+
+    /// `<dir>/<sha256>.txt`
+    fn path(&self, model: &str, prompt: &str) -> Option<PathBuf> {
+        let mut hasher = Sha256::new();
+        hasher.update(model.as_bytes());
+        hasher.update([0]);
+        hasher.update(prompt.as_bytes());
+        let key = format!("{:x}", hasher.finalize());
+        Some(self.dir.as_ref()?.join(format!("{}.txt", key)))
+    }
+}
+
+/// What `cache stats` reports
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct CacheStats {
+    pub replies: usize,
+    pub bytes: u64,
+    /// Replies past the TTL, pruned by the next run
+    pub expired: usize,
+    pub oldest: Option<SystemTime>,
+    pub newest: Option<SystemTime>,
+}
+
+/// The replies in `dir` and their size
+pub fn stats(dir: &Path, limits: CacheLimits) -> io::Result<CacheStats> {
+    let mut stats = CacheStats::default();
+    for (_, bytes, modified) in entries(dir)? {
+        stats.replies += 1;
+        stats.bytes += bytes;
+        stats.expired += usize::from(limits.ttl.is_some_and(|ttl| is_expired(modified, ttl)));
+        stats.oldest = Some(stats.oldest.map_or(modified, |oldest| oldest.min(modified)));
+        stats.newest = Some(stats.newest.map_or(modified, |newest| newest.max(modified)));
+    }
+    Ok(stats)
+}
+
+/// Remove every reply in `dir`, returning how many and their size
+pub fn clear(dir: &Path) -> io::Result<(usize, u64)> {
+    remove(entries(dir)?)
+}
+
+/// Remove the expired replies in `dir`, then the oldest ones until it fits `max_bytes`
+pub fn prune(dir: &Path, limits: CacheLimits) -> io::Result<(usize, u64)> {
+    if limits == CacheLimits::default() {
+        return Ok((0, 0));
+    }
+    let (expired, mut kept): (Vec<_>, Vec<_>) = entries(dir)?
+        .into_iter()
+        .partition(|(_, _, modified)| limits.ttl.is_some_and(|ttl| is_expired(*modified, ttl)));
+    let mut doomed = expired;
+    if let Some(max_bytes) = limits.max_bytes {
+        kept.sort_by_key(|(_, _, modified)| *modified);
+        let mut total: u64 = kept.iter().map(|(_, bytes, _)| bytes).sum();
+        let mut count = 0;
+        while total > max_bytes && count < kept.len() {
+            total -= kept[count].1;
+            count += 1;
+        }
+        doomed.extend(kept.drain(..count));
+    }
+    remove(doomed)
+}
+
+/// `$XDG_CACHE_HOME/srt-bullet-summarizer`, or `~/.cache/...` without XDG
+pub fn default_cache_dir() -> Option<PathBuf> {
+    let base = env::var_os("XDG_CACHE_HOME")
+        .filter(|v| !v.is_empty())
+        .map(PathBuf::from)
+        .or_else(|| env::var_os("HOME").map(|home| PathBuf::from(home).join(".cache")))?;
+    Some(base.join("srt-bullet-summarizer"))
+}
+
+/// Where the model replies are kept, next to the transcripts of `transcribe`
+pub fn responses_dir() -> Option<PathBuf> {
+    default_cache_dir().map(|dir| dir.join("responses"))
+}
+
+/// `1.2 MB`, `340 KB` or `12 B`
+pub fn format_size(bytes: u64) -> String {
+    if bytes < 1_000 {
+        format!("{} B", bytes)
+    } else if bytes < 1_000_000 {
+        format!("{:.0} KB", bytes as f64 / 1e3)
+    } else {
+        format!("{:.1} MB", bytes as f64 / 1e6)
+    }
+}
+
+/// The replies in `dir` with their size and modification time; none when it doesn't exist yet
+fn entries(dir: &Path) -> io::Result<Vec<(PathBuf, u64, SystemTime)>> {
+    let read = match fs::read_dir(dir) {
+        Ok(read) => read,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(e),
+    };
+    let mut entries = Vec::new();
+    for entry in read {
+        let path = entry?.path();
+        if path.extension().is_some_and(|ext| ext == "txt") {
+            let metadata = fs::metadata(&path)?;
+            entries.push((path, metadata.len(), metadata.modified()?));
+        }
+    }
+    Ok(entries)
+}
+
+fn remove(entries: Vec<(PathBuf, u64, SystemTime)>) -> io::Result<(usize, u64)> {
+    let mut bytes = 0;
+    for (path, size, _) in &entries {
+        fs::remove_file(path)?;
+        bytes += size;
+    }
+    Ok((entries.len(), bytes))
+}
+
+fn is_expired(modified: SystemTime, ttl: Duration) -> bool {
+    modified.elapsed().is_ok_and(|age| age > ttl)
+}
@@ -0,0 +1,83 @@
+// YouTube-style chapters for timed transcripts.
+//
+// Every map chunk of a subtitle file starts at a known cue, so the chunk summaries can be
+// labelled with their start time and handed to the model in one extra call that merges
+// neighbouring chunks on the same topic into titled chapters.
+
+use std::time::Duration;
+
+use crate::parser;
+
+pub const CHAPTERS_TEMPLATE: &str = r#"Below are summaries of consecutive sections of a video transcript, each labelled with the time the section starts.
+Group neighbouring sections that share a topic into chapters and give each chapter a short title of at most six words.
+Answer only with one chapter per line in the format "MM:SS Title", using the start time of the chapter's first section.
+Sections:
+{text}
+
+CHAPTERS:"#;
+
+/// One chapter marker
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Chapter {
+    pub start: Duration,
+    pub title: String,
+}
+
+/// Label each chunk summary with the time its chunk starts, as input for `CHAPTERS_TEMPLATE`
+pub fn sections(starts: &[Duration], summaries: &[String]) -> String {
+    starts
+        .iter()
+        .zip(summaries)
+        .map(|(start, summary)| format!("[{}]\n{}", format_timestamp(*start), summary.trim()))
+        .collect::<Vec<_>>()
+        .join("\n\n")
+}
+
+/// Extract `MM:SS Title` lines from a model reply.
+///
+/// YouTube only accepts chapter lists that start at 00:00 and increase, so out-of-order lines are
+/// dropped and the first chapter is moved to 00:00.
+pub fn parse(reply: &str) -> Vec<Chapter> {
+    let mut chapters: Vec<Chapter> = Vec::new();
+    for line in reply.lines() {
+        let line = line.trim().trim_start_matches(['-', '*', '•']).trim_start();
+        let Some((timestamp, title)) = line.split_once(char::is_whitespace) else {
+            continue;
+        };
+        let Some(start) = parser::parse_timestamp(timestamp.trim_matches(['[', ']'])) else {
+            continue;
+        };
+        let title = title.trim().trim_start_matches(['-', '–', ':']).trim();
+        if title.is_empty() || chapters.last().is_some_and(|last| start <= last.start) {
+            continue;
+        }
+        chapters.push(Chapter {
+            start,
+            title: title.to_string(),
+        });
+    }
+
+    if let Some(first) = chapters.first_mut() {
+        first.start = Duration::ZERO;
+    }
+    chapters
+}
+
+/// One `MM:SS Title` line per chapter, ready to paste into a video description
+pub fn render(chapters: &[Chapter]) -> String {
+    chapters
+        .iter()
+        .map(|chapter| format!("{} {}\n", format_timestamp(chapter.start), chapter.title))
+        .collect()
+}
+
+/// `MM:SS`, or `H:MM:SS` from one hour on
+pub fn format_timestamp(time: Duration) -> String {
+    let seconds = time.as_secs();
+    let (hours, minutes, seconds) = (seconds / 3600, seconds / 60 % 60, seconds % 60);
+    if hours > 0 {
+        format!("{}:{:02}:{:02}", hours, minutes, seconds)
+    } else {
+        format!("{:02}:{:02}", minutes, seconds)
+    }
+}
@@ -0,0 +1,216 @@
+// Checkpoints for the per-chunk step (the map step, or the running summaries of refine).
+//
+// Completed chunk summaries are written to a hidden sidecar file next to the input
+// (`.<file name>.summarizer-state.json`) as soon as each one finishes. The file is keyed by a
+// hash of the chunks, model and prompts, so `--resume` only reuses summaries that would be
+// produced again by the same run. The sidecar is removed once the summary has been saved,
+// unless `--incremental` keeps what it needs for the next run of a growing file in it: how many
+// cues were summarized, their chunk summaries and the summary so far.
+// In-memory checkpoints (for text that doesn't come from a file) never touch the disk.
+
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::{
+    collections::BTreeMap,
+    error::Error,
+    fs,
+    path::{Path, PathBuf},
+    time::Duration,
+};
+use tracing::info;
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Checkpoint {
+    key: String,
+    total_chunks: usize,
+    summaries: BTreeMap<usize, String>,
+    /// Kept across runs, and across interrupted ones
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    incremental: Option<Incremental>,
+    #[serde(skip)]
+    path: Option<PathBuf>,
+}
+
+/// What `--incremental` remembers of a growing subtitle file after summarizing it
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Incremental {
+    /// Model and prompts of the summary; see `settings_key`
+    pub key: String,
+    /// Number of cues summarized, from the first
+    pub cues: usize,
+    pub chunk_summaries: Vec<String>,
+    pub chunk_starts: Vec<Duration>,
+    pub final_summary: String,
+}
+
+impl Checkpoint {
+    /// Start a checkpoint for `input`, picking up saved summaries when `resume` is set and the
+    /// saved state belongs to the same chunks, model and `prompts` (those that produce the
+    /// per-chunk summaries)
+    pub fn open(
+        input: &Path,
+        chunks: &[String],
+        model: &str,
+        prompts: &[&str],
+        resume: bool,
+    ) -> Result<Self, Box<dyn Error>> {
+        let mut key = RunKey::new(model, prompts);
+        for chunk in chunks {
+            key.add(chunk);
+        }
+        Self::open_with_key(input, key.finish(), chunks.len(), resume)
+    }
+
+    /// Like `open`, for the `total_chunks` chunks a `RunKey` was built from as they were read
+    pub fn open_with_key(input: &Path, key: String, total_chunks: usize, resume: bool) -> Result<Self, Box<dyn Error>> {
+        let path = state_path(input);
+        let mut incremental = None;
+
+        if path.exists() {
+            let saved = read(&path);
+            incremental = saved.as_ref().and_then(|saved| saved.incremental.clone());
+            // A state file left for `--incremental` has no chunks of an unfinished run
+            match saved.filter(|saved| saved.total_chunks > 0) {
+                Some(saved) if resume && saved.key == key => {
+                    info!(
+                        "Resuming: {}/{} chunks already summarized",
+                        saved.summaries.len(),
+                        saved.total_chunks
+                    );
+                    return Ok(Self {
+                        path: Some(path),
+                        ..saved
+                    });
+                }
+                Some(_) if resume => {
+                    info!("Checkpoint does not match this input or settings; starting over");
+                }
+                Some(saved) => info!(
+                    "Found a checkpoint with {}/{} chunks; pass --resume to continue it",
+                    saved.summaries.len(),
+                    saved.total_chunks
+                ),
+                None => {}
+            }
+        } else if resume {
+            info!("No checkpoint found; starting from the first chunk");
+        }
+
+        Ok(Self {
+            key,
+            total_chunks,
+            summaries: BTreeMap::new(),
+            incremental,
+            path: Some(path),
+        })
+    }
+
+    /// What the last `--incremental` run of `input` left in its state file
+    pub fn incremental(input: &Path) -> Option<Incremental> {
+        read(&state_path(input))?.incremental
+    }
+
+    /// A checkpoint that only collects summaries, for input that has no file to sit next to
+    pub fn in_memory(total_chunks: usize) -> Self {
+        Self {
+            key: String::new(),
+            total_chunks,
+            summaries: BTreeMap::new(),
+            incremental: None,
+            path: None,
+        }
+    }
+
+    pub fn is_done(&self, index: usize) -> bool {
+        self.summaries.contains_key(&index)
+    }
+
+    pub fn completed(&self) -> usize {
+        self.summaries.len()
+    }
+
+    pub fn total(&self) -> usize {
+        self.total_chunks
+    }
+
+    /// Store a finished chunk summary and flush the state file
+    pub fn record(&mut self, index: usize, summary: String) -> Result<(), Box<dyn Error>> {
+        self.summaries.insert(index, summary);
+        if let Some(path) = &self.path {
+            let tmp = path.with_extension("json.tmp");
+            fs::write(&tmp, serde_json::to_string(self)?)?;
+            fs::rename(&tmp, path)?;
+        }
+        Ok(())
+    }
+
+    /// Chunk summaries in transcript order
+    pub fn summaries(&self) -> Vec<String> {
+        self.summaries.values().cloned().collect()
+    }
+
+    /// Delete the state file after a successful run
+    pub fn remove(self) -> Result<(), Box<dyn Error>> {
+        match &self.path {
+            Some(path) if path.exists() => Ok(fs::remove_file(path)?),
+            _ => Ok(()),
+        }
+    }
+
+    /// Replace the chunk summaries of a successful run with what the next `--incremental` run
+    /// needs, keeping the state file
+    pub fn keep_incremental(mut self, incremental: Incremental) -> Result<(), Box<dyn Error>> {
+        self.key = String::new();
+        self.total_chunks = 0;
+        self.summaries.clear();
+        self.incremental = Some(incremental);
+        match &self.path {
+            Some(path) => {
+                let tmp = path.with_extension("json.tmp");
+                fs::write(&tmp, serde_json::to_string(&self)?)?;
+                Ok(fs::rename(&tmp, path)?)
+            }
+            None => Ok(()),
+        }
+    }
+}
+
+/// Key of the settings an `Incremental` state was made with, from the model and `prompts`
+pub fn settings_key(model: &str, prompts: &[&str]) -> String {
+    RunKey::new(model, prompts).finish()
+}
+
+/// Hash of the chunks, model and prompts of a run, fed one chunk at a time
+pub struct RunKey(Sha256);
+
+impl RunKey {
+    pub fn new(model: &str, prompts: &[&str]) -> Self {
+        let mut hasher = Sha256::new();
+        for part in std::iter::once(model).chain(prompts.iter().copied()) {
+            hasher.update(part.as_bytes());
+            hasher.update([0]);
+        }
+        Self(hasher)
+    }
+
+    pub fn add(&mut self, chunk: &str) {
+        self.0.update(chunk.as_bytes());
+        self.0.update([0]);
+    }
+
+    pub fn finish(self) -> String {
+        format!("{:x}", self.0.finalize())
+    }
+}
+
+fn read(path: &Path) -> Option<Checkpoint> {
+    let text = fs::read_to_string(path).ok()?;
+    serde_json::from_str(&text).ok()
+}
+
+/// `<dir>/.<file name>.summarizer-state.json`
+fn state_path(input: &Path) -> PathBuf {
+    let parent = input.parent().unwrap_or_else(|| Path::new("."));
+    let name = input.file_name().unwrap_or_default().to_string_lossy();
+    parent.join(format!(".{}.summarizer-state.json", name))
+}
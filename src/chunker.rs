@@ -0,0 +1,416 @@
+// Text splitters that turn a cleaned transcript into overlapping chunks for the map step.
+
+use std::{
+    error::Error,
+    io::{self, BufRead},
+    ops::Range,
+    sync::OnceLock,
+    time::Duration,
+};
+use tiktoken_rs::CoreBPE;
+
+/// Tokens of the `\n\n` separator between joined summaries
+const JOIN_TOKENS: usize = 1;
+
+/// Simple word-based splitter with overlap
+pub fn split_words(text: &str, chunk_size: usize, chunk_overlap: usize) -> Vec<String> {
+    let words: Vec<&str> = text.split_whitespace().collect();
+    join_windows(&words, word_windows(words.len(), chunk_size, chunk_overlap))
+}
+
+/// Word index ranges of the chunks `split_words` produces for a text of `word_count` words
+pub fn word_windows(word_count: usize, chunk_size: usize, chunk_overlap: usize) -> Vec<Range<usize>> {
+    let mut windows = Vec::new();
+    let mut start = 0;
+
+    while start < word_count {
+        let end = usize::min(start + chunk_size, word_count);
+        windows.push(start..end);
+        if end == word_count {
+            break;
+        }
+        start += chunk_size.saturating_sub(chunk_overlap);
+    }
+
+    windows
+}
+
+/// Token-budgeted splitter with token overlap.
+///
+/// Chunks are still cut at word boundaries so no word is split across two chunks; each chunk
+/// holds as many words as fit in `max_tokens` (a single oversized word gets a chunk of its own),
+/// and starts with roughly `overlap_tokens` tokens from the end of the previous chunk.
+pub fn split_tokens(
+    text: &str,
+    max_tokens: usize,
+    overlap_tokens: usize,
+) -> Result<Vec<String>, Box<dyn Error>> {
+    let words: Vec<&str> = text.split_whitespace().collect();
+    Ok(join_windows(&words, token_windows(&words, max_tokens, overlap_tokens)?))
+}
+
+/// Word index ranges of the chunks `split_tokens` produces for `words`
+pub fn token_windows(
+    words: &[&str],
+    max_tokens: usize,
+    overlap_tokens: usize,
+) -> Result<Vec<Range<usize>>, Box<dyn Error>> {
+    let bpe = tokenizer()?;
+    let counts: Vec<usize> = words.iter().map(|w| word_tokens(bpe, w)).collect();
+    let mut windows = Vec::new();
+    let mut start = 0;
+
+    while start < words.len() {
+        let mut end = start;
+        let mut used = 0;
+        while end < words.len() && (end == start || used + counts[end] <= max_tokens) {
+            used += counts[end];
+            end += 1;
+        }
+        windows.push(start..end);
+        if end == words.len() {
+            break;
+        }
+
+        // Step back over whole words until the overlap budget is spent, always moving forward
+        let mut next = end;
+        let mut overlap = 0;
+        while next > start + 1 && overlap + counts[next - 1] <= overlap_tokens {
+            next -= 1;
+            overlap += counts[next];
+        }
+        start = next;
+    }
+
+    Ok(windows)
+}
+
+/// How cleanly a chunk can end after a word
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Break {
+    /// Mid-sentence
+    None,
+    /// At the end of a subtitle cue, which auto-generated captions without punctuation still have
+    Cue,
+    /// At the end of a sentence
+    Sentence,
+    /// Before a Markdown heading or an EPUB chapter
+    Section,
+}
+
+/// Abbreviations whose period doesn't end a sentence
+const ABBREVIATIONS: &[&str] = &[
+    "mr.", "mrs.", "ms.", "dr.", "prof.", "st.", "vs.", "etc.", "e.g.", "i.e.", "approx.", "no.",
+];
+
+/// The break after every word of `words`; `word_cues` gives the cue each word came from, and
+/// `sections` the first word of every section
+pub fn breaks(words: &[&str], word_cues: Option<&[usize]>, sections: &[usize]) -> Vec<Break> {
+    words
+        .iter()
+        .enumerate()
+        .map(|(i, word)| {
+            if sections.binary_search(&(i + 1)).is_ok() {
+                Break::Section
+            } else if ends_sentence(word) {
+                Break::Sentence
+            } else if word_cues.is_some_and(|cues| cues.get(i + 1).is_some_and(|next| *next != cues[i])) {
+                Break::Cue
+            } else {
+                Break::None
+            }
+        })
+        .collect()
+}
+
+fn ends_sentence(word: &str) -> bool {
+    let word = word.trim_end_matches(['"', '\'', '”', '’', ')', ']', '»']);
+    if !word.ends_with(['.', '!', '?', '…', '。', '！', '？', '؟']) {
+        return false;
+    }
+    // Initials such as "J." and common abbreviations
+    let initial = word.chars().count() == 2 && word.starts_with(char::is_uppercase);
+    !(initial || ABBREVIATIONS.contains(&word.to_lowercase().as_str()))
+}
+
+/// Word index ranges of chunks costing at most `budget` (`costs` has one entry per word: 1 to
+/// count words, or each word's tokens). Each chunk ends at the strongest break in the back half
+/// of the words that fit, or else anywhere in them; only a sentence too long for that is cut
+/// between two words. Consecutive chunks share up to `overlap` of the budget, starting at the
+/// beginning of a sentence when the overlap contains one.
+pub fn sentence_windows(costs: &[usize], breaks: &[Break], budget: usize, overlap: usize) -> Vec<Range<usize>> {
+    windows_cut_by(costs, breaks, budget, overlap, |start, end| best_break(breaks, start, end))
+}
+
+/// Like `sentence_windows`, but each chunk ends at the passage boundary in the back half of the
+/// words that fit where the passages either side are least alike, i.e. where the topic most
+/// likely changes. `boundaries` holds the position after every passage but the last with the
+/// similarity of the passages on its two sides; a chunk without a boundary in it ends like a
+/// sentence chunk.
+pub fn semantic_windows(
+    costs: &[usize],
+    breaks: &[Break],
+    boundaries: &[(usize, f32)],
+    budget: usize,
+    overlap: usize,
+) -> Vec<Range<usize>> {
+    windows_cut_by(costs, breaks, budget, overlap, |start, end| {
+        let middle = start + (end - start) / 2;
+        let least_similar = |from: usize| {
+            boundaries
+                .iter()
+                .filter(|&&(at, _)| at > from && at < end)
+                .min_by(|a, b| a.1.total_cmp(&b.1))
+                .map(|&(at, _)| at)
+        };
+        least_similar(middle)
+            .or_else(|| least_similar(start))
+            .unwrap_or_else(|| best_break(breaks, start, end))
+    })
+}
+
+/// Consecutive passages of at least `min_words` words, each ending at a sentence or cue end (or
+/// before a section); passages without such a break are cut after four times `min_words`
+pub fn passages(breaks: &[Break], min_words: usize) -> Vec<Range<usize>> {
+    let mut passages = Vec::new();
+    let mut start = 0;
+    for (i, &kind) in breaks.iter().enumerate() {
+        let words = i + 1 - start;
+        if kind == Break::Section || (kind >= Break::Cue && words >= min_words) || words >= min_words * 4 {
+            passages.push(start..i + 1);
+            start = i + 1;
+        }
+    }
+    if start < breaks.len() {
+        passages.push(start..breaks.len());
+    }
+    passages
+}
+
+/// Cosine similarity of two embeddings; 0 when either is all zeros
+pub fn similarity(a: &[f32], b: &[f32]) -> f32 {
+    let dot: f32 = a.iter().zip(b).map(|(x, y)| x * y).sum();
+    let norm = |v: &[f32]| v.iter().map(|x| x * x).sum::<f32>().sqrt();
+    let norms = norm(a) * norm(b);
+    if norms == 0.0 {
+        0.0
+    } else {
+        dot / norms
+    }
+}
+
+/// Chunks of at most `budget` whose end is chosen by `cut` from the words that fit, sharing up
+/// to `overlap` as `sentence_windows` describes
+fn windows_cut_by(
+    costs: &[usize],
+    breaks: &[Break],
+    budget: usize,
+    overlap: usize,
+    cut: impl Fn(usize, usize) -> usize,
+) -> Vec<Range<usize>> {
+    let mut windows = Vec::new();
+    let mut start = 0;
+
+    while start < costs.len() {
+        let mut end = start;
+        let mut used = 0;
+        while end < costs.len() && (end == start || used + costs[end] <= budget) {
+            used += costs[end];
+            end += 1;
+        }
+        if end < costs.len() {
+            end = cut(start, end);
+        }
+        windows.push(start..end);
+        if end == costs.len() {
+            break;
+        }
+
+        let mut next = end;
+        let mut shared = 0;
+        while next > start + 1 && shared + costs[next - 1] <= overlap {
+            next -= 1;
+            shared += costs[next];
+        }
+        // Start the overlap at a sentence start, giving up some of it if needed
+        start = (next..end)
+            .find(|&i| breaks[i - 1] >= Break::Sentence)
+            .unwrap_or(next);
+    }
+
+    windows
+}
+
+/// End of a chunk covering `start..end` at the position after its strongest break, preferring
+/// the back half so chunks don't shrink to a few words
+fn best_break(breaks: &[Break], start: usize, end: usize) -> usize {
+    let middle = start + (end - start) / 2;
+    let strongest = |from: usize| {
+        // The last of the strongest breaks
+        (from..end)
+            .filter(|&i| breaks[i] > Break::None)
+            .max_by_key(|&i| breaks[i])
+    };
+    strongest(middle)
+        .or_else(|| strongest(start))
+        .map_or(end, |i| i + 1)
+}
+
+/// Word index ranges of consecutive words whose start times (`starts`, one per word, in order)
+/// fall in the same `window` counted from 00:00; windows without words are skipped
+pub fn time_windows(starts: &[Duration], window: Duration) -> Vec<Range<usize>> {
+    let slot = |start: Duration| start.as_millis() / window.as_millis().max(1);
+    let mut windows: Vec<Range<usize>> = Vec::new();
+    for (i, start) in starts.iter().enumerate() {
+        match windows.last_mut() {
+            Some(last) if slot(starts[last.start]) == slot(*start) => last.end = i + 1,
+            _ => windows.push(i..i + 1),
+        }
+    }
+    windows
+}
+
+/// Tokens of every word as it appears mid-text, for token-budgeted `sentence_windows`
+pub fn word_token_counts(words: &[&str]) -> Result<Vec<usize>, Box<dyn Error>> {
+    let bpe = tokenizer()?;
+    Ok(words.iter().map(|w| word_tokens(bpe, w)).collect())
+}
+
+/// Group consecutive `texts` into batches of at most `max_tokens` tokens each, counting the
+/// blank line they are joined with; a text over the budget gets a batch of its own
+pub fn token_batches(texts: &[String], max_tokens: usize) -> Result<Vec<Range<usize>>, Box<dyn Error>> {
+    let bpe = tokenizer()?;
+    let mut batches = Vec::new();
+    let mut start = 0;
+    let mut used = 0;
+
+    for (i, text) in texts.iter().enumerate() {
+        let tokens = bpe.encode_ordinary(text).len() + JOIN_TOKENS;
+        if i > start && used + tokens > max_tokens {
+            batches.push(start..i);
+            start = i;
+            used = 0;
+        }
+        used += tokens;
+    }
+    if start < texts.len() {
+        batches.push(start..texts.len());
+    }
+
+    Ok(batches)
+}
+
+fn join_windows(words: &[&str], windows: Vec<Range<usize>>) -> Vec<String> {
+    windows.into_iter().map(|range| words[range].join(" ")).collect()
+}
+
+/// The chunks of a text read line by line from `reader`, for files too large to load whole.
+/// `cut` returns the word index ranges of the chunks of the words it is given, like
+/// `sentence_windows`; it only ever sees the `look_ahead` words from the start of the next chunk,
+/// which must be more than a chunk can hold so that every chunk ends where it would in the whole
+/// text. Bytes that aren't UTF-8 are replaced with U+FFFD.
+pub struct StreamedChunks<R, F> {
+    reader: R,
+    cut: F,
+    look_ahead: usize,
+    words: Vec<String>,
+    line: Vec<u8>,
+    started: bool,
+    done: bool,
+}
+
+impl<R, F> StreamedChunks<R, F>
+where
+    R: BufRead,
+    F: FnMut(&[&str]) -> Result<Vec<Range<usize>>, Box<dyn Error>>,
+{
+    pub fn new(reader: R, look_ahead: usize, cut: F) -> Self {
+        Self {
+            reader,
+            cut,
+            look_ahead,
+            words: Vec::new(),
+            line: Vec::new(),
+            started: false,
+            done: false,
+        }
+    }
+
+    /// Read lines until `look_ahead` words are held or the text ends
+    fn fill(&mut self) -> io::Result<()> {
+        while !self.done && self.words.len() < self.look_ahead {
+            self.line.clear();
+            if self.reader.read_until(b'\n', &mut self.line)? == 0 {
+                self.done = true;
+                break;
+            }
+            let line = String::from_utf8_lossy(&self.line);
+            // A byte order mark is not a word
+            let line = if self.started {
+                &line
+            } else {
+                line.strip_prefix('\u{feff}').unwrap_or(&line)
+            };
+            self.words.extend(line.split_whitespace().map(str::to_string));
+            self.started = true;
+        }
+        Ok(())
+    }
+}
+
+impl<R, F> Iterator for StreamedChunks<R, F>
+where
+    R: BufRead,
+    F: FnMut(&[&str]) -> Result<Vec<Range<usize>>, Box<dyn Error>>,
+{
+    type Item = io::Result<String>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if let Err(e) = self.fill() {
+            self.done = true;
+            self.words.clear();
+            return Some(Err(e));
+        }
+        if self.words.is_empty() {
+            return None;
+        }
+        let words: Vec<&str> = self.words.iter().map(String::as_str).collect();
+        let windows = match (self.cut)(&words) {
+            Ok(windows) => windows,
+            Err(e) => {
+                self.done = true;
+                self.words.clear();
+                return Some(Err(io::Error::other(e.to_string())));
+            }
+        };
+        let chunk = words[windows[0].clone()].join(" ");
+        // Everything before the next chunk, which starts inside this one when they overlap
+        let consumed = windows.get(1).map_or(words.len(), |next| next.start);
+        self.words.drain(..consumed);
+        Some(Ok(chunk))
+    }
+}
+
+/// Tokens in `text`, by the same approximation the chunker uses; falls back to the word count
+/// if the tokenizer cannot be loaded
+pub fn count_tokens(text: &str) -> usize {
+    match tokenizer() {
+        Ok(bpe) => bpe.encode_ordinary(text).len(),
+        Err(_) => text.split_whitespace().count(),
+    }
+}
+
+/// cl100k_base is used as a model-agnostic approximation; local models' own tokenizers
+/// differ, but are within a small factor for typical subtitle text. It is built once, as
+/// building it takes longer than tokenizing a transcript.
+fn tokenizer() -> Result<&'static CoreBPE, Box<dyn Error>> {
+    static BPE: OnceLock<Result<CoreBPE, String>> = OnceLock::new();
+    BPE.get_or_init(|| tiktoken_rs::cl100k_base().map_err(|e| e.to_string()))
+        .as_ref()
+        .map_err(|e| format!("Cannot load tokenizer: {}", e).into())
+}
+
+/// Tokens for a word as it appears mid-text, i.e. with its leading space
+fn word_tokens(bpe: &CoreBPE, word: &str) -> usize {
+    bpe.encode_ordinary(&format!(" {}", word)).len()
+}
@@ -0,0 +1,928 @@
+// Command-line interface definition.
+//
+// Running the binary with file paths and no subcommand behaves like `summarize`, so
+// `srt-bullet-summarizer lecture.srt` (as used by `monitor-srt.sh`) keeps working.
+
+use clap::{Args, Parser, Subcommand};
+use std::{
+    error::Error,
+    fs,
+    net::SocketAddr,
+    path::{Path, PathBuf},
+    time::Duration,
+};
+use tracing::{debug, info};
+
+use srt_bullet_summarizer::{ask, backend, config::{BackendKind, TranscriberKind}, parser, post, Length, Mode, OutputFormat, Settings, SettingsLayer, Split, Strategy};
+
+use crate::logging::Verbosity;
+
+#[derive(Debug, Parser)]
+#[command(
+    version,
+    about = "Summarize subtitle and text files into bullet points using a local LLM",
+    args_conflicts_with_subcommands = true,
+    subcommand_negates_reqs = true
+)]
+pub struct Cli {
+    #[command(subcommand)]
+    command: Option<Command>,
+
+    // Not an `Option`: clap can't tell whether an optional flatten with a nested flatten
+    // (`SettingsArgs`) was given, so INPUT is required unless a subcommand is
+    #[command(flatten)]
+    summarize: SummarizeArgs,
+
+    #[command(flatten)]
+    pub log: LogArgs,
+}
+
+/// Logging flags, accepted by every subcommand
+#[derive(Debug, Args)]
+pub struct LogArgs {
+    /// Also log per-request timings, token counts and other request details
+    #[arg(short, long, global = true)]
+    pub verbose: bool,
+
+    /// Print nothing but errors (and the results: --stdout summaries, streamed replies, plans and listings)
+    #[arg(short, long, global = true, conflicts_with = "verbose")]
+    pub quiet: bool,
+
+    /// Append a structured JSON log of every event, verbose ones included, to this file
+    #[arg(long, global = true, value_name = "PATH")]
+    pub log_file: Option<PathBuf>,
+}
+
+impl LogArgs {
+    pub fn verbosity(&self) -> Verbosity {
+        if self.quiet {
+            Verbosity::Quiet
+        } else if self.verbose {
+            Verbosity::Verbose
+        } else {
+            Verbosity::Normal
+        }
+    }
+}
+
+impl Cli {
+    /// Resolve the subcommand to run, treating bare arguments as `summarize`
+    pub fn into_command(self) -> Command {
+        match self.command {
+            Some(command) => command,
+            None => Command::Summarize(Box::new(self.summarize)),
+        }
+    }
+}
+
+#[derive(Debug, Subcommand)]
+pub enum Command {
+    /// Summarize a file into bullet points (the default when no subcommand is given)
+    Summarize(Box<SummarizeArgs>),
+    /// Summarize two inputs and report how they differ: topics only in one of them and changed decisions
+    Compare(Box<CompareArgs>),
+    /// Answer a question about a transcript from the parts of it most relevant to the question, citing their timestamps
+    Ask(Box<AskArgs>),
+    /// Synthesize the episode summaries in a directory into one overview of the series, e.g. a podcast season or a course
+    Rollup(Box<RollupArgs>),
+    /// Convert a subtitle file into cleaned plain text without calling the LLM
+    Convert(ConvertArgs),
+    /// Serve `POST /summarize` over HTTP, answering with the JSON summary
+    Serve(Box<ServeArgs>),
+    /// Serve `summarize_file` and `summarize_text` tools to MCP clients such as Claude Desktop, over stdin and stdout
+    Mcp(Box<McpArgs>),
+    /// Watch a directory and summarize every subtitle or text file dropped into it
+    Watch(Box<WatchArgs>),
+    /// Download the subtitles (or audio) of videos and playlists with yt-dlp, then summarize each video
+    Fetch(Box<FetchArgs>),
+    /// Check the API is reachable, the models exist and answer, with what to fix when they don't
+    Doctor(Box<DoctorArgs>),
+    /// Manage the configuration file
+    Config {
+        #[command(subcommand)]
+        command: ConfigCommand,
+    },
+    /// Browse the summaries recorded with `--history`
+    History {
+        #[command(subcommand)]
+        command: HistoryCommand,
+    },
+    /// Inspect or empty the cache of model replies
+    Cache {
+        #[command(subcommand)]
+        command: CacheCommand,
+    },
+}
+
+#[derive(Debug, Subcommand)]
+pub enum ConfigCommand {
+    /// Write a commented default config file
+    Init {
+        /// Where to write it [default: ~/.config/srt-bullet-summarizer/config.toml]
+        #[arg(long)]
+        path: Option<PathBuf>,
+
+        /// Overwrite an existing file
+        #[arg(long)]
+        force: bool,
+    },
+    /// Print the default config file location
+    Path,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum HistoryCommand {
+    /// List recorded summaries, newest first
+    List {
+        /// Only list summaries whose input path or text contains this
+        query: Option<String>,
+
+        /// Number of summaries to list
+        #[arg(short = 'n', long, default_value_t = 20)]
+        limit: usize,
+    },
+    /// Print a recorded summary with its details
+    Show {
+        /// Id from `history list`
+        id: i64,
+    },
+}
+
+#[derive(Debug, Subcommand)]
+pub enum CacheCommand {
+    /// Print where the cache is, how many replies it holds and their size
+    Stats,
+    /// Remove every cached reply
+    Clear,
+}
+
+#[derive(Debug, Args)]
+pub struct SummarizeArgs {
+    /// Input files, directories, glob patterns (.srt, .vtt, .ass, .ssa, Whisper .json, .pdf, .epub, .html, .md, plain text or .mp3/.wav/.m4a recordings), video or web page URLs, or - to read stdin
+    #[arg(required = true)]
+    pub inputs: Vec<PathBuf>,
+
+    /// Where to write the summary, or - for stdout; only valid for a single input [default: <input stem>_summary.txt next to the input; stdout for -]
+    #[arg(short, long)]
+    pub output: Option<PathBuf>,
+
+    /// Print the summary to stdout instead of writing a file; progress goes to stderr
+    #[arg(long, conflicts_with = "output")]
+    pub stdout: bool,
+
+    /// Overwrite summaries that already exist instead of refusing to
+    #[arg(short, long)]
+    pub force: bool,
+
+    /// Directory to collect summaries (and chapters) in, created if missing [default: next to each input]
+    #[arg(long, conflicts_with = "output")]
+    pub output_dir: Option<PathBuf>,
+
+    /// Summary file name template with {stem}, {date}, {model}, {mode} and {ext}, e.g. "{stem}.{date}.summary.{ext}" [default: {stem}_summary.{ext}]
+    #[arg(long, conflicts_with = "output")]
+    pub output_name: Option<String>,
+
+    /// Descend into subdirectories when an input is a directory
+    #[arg(short, long)]
+    pub recursive: bool,
+
+    /// Summarize up to this many files at once, sharing --concurrency requests between them; their messages start with the file name
+    #[arg(long, default_value_t = 1, value_parser = positive, conflicts_with_all = ["merge", "stream"])]
+    pub jobs: usize,
+
+    /// Summarize all inputs as one text, in the order given (each directory or pattern sorted), e.g. the parts of a lecture series; the summary is named after the first input, <stem>_merged_summary.txt
+    #[arg(long)]
+    pub merge: bool,
+
+    /// Subtitle language to fetch for video URLs, e.g. en or de; manual tracks are preferred over auto-generated ones [default: the video's language, else en]
+    #[arg(long)]
+    pub sub_lang: Option<String>,
+
+    /// Continue an interrupted run from its checkpoint instead of re-summarizing every chunk
+    #[arg(long)]
+    pub resume: bool,
+
+    /// Summarize only the cues a growing subtitle file gained since the last --incremental run, e.g. a lecture being captured live, and merge them into its summary, which is overwritten
+    #[arg(long, conflicts_with = "merge")]
+    pub incremental: bool,
+
+    /// Print the final summary as it is generated; the full file is still written at the end
+    #[arg(long)]
+    pub stream: bool,
+
+    /// Write every rendered prompt and the model's raw reply to numbered files in this directory, e.g. 002_chunk_2_prompt.txt
+    #[arg(long, value_name = "DIR")]
+    pub debug_dump: Option<PathBuf>,
+
+    /// Parse, clean and split the inputs, then print the chunks and the number of LLM requests a run would make, without making any
+    #[arg(long)]
+    pub dry_run: bool,
+
+    /// Only summarize the part of a subtitle file from this time on, e.g. 00:15:00 or 15:00
+    #[arg(long, value_name = "TIME", value_parser = clock)]
+    pub from: Option<Duration>,
+
+    /// Only summarize the part of a subtitle file up to this time, e.g. 01:05:00
+    #[arg(long, value_name = "TIME", value_parser = clock)]
+    pub to: Option<Duration>,
+
+    /// Also write YouTube chapter markers (`00:00 Introduction`) to <input stem>_chapters.txt; needs timed subtitles
+    #[arg(long)]
+    pub chapters: bool,
+
+    /// Add a "Topics:" line of key terms and entities, from one extra request over the summary
+    #[arg(long)]
+    pub with_keywords: bool,
+
+    /// List the people, organizations, tools and products, and numbers and dates mentioned below the summary, from extra requests over the chunk summaries
+    #[arg(long)]
+    pub with_entities: bool,
+
+    /// Start the output with a generated one-line title
+    #[arg(long)]
+    pub with_title: bool,
+
+    /// Start the output with a one-sentence TL;DR above the bullets
+    #[arg(long)]
+    pub with_tldr: bool,
+
+    #[command(flatten)]
+    pub settings: SettingsArgs,
+}
+
+/// Settings flags shared by `summarize` and `serve`; each one overrides the config file
+#[derive(Debug, Args)]
+pub struct SettingsArgs {
+    /// Config file to load [default: ~/.config/srt-bullet-summarizer/config.toml]
+    #[arg(long, env = "SRT_SUMMARIZER_CONFIG")]
+    pub config: Option<PathBuf>,
+
+    /// Which API to talk to [default: openai]
+    #[arg(long, value_enum, env = "SRT_SUMMARIZER_BACKEND")]
+    pub backend: Option<BackendKind>,
+
+    /// Model name to request from the API [default: llama3.2, or claude-3-5-haiku-latest for anthropic]
+    #[arg(long, env = "SRT_SUMMARIZER_MODEL")]
+    pub model: Option<String>,
+
+    /// Model summarizing the chunks, e.g. a small fast one [default: --model]
+    #[arg(long, value_name = "MODEL")]
+    pub map_model: Option<String>,
+
+    /// Model for the steps after the chunk summaries: combining, chapters, topics, titles and translation [default: --model]
+    #[arg(long, value_name = "MODEL")]
+    pub combine_model: Option<String>,
+
+    /// Model embedding the chunks `ask` retrieves and the passages of --split semantic [default: nomic-embed-text, or text-embedding-004 for gemini]
+    #[arg(long, value_name = "MODEL")]
+    pub embedding_model: Option<String>,
+
+    /// Base URL of the API [default: http://localhost:11434/v1; http://localhost:11434 for ollama; https://api.anthropic.com for anthropic; https://generativelanguage.googleapis.com for gemini; $AZURE_OPENAI_ENDPOINT for azure]
+    #[arg(long, env = "SRT_SUMMARIZER_API_BASE")]
+    pub api_base: Option<String>,
+
+    /// API key for hosted endpoints [default: $OPENAI_API_KEY; $ANTHROPIC_API_KEY for anthropic; $GEMINI_API_KEY for gemini; $AZURE_OPENAI_API_KEY for azure]
+    #[arg(long)]
+    pub api_key: Option<String>,
+
+    /// How long Ollama keeps the model loaded after a request, e.g. 10m or -1 (ollama backend)
+    #[arg(long)]
+    pub keep_alive: Option<String>,
+
+    /// Download the model if Ollama doesn't have it yet (ollama backend)
+    #[arg(long)]
+    pub auto_pull: bool,
+
+    /// Azure deployment serving --model [default: the model name] (azure backend)
+    #[arg(long, value_name = "NAME", env = "AZURE_OPENAI_DEPLOYMENT")]
+    pub deployment: Option<String>,
+
+    /// Azure OpenAI API version (azure backend) [default: 2024-10-21]
+    #[arg(long, value_name = "VERSION", env = "OPENAI_API_VERSION")]
+    pub api_version: Option<String>,
+
+    /// GGUF model file to run in-process (local backend)
+    #[arg(long, value_name = "PATH", env = "SRT_SUMMARIZER_MODEL_PATH")]
+    pub model_path: Option<PathBuf>,
+
+    /// tokenizer.json of the model, for GGUF files whose vocabulary can't be used directly (local backend) [default: a tokenizer.json next to the model]
+    #[arg(long, value_name = "PATH")]
+    pub tokenizer: Option<PathBuf>,
+
+    /// Backend to ask when the primary one is down or a request still fails after its retries
+    #[arg(long, value_enum, value_name = "BACKEND", env = "SRT_SUMMARIZER_FALLBACK")]
+    pub fallback: Option<BackendKind>,
+
+    /// Model of the fallback backend (a deployment name for azure) [default: the --model default of that backend]
+    #[arg(long, value_name = "MODEL")]
+    pub fallback_model: Option<String>,
+
+    /// Base URL of the fallback backend [default: the --api-base default of that backend]
+    #[arg(long, value_name = "URL")]
+    pub fallback_api_base: Option<String>,
+
+    /// API key of the fallback backend [default: $OPENAI_API_KEY, $ANTHROPIC_API_KEY or $GEMINI_API_KEY]
+    #[arg(long, value_name = "KEY")]
+    pub fallback_api_key: Option<String>,
+
+    /// Speech-to-text API that transcribes .mp3, .wav, .m4a, .flac and .ogg inputs [default: whisper-cpp]
+    #[arg(long, value_enum, env = "SRT_SUMMARIZER_TRANSCRIBER")]
+    pub transcriber: Option<TranscriberKind>,
+
+    /// Base URL of the transcription API [default: http://127.0.0.1:8080 for whisper-cpp; https://api.openai.com/v1 for openai]
+    #[arg(long, env = "SRT_SUMMARIZER_TRANSCRIBE_API_BASE")]
+    pub transcribe_api_base: Option<String>,
+
+    /// Transcription model requested from OpenAI-compatible endpoints [default: whisper-1]
+    #[arg(long)]
+    pub transcribe_model: Option<String>,
+
+    /// API key of the transcription API [default: $OPENAI_API_KEY for openai]
+    #[arg(long)]
+    pub transcribe_api_key: Option<String>,
+
+    /// Sampling temperature from 0 to 2; lower is more deterministic [default: the backend's]
+    #[arg(long)]
+    pub temperature: Option<f64>,
+
+    /// Nucleus sampling probability mass from 0 to 1 [default: the backend's]
+    #[arg(long)]
+    pub top_p: Option<f64>,
+
+    /// Most tokens the model may write in one reply [default: the backend's; 4096 for anthropic]
+    #[arg(long, value_parser = positive_u32)]
+    pub max_tokens: Option<u32>,
+
+    /// Seed for reproducible replies, e.g. for regression comparisons (openai and ollama backends)
+    #[arg(long)]
+    pub seed: Option<u64>,
+
+    /// Number of words per chunk [default: what fits the context window, or 2000]
+    #[arg(long, value_parser = positive)]
+    pub chunk_size: Option<usize>,
+
+    /// Context window of the model in tokens, which chunk sizes and reduce batches are fitted to [default: asked from the backend]
+    #[arg(long, value_name = "TOKENS", value_parser = positive)]
+    pub context_window: Option<usize>,
+
+    /// Split by tokens instead of words, with at most this many tokens per chunk
+    #[arg(long, value_parser = positive)]
+    pub max_tokens_per_chunk: Option<usize>,
+
+    /// Where chunks end: near a sentence end (or subtitle cue end), exactly at the budget, or where the topic changes [default: sentence]
+    #[arg(long, value_enum)]
+    pub split: Option<Split>,
+
+    /// Chunk subtitle files into windows of this many minutes, each labelled with its time range
+    #[arg(long, value_parser = positive, value_name = "N")]
+    pub chunk_minutes: Option<usize>,
+
+    /// Number of words (or tokens, with --max-tokens-per-chunk) shared between consecutive chunks [default: a tenth of the chunk with a known context window, or 200]
+    #[arg(long)]
+    pub chunk_overlap: Option<usize>,
+
+    /// Retries per LLM request before giving up [default: 3]
+    #[arg(long)]
+    pub max_retries: Option<u32>,
+
+    /// Cancel an LLM request that takes longer than this and retry it [default: no limit]
+    #[arg(long, value_name = "SECS", value_parser = positive_u64)]
+    pub request_timeout: Option<u64>,
+
+    /// Stop a summary that takes longer than this, saving the finished chunks like Ctrl+C does [default: no limit]
+    #[arg(long, value_name = "SECS", value_parser = positive_u64)]
+    pub total_timeout: Option<u64>,
+
+    /// Send at most this many LLM requests per minute, waiting for a turn when over it
+    #[arg(long, value_name = "N", value_parser = positive_u32)]
+    pub rpm: Option<u32>,
+
+    /// Send at most this many tokens (prompts and replies) per minute
+    #[arg(long, value_name = "N", value_parser = positive_u32)]
+    pub tpm: Option<u32>,
+
+    /// Price in dollars per 1000 tokens, for the cost estimate in the token usage report
+    #[arg(long, value_name = "USD")]
+    pub price_per_1k: Option<f64>,
+
+    /// Maximum number of chunks summarized in parallel, across all the files of --jobs [default: 1]
+    #[arg(short = 'j', long, value_parser = positive)]
+    pub concurrency: Option<usize>,
+
+    /// Send every request again instead of reusing replies from ~/.cache/srt-bullet-summarizer
+    #[arg(long)]
+    pub no_cache: bool,
+
+    /// Record the summary in the history database, for `history list` and `history show`
+    #[arg(long)]
+    pub history: bool,
+
+    /// POST every written summary as JSON to this URL, e.g. an n8n or Zapier webhook
+    #[arg(long, value_name = "URL", env = "SRT_SUMMARIZER_WEBHOOK")]
+    pub webhook: Option<String>,
+
+    /// Sign webhook deliveries with an HMAC-SHA256 of the body under this secret [default: $SRT_SUMMARIZER_WEBHOOK_SECRET]
+    #[arg(long, value_name = "SECRET")]
+    pub webhook_secret: Option<String>,
+
+    /// Post every written summary to a Slack or Discord incoming webhook, split into several messages when long; repeatable
+    #[arg(long, value_name = "PLATFORM:URL", value_parser = post_target)]
+    pub post: Vec<String>,
+
+    /// Token budget of the summaries combined in one request; longer map output is reduced in batches first [default: what fits the context window, or 6000]
+    #[arg(long, value_parser = positive)]
+    pub max_reduce_tokens: Option<usize>,
+
+    /// Combine the map output even when the input fits in one chunk, instead of summarizing it in one request
+    #[arg(long)]
+    pub always_reduce: bool,
+
+    /// Keep a final reply without bullet points instead of asking the model once more
+    #[arg(long)]
+    pub no_bullet_retry: bool,
+
+    /// Combine the chunk summaries as written, keeping the bullets a chunk repeats from the one before it
+    #[arg(long)]
+    pub no_dedup: bool,
+
+    /// Leave out chunks whose request still fails after the retries instead of stopping, and list them at the end
+    #[arg(long)]
+    pub skip_failed_chunks: bool,
+
+    /// What to write: bullet-point summary, meeting minutes with decisions, action items, open questions and discussion points, an action-items checklist, qa study questions with answers, an outline of topics with nested sub-points, podcast shownotes with a description, chapters, key quotes and mentions, or lecture notes with key concepts, main points, examples and further questions [default: summary]
+    #[arg(long, value_enum)]
+    pub mode: Option<Mode>,
+
+    /// How long the final summary should be: short (at most 5 bullets), medium (10) or long (20)
+    #[arg(long, value_enum)]
+    pub length: Option<Length>,
+
+    /// Most bullets the final summary may have; a longer reply is condensed once, then truncated
+    #[arg(long, value_parser = positive, value_name = "N")]
+    pub max_bullets: Option<usize>,
+
+    /// Give the final summary a second pass that merges duplicate bullets, orders them and tightens the wording
+    #[arg(long)]
+    pub polish: bool,
+
+    /// Run the final combine step N times at a temperature above 0 and merge the replies into a consensus summary; steadies small models whose summaries vary from run to run [default: 1]
+    #[arg(long, value_parser = positive, value_name = "N")]
+    pub samples: Option<usize>,
+
+    /// Number of question and answer pairs written by --mode qa [default: 10]
+    #[arg(long, value_parser = positive, value_name = "N")]
+    pub questions: Option<usize>,
+
+    /// How chunk summaries become one summary: map-reduce, or refine a running summary chunk by chunk [default: map-reduce]
+    #[arg(long, value_enum)]
+    pub strategy: Option<Strategy>,
+
+    /// Language of the summary, e.g. ar, de or Arabic; replies in another language are retried once [default: the language of the input]
+    #[arg(long)]
+    pub lang: Option<String>,
+
+    /// Only summarize content relevant to this topic, e.g. "pricing and licensing"
+    #[arg(long, value_name = "QUERY")]
+    pub focus: Option<String>,
+
+    /// Translate the final summary into this language as a last step, e.g. en, ar or German
+    #[arg(long, value_name = "LANG")]
+    pub translate: Option<String>,
+
+    /// With --translate, also write the translated cleaned transcript to <input stem>_transcript_<language>.txt
+    #[arg(long, requires = "translate")]
+    pub translate_transcript: bool,
+
+    /// File with the prompt applied to each chunk; must contain {text}
+    #[arg(long, value_name = "FILE")]
+    pub map_prompt: Option<PathBuf>,
+
+    /// File with the prompt combining the chunk summaries; must contain {text}
+    #[arg(long, value_name = "FILE")]
+    pub combine_prompt: Option<PathBuf>,
+
+    /// File with the refine strategy's prompt; must contain {summary} and {text}
+    #[arg(long, value_name = "FILE")]
+    pub refine_prompt: Option<PathBuf>,
+
+    /// Output format [default: txt]
+    #[arg(long, value_enum)]
+    pub format: Option<OutputFormat>,
+
+    /// Obsidian vault folder that `--format obsidian` notes are written to when --output-dir isn't given
+    #[arg(long, value_name = "DIR")]
+    pub vault: Option<PathBuf>,
+
+    /// Video that the chapter timestamps of `--format html` pages link to with `#t=` fragments
+    #[arg(long, value_name = "URL")]
+    pub video_url: Option<String>,
+
+    /// Anki deck that `--format anki` flashcards are imported into
+    #[arg(long, value_name = "NAME")]
+    pub deck_name: Option<String>,
+
+    /// Start every bullet with the time range of its source material, e.g. [00:14:10–00:18:42] (subtitle files only)
+    #[arg(long)]
+    pub timestamps: bool,
+
+    /// Write one section per speaker on what they said or committed to, from `NAME:` prefixes or VTT <v Name> tags
+    #[arg(long)]
+    pub by_speaker: bool,
+
+    /// Keep sound descriptions such as [music], (laughter) and ♪ lyric lines in subtitle text
+    #[arg(long)]
+    pub keep_sfx: bool,
+}
+
+impl SettingsArgs {
+    /// These flags over the config file, over the built-in defaults
+    pub fn resolve(&self) -> Result<Settings, Box<dyn Error>> {
+        let file_layer = SettingsLayer::load(self.config.as_deref())?;
+        Settings::resolve(self.settings_layer()?.or(file_layer))
+    }
+
+    /// Like `resolve`, but fitting the chunks and reduce batches to the context window of the
+    /// model, which the backend is asked for unless --context-window gives it
+    pub async fn resolve_sized(&self) -> Result<Settings, Box<dyn Error>> {
+        let layer = self.settings_layer()?.or(SettingsLayer::load(self.config.as_deref())?);
+        let settings = Settings::resolve(layer.clone())?;
+        let sized = (layer.chunk_size.is_some() || layer.max_tokens_per_chunk.is_some())
+            && layer.chunk_overlap.is_some()
+            && layer.max_reduce_tokens.is_some();
+        if settings.context_window.is_some() || sized {
+            return Ok(settings);
+        }
+        let Some(window) = backend::context_window(&settings).await else {
+            debug!("The context window of {} is unknown; keeping the fixed chunk sizes", settings.model);
+            return Ok(settings);
+        };
+        let settings = Settings::resolve(SettingsLayer {
+            context_window: Some(window),
+            ..layer
+        })?;
+        let chunks = match settings.max_tokens_per_chunk {
+            Some(max_tokens) => format!("{} tokens", max_tokens),
+            None => format!("{} words", settings.chunk_size),
+        };
+        info!(
+            "Context window of {}: {} tokens; chunks of {}, reduce batches of {} tokens",
+            settings.model, window, chunks, settings.max_reduce_tokens
+        );
+        Ok(settings)
+    }
+
+    /// The settings given on the command line (or through environment variables)
+    pub fn settings_layer(&self) -> Result<SettingsLayer, Box<dyn Error>> {
+        Ok(SettingsLayer {
+            backend: self.backend,
+            model: self.model.clone(),
+            map_model: self.map_model.clone(),
+            combine_model: self.combine_model.clone(),
+            embedding_model: self.embedding_model.clone(),
+            api_base: self.api_base.clone(),
+            api_key: self.api_key.clone(),
+            keep_alive: self.keep_alive.clone(),
+            deployment: self.deployment.clone(),
+            api_version: self.api_version.clone(),
+            model_path: self.model_path.clone(),
+            tokenizer: self.tokenizer.clone(),
+            // A bare flag can only switch it on; leave the config file in charge otherwise
+            auto_pull: self.auto_pull.then_some(true),
+            transcriber: self.transcriber,
+            transcribe_api_base: self.transcribe_api_base.clone(),
+            transcribe_model: self.transcribe_model.clone(),
+            transcribe_api_key: self.transcribe_api_key.clone(),
+            fallback: self.fallback,
+            fallback_model: self.fallback_model.clone(),
+            fallback_api_base: self.fallback_api_base.clone(),
+            fallback_api_key: self.fallback_api_key.clone(),
+            temperature: self.temperature,
+            top_p: self.top_p,
+            max_tokens: self.max_tokens,
+            seed: self.seed,
+            chunk_size: self.chunk_size,
+            chunk_overlap: self.chunk_overlap,
+            max_tokens_per_chunk: self.max_tokens_per_chunk,
+            context_window: self.context_window,
+            split: self.split,
+            chunk_minutes: self.chunk_minutes,
+            concurrency: self.concurrency,
+            cache: self.no_cache.then_some(false),
+            history: self.history.then_some(true),
+            webhook: self.webhook.clone(),
+            webhook_secret: self.webhook_secret.clone(),
+            post: (!self.post.is_empty()).then(|| self.post.clone()),
+            max_reduce_tokens: self.max_reduce_tokens,
+            always_reduce: self.always_reduce.then_some(true),
+            bullet_retry: self.no_bullet_retry.then_some(false),
+            dedup: self.no_dedup.then_some(false),
+            skip_failed_chunks: self.skip_failed_chunks.then_some(true),
+            max_retries: self.max_retries,
+            request_timeout: self.request_timeout,
+            total_timeout: self.total_timeout,
+            rpm: self.rpm,
+            tpm: self.tpm,
+            price_per_1k: self.price_per_1k,
+            strategy: self.strategy,
+            mode: self.mode,
+            length: self.length,
+            max_bullets: self.max_bullets,
+            polish: self.polish.then_some(true),
+            samples: self.samples,
+            questions: self.questions,
+            format: self.format,
+            vault: self.vault.clone(),
+            video_url: self.video_url.clone(),
+            deck_name: self.deck_name.clone(),
+            timestamps: self.timestamps.then_some(true),
+            by_speaker: self.by_speaker.then_some(true),
+            keep_sfx: self.keep_sfx.then_some(true),
+            lang: self.lang.clone(),
+            focus: self.focus.clone(),
+            translate: self.translate.clone(),
+            translate_transcript: self.translate_transcript.then_some(true),
+            map_prompt: read_prompt(self.map_prompt.as_deref(), &["{text}"])?,
+            combine_prompt: read_prompt(self.combine_prompt.as_deref(), &["{text}"])?,
+            refine_prompt: read_prompt(self.refine_prompt.as_deref(), &["{summary}", "{text}"])?,
+            ..SettingsLayer::default()
+        })
+    }
+}
+
+#[derive(Debug, Args)]
+pub struct CompareArgs {
+    /// First input, e.g. last week's meeting or the earlier draft of a talk
+    pub a: PathBuf,
+
+    /// Second input, compared against the first
+    pub b: PathBuf,
+
+    /// Where to write the report, or - for stdout [default: <a stem>_vs_<b stem>.txt next to the first input]
+    #[arg(short, long)]
+    pub output: Option<PathBuf>,
+
+    /// Overwrite a report that already exists instead of refusing to
+    #[arg(short, long)]
+    pub force: bool,
+
+    /// Subtitle language to fetch for video URLs, e.g. en or de [default: the video's language, else en]
+    #[arg(long)]
+    pub sub_lang: Option<String>,
+
+    #[command(flatten)]
+    pub settings: SettingsArgs,
+}
+
+#[derive(Debug, Args)]
+pub struct AskArgs {
+    /// Transcript to answer from: a subtitle, text, document or audio file
+    pub file: PathBuf,
+
+    /// The question, e.g. "What did they decide about pricing?"
+    pub question: String,
+
+    /// How many chunks of the transcript to answer from, the ones closest to the question
+    #[arg(short = 'k', long, default_value_t = ask::DEFAULT_TOP_K, value_parser = positive)]
+    pub top_k: usize,
+
+    /// Write the answer to a file instead of stdout
+    #[arg(short, long)]
+    pub output: Option<PathBuf>,
+
+    #[command(flatten)]
+    pub settings: SettingsArgs,
+}
+
+#[derive(Debug, Args)]
+pub struct RollupArgs {
+    /// Directory with the episode summaries (<stem>_summary.txt, .md or .json) written by earlier runs
+    pub dir: PathBuf,
+
+    /// Where to write the overview, or - for stdout [default: <directory name>_rollup_summary.txt in the directory]
+    #[arg(short, long)]
+    pub output: Option<PathBuf>,
+
+    /// Overwrite an overview that already exists instead of refusing to
+    #[arg(short, long)]
+    pub force: bool,
+
+    /// Also read the summaries in subdirectories, e.g. one per season
+    #[arg(short, long)]
+    pub recursive: bool,
+
+    #[command(flatten)]
+    pub settings: SettingsArgs,
+}
+
+#[derive(Debug, Args)]
+pub struct ServeArgs {
+    /// Address to listen on
+    #[arg(long, default_value = "127.0.0.1:8080", env = "SRT_SUMMARIZER_ADDR")]
+    pub addr: SocketAddr,
+
+    /// Also add YouTube chapter markers to the responses for timed subtitles
+    #[arg(long)]
+    pub chapters: bool,
+
+    /// Also add the key topics of every summary to the responses
+    #[arg(long)]
+    pub with_keywords: bool,
+
+    /// Also add the people, organizations, products, and numbers and dates mentioned to the responses
+    #[arg(long)]
+    pub with_entities: bool,
+
+    /// Also add a generated title to the responses
+    #[arg(long)]
+    pub with_title: bool,
+
+    /// Also add a one-sentence TL;DR to the responses
+    #[arg(long)]
+    pub with_tldr: bool,
+
+    #[command(flatten)]
+    pub settings: SettingsArgs,
+}
+
+#[derive(Debug, Args)]
+pub struct McpArgs {
+    /// Also add YouTube chapter markers to the summaries of timed subtitles
+    #[arg(long)]
+    pub chapters: bool,
+
+    /// Also add the key topics of every summary
+    #[arg(long)]
+    pub with_keywords: bool,
+
+    /// Also add the people, organizations, products, and numbers and dates mentioned
+    #[arg(long)]
+    pub with_entities: bool,
+
+    /// Also add a generated title
+    #[arg(long)]
+    pub with_title: bool,
+
+    /// Also add a one-sentence TL;DR
+    #[arg(long)]
+    pub with_tldr: bool,
+
+    #[command(flatten)]
+    pub settings: SettingsArgs,
+}
+
+#[derive(Debug, Args)]
+pub struct WatchArgs {
+    /// Directory to watch for new .srt, .vtt, .ass, .ssa, .txt, .md, .pdf and .epub files and .mp3/.wav/.m4a/.flac/.ogg recordings
+    pub dir: PathBuf,
+
+    /// Write summaries to this directory (created if missing) instead of next to each input
+    #[arg(long)]
+    pub output_dir: Option<PathBuf>,
+
+    /// Summarize files again when they change, overwriting their earlier summary
+    #[arg(short, long)]
+    pub force: bool,
+
+    /// When a subtitle file grows, summarize only its new cues and merge them into its summary; implies --force
+    #[arg(long)]
+    pub incremental: bool,
+
+    /// Also watch subdirectories
+    #[arg(short, long)]
+    pub recursive: bool,
+
+    /// Also write YouTube chapter markers for timed subtitles
+    #[arg(long)]
+    pub chapters: bool,
+
+    /// Add a "Topics:" line with the key terms of every summary
+    #[arg(long)]
+    pub with_keywords: bool,
+
+    /// List the people, organizations, products, and numbers and dates mentioned below every summary
+    #[arg(long)]
+    pub with_entities: bool,
+
+    /// Add a generated title above every summary
+    #[arg(long)]
+    pub with_title: bool,
+
+    /// Add a one-sentence TL;DR above every summary
+    #[arg(long)]
+    pub with_tldr: bool,
+
+    #[command(flatten)]
+    pub settings: SettingsArgs,
+}
+
+#[derive(Debug, Args)]
+pub struct FetchArgs {
+    /// Video, playlist or channel URLs
+    #[arg(required = true)]
+    pub urls: Vec<String>,
+
+    /// Download the audio and transcribe it instead of fetching subtitles; videos without subtitles get this anyway
+    #[arg(long)]
+    pub audio: bool,
+
+    /// Subtitle language to fetch, e.g. en or de [default: the video's language, else en]
+    #[arg(long)]
+    pub sub_lang: Option<String>,
+
+    /// Only the first N videos of a playlist or channel
+    #[arg(long, value_name = "N", value_parser = positive)]
+    pub limit: Option<usize>,
+
+    /// Directory to save the subtitles (or audio) and summaries in, created if missing [default: the current directory]
+    #[arg(long)]
+    pub output_dir: Option<PathBuf>,
+
+    /// Summarize videos again whose summary already exists, instead of skipping them
+    #[arg(short, long)]
+    pub force: bool,
+
+    /// Also write YouTube chapter markers for every video
+    #[arg(long)]
+    pub chapters: bool,
+
+    /// Add a "Topics:" line with the key terms of every summary
+    #[arg(long)]
+    pub with_keywords: bool,
+
+    /// List the people, organizations, products, and numbers and dates mentioned below every summary
+    #[arg(long)]
+    pub with_entities: bool,
+
+    /// Add a generated title above every summary
+    #[arg(long)]
+    pub with_title: bool,
+
+    /// Add a one-sentence TL;DR above every summary
+    #[arg(long)]
+    pub with_tldr: bool,
+
+    #[command(flatten)]
+    pub settings: SettingsArgs,
+}
+
+#[derive(Debug, Args)]
+pub struct DoctorArgs {
+    #[command(flatten)]
+    pub settings: SettingsArgs,
+}
+
+#[derive(Debug, Args)]
+pub struct ConvertArgs {
+    /// Input subtitle file (.srt, .vtt, .ass, .ssa or Whisper .json)
+    pub input: PathBuf,
+
+    /// Where to write the text [default: <input stem>_transcript.txt next to the input]
+    #[arg(short, long)]
+    pub output: Option<PathBuf>,
+
+    /// Keep sound descriptions such as [music], (laughter) and ♪ lyric lines
+    #[arg(long)]
+    pub keep_sfx: bool,
+}
+
+/// Read a prompt template file, checking it has the placeholders the prompt is rendered with
+fn read_prompt(path: Option<&Path>, placeholders: &[&str]) -> Result<Option<String>, Box<dyn Error>> {
+    let Some(path) = path else {
+        return Ok(None);
+    };
+    let template = fs::read_to_string(path)
+        .map_err(|e| format!("Cannot read prompt file {:?}: {}", path, e))?;
+    if let Some(missing) = placeholders.iter().find(|p| !template.contains(**p)) {
+        return Err(format!("Prompt file {:?} must contain the {} placeholder", path, missing).into());
+    }
+    Ok(Some(template))
+}
+
+/// Parse a `--from`/`--to` time
+fn clock(s: &str) -> Result<Duration, String> {
+    parser::parse_clock(s).ok_or_else(|| "expected [HH:]MM:SS, e.g. 00:15:00".to_string())
+}
+
+/// Check a `--post` target is `slack:<url>` or `discord:<url>`
+fn post_target(s: &str) -> Result<String, String> {
+    post::Target::parse(s).map(|_| s.to_string())
+}
+
+/// Parse an integer that must be at least 1
+fn positive(s: &str) -> Result<usize, String> {
+    match s.parse::<usize>() {
+        Ok(0) => Err("must be at least 1".to_string()),
+        Ok(n) => Ok(n),
+        Err(e) => Err(e.to_string()),
+    }
+}
+
+/// Parse a token count that must be at least 1
+fn positive_u32(s: &str) -> Result<u32, String> {
+    positive(s).and_then(|n| u32::try_from(n).map_err(|e| e.to_string()))
+}
+
+/// Parse a number of seconds that must be at least 1
+fn positive_u64(s: &str) -> Result<u64, String> {
+    positive(s).map(|n| n as u64)
+}
@@ -0,0 +1,148 @@
+// Difference reports written by `compare`.
+//
+// Both inputs are summarized as usual, then their final summaries go to the model in one more
+// request as summary A and summary B. The reply lists what only one of them covers and the
+// decisions that changed between them, under headings matched loosely (`**Only in B:**` and
+// `## Topics only in summary B` both count), and is rewritten under headings naming the inputs.
+
+pub const COMPARE_TEMPLATE: &str = r#"Compare these two summaries, e.g. of two weekly meetings or two drafts of a talk. Summary A is of {name_a} and summary B of {name_b}.
+List the differences under these three headings, as '-' bullet points:
+## Only in A
+Topics, points and facts summary A covers that summary B does not mention
+## Only in B
+Topics, points and facts summary B covers that summary A does not mention
+## Changed decisions
+Decisions, plans, numbers, owners or positions both mention but that differ, naming the summaries by their inputs, e.g. "Launch date: May in {name_a}, June in {name_b}"
+Leave out what both say the same way, and write "- None" under a heading with nothing to list.
+Summary A:
+{a}
+
+Summary B:
+{b}
+
+DIFFERENCES:"#;
+
+/// The differences between two summaries
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Comparison {
+    /// Names of the two inputs, e.g. their file names
+    pub names: (String, String),
+    pub only_a: Vec<String>,
+    pub only_b: Vec<String>,
+    /// Decisions, plans and figures both mention but that differ
+    pub changed: Vec<String>,
+}
+
+impl Comparison {
+    pub fn is_empty(&self) -> bool {
+        self.only_a.is_empty() && self.only_b.is_empty() && self.changed.is_empty()
+    }
+}
+
+#[derive(Clone, Copy)]
+enum Section {
+    OnlyA,
+    OnlyB,
+    Changed,
+}
+
+/// The compare prompt for the final summaries `a` and `b` of the inputs `names`
+pub fn prompt(a: &str, b: &str, names: &(String, String)) -> String {
+    COMPARE_TEMPLATE
+        .replace("{name_a}", &names.0)
+        .replace("{name_b}", &names.1)
+        .replace("{a}", a.trim())
+        .replace("{b}", b.trim())
+}
+
+/// Extract the differences of a model reply; bullets before the first heading and `- None`
+/// placeholders are dropped
+pub fn parse(reply: &str, names: (String, String)) -> Comparison {
+    let mut comparison = Comparison {
+        names,
+        ..Comparison::default()
+    };
+    let mut section = None;
+    for line in reply.lines() {
+        let line = line.trim();
+        if line.is_empty() {
+            continue;
+        }
+        if let Some(found) = heading(line) {
+            section = Some(found);
+            continue;
+        }
+        // Items under any other heading are not differences
+        if line.starts_with('#') {
+            section = None;
+            continue;
+        }
+        let Some(item) = line.strip_prefix(['-', '*', '•']).map(str::trim) else {
+            // A wrapped line continues the item above it
+            if let Some(last) = section.and_then(|section| items(&mut comparison, section).last_mut()) {
+                last.push(' ');
+                last.push_str(line);
+            }
+            continue;
+        };
+        let Some(section) = section else {
+            continue;
+        };
+        let items = items(&mut comparison, section);
+        if !item.is_empty() && !item.eq_ignore_ascii_case("none") && !items.iter().any(|known| known == item) {
+            items.push(item.to_string());
+        }
+    }
+    comparison
+}
+
+/// A `# A vs B` title and the three sections under headings naming the inputs, `- None` under
+/// an empty one
+pub fn render(comparison: &Comparison) -> String {
+    let (a, b) = &comparison.names;
+    let list = |items: &[String]| match items {
+        [] => "- None".to_string(),
+        items => items.iter().map(|item| format!("- {}", item)).collect::<Vec<_>>().join("\n"),
+    };
+    format!(
+        "# {} vs {}\n\n## Only in {}\n{}\n\n## Only in {}\n{}\n\n## Changed decisions\n{}\n",
+        a,
+        b,
+        a,
+        list(&comparison.only_a),
+        b,
+        list(&comparison.only_b),
+        list(&comparison.changed)
+    )
+}
+
+fn items(comparison: &mut Comparison, section: Section) -> &mut Vec<String> {
+    match section {
+        Section::OnlyA => &mut comparison.only_a,
+        Section::OnlyB => &mut comparison.only_b,
+        Section::Changed => &mut comparison.changed,
+    }
+}
+
+/// The section a `## Heading`, `**Heading**` or `Heading:` line starts
+fn heading(line: &str) -> Option<Section> {
+    let text = if let Some(heading) = line.strip_prefix('#') {
+        heading
+    } else if (line.starts_with("**") && line.ends_with("**") || line.ends_with(':')) && line.split_whitespace().count() <= 6 {
+        line
+    } else {
+        return None;
+    };
+    let text = text.trim_matches(['#', '*', ':', ' ']).to_lowercase();
+    let words: Vec<&str> = text.split(|c: char| !c.is_alphanumeric()).filter(|word| !word.is_empty()).collect();
+    let only = words.iter().any(|word| ["only", "missing", "unique"].contains(word));
+    if words.iter().any(|word| word.starts_with("chang") || word.starts_with("decision")) {
+        Some(Section::Changed)
+    } else if only && (words.contains(&"b") || words.contains(&"second")) {
+        Some(Section::OnlyB)
+    } else if only && (words.contains(&"a") || words.contains(&"first")) {
+        Some(Section::OnlyA)
+    } else {
+        None
+    }
+}
@@ -0,0 +1,1218 @@
+// Configuration file support.
+//
+// Settings are resolved in this order, first match wins:
+// command-line flag > environment variable > config file > built-in default.
+// The config file is `$XDG_CONFIG_HOME/srt-bullet-summarizer/config.toml`
+// (falling back to `~/.config/...`) unless `--config` points elsewhere.
+
+use clap::ValueEnum;
+use serde::{Deserialize, Serialize};
+use std::{
+    env,
+    error::Error,
+    fmt, fs,
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use crate::{
+    backend::Sampling,
+    cache::CacheLimits,
+    error::SummarizerError,
+    language::Language,
+    post,
+    qa,
+    retry::RetryPolicy,
+    summarizer::{default_templates, REFINE_TEMPLATE},
+};
+
+pub const DEFAULT_MODEL: &str = "llama3.2";
+pub const DEFAULT_API_BASE: &str = "http://localhost:11434/v1";
+pub const DEFAULT_OLLAMA_API_BASE: &str = "http://localhost:11434";
+pub const DEFAULT_ANTHROPIC_API_BASE: &str = "https://api.anthropic.com";
+pub const DEFAULT_ANTHROPIC_MODEL: &str = "claude-3-5-haiku-latest";
+pub const DEFAULT_GEMINI_API_BASE: &str = "https://generativelanguage.googleapis.com";
+pub const DEFAULT_GEMINI_MODEL: &str = "gemini-2.0-flash";
+pub const DEFAULT_AZURE_MODEL: &str = "gpt-4o-mini";
+pub const DEFAULT_EMBEDDING_MODEL: &str = "nomic-embed-text";
+pub const DEFAULT_GEMINI_EMBEDDING_MODEL: &str = "text-embedding-004";
+pub const DEFAULT_AZURE_API_VERSION: &str = "2024-10-21";
+pub const DEFAULT_WHISPER_CPP_API_BASE: &str = "http://127.0.0.1:8080";
+pub const DEFAULT_TRANSCRIBE_API_BASE: &str = "https://api.openai.com/v1";
+pub const DEFAULT_TRANSCRIBE_MODEL: &str = "whisper-1";
+pub const DEFAULT_CHUNK_SIZE: usize = 2000;
+pub const DEFAULT_CHUNK_OVERLAP: usize = 200;
+pub const DEFAULT_CONCURRENCY: usize = 1;
+pub const DEFAULT_MAX_REDUCE_TOKENS: usize = 6000;
+pub const DEFAULT_STREAM_ABOVE_MB: u64 = 64;
+
+/// Tokens of a prompt template besides the text filled into it, reserved when sizing chunks for
+/// a context window
+const PROMPT_OVERHEAD_TOKENS: usize = 500;
+/// Tokens reserved for the reply when `max_tokens` doesn't say
+const REPLY_TOKENS: usize = 1024;
+/// Chunks sized for a context window stay at most this big, as the summary of a huge chunk
+/// loses detail
+const MAX_SIZED_CHUNK_TOKENS: usize = 8000;
+/// Reduce batches sized for a context window stay at most this big
+const MAX_SIZED_REDUCE_TOKENS: usize = 32000;
+/// Words per token of English text, for chunk sizes counted in words
+const WORDS_PER_TOKEN: f64 = 0.75;
+pub const DEFAULT_MAX_RETRIES: u32 = 3;
+pub const DEFAULT_RETRY_BASE_DELAY_MS: u64 = 1000;
+
+/// Commented template written by `config init`
+pub const DEFAULT_CONFIG: &str = r#"# srt-bullet-summarizer configuration
+#
+# Every setting is optional. Command-line flags and environment variables override these values.
+
+# Backend: "openai" (any OpenAI-compatible API), "ollama" (Ollama's native API),
+# "anthropic" (Anthropic Messages API), "gemini" (Google Gemini API), "azure" (Azure OpenAI) or
+# "local" (a GGUF model run in-process; needs a build with `--features local`)
+# backend = "openai"
+
+# Model name to request from the API
+# model = "llama3.2"
+
+# A small, fast model for the chunk summaries and a stronger one for every step after them
+# (combining, chapters, topics, ...); each defaults to model
+# map_model = "llama3.2:1b"
+# combine_model = "llama3.3"
+
+# Model embedding the chunks `ask` retrieves and the passages of split = "semantic" (defaults to
+# nomic-embed-text, or text-embedding-004 for the gemini backend; hosted OpenAI needs e.g.
+# text-embedding-3-small)
+# embedding_model = "nomic-embed-text"
+
+# Base URL of the API (defaults to http://localhost:11434 for the ollama backend,
+# https://api.anthropic.com for the anthropic backend and
+# https://generativelanguage.googleapis.com for the gemini backend); the azure backend needs
+# the endpoint of its resource, e.g. https://my-resource.openai.azure.com
+# api_base = "http://localhost:11434/v1"
+
+# Ollama backend only: how long the model stays loaded after a request, and whether to
+# download the model when it isn't installed
+# keep_alive = "10m"
+# auto_pull = false
+
+# Azure backend only: the deployment serving model (defaults to the model name; map_model and
+# combine_model name deployments too), and the API version requested
+# deployment = "gpt-4o-mini"
+# api_version = "2024-10-21"
+
+# Local backend only: the GGUF model file (model defaults to its name; map_model and
+# combine_model may name other .gguf files), and a tokenizer.json for models whose vocabulary the
+# GGUF file doesn't describe well enough (defaults to a tokenizer.json next to the model)
+# model_path = "~/models/Llama-3.2-3B-Instruct-Q4_K_M.gguf"
+# tokenizer = "~/models/tokenizer.json"
+
+# API key for hosted endpoints (local servers like Ollama ignore it); falls back to
+# OPENAI_API_KEY, ANTHROPIC_API_KEY, GEMINI_API_KEY or AZURE_OPENAI_API_KEY depending on the
+# backend
+# api_key = "sk-..."
+
+# Backend that a request goes to when the primary one is down or keeps failing after its
+# retries, e.g. a hosted API behind a local Ollama. Its model and base URL default like those of
+# backend, and the key falls back to the API key variable of the fallback backend
+# fallback = "openai"
+# fallback_model = "gpt-4o-mini"
+# fallback_api_base = "https://api.openai.com/v1"
+# fallback_api_key = "sk-..."
+
+# Audio inputs (.mp3, .wav, .m4a, .flac, .ogg) are transcribed first, by a whisper.cpp server
+# ("whisper-cpp", its /inference endpoint) or an OpenAI-compatible /audio/transcriptions endpoint
+# ("openai"). transcribe_api_base defaults to http://127.0.0.1:8080 for whisper-cpp and
+# https://api.openai.com/v1 for openai; the model is only sent to openai endpoints, and the key
+# falls back to OPENAI_API_KEY
+# transcriber = "whisper-cpp"
+# transcribe_api_base = "http://127.0.0.1:8080"
+# transcribe_model = "whisper-1"
+# transcribe_api_key = "sk-..."
+
+# Sampling parameters sent with every request (the backend's defaults when unset): temperature
+# (0-2), nucleus sampling top_p (0-1), the most tokens of one reply, and a seed for reproducible
+# runs (openai and ollama backends; the anthropic API has no seed)
+# temperature = 0.2
+# top_p = 0.9
+# max_tokens = 1024
+# seed = 42
+
+# Context window of the model in tokens. When it is known, chunk_size, chunk_overlap and
+# max_reduce_tokens default to what fits in it, leaving room for the prompt and the reply;
+# without this key the backend is asked (Ollama's /api/show, vLLM's and LM Studio's /models,
+# llama.cpp's /props, Gemini's model info, or a table of hosted models)
+# context_window = 8192
+
+# Number of words per chunk, and words shared between consecutive chunks (without a known
+# context window: 2000 and 200)
+# chunk_size = 2000
+# chunk_overlap = 200
+
+# Split by tokens instead of words; chunk_overlap then counts tokens too
+# max_tokens_per_chunk = 3000
+
+# Where chunks end: "sentence" (at a sentence end, or a subtitle cue end, near the end of the
+# budget; a sentence is only cut when it is too long), "word" (exactly at the budget) or
+# "semantic" (where the topic changes, found by embedding passages with embedding_model)
+# split = "sentence"
+
+# Chunk subtitle files by time instead: one chunk per window of this many minutes
+# (00:00-00:10, 00:10-00:20, ...), labelled with its time range; chunk_size, chunk_overlap and
+# split then only apply to files without timings
+# chunk_minutes = 10
+
+# Maximum number of chunks summarized in parallel
+# concurrency = 1
+
+# Keep the model's replies in ~/.cache/srt-bullet-summarizer/responses (keyed by model,
+# sampling parameters and prompt), so a re-run only pays for requests whose text or prompt
+# changed; `cache stats` and `cache clear` show and empty it
+# cache = true
+
+# Bound the cache: replies older than this many days are ignored, and the oldest ones are removed
+# while it is larger than this many megabytes; both are pruned when a run starts (unbounded when
+# unset)
+# cache_ttl_days = 30
+# cache_max_mb = 500
+
+# Plain-text files larger than this many megabytes are read a chunk at a time while they are
+# summarized, instead of all at once (0 reads every plain-text file that way)
+# stream_above_mb = 64
+
+# Record every summary (input, content hash, model, mode, tokens and the summary itself) in
+# ~/.local/share/srt-bullet-summarizer/history.sqlite3, for `history list` and `history show`
+# history = false
+
+# POST every written summary as JSON to this URL, e.g. an n8n or Zapier webhook, signed with
+# HMAC-SHA256 of the body under the secret (or $SRT_SUMMARIZER_WEBHOOK_SECRET) when there is one
+# webhook = "https://hooks.example.com/summaries"
+# webhook_secret = "..."
+
+# Post every written summary to Slack or Discord incoming webhooks, formatted for the platform and
+# split into several messages when it is too long for one
+# post = ["slack:https://hooks.slack.com/services/...", "discord:https://discord.com/api/webhooks/..."]
+
+# Token budget for the summaries combined in one request; longer map output is reduced in
+# batches, level by level, until it fits (6000 without a known context window)
+# max_reduce_tokens = 6000
+
+# Combine the map output even when the input fits in one chunk; by default a single chunk is
+# summarized in one request
+# always_reduce = false
+
+# Ask once more when the final reply has no bullet points at all
+# bullet_retry = true
+
+# Drop the bullets a chunk summary repeats from the chunk before it (the overlap between chunks
+# makes them common, nearly word for word) before combining
+# dedup = true
+
+# Leave out chunks whose request still fails after the retries, instead of stopping the run;
+# skipped chunks are listed at the end
+# skip_failed_chunks = false
+
+# Retries per LLM request, and the delay before the first retry (doubled each time, with jitter)
+# max_retries = 3
+# retry_base_delay_ms = 1000
+
+# Seconds one LLM request may take before it is cancelled and retried, and seconds one summary
+# may take before it stops like Ctrl+C would (its checkpoint is kept for --resume); no limit
+# when unset
+# request_timeout = 300
+# total_timeout = 3600
+
+# Most LLM requests and tokens (prompt and reply) per minute, to stay under a provider's rate
+# limits; requests wait for their turn instead of failing with 429 errors
+# rpm = 500
+# tpm = 200000
+
+# Dollars per 1000 tokens; adds an estimated cost to the token usage report printed after
+# every summary
+# price_per_1k = 0.002
+
+# How chunk summaries become one summary: "map-reduce" (summarize chunks independently, then
+# combine) or "refine" (revise a running summary chunk by chunk; slower, often more coherent
+# for narrative content like lectures)
+# strategy = "map-reduce"
+
+# What to write: "summary" (bullet points), "minutes" (decisions, action items with owners,
+# open questions and key discussion points) or "action-items" (a "- [ ] owner: task" checklist
+# of follow-ups only), "qa" (study questions with answers), "outline" (topics with indented
+# sub-points and details), "shownotes" (a podcast episode's description, chapters, key quotes,
+# and links and mentions), "lecture" (key concepts with definitions, main points, examples
+# and further questions) or "topics" (a heading with its start time and bullets per topic,
+# chunked where the topic changes unless split is "word"); selects the default prompts
+# mode = "summary"
+
+# Number of question and answer pairs written by the qa mode
+# questions = 10
+
+# How long the final summary should be: "short" (at most 5 bullets), "medium" (10) or "long"
+# (20); a longer reply is condensed once, then truncated. max_bullets sets the limit directly
+# length = "medium"
+# max_bullets = 8
+
+# Send the final summary back to the model once more, to merge the duplicate and near-duplicate
+# bullets that overlapping chunks leave, order them and tighten their wording
+# polish = false
+
+# Run the final combine step this many times at a temperature above 0 (temperature, or 0.7) and
+# ask the model for the points the replies agree on; steadies small models whose summaries vary
+# from run to run, at the cost of samples + 1 final requests
+# samples = 1
+
+# Output format: "txt", "md", "json", "obsidian" (a Markdown note with YAML front matter
+# whose tags are the summary's key topics), "html" (a standalone page) or "anki" (flashcards
+# from the qa mode's questions, which it selects, to import into Anki)
+# format = "txt"
+
+# Folder in an Obsidian vault where "obsidian" notes go unless --output-dir is given
+# vault = "~/Documents/Notes/Summaries"
+
+# Video that the chapter timestamps of HTML pages link to, as <video_url>#t=<seconds>; video URL
+# inputs link to themselves
+# video_url = "https://www.youtube.com/watch?v=VIDEO_ID"
+
+# Anki deck that "anki" flashcards go into when imported; without it, Anki asks
+# deck_name = "Lectures::Rust"
+
+# Start every bullet with the time range it covers, e.g. "- [00:14:10–00:18:42] ..."
+# (subtitle files only; switches the summary mode's default prompts to timestamp-aware ones)
+# timestamps = false
+
+# One section per speaker on what they said or committed to, from "NAME:" prefixes, VTT
+# <v Name> tags or the ASS Name field (summary mode only; not combinable with timestamps)
+# by_speaker = false
+
+# Keep sound descriptions such as [music], (laughter) and ♪ lyric lines in subtitle text
+# keep_sfx = false
+
+# Language of the summary: an ISO 639-1 code ("ar"), an ISO 639-3 code ("ara") or a name
+# ("Arabic"). Every prompt asks for it, and a final summary in another language is retried once.
+# Without it, the language of each input is detected and used instead.
+# lang = "ar"
+
+# Only summarize what is relevant to this topic; the map and combine prompts are told to leave
+# out unrelated material
+# focus = "pricing and licensing"
+
+# Summarize in the language of the input, then translate the summary into this language as a
+# last step ("en", "ar", "German"); translate_transcript also writes the cleaned transcript,
+# translated chunk by chunk, to <stem>_transcript_<language>.txt
+# translate = "en"
+# translate_transcript = false
+
+# Prompt templates; all of them must contain the {text} placeholder
+# map_prompt = """
+# Write a detailed summary of this text section in bullet points.
+# Use '-' for bullet points and answer only the bullet points.
+# Text:
+# {text}
+#
+# SUMMARY:"""
+# combine_prompt = """
+# Combine these summaries into a final summary in bullet points.
+# Use '-' for bullet points and answer only the bullet points.
+# Text:
+# {text}
+#
+# FINAL SUMMARY:"""
+# Refine strategy only; {summary} is the running summary
+# refine_prompt = """
+# Here is a summary of the beginning of a text in bullet points:
+# {summary}
+#
+# Revise the summary so that it also covers the next section of the text below.
+# Keep the points that are still accurate, and add or merge points for the new material.
+# Use '-' for bullet points and answer only the bullet points.
+# Text:
+# {text}
+#
+# REVISED SUMMARY:"""
+"#;
+
+/// Which API the summarizer talks to
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum BackendKind {
+    /// Any OpenAI-compatible chat completions API
+    #[default]
+    Openai,
+    /// Ollama's native API, with keep_alive and model auto-pull
+    Ollama,
+    /// Anthropic Messages API
+    Anthropic,
+    /// Google Gemini API
+    Gemini,
+    /// Azure OpenAI deployments
+    Azure,
+    /// A GGUF model run in-process, without a server (needs the `local` feature)
+    Local,
+}
+
+impl fmt::Display for BackendKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            BackendKind::Openai => "OpenAI-compatible",
+            BackendKind::Ollama => "Ollama",
+            BackendKind::Anthropic => "Anthropic",
+            BackendKind::Gemini => "Gemini",
+            BackendKind::Azure => "Azure OpenAI",
+            BackendKind::Local => "local GGUF",
+        })
+    }
+}
+
+impl BackendKind {
+    /// Environment variable holding the API key when none is configured
+    pub fn api_key_env(self) -> Option<&'static str> {
+        match self {
+            BackendKind::Openai => Some("OPENAI_API_KEY"),
+            BackendKind::Ollama => None,
+            BackendKind::Anthropic => Some("ANTHROPIC_API_KEY"),
+            BackendKind::Gemini => Some("GEMINI_API_KEY"),
+            BackendKind::Azure => Some("AZURE_OPENAI_API_KEY"),
+            BackendKind::Local => None,
+        }
+    }
+
+    /// The API key from `api_key_env`, when it is set
+    fn env_api_key(self) -> Option<String> {
+        self.api_key_env()
+            .and_then(|name| env::var(name).ok())
+            .filter(|key| !key.is_empty())
+    }
+
+    /// Default base URL and model
+    fn defaults(self) -> (&'static str, &'static str) {
+        match self {
+            BackendKind::Openai => (DEFAULT_API_BASE, DEFAULT_MODEL),
+            BackendKind::Ollama => (DEFAULT_OLLAMA_API_BASE, DEFAULT_MODEL),
+            BackendKind::Anthropic => (DEFAULT_ANTHROPIC_API_BASE, DEFAULT_ANTHROPIC_MODEL),
+            BackendKind::Gemini => (DEFAULT_GEMINI_API_BASE, DEFAULT_GEMINI_MODEL),
+            // Every Azure resource has its own endpoint
+            BackendKind::Azure => ("", DEFAULT_AZURE_MODEL),
+            // The model is a file rather than an API
+            BackendKind::Local => ("", DEFAULT_MODEL),
+        }
+    }
+}
+
+/// Which speech-to-text API transcribes audio inputs
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum TranscriberKind {
+    /// The server of whisper.cpp (`whisper-server`), at its `/inference` endpoint
+    #[default]
+    WhisperCpp,
+    /// Any OpenAI-compatible `/audio/transcriptions` endpoint (OpenAI, faster-whisper-server, LocalAI)
+    Openai,
+}
+
+/// What kind of document to write
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum, Deserialize, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Mode {
+    /// A flat list of bullet points
+    #[default]
+    Summary,
+    /// Meeting minutes: decisions, action items with owners, open questions and discussion points
+    Minutes,
+    /// Only the concrete follow-ups, as a `- [ ] owner: task (due date)` checklist
+    ActionItems,
+    /// Study questions with their answers, for revising a lecture
+    Qa,
+    /// Topics with indented sub-points and details, instead of a flat bullet list
+    Outline,
+    /// Podcast show notes: an episode description, chapters, key quotes, and links and mentions
+    Shownotes,
+    /// Lecture notes: key concepts with definitions, main points, examples and further questions
+    Lecture,
+    /// A heading with its start time and bullets for every topic, for meetings that wander
+    /// between subjects
+    Topics,
+}
+
+impl Mode {
+    /// Whether the final reply is bullet points, which `bullet_retry` and `polish` apply to
+    pub fn is_bulleted(self) -> bool {
+        matches!(self, Mode::Summary | Mode::Minutes | Mode::Outline | Mode::Lecture | Mode::Topics)
+    }
+}
+
+/// How long the final summary should be
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Length {
+    /// Only the most important points, at most 5 bullets
+    Short,
+    /// The main points, at most 10 bullets
+    Medium,
+    /// Every significant point, at most 20 bullets
+    Long,
+}
+
+impl Length {
+    pub fn max_bullets(self) -> usize {
+        match self {
+            Length::Short => 5,
+            Length::Medium => 10,
+            Length::Long => 20,
+        }
+    }
+
+    /// What the combine prompt asks for besides the bullet limit
+    pub fn instruction(self) -> &'static str {
+        match self {
+            Length::Short => "Keep the summary short and cover only the most important points.",
+            Length::Medium => "Cover the main points and leave out minor details.",
+            Length::Long => "Write a detailed summary that covers every significant point.",
+        }
+    }
+}
+
+/// How the per-chunk work is turned into one summary
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Strategy {
+    /// Summarize chunks independently (in parallel), then combine the summaries
+    #[default]
+    MapReduce,
+    /// Summarize the first chunk, then revise the running summary with each following chunk
+    Refine,
+}
+
+/// Where chunks are allowed to end
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Split {
+    /// At sentence ends, or at subtitle cue ends; overlong sentences fall back to word splits
+    #[default]
+    Sentence,
+    /// At the word where the budget runs out
+    Word,
+    /// Where the topic changes: between the sentences or cues whose embeddings are least alike
+    Semantic,
+}
+
+/// Format of the written summary
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum OutputFormat {
+    /// The bullet list as plain text
+    #[default]
+    Txt,
+    /// Markdown with a title and metadata header
+    Md,
+    /// Machine-readable JSON including the per-chunk summaries
+    Json,
+    /// An Obsidian note: Markdown below YAML front matter with the title, date, source, duration
+    /// and the key topics as tags
+    Obsidian,
+    /// A standalone HTML page, with collapsible chapters linking to their time in the video
+    Html,
+    /// Anki flashcards, one tab-separated question and answer per line (qa mode only)
+    Anki,
+}
+
+impl OutputFormat {
+    /// File extension used for default output paths
+    pub fn extension(self) -> &'static str {
+        match self {
+            OutputFormat::Txt => "txt",
+            OutputFormat::Md => "md",
+            OutputFormat::Json => "json",
+            OutputFormat::Obsidian => "md",
+            OutputFormat::Html => "html",
+            OutputFormat::Anki => "tsv",
+        }
+    }
+}
+
+/// Settings from one source (the config file or the command line); every key is optional
+#[derive(Debug, Default, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct SettingsLayer {
+    pub backend: Option<BackendKind>,
+    pub model: Option<String>,
+    pub map_model: Option<String>,
+    pub combine_model: Option<String>,
+    pub embedding_model: Option<String>,
+    pub api_base: Option<String>,
+    pub api_key: Option<String>,
+    pub keep_alive: Option<String>,
+    pub auto_pull: Option<bool>,
+    pub deployment: Option<String>,
+    pub api_version: Option<String>,
+    pub model_path: Option<PathBuf>,
+    pub tokenizer: Option<PathBuf>,
+    pub transcriber: Option<TranscriberKind>,
+    pub transcribe_api_base: Option<String>,
+    pub transcribe_model: Option<String>,
+    pub transcribe_api_key: Option<String>,
+    pub fallback: Option<BackendKind>,
+    pub fallback_model: Option<String>,
+    pub fallback_api_base: Option<String>,
+    pub fallback_api_key: Option<String>,
+    pub temperature: Option<f64>,
+    pub top_p: Option<f64>,
+    pub max_tokens: Option<u32>,
+    pub seed: Option<u64>,
+    pub chunk_size: Option<usize>,
+    pub chunk_overlap: Option<usize>,
+    pub max_tokens_per_chunk: Option<usize>,
+    pub context_window: Option<usize>,
+    pub split: Option<Split>,
+    pub chunk_minutes: Option<usize>,
+    pub concurrency: Option<usize>,
+    pub cache: Option<bool>,
+    pub cache_ttl_days: Option<u64>,
+    pub cache_max_mb: Option<u64>,
+    pub stream_above_mb: Option<u64>,
+    pub history: Option<bool>,
+    pub webhook: Option<String>,
+    pub webhook_secret: Option<String>,
+    pub post: Option<Vec<String>>,
+    pub max_reduce_tokens: Option<usize>,
+    pub always_reduce: Option<bool>,
+    pub bullet_retry: Option<bool>,
+    pub dedup: Option<bool>,
+    pub skip_failed_chunks: Option<bool>,
+    pub max_retries: Option<u32>,
+    pub retry_base_delay_ms: Option<u64>,
+    pub request_timeout: Option<u64>,
+    pub total_timeout: Option<u64>,
+    pub rpm: Option<u32>,
+    pub tpm: Option<u32>,
+    pub price_per_1k: Option<f64>,
+    pub strategy: Option<Strategy>,
+    pub mode: Option<Mode>,
+    pub length: Option<Length>,
+    pub max_bullets: Option<usize>,
+    pub polish: Option<bool>,
+    pub samples: Option<usize>,
+    pub questions: Option<usize>,
+    pub format: Option<OutputFormat>,
+    pub vault: Option<PathBuf>,
+    pub video_url: Option<String>,
+    pub deck_name: Option<String>,
+    pub timestamps: Option<bool>,
+    pub by_speaker: Option<bool>,
+    pub keep_sfx: Option<bool>,
+    pub lang: Option<String>,
+    pub focus: Option<String>,
+    pub translate: Option<String>,
+    pub translate_transcript: Option<bool>,
+    pub map_prompt: Option<String>,
+    pub combine_prompt: Option<String>,
+    pub refine_prompt: Option<String>,
+}
+
+impl SettingsLayer {
+    /// Load the config file at `path`, or the default location when `path` is `None`.
+    /// A missing default file is not an error; a missing explicit file is.
+    pub fn load(path: Option<&Path>) -> Result<Self, Box<dyn Error>> {
+        let (path, explicit) = match path {
+            Some(path) => (path.to_path_buf(), true),
+            None => match default_config_path() {
+                Some(path) => (path, false),
+                None => return Ok(Self::default()),
+            },
+        };
+        if !explicit && !path.exists() {
+            return Ok(Self::default());
+        }
+
+        let text = fs::read_to_string(&path)
+            .map_err(|e| format!("Cannot read config file {:?}: {}", path, e))?;
+        toml::from_str(&text).map_err(|e| {
+            SummarizerError::ParseError {
+                path: path.to_path_buf(),
+                message: e.to_string(),
+            }
+            .into()
+        })
+    }
+
+    /// Fill every key missing from `self` from `lower`
+    pub fn or(self, lower: SettingsLayer) -> SettingsLayer {
+        SettingsLayer {
+            backend: self.backend.or(lower.backend),
+            model: self.model.or(lower.model),
+            map_model: self.map_model.or(lower.map_model),
+            combine_model: self.combine_model.or(lower.combine_model),
+            embedding_model: self.embedding_model.or(lower.embedding_model),
+            api_base: self.api_base.or(lower.api_base),
+            api_key: self.api_key.or(lower.api_key),
+            keep_alive: self.keep_alive.or(lower.keep_alive),
+            deployment: self.deployment.or(lower.deployment),
+            api_version: self.api_version.or(lower.api_version),
+            model_path: self.model_path.or(lower.model_path),
+            tokenizer: self.tokenizer.or(lower.tokenizer),
+            auto_pull: self.auto_pull.or(lower.auto_pull),
+            transcriber: self.transcriber.or(lower.transcriber),
+            transcribe_api_base: self.transcribe_api_base.or(lower.transcribe_api_base),
+            transcribe_model: self.transcribe_model.or(lower.transcribe_model),
+            transcribe_api_key: self.transcribe_api_key.or(lower.transcribe_api_key),
+            fallback: self.fallback.or(lower.fallback),
+            fallback_model: self.fallback_model.or(lower.fallback_model),
+            fallback_api_base: self.fallback_api_base.or(lower.fallback_api_base),
+            fallback_api_key: self.fallback_api_key.or(lower.fallback_api_key),
+            temperature: self.temperature.or(lower.temperature),
+            top_p: self.top_p.or(lower.top_p),
+            max_tokens: self.max_tokens.or(lower.max_tokens),
+            seed: self.seed.or(lower.seed),
+            chunk_size: self.chunk_size.or(lower.chunk_size),
+            chunk_overlap: self.chunk_overlap.or(lower.chunk_overlap),
+            max_tokens_per_chunk: self.max_tokens_per_chunk.or(lower.max_tokens_per_chunk),
+            context_window: self.context_window.or(lower.context_window),
+            split: self.split.or(lower.split),
+            chunk_minutes: self.chunk_minutes.or(lower.chunk_minutes),
+            concurrency: self.concurrency.or(lower.concurrency),
+            cache: self.cache.or(lower.cache),
+            cache_ttl_days: self.cache_ttl_days.or(lower.cache_ttl_days),
+            cache_max_mb: self.cache_max_mb.or(lower.cache_max_mb),
+            stream_above_mb: self.stream_above_mb.or(lower.stream_above_mb),
+            history: self.history.or(lower.history),
+            webhook: self.webhook.or(lower.webhook),
+            webhook_secret: self.webhook_secret.or(lower.webhook_secret),
+            post: self.post.or(lower.post),
+            max_reduce_tokens: self.max_reduce_tokens.or(lower.max_reduce_tokens),
+            always_reduce: self.always_reduce.or(lower.always_reduce),
+            bullet_retry: self.bullet_retry.or(lower.bullet_retry),
+            dedup: self.dedup.or(lower.dedup),
+            skip_failed_chunks: self.skip_failed_chunks.or(lower.skip_failed_chunks),
+            max_retries: self.max_retries.or(lower.max_retries),
+            retry_base_delay_ms: self.retry_base_delay_ms.or(lower.retry_base_delay_ms),
+            request_timeout: self.request_timeout.or(lower.request_timeout),
+            total_timeout: self.total_timeout.or(lower.total_timeout),
+            rpm: self.rpm.or(lower.rpm),
+            tpm: self.tpm.or(lower.tpm),
+            price_per_1k: self.price_per_1k.or(lower.price_per_1k),
+            strategy: self.strategy.or(lower.strategy),
+            mode: self.mode.or(lower.mode),
+            length: self.length.or(lower.length),
+            max_bullets: self.max_bullets.or(lower.max_bullets),
+            polish: self.polish.or(lower.polish),
+            samples: self.samples.or(lower.samples),
+            questions: self.questions.or(lower.questions),
+            format: self.format.or(lower.format),
+            vault: self.vault.or(lower.vault),
+            video_url: self.video_url.or(lower.video_url),
+            deck_name: self.deck_name.or(lower.deck_name),
+            timestamps: self.timestamps.or(lower.timestamps),
+            by_speaker: self.by_speaker.or(lower.by_speaker),
+            keep_sfx: self.keep_sfx.or(lower.keep_sfx),
+            lang: self.lang.or(lower.lang),
+            focus: self.focus.or(lower.focus),
+            translate: self.translate.or(lower.translate),
+            translate_transcript: self.translate_transcript.or(lower.translate_transcript),
+            map_prompt: self.map_prompt.or(lower.map_prompt),
+            combine_prompt: self.combine_prompt.or(lower.combine_prompt),
+            refine_prompt: self.refine_prompt.or(lower.refine_prompt),
+        }
+    }
+}
+
+/// Fully resolved settings for a summarize run
+#[derive(Debug, Clone)]
+pub struct Settings {
+    pub backend: BackendKind,
+    pub model: String,
+    /// Model summarizing the chunks, instead of `model`
+    pub map_model: Option<String>,
+    /// Model for every step after the chunk summaries (reducing, combining, refining, chapters,
+    /// topics, titles and translation), instead of `model`
+    pub combine_model: Option<String>,
+    /// Model embedding the chunks `ask` retrieves and the passages of `--split semantic`, instead of the backend's default
+    pub embedding_model: Option<String>,
+    pub api_base: String,
+    pub api_key: Option<String>,
+    /// Ollama `keep_alive` duration, e.g. `"10m"` or `"-1"`
+    pub keep_alive: Option<String>,
+    /// Pull a missing model before starting (Ollama only)
+    pub auto_pull: bool,
+    /// Azure deployment serving `model`, when it isn't named after it
+    pub deployment: Option<String>,
+    /// Azure OpenAI API version
+    pub api_version: String,
+    /// GGUF file of `model` (local backend)
+    pub model_path: Option<PathBuf>,
+    /// `tokenizer.json` of `model_path`, instead of the vocabulary in the GGUF file (local backend)
+    pub tokenizer: Option<PathBuf>,
+    /// Speech-to-text API that transcribes audio inputs
+    pub transcriber: TranscriberKind,
+    pub transcribe_api_base: String,
+    /// Transcription model, sent to OpenAI-compatible endpoints only
+    pub transcribe_model: String,
+    pub transcribe_api_key: Option<String>,
+    /// Backend that requests go to when the primary one keeps failing or is down; the model,
+    /// base URL and key default like those of the primary backend
+    pub fallback: Option<BackendKind>,
+    pub fallback_model: Option<String>,
+    pub fallback_api_base: Option<String>,
+    pub fallback_api_key: Option<String>,
+    pub temperature: Option<f64>,
+    pub top_p: Option<f64>,
+    /// Most tokens of one reply
+    pub max_tokens: Option<u32>,
+    /// Seed for reproducible replies (openai and ollama backends)
+    pub seed: Option<u64>,
+    pub chunk_size: usize,
+    pub chunk_overlap: usize,
+    /// When set, chunks are budgeted in tokens and `chunk_overlap` counts tokens
+    pub max_tokens_per_chunk: Option<usize>,
+    /// Context window of the model in tokens, which the chunk size, overlap and reduce budget
+    /// default to fitting when it is known
+    pub context_window: Option<usize>,
+    pub split: Split,
+    /// Chunk timed transcripts into windows of this many minutes instead of by size
+    pub chunk_minutes: Option<usize>,
+    pub concurrency: usize,
+    /// Reuse replies from the disk cache, and store new ones there
+    pub cache: bool,
+    /// Age in days after which cached replies expire
+    pub cache_ttl_days: Option<u64>,
+    /// Size in megabytes the cache is pruned to
+    pub cache_max_mb: Option<u64>,
+    /// Size in megabytes above which plain-text files are streamed rather than loaded whole
+    pub stream_above_mb: u64,
+    /// Record summaries in the history database
+    pub history: bool,
+    /// URL every written summary is POSTed to
+    pub webhook: Option<String>,
+    /// Key of the HMAC-SHA256 signature of webhook deliveries
+    pub webhook_secret: Option<String>,
+    /// Slack and Discord webhooks every written summary is posted to
+    pub post: Vec<post::Target>,
+    /// Token budget of the joined summaries in one combine request
+    pub max_reduce_tokens: usize,
+    /// Run the combine step even for a single chunk, instead of using its map reply as is
+    pub always_reduce: bool,
+    /// Re-send the final prompt once when its reply has no bullet points
+    pub bullet_retry: bool,
+    /// Drop near copies of the previous chunk summary's bullets before combining
+    pub dedup: bool,
+    /// Leave out chunks whose request fails instead of failing the run
+    pub skip_failed_chunks: bool,
+    pub max_retries: u32,
+    pub retry_base_delay_ms: u64,
+    /// Seconds before an LLM request is cancelled and counts as failed
+    pub request_timeout: Option<u64>,
+    /// Seconds before a summary stops with `SummarizerError::TimedOut`
+    pub total_timeout: Option<u64>,
+    /// Most LLM requests per minute
+    pub rpm: Option<u32>,
+    /// Most prompt and reply tokens per minute
+    pub tpm: Option<u32>,
+    /// Dollars per 1000 tokens, for the cost estimate of the usage report
+    pub price_per_1k: Option<f64>,
+    pub strategy: Strategy,
+    /// Selects the default map and combine prompts
+    pub mode: Mode,
+    pub length: Option<Length>,
+    /// Upper limit on the bullets of the final summary; overrides the limit of `length`
+    pub max_bullets: Option<usize>,
+    /// Revise the final summary in one more request that deduplicates and orders its bullets
+    pub polish: bool,
+    /// Final combine replies merged into a consensus summary; 1 sends the combine request once
+    pub samples: usize,
+    /// Question and answer pairs of the qa mode
+    pub questions: usize,
+    pub format: OutputFormat,
+    /// Folder of an Obsidian vault that `obsidian` notes are written to when no output directory
+    /// is given
+    pub vault: Option<PathBuf>,
+    /// Page of the summarized video, which HTML chapter timestamps link into with a `#t=`
+    /// fragment; video URL inputs link to themselves
+    pub video_url: Option<String>,
+    /// Anki deck that `anki` flashcards are imported into; Anki asks when `None`
+    pub deck_name: Option<String>,
+    /// Start each bullet with the time range of its source material (subtitle files only)
+    pub timestamps: bool,
+    /// One section per speaker, from the speaker labels of the transcript
+    pub by_speaker: bool,
+    /// Keep sound descriptions like `[music]` and `(laughter)` in subtitle text
+    pub keep_sfx: bool,
+    /// Language the summary is written in; detected per input when `None`
+    pub lang: Option<Language>,
+    /// Topic the summary is restricted to
+    pub focus: Option<String>,
+    /// Language the final summary is translated into after it is written
+    pub translate: Option<Language>,
+    /// Also translate the cleaned transcript of file inputs
+    pub translate_transcript: bool,
+    pub map_prompt: String,
+    pub combine_prompt: String,
+    /// Template with `{summary}` and `{text}` placeholders for the refine strategy
+    pub refine_prompt: String,
+}
+
+impl Settings {
+    /// Apply built-in defaults to the keys `layer` leaves unset and validate the result
+    pub fn resolve(layer: SettingsLayer) -> Result<Self, Box<dyn Error>> {
+        let backend = layer.backend.unwrap_or_default();
+        let model_path = layer.model_path.map(expand_home);
+        let (default_api_base, default_model) = backend.defaults();
+        let transcriber = layer.transcriber.unwrap_or_default();
+        let post_targets = layer
+            .post
+            .iter()
+            .flatten()
+            .map(|target| post::Target::parse(target).map_err(|e| format!("post {:?}: {}", target, e)))
+            .collect::<Result<Vec<_>, _>>()?;
+        let default_transcribe_api_base = match transcriber {
+            TranscriberKind::WhisperCpp => DEFAULT_WHISPER_CPP_API_BASE,
+            TranscriberKind::Openai => DEFAULT_TRANSCRIBE_API_BASE,
+        };
+        // Flashcards are made of questions and answers
+        let format = layer.format.unwrap_or_default();
+        let mode = layer
+            .mode
+            .unwrap_or(if format == OutputFormat::Anki { Mode::Qa } else { Mode::default() });
+        let timestamps = layer.timestamps.unwrap_or(false);
+        let by_speaker = layer.by_speaker.unwrap_or(false);
+        let (default_map_prompt, default_combine_prompt) = default_templates(mode, timestamps, by_speaker);
+        // A known context window sizes what isn't set
+        let budget = layer.context_window.map(|window| prompt_budget(window, layer.max_tokens));
+        let chunk_size = layer
+            .chunk_size
+            .or(budget.map(|budget| (budget.min(MAX_SIZED_CHUNK_TOKENS) as f64 * WORDS_PER_TOKEN) as usize))
+            .unwrap_or(DEFAULT_CHUNK_SIZE);
+        let chunk_overlap = layer
+            .chunk_overlap
+            .or(budget.map(|_| layer.max_tokens_per_chunk.unwrap_or(chunk_size) / 10))
+            .unwrap_or(DEFAULT_CHUNK_OVERLAP);
+        let max_reduce_tokens = layer
+            .max_reduce_tokens
+            .or(budget.map(|budget| budget.min(MAX_SIZED_REDUCE_TOKENS)))
+            .unwrap_or(DEFAULT_MAX_REDUCE_TOKENS);
+        let settings = Self {
+            backend,
+            // An Azure deployment is enough to name the model
+            model: layer
+                .model
+                .or_else(|| layer.deployment.clone().filter(|_| backend == BackendKind::Azure))
+                // ... and a GGUF file names a local one
+                .or_else(|| model_path.as_deref().filter(|_| backend == BackendKind::Local).and_then(model_name))
+                .unwrap_or_else(|| default_model.to_string()),
+            map_model: layer.map_model,
+            combine_model: layer.combine_model,
+            embedding_model: layer.embedding_model,
+            api_base: layer
+                .api_base
+                .or_else(|| env::var("AZURE_OPENAI_ENDPOINT").ok().filter(|_| backend == BackendKind::Azure))
+                .unwrap_or_else(|| default_api_base.to_string()),
+            api_key: layer.api_key.or_else(|| backend.env_api_key()),
+            keep_alive: layer.keep_alive,
+            deployment: layer.deployment,
+            api_version: layer.api_version.unwrap_or_else(|| DEFAULT_AZURE_API_VERSION.to_string()),
+            model_path,
+            tokenizer: layer.tokenizer.map(expand_home),
+            auto_pull: layer.auto_pull.unwrap_or(false),
+            transcriber,
+            transcribe_api_base: layer
+                .transcribe_api_base
+                .unwrap_or_else(|| default_transcribe_api_base.to_string()),
+            transcribe_model: layer
+                .transcribe_model
+                .unwrap_or_else(|| DEFAULT_TRANSCRIBE_MODEL.to_string()),
+            transcribe_api_key: layer.transcribe_api_key.or_else(|| {
+                (transcriber == TranscriberKind::Openai)
+                    .then(|| env::var("OPENAI_API_KEY").ok())
+                    .flatten()
+                    .filter(|key| !key.is_empty())
+            }),
+            fallback: layer.fallback,
+            fallback_model: layer.fallback_model,
+            fallback_api_base: layer.fallback_api_base,
+            fallback_api_key: layer.fallback_api_key,
+            temperature: layer.temperature,
+            top_p: layer.top_p,
+            max_tokens: layer.max_tokens,
+            seed: layer.seed,
+            chunk_size,
+            chunk_overlap,
+            max_tokens_per_chunk: layer.max_tokens_per_chunk,
+            context_window: layer.context_window,
+            split: layer.split.unwrap_or_default(),
+            chunk_minutes: layer.chunk_minutes,
+            concurrency: layer.concurrency.unwrap_or(DEFAULT_CONCURRENCY),
+            cache: layer.cache.unwrap_or(true),
+            cache_ttl_days: layer.cache_ttl_days,
+            cache_max_mb: layer.cache_max_mb,
+            stream_above_mb: layer.stream_above_mb.unwrap_or(DEFAULT_STREAM_ABOVE_MB),
+            history: layer.history.unwrap_or(false),
+            webhook: layer.webhook,
+            webhook_secret: layer
+                .webhook_secret
+                .or_else(|| env::var("SRT_SUMMARIZER_WEBHOOK_SECRET").ok().filter(|secret| !secret.is_empty())),
+            post: post_targets,
+            max_reduce_tokens,
+            always_reduce: layer.always_reduce.unwrap_or(false),
+            bullet_retry: layer.bullet_retry.unwrap_or(true),
+            dedup: layer.dedup.unwrap_or(true),
+            skip_failed_chunks: layer.skip_failed_chunks.unwrap_or(false),
+            max_retries: layer.max_retries.unwrap_or(DEFAULT_MAX_RETRIES),
+            retry_base_delay_ms: layer.retry_base_delay_ms.unwrap_or(DEFAULT_RETRY_BASE_DELAY_MS),
+            request_timeout: layer.request_timeout,
+            total_timeout: layer.total_timeout,
+            rpm: layer.rpm,
+            tpm: layer.tpm,
+            price_per_1k: layer.price_per_1k,
+            strategy: layer.strategy.unwrap_or_default(),
+            mode,
+            length: layer.length,
+            max_bullets: layer.max_bullets,
+            polish: layer.polish.unwrap_or(false),
+            samples: layer.samples.unwrap_or(1),
+            questions: layer.questions.unwrap_or(qa::DEFAULT_QUESTIONS),
+            format,
+            vault: layer.vault.map(expand_home),
+            video_url: layer.video_url,
+            deck_name: layer.deck_name.filter(|deck| !deck.trim().is_empty()),
+            timestamps,
+            by_speaker,
+            keep_sfx: layer.keep_sfx.unwrap_or(false),
+            lang: layer.lang.as_deref().map(Language::parse).transpose()?,
+            focus: layer
+                .focus
+                .map(|focus| focus.trim().to_string())
+                .filter(|focus| !focus.is_empty()),
+            translate: layer.translate.as_deref().map(Language::parse).transpose()?,
+            translate_transcript: layer.translate_transcript.unwrap_or(false),
+            map_prompt: layer.map_prompt.unwrap_or_else(|| default_map_prompt.to_string()),
+            combine_prompt: layer
+                .combine_prompt
+                .unwrap_or_else(|| default_combine_prompt.to_string()),
+            refine_prompt: layer.refine_prompt.unwrap_or_else(|| REFINE_TEMPLATE.to_string()),
+        };
+
+        if settings.chunk_size == 0
+            || settings.concurrency == 0
+            || settings.max_tokens_per_chunk == Some(0)
+            || settings.context_window == Some(0)
+            || settings.chunk_minutes == Some(0)
+            || settings.max_bullets == Some(0)
+            || settings.questions == 0
+            || settings.max_reduce_tokens == 0
+            || settings.samples == 0
+        {
+            return Err(
+                "chunk_size, max_tokens_per_chunk, context_window, chunk_minutes, max_reduce_tokens, concurrency, max_bullets, questions and samples must be at least 1"
+                    .into(),
+            );
+        }
+        match settings.max_tokens_per_chunk {
+            Some(max_tokens) if settings.chunk_overlap >= max_tokens => {
+                return Err("chunk_overlap must be smaller than max_tokens_per_chunk".into());
+            }
+            None if settings.chunk_overlap >= settings.chunk_size => {
+                return Err("chunk_overlap must be smaller than chunk_size".into());
+            }
+            _ => {}
+        }
+        if settings.request_timeout == Some(0) || settings.total_timeout == Some(0) {
+            return Err("request_timeout and total_timeout must be at least 1 second".into());
+        }
+        if settings.rpm == Some(0) || settings.tpm == Some(0) {
+            return Err("rpm and tpm must be at least 1".into());
+        }
+        if settings.price_per_1k.is_some_and(|price| price.is_nan() || price < 0.0) {
+            return Err("price_per_1k must be a non-negative number".into());
+        }
+        if settings.temperature.is_some_and(|t| !(0.0..=2.0).contains(&t)) {
+            return Err("temperature must be between 0 and 2".into());
+        }
+        if settings.top_p.is_some_and(|p| !(0.0..=1.0).contains(&p)) {
+            return Err("top_p must be between 0 and 1".into());
+        }
+        if settings.max_tokens == Some(0) {
+            return Err("max_tokens must be at least 1".into());
+        }
+        if settings.translate_transcript && settings.translate.is_none() {
+            return Err("translate_transcript needs a translate language".into());
+        }
+        if settings.format == OutputFormat::Anki && settings.mode != Mode::Qa {
+            return Err("the anki format needs the qa mode".into());
+        }
+        if settings.by_speaker && (settings.mode != Mode::Summary || settings.timestamps) {
+            return Err("by_speaker cannot be combined with timestamps or a mode other than summary".into());
+        }
+        for (name, template) in [
+            ("map_prompt", &settings.map_prompt),
+            ("combine_prompt", &settings.combine_prompt),
+            ("refine_prompt", &settings.refine_prompt),
+        ] {
+            if !template.contains("{text}") {
+                return Err(format!("{} must contain the {{text}} placeholder", name).into());
+            }
+        }
+        if !settings.refine_prompt.contains("{summary}") {
+            return Err("refine_prompt must contain the {summary} placeholder".into());
+        }
+
+        Ok(settings)
+    }
+
+    /// The sampling parameters sent with every request
+    pub fn sampling(&self) -> Sampling {
+        Sampling {
+            temperature: self.temperature,
+            top_p: self.top_p,
+            max_tokens: self.max_tokens,
+            seed: self.seed,
+        }
+    }
+
+    /// The vault folder when notes are written in the `obsidian` format
+    pub fn vault_dir(&self) -> Option<PathBuf> {
+        self.vault.clone().filter(|_| self.format == OutputFormat::Obsidian)
+    }
+
+    /// These settings pointed at the fallback backend, when there is one; every step then uses
+    /// its model
+    pub fn fallback_settings(&self) -> Option<Settings> {
+        let backend = self.fallback?;
+        let (default_api_base, default_model) = backend.defaults();
+        Some(Settings {
+            backend,
+            model: self
+                .fallback_model
+                .clone()
+                .or_else(|| self.model_path.as_deref().filter(|_| backend == BackendKind::Local).and_then(model_name))
+                .unwrap_or_else(|| default_model.to_string()),
+            map_model: None,
+            combine_model: None,
+            embedding_model: None,
+            api_base: self
+                .fallback_api_base
+                .clone()
+                .or_else(|| env::var("AZURE_OPENAI_ENDPOINT").ok().filter(|_| backend == BackendKind::Azure))
+                .unwrap_or_else(|| default_api_base.to_string()),
+            api_key: self.fallback_api_key.clone().or_else(|| backend.env_api_key()),
+            deployment: None,
+            fallback: None,
+            ..self.clone()
+        })
+    }
+
+    /// The model of the chunk summaries
+    pub fn chunk_model(&self) -> &str {
+        self.map_model.as_deref().unwrap_or(&self.model)
+    }
+
+    /// The model of the steps after the chunk summaries
+    pub fn final_model(&self) -> &str {
+        self.combine_model.as_deref().unwrap_or(&self.model)
+    }
+
+    /// The model embedding the chunks of `ask`
+    pub fn embedding_model(&self) -> &str {
+        let default = match self.backend {
+            BackendKind::Gemini => DEFAULT_GEMINI_EMBEDDING_MODEL,
+            _ => DEFAULT_EMBEDDING_MODEL,
+        };
+        self.embedding_model.as_deref().unwrap_or(default)
+    }
+
+    /// The model answering requests of `stage` (`map` for chunk summaries)
+    pub fn stage_model(&self, stage: &str) -> &str {
+        if stage == "map" {
+            self.chunk_model()
+        } else {
+            self.final_model()
+        }
+    }
+
+    /// The models of a summary, e.g. `llama3.2:1b + llama3.3` when chunks use another model
+    pub fn model_label(&self) -> String {
+        match (self.chunk_model(), self.final_model()) {
+            (map, combine) if map == combine => map.to_string(),
+            (map, combine) => format!("{} + {}", map, combine),
+        }
+    }
+
+    /// The bounds of the disk cache
+    pub fn cache_limits(&self) -> CacheLimits {
+        CacheLimits {
+            ttl: self.cache_ttl_days.map(|days| Duration::from_secs(days * 24 * 60 * 60)),
+            max_bytes: self.cache_max_mb.map(|mb| mb * 1_000_000),
+        }
+    }
+
+    /// Name the cache keys the replies of `model` by; replies sampled with other parameters
+    /// are kept apart, while runs with the backend's defaults share the plain model name
+    pub fn cache_model(&self, model: &str) -> String {
+        let sampling = self.sampling();
+        if sampling == Sampling::default() {
+            model.to_string()
+        } else {
+            format!("{} {:?}", model, sampling)
+        }
+    }
+
+    /// Most bullets the final summary may have, from `max_bullets` or else `length`
+    pub fn bullet_limit(&self) -> Option<usize> {
+        self.max_bullets.or(self.length.map(Length::max_bullets))
+    }
+
+    pub fn retry_policy(&self) -> RetryPolicy {
+        RetryPolicy {
+            max_retries: self.max_retries,
+            base_delay: Duration::from_millis(self.retry_base_delay_ms),
+        }
+    }
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self::resolve(SettingsLayer::default()).expect("built-in defaults are valid")
+    }
+}
+
+/// Tokens of a context window of `window` tokens left for the text filled into a prompt, once the
+/// template and a reply of `max_tokens` have their room
+fn prompt_budget(window: usize, max_tokens: Option<u32>) -> usize {
+    let reply = max_tokens.map_or(REPLY_TOKENS, |max_tokens| max_tokens as usize);
+    // A tiny window still gets chunks that say something
+    window.saturating_sub(PROMPT_OVERHEAD_TOKENS + reply).max(256)
+}
+
+/// The name of the model in a GGUF file: its name without the extension
+fn model_name(path: &Path) -> Option<String> {
+    path.file_stem().map(|stem| stem.to_string_lossy().into_owned())
+}
+
+/// `path` with a leading `~` replaced by the home directory
+fn expand_home(path: PathBuf) -> PathBuf {
+    match (path.strip_prefix("~"), env::var_os("HOME")) {
+        (Ok(rest), Some(home)) => PathBuf::from(home).join(rest),
+        _ => path,
+    }
+}
+
+/// `$XDG_CONFIG_HOME/srt-bullet-summarizer/config.toml`, or `~/.config/...` without XDG
+pub fn default_config_path() -> Option<PathBuf> {
+    let base = env::var_os("XDG_CONFIG_HOME")
+        .filter(|v| !v.is_empty())
+        .map(PathBuf::from)
+        .or_else(|| env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")))?;
+    Some(base.join("srt-bullet-summarizer").join("config.toml"))
+}
+
+/// Write the commented default config, refusing to overwrite unless `force` is set
+pub fn init(path: Option<&Path>, force: bool) -> Result<PathBuf, Box<dyn Error>> {
+    let path = match path {
+        Some(path) => path.to_path_buf(),
+        None => default_config_path().ok_or("Cannot determine the config directory; pass --path")?,
+    };
+    if path.exists() && !force {
+        return Err(format!("{:?} already exists (use --force to overwrite)", path).into());
+    }
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    fs::write(&path, DEFAULT_CONFIG)?;
+    Ok(path)
+}
@@ -0,0 +1,67 @@
+// Prompts and raw replies written to a directory by `--debug-dump`.
+//
+// Every request gets a number in the order it was sent, so `003_chunk_3_prompt.txt` and
+// `003_chunk_3_response.txt` sit next to each other. Responses are the model's text before any
+// cleanup (bullet normalization, preamble stripping, truncation), which is what's needed to see
+// why a section came out badly. Failed requests leave `_error.txt` instead.
+
+use std::{
+    error::Error,
+    fs,
+    path::{Path, PathBuf},
+    sync::atomic::{AtomicUsize, Ordering},
+};
+use tracing::warn;
+
+/// Directory receiving numbered prompt and response files
+#[derive(Debug)]
+pub struct DebugDump {
+    dir: PathBuf,
+    next: AtomicUsize,
+}
+
+impl DebugDump {
+    /// Dump into `dir`, creating it if missing
+    pub fn new(dir: &Path) -> Result<Self, Box<dyn Error>> {
+        fs::create_dir_all(dir).map_err(|e| format!("Cannot create debug dump directory {:?}: {}", dir, e))?;
+        Ok(Self {
+            dir: dir.to_path_buf(),
+            next: AtomicUsize::new(1),
+        })
+    }
+
+    /// Write the prompt of the request `what` and return the name its reply is written under
+    pub fn prompt(&self, what: &str, prompt: &str) -> String {
+        let number = self.next.fetch_add(1, Ordering::Relaxed);
+        let name = format!("{:03}_{}", number, slug(what));
+        self.write(&name, "prompt", prompt);
+        name
+    }
+
+    /// Write the raw reply to the request dumped as `name`
+    pub fn response(&self, name: &str, response: &str) {
+        self.write(name, "response", response);
+    }
+
+    /// Write why the request dumped as `name` failed
+    pub fn error(&self, name: &str, error: &dyn Error) {
+        self.write(name, "error", &error.to_string());
+    }
+
+    /// A dump that cannot be written only costs a warning
+    fn write(&self, name: &str, kind: &str, text: &str) {
+        let path = self.dir.join(format!("{}_{}.txt", name, kind));
+        if let Err(e) = fs::write(&path, text) {
+            warn!("Cannot write debug dump {:?}: {}", path, e);
+        }
+    }
+}
+
+/// `Chunk 3` → `chunk_3`, `Combine step` → `combine_step`
+fn slug(what: &str) -> String {
+    let slug: String = what
+        .chars()
+        .map(|c| if c.is_alphanumeric() { c.to_ascii_lowercase() } else { '_' })
+        .collect();
+    slug.split('_').filter(|part| !part.is_empty()).collect::<Vec<_>>().join("_")
+}
@@ -0,0 +1,255 @@
+// Health checks behind the `doctor` subcommand.
+//
+// The backend (and the fallback backend, when there is one) is asked for its model list (a local
+// model is loaded instead), checked for the models the settings use, and timed on a one-token
+// request, so a wrong URL, key or model name shows up in seconds rather than partway through a
+// long run. Every failed check says what to change.
+
+use std::time::{Duration, Instant};
+
+use crate::{
+    backend::{self, BackendError},
+    config::{BackendKind, Settings},
+    error::SummarizerError,
+};
+
+/// How long a check waits for the API; loading a large local model can take a while
+const CHECK_TIMEOUT: Duration = Duration::from_secs(60);
+
+const PING_PROMPT: &str = "Reply with the single word OK.";
+
+/// Models named in a fix, at most
+const LISTED_MODELS: usize = 10;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Status {
+    Ok,
+    /// Not fatal, but worth a look
+    Warning,
+    Failed,
+}
+
+/// The outcome of one check
+#[derive(Debug, Clone)]
+pub struct Check {
+    pub status: Status,
+    pub message: String,
+    /// What to do about a warning or failure
+    pub fix: Option<String>,
+}
+
+impl Check {
+    fn ok(message: String) -> Self {
+        Self {
+            status: Status::Ok,
+            message,
+            fix: None,
+        }
+    }
+
+    fn warning(message: String, fix: Option<String>) -> Self {
+        Self {
+            status: Status::Warning,
+            message,
+            fix,
+        }
+    }
+
+    fn failed(message: String, fix: Option<String>) -> Self {
+        Self {
+            status: Status::Failed,
+            message,
+            fix,
+        }
+    }
+}
+
+/// Check the backend of `settings` and its fallback
+pub async fn run(settings: &Settings) -> Vec<Check> {
+    let mut checks = Vec::new();
+    check_backend(settings, "", &mut checks).await;
+    if let Some(fallback) = settings.fallback_settings() {
+        let mut fallback_checks = Vec::new();
+        check_backend(&fallback, "fallback-", &mut fallback_checks).await;
+        checks.extend(fallback_checks.into_iter().map(|check| Check {
+            message: format!("Fallback: {}", check.message),
+            ..check
+        }));
+    }
+    checks
+}
+
+/// Check one backend; `prefix` starts the names of its options (`fallback-` for `--fallback-model`)
+async fn check_backend(settings: &Settings, prefix: &str, checks: &mut Vec<Check>) {
+    let api = format!("{} API", settings.backend);
+    let backend = match backend::from_settings(settings) {
+        Ok(backend) => backend,
+        Err(e) => return checks.push(Check::failed(e.to_string(), None)),
+    };
+
+    // There is no API to reach; loading the model file is the first check
+    if settings.backend == BackendKind::Local {
+        let start = Instant::now();
+        if let Err(e) = with_timeout(backend.prepare()).await {
+            return checks.push(Check::failed(describe(e.as_ref()), None));
+        }
+        let message = format!("The model loads in {} ms", start.elapsed().as_millis());
+        checks.push(Check::ok(message));
+    }
+
+    let models = match with_timeout(backend.models()).await {
+        Ok(models) if settings.backend == BackendKind::Local => models,
+        Ok(models) => {
+            let listed = models
+                .as_ref()
+                .map(|models| match models.len() {
+                    1 => " and lists 1 model".to_string(),
+                    n => format!(" and lists {} models", n),
+                })
+                .unwrap_or_default();
+            checks.push(Check::ok(format!("The {} at {} answers{}", api, settings.api_base, listed)));
+            models
+        }
+        Err(e) => {
+            let fix = remedy(e.as_ref(), settings, prefix);
+            return checks.push(Check::failed(describe(e.as_ref()), fix));
+        }
+    };
+
+    for (model, option) in models_in_use(settings, prefix) {
+        let listed = models.as_ref().map(|models| backend::has_model(models, &model));
+        match listed {
+            Some(true) => checks.push(Check::ok(format!("Model {:?} is available", model))),
+            Some(false) if settings.backend == BackendKind::Ollama => {
+                let message = format!("Model {:?} is not installed in Ollama", model);
+                if settings.auto_pull {
+                    checks.push(Check::warning(message, Some("--auto-pull will pull it before the first run".into())));
+                } else {
+                    let fix = format!("run `ollama pull {}`, pass --auto-pull, or pick an installed model with {}", model, option);
+                    checks.push(Check::failed(message, Some(fix)));
+                }
+                continue;
+            }
+            Some(false) => checks.push(Check::warning(
+                format!("Model {:?} is not in the model list of the {}", model, api),
+                Some(available(models.as_deref().unwrap_or_default(), &option)),
+            )),
+            None => {}
+        }
+
+        match with_timeout(ping(settings, &model)).await {
+            Ok(elapsed) => checks.push(Check::ok(format!(
+                "A one-token request to {:?} took {} ms",
+                model,
+                elapsed.as_millis()
+            ))),
+            Err(e) => {
+                let fix = match e.downcast_ref() {
+                    Some(SummarizerError::ModelNotFound { .. }) => {
+                        Some(available(models.as_deref().unwrap_or_default(), &option))
+                    }
+                    _ => remedy(e.as_ref(), settings, prefix),
+                };
+                let message = format!("A one-token request to {:?} failed: {}", model, describe(e.as_ref()));
+                checks.push(Check::failed(message, fix));
+            }
+        }
+    }
+
+    // Only the primary backend's models size the chunks
+    if prefix.is_empty() && settings.context_window.is_none() {
+        match backend::context_window(settings).await {
+            Some(window) => checks.push(Check::ok(format!("Chunks are sized for a context window of {} tokens", window))),
+            None => checks.push(Check::warning(
+                "The context window of the model is unknown, so chunks keep the fixed sizes".into(),
+                Some("pass --context-window to size them for it".into()),
+            )),
+        }
+    }
+}
+
+/// The models requests go to, each with the option that sets it
+fn models_in_use(settings: &Settings, prefix: &str) -> Vec<(String, String)> {
+    // Azure's --deployment names the deployment of --model
+    let model_option = match settings.deployment {
+        Some(_) if settings.backend == BackendKind::Azure => "deployment",
+        _ => "model",
+    };
+    let option = |set: bool, name: &str| format!("--{}{}", prefix, if set { name } else { model_option });
+    let mut models = vec![(settings.chunk_model().to_string(), option(settings.map_model.is_some(), "map-model"))];
+    if settings.final_model() != settings.chunk_model() {
+        models.push((settings.final_model().to_string(), option(settings.combine_model.is_some(), "combine-model")));
+    }
+    models
+}
+
+/// Time a request for a single token of `model`
+async fn ping(settings: &Settings, model: &str) -> Result<Duration, BackendError> {
+    let settings = Settings {
+        max_tokens: Some(1),
+        ..settings.clone()
+    };
+    let backend = backend::for_model(&settings, model).map_err(|e| e.to_string())?;
+    let start = Instant::now();
+    backend.complete(PING_PROMPT).await?;
+    Ok(start.elapsed())
+}
+
+async fn with_timeout<T>(request: impl std::future::Future<Output = Result<T, BackendError>>) -> Result<T, BackendError> {
+    tokio::time::timeout(CHECK_TIMEOUT, request)
+        .await
+        .unwrap_or_else(|_| Err(format!("no reply within {} s", CHECK_TIMEOUT.as_secs()).into()))
+}
+
+/// `error` without the hint its message ends with, as the check's fix replaces it
+fn describe(error: &(dyn std::error::Error + Send + Sync + 'static)) -> String {
+    match error.downcast_ref() {
+        Some(SummarizerError::BackendUnreachable {
+            backend,
+            api_base,
+            message,
+        }) => format!("Cannot reach the {} API at {}: {}", backend, api_base, message),
+        Some(SummarizerError::Unauthorized { backend, message }) => {
+            format!("The {} API rejected the API key: {}", backend, message)
+        }
+        Some(SummarizerError::ModelNotFound { backend, model, message }) => {
+            format!("Model {:?} is not available from the {} API: {}", model, backend, message)
+        }
+        _ => error.to_string(),
+    }
+}
+
+/// What to change for the errors a setting can fix
+fn remedy(error: &(dyn std::error::Error + Send + Sync + 'static), settings: &Settings, prefix: &str) -> Option<String> {
+    match error.downcast_ref()? {
+        SummarizerError::BackendUnreachable { .. } if settings.api_base.contains(":11434") => Some(format!(
+            "start Ollama with `ollama serve`, or point --{}api-base at the server",
+            prefix
+        )),
+        SummarizerError::BackendUnreachable { .. }
+            if matches!(settings.backend, BackendKind::Anthropic | BackendKind::Gemini | BackendKind::Azure) =>
+        {
+            Some(format!("check your network connection and --{}api-base", prefix))
+        }
+        SummarizerError::BackendUnreachable { .. } => {
+            Some(format!("start the server, or point --{}api-base at the one that is running", prefix))
+        }
+        SummarizerError::Unauthorized { .. } => Some(match settings.backend.api_key_env() {
+            Some(env) if prefix.is_empty() => format!("pass a valid key with --api-key or set {}", env),
+            _ => format!("pass a valid key with --{}api-key", prefix),
+        }),
+        _ => None,
+    }
+}
+
+/// A fix naming some of the `models` to choose from with `option`
+fn available(models: &[String], option: &str) -> String {
+    if models.is_empty() {
+        return format!("check {}", option);
+    }
+    let mut names = models.iter().take(LISTED_MODELS).cloned().collect::<Vec<_>>().join(", ");
+    if models.len() > LISTED_MODELS {
+        names.push_str(&format!(" and {} more", models.len() - LISTED_MODELS));
+    }
+    format!("pick one of {} with {}", names, option)
+}
@@ -0,0 +1,116 @@
+// Named things a transcript mentions, for `--with-entities`.
+//
+// Unlike the key topics, entities come from the chunk summaries rather than the final summary,
+// which keeps only the main points and drops most of the names and figures an interview is
+// searched by. The chunk summaries are sent in batches of `max_reduce_tokens` and the lists of
+// every batch merged.
+
+pub const ENTITIES_TEMPLATE: &str = r#"List what this text mentions in four categories:
+People: the people named, with their role when the text gives it, e.g. "Ada Lovelace (mathematician)"
+Organizations: companies, institutions, teams and groups
+Tools and products: software, devices, products, books and services
+Numbers and dates: figures, amounts, percentages and dates with a few words on what they refer to, e.g. "40% (market share in 2023)"
+Answer only with the four lines, each starting with its category and a colon and listing the items separated by semicolons, or "None" when there are none.
+Text:
+{text}
+
+ENTITIES:"#;
+
+/// Words of the labels of the people, organizations, products and numbers lines
+const CATEGORY_WORDS: [&[&str]; 4] = [&["people", "person"], &["organi", "compan"], &["product", "tool"], &["number", "date"]];
+
+/// The named things mentioned, by category, in the order they first come up
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Entities {
+    pub people: Vec<String>,
+    pub organizations: Vec<String>,
+    pub products: Vec<String>,
+    pub numbers: Vec<String>,
+}
+
+impl Entities {
+    pub fn is_empty(&self) -> bool {
+        self.people.is_empty() && self.organizations.is_empty() && self.products.is_empty() && self.numbers.is_empty()
+    }
+
+    /// Add the entities of `other` that are not listed yet
+    pub fn merge(&mut self, other: Entities) {
+        for (list, items) in [
+            (&mut self.people, other.people),
+            (&mut self.organizations, other.organizations),
+            (&mut self.products, other.products),
+            (&mut self.numbers, other.numbers),
+        ] {
+            for item in items {
+                add(list, item);
+            }
+        }
+    }
+
+    /// The categories with their names, including empty ones
+    pub fn categories(&self) -> [(&'static str, &[String]); 4] {
+        [
+            ("People", &self.people),
+            ("Organizations", &self.organizations),
+            ("Tools and products", &self.products),
+            ("Numbers and dates", &self.numbers),
+        ]
+    }
+}
+
+/// Extract the categorized entities of a model reply; items may also be listed on bullet lines
+/// below their category
+pub fn parse(reply: &str) -> Entities {
+    let mut entities = Entities::default();
+    // Category of the line above
+    let mut current: Option<usize> = None;
+    for line in reply.lines() {
+        let line = line.trim().trim_start_matches(['-', '*', '•', '#']).trim();
+        if line.is_empty() {
+            continue;
+        }
+        let (label, rest) = match line.split_once(':') {
+            Some((label, rest)) if label.split_whitespace().count() <= 4 => (label.trim_matches(['*', ' ']).to_lowercase(), rest),
+            _ => (String::new(), line),
+        };
+        let items = match CATEGORY_WORDS.iter().position(|words| words.iter().any(|word| label.contains(word))) {
+            Some(category) => {
+                current = Some(category);
+                rest
+            }
+            // A line without a category continues the one above it
+            None => line,
+        };
+        let list = match current {
+            None => continue,
+            Some(0) => &mut entities.people,
+            Some(1) => &mut entities.organizations,
+            Some(2) => &mut entities.products,
+            Some(_) => &mut entities.numbers,
+        };
+        for item in items.split(';') {
+            let item = item.trim().trim_matches(['*', '.', ' ']);
+            if !item.is_empty() && !item.eq_ignore_ascii_case("none") {
+                add(list, item.to_string());
+            }
+        }
+    }
+    entities
+}
+
+/// An `Entities:` block with one line per category that has items
+pub fn render(entities: &Entities) -> String {
+    let lines: String = entities
+        .categories()
+        .iter()
+        .filter(|(_, items)| !items.is_empty())
+        .map(|(name, items)| format!("{}: {}\n", name, items.join("; ")))
+        .collect();
+    format!("Entities:\n{}", lines)
+}
+
+fn add(list: &mut Vec<String>, item: String) {
+    if !list.iter().any(|known| known.eq_ignore_ascii_case(&item)) {
+        list.push(item);
+    }
+}
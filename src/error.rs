@@ -0,0 +1,173 @@
+// Errors worth explaining to the user.
+//
+// Most failures still travel as `Box<dyn Error>`, but the ones a user can act on (a missing
+// file, a server that isn't running (LLM or transcription), a rejected API key, a prompt over the context window) are
+// raised as a `SummarizerError`, whose message says what to do about it. Callers can downcast
+// to it to react to a specific case.
+
+use std::path::PathBuf;
+use thiserror::Error;
+
+use crate::config::{BackendKind, TranscriberKind};
+
+#[derive(Debug, Error)]
+pub enum SummarizerError {
+    #[error("File not found: {0:?}")]
+    FileNotFound(PathBuf),
+
+    #[error("Cannot parse {path:?}: {message}")]
+    ParseError { path: PathBuf, message: String },
+
+    #[error("Cannot reach the {backend} API at {api_base}: {message}; {}", unreachable_hint(*.backend, .api_base))]
+    BackendUnreachable {
+        backend: BackendKind,
+        api_base: String,
+        message: String,
+    },
+
+    #[error("Cannot reach the transcription server at {api_base}: {message}; {}", transcriber_hint(*.transcriber))]
+    TranscriberUnreachable {
+        transcriber: TranscriberKind,
+        api_base: String,
+        message: String,
+    },
+
+    #[error("The {backend} API rejected the API key: {message}; {}", key_hint(*.backend))]
+    Unauthorized { backend: BackendKind, message: String },
+
+    #[error("Model {model:?} is not available from the {backend} API: {message}; check --model")]
+    ModelNotFound {
+        backend: BackendKind,
+        model: String,
+        message: String,
+    },
+
+    #[error("The prompt does not fit the context window of {model:?}: {message}; lower --chunk-size (or --max-tokens-per-chunk) and --max-reduce-tokens")]
+    ContextOverflow { model: String, message: String },
+
+    #[error("The {backend} API blocked the request for safety reasons ({reason}), even with relaxed safety settings")]
+    Blocked { backend: BackendKind, reason: String },
+
+    #[error("No reply within {0} s; raise --request-timeout, or lower --chunk-size for quicker replies")]
+    RequestTimeout(u64),
+
+    #[error("The {backend} API returned {status}: {message}")]
+    Backend {
+        backend: BackendKind,
+        status: u16,
+        message: String,
+    },
+
+    /// The backend can't embed, for `ask`, `--split semantic` and `--mode topics`
+    #[error("This backend has no embeddings API; embeddings need the openai, ollama, gemini or azure backend")]
+    NoEmbeddings,
+
+    /// `--incremental` found no cues after those the last run summarized
+    #[error("{0:?} has no new cues since its last summary, which is up to date")]
+    UpToDate(PathBuf),
+
+    /// Ctrl+C stopped a run; `partial` holds the finished chunk summaries
+    #[error("Interrupted after {completed} of {total} chunks")]
+    Interrupted {
+        completed: usize,
+        total: usize,
+        partial: String,
+    },
+
+    /// `--total-timeout` stopped a run; `partial` holds the finished chunk summaries
+    #[error("Stopped by --total-timeout after {seconds} s, with {completed} of {total} chunks done")]
+    TimedOut {
+        seconds: u64,
+        completed: usize,
+        total: usize,
+        partial: String,
+    },
+}
+
+impl SummarizerError {
+    /// Classify a non-2xx API response by its status and error message
+    pub fn from_status(backend: BackendKind, model: &str, status: u16, message: String) -> Self {
+        if is_context_overflow(&message.to_lowercase()) {
+            SummarizerError::ContextOverflow {
+                model: model.to_string(),
+                message,
+            }
+        } else if status == 401 || status == 403 {
+            SummarizerError::Unauthorized { backend, message }
+        } else if status == 404 && ["model", "deployment"].iter().any(|word| message.to_lowercase().contains(word)) {
+            SummarizerError::ModelNotFound {
+                backend,
+                model: model.to_string(),
+                message,
+            }
+        } else {
+            SummarizerError::Backend {
+                backend,
+                status,
+                message,
+            }
+        }
+    }
+}
+
+/// Whether `error` is a `SummarizerError::ContextOverflow`, which no retry of the same prompt fixes
+pub(crate) fn is_overflow(error: &(dyn std::error::Error + 'static)) -> bool {
+    matches!(error.downcast_ref(), Some(SummarizerError::ContextOverflow { .. }))
+}
+
+/// Whether `error` is a `SummarizerError::Blocked`, which a retry of the same prompt won't get past
+pub(crate) fn is_blocked(error: &(dyn std::error::Error + 'static)) -> bool {
+    matches!(error.downcast_ref(), Some(SummarizerError::Blocked { .. }))
+}
+
+/// Whether `error` is a `SummarizerError::NoEmbeddings`, which no retry changes
+pub(crate) fn is_unsupported(error: &(dyn std::error::Error + 'static)) -> bool {
+    matches!(error.downcast_ref(), Some(SummarizerError::NoEmbeddings))
+}
+
+pub(crate) fn is_unreachable(error: &(dyn std::error::Error + 'static)) -> bool {
+    matches!(error.downcast_ref(), Some(SummarizerError::BackendUnreachable { .. }))
+}
+
+/// How llama.cpp, vLLM, OpenAI, Anthropic and Gemini word a prompt that is too long
+pub(crate) fn is_context_overflow(lower_message: &str) -> bool {
+    [
+        "context length",
+        "context_length_exceeded",
+        "context window",
+        "context size",
+        "prompt is too long",
+        "too many tokens",
+        "input token count",
+    ]
+    .iter()
+    .any(|pattern| lower_message.contains(pattern))
+}
+
+fn unreachable_hint(backend: BackendKind, api_base: &str) -> &'static str {
+    match backend {
+        BackendKind::Anthropic | BackendKind::Gemini | BackendKind::Azure => {
+            "check your network connection and --api-base"
+        }
+        _ if api_base.contains(":11434") => "is Ollama running on localhost:11434? Start it with `ollama serve`",
+        _ => "is the server running? Check --api-base",
+    }
+}
+
+fn transcriber_hint(transcriber: TranscriberKind) -> &'static str {
+    match transcriber {
+        TranscriberKind::WhisperCpp => {
+            "is whisper.cpp's server running? Start it with `whisper-server -m models/ggml-base.en.bin --convert`, or set --transcribe-api-base"
+        }
+        TranscriberKind::Openai => "check your network connection and --transcribe-api-base",
+    }
+}
+
+fn key_hint(backend: BackendKind) -> &'static str {
+    match backend {
+        BackendKind::Anthropic => "check --api-key or ANTHROPIC_API_KEY",
+        BackendKind::Gemini => "check --api-key or GEMINI_API_KEY",
+        BackendKind::Azure => "check --api-key or AZURE_OPENAI_API_KEY",
+        _ => "check --api-key or OPENAI_API_KEY",
+    }
+}
@@ -0,0 +1,63 @@
+// Generated title and TL;DR shown above the bullets (`--with-title`, `--with-tldr`).
+//
+// Both come from one extra request over the final summary, asking for labelled lines so the
+// reply can be told apart from any preamble.
+
+/// What the headline request produced; a part is `None` when not requested or not found
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Headline {
+    pub title: Option<String>,
+    pub tldr: Option<String>,
+}
+
+/// Prompt asking for the requested `Title:` and `TL;DR:` lines about `summary`
+pub fn prompt(title: bool, tldr: bool, summary: &str) -> String {
+    let (task, format) = match (title, tldr) {
+        (true, true) => (
+            "a title of at most ten words and a TL;DR of a single sentence",
+            "Title: ...\nTL;DR: ...",
+        ),
+        (true, false) => ("a title of at most ten words", "Title: ..."),
+        _ => ("a TL;DR of a single sentence", "TL;DR: ..."),
+    };
+    format!(
+        "Write {} for this summary.\nAnswer only in this format:\n{}\nSummary:\n{}\n\nANSWER:",
+        task, format, summary
+    )
+}
+
+/// Extract the labelled lines of a reply; without labels, the first line is taken for the one
+/// part that was asked for
+pub fn parse(reply: &str, title: bool, tldr: bool) -> Headline {
+    let mut headline = Headline::default();
+    let lines: Vec<&str> = reply.lines().map(clean).filter(|line| !line.is_empty()).collect();
+    for line in &lines {
+        if let Some(rest) = strip_label(line, "title:") {
+            headline.title = Some(clean(rest).to_string());
+        } else if let Some(rest) = strip_label(line, "tl;dr:").or_else(|| strip_label(line, "tldr:")) {
+            headline.tldr = Some(clean(rest).to_string());
+        }
+    }
+    if headline.title.is_none() && headline.tldr.is_none() && title != tldr {
+        let first = lines.first().map(|line| line.to_string());
+        if title {
+            headline.title = first;
+        } else {
+            headline.tldr = first;
+        }
+    }
+    headline.title = headline.title.filter(|_| title).filter(|t| !t.is_empty());
+    headline.tldr = headline.tldr.filter(|_| tldr).filter(|t| !t.is_empty());
+    headline
+}
+
+/// `line` after a case-insensitive `label`
+fn strip_label<'a>(line: &'a str, label: &str) -> Option<&'a str> {
+    let head = line.get(..label.len())?;
+    head.eq_ignore_ascii_case(label).then(|| &line[label.len()..])
+}
+
+/// Trim Markdown emphasis, heading marks and quotes around a line
+fn clean(line: &str) -> &str {
+    line.trim().trim_start_matches('#').trim().trim_matches(['*', '"', '“', '”']).trim()
+}
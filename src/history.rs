@@ -0,0 +1,173 @@
+// Local history of summaries, kept when `history = true`.
+//
+// Every written summary is recorded in a SQLite database with its input, a sha256 of the input
+// file, the model, mode, duration and token counts, so past summaries can be found with
+// `history list` and `history show` instead of by digging through folders.
+
+use chrono::{DateTime, Local};
+use clap::ValueEnum;
+use rusqlite::{params, Connection, OptionalExtension, Row};
+use sha2::{Digest, Sha256};
+use std::{
+    env,
+    error::Error,
+    fs,
+    io::{self, BufReader},
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use crate::{config::OutputFormat, output, summarizer::Summary};
+
+const SCHEMA: &str = "CREATE TABLE IF NOT EXISTS summaries (
+    id INTEGER PRIMARY KEY,
+    created_at TEXT NOT NULL,
+    input TEXT,
+    output TEXT,
+    content_hash TEXT,
+    model TEXT NOT NULL,
+    mode TEXT NOT NULL,
+    duration_ms INTEGER NOT NULL,
+    prompt_tokens INTEGER NOT NULL,
+    completion_tokens INTEGER NOT NULL,
+    summary TEXT NOT NULL
+)";
+
+const COLUMNS: &str = "id, created_at, input, output, content_hash, model, mode, duration_ms, \
+                       prompt_tokens, completion_tokens, summary";
+
+/// One recorded summary
+#[derive(Debug, Clone)]
+pub struct Entry {
+    pub id: i64,
+    pub created_at: DateTime<Local>,
+    /// The summarized file or video URL
+    pub input: Option<String>,
+    /// Where the summary was written
+    pub output: Option<String>,
+    /// sha256 of the input file's bytes
+    pub content_hash: Option<String>,
+    pub model: String,
+    pub mode: String,
+    pub duration: Duration,
+    pub prompt_tokens: u64,
+    pub completion_tokens: u64,
+    /// The summary as plain text, with its title, TL;DR and topics
+    pub summary: String,
+}
+
+impl Entry {
+    fn from_row(row: &Row) -> rusqlite::Result<Self> {
+        let created_at: String = row.get(1)?;
+        // SQLite integers are signed
+        let count = |i| row.get::<_, i64>(i).map(|n| n as u64);
+        Ok(Self {
+            id: row.get(0)?,
+            created_at: DateTime::parse_from_rfc3339(&created_at)
+                .map(|t| t.with_timezone(&Local))
+                .unwrap_or_default(),
+            input: row.get(2)?,
+            output: row.get(3)?,
+            content_hash: row.get(4)?,
+            model: row.get(5)?,
+            mode: row.get(6)?,
+            duration: Duration::from_millis(count(7)?),
+            prompt_tokens: count(8)?,
+            completion_tokens: count(9)?,
+            summary: row.get(10)?,
+        })
+    }
+}
+
+/// The history database
+pub struct History {
+    conn: Connection,
+}
+
+impl History {
+    /// Open the database at `default_history_path()`, creating it if needed
+    pub fn open_default() -> Result<Self, Box<dyn Error>> {
+        let path = default_history_path().ok_or("Cannot determine the data directory for the history")?;
+        Self::open(&path)
+    }
+
+    /// Open the database at `path`, creating it if needed
+    pub fn open(path: &Path) -> Result<Self, Box<dyn Error>> {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let conn = Connection::open(path).map_err(|e| format!("Cannot open history {:?}: {}", path, e))?;
+        conn.execute(SCHEMA, [])?;
+        Ok(Self { conn })
+    }
+
+    /// Record `summary`, written to `output`, and return its id
+    pub fn record(
+        &self,
+        summary: &Summary,
+        output: Option<&Path>,
+        content_hash: Option<&str>,
+    ) -> Result<i64, Box<dyn Error>> {
+        let mode = summary.mode.to_possible_value().map(|value| value.get_name().to_string());
+        let total = summary.usage.total();
+        // Absolute, so the file can be found from anywhere later; URLs are kept as they are
+        let input = summary.input.as_ref().map(|p| fs::canonicalize(p).unwrap_or_else(|_| p.clone()));
+        self.conn.execute(
+            "INSERT INTO summaries (created_at, input, output, content_hash, model, mode, duration_ms, \
+             prompt_tokens, completion_tokens, summary) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10)",
+            params![
+                summary.generated_at.to_rfc3339(),
+                input.map(|p| p.to_string_lossy().into_owned()),
+                output.map(|p| p.to_string_lossy().into_owned()),
+                content_hash,
+                summary.model,
+                mode.unwrap_or_default(),
+                summary.duration.as_millis() as i64,
+                total.prompt_tokens as i64,
+                total.completion_tokens as i64,
+                output::render(OutputFormat::Txt, summary),
+            ],
+        )?;
+        Ok(self.conn.last_insert_rowid())
+    }
+
+    /// The latest `limit` entries, newest first, optionally only those whose input or summary
+    /// contains `query` (case-insensitive)
+    pub fn list(&self, query: Option<&str>, limit: usize) -> Result<Vec<Entry>, Box<dyn Error>> {
+        let pattern = format!("%{}%", query.unwrap_or_default());
+        let mut statement = self.conn.prepare(&format!(
+            "SELECT {} FROM summaries WHERE input LIKE ?1 OR summary LIKE ?1 ORDER BY id DESC LIMIT ?2",
+            COLUMNS
+        ))?;
+        let entries = statement
+            .query_map(params![pattern, limit as i64], Entry::from_row)?
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(entries)
+    }
+
+    /// The entry with `id`
+    pub fn get(&self, id: i64) -> Result<Option<Entry>, Box<dyn Error>> {
+        let entry = self
+            .conn
+            .query_row(&format!("SELECT {} FROM summaries WHERE id = ?1", COLUMNS), [id], Entry::from_row)
+            .optional()?;
+        Ok(entry)
+    }
+}
+
+/// sha256 of a file's bytes, identifying its content however it is named; the file is read a
+/// buffer at a time, so huge inputs aren't loaded whole
+pub fn content_hash(path: &Path) -> io::Result<String> {
+    let mut hasher = Sha256::new();
+    io::copy(&mut BufReader::new(fs::File::open(path)?), &mut hasher)?;
+    Ok(format!("{:x}", hasher.finalize()))
+}
+
+/// `$XDG_DATA_HOME/srt-bullet-summarizer/history.sqlite3`, or `~/.local/share/...` without XDG
+pub fn default_history_path() -> Option<PathBuf> {
+    let base = env::var_os("XDG_DATA_HOME")
+        .filter(|v| !v.is_empty())
+        .map(PathBuf::from)
+        .or_else(|| env::var_os("HOME").map(|home| PathBuf::from(home).join(".local").join("share")))?;
+    Some(base.join("srt-bullet-summarizer").join("history.sqlite3"))
+}
@@ -0,0 +1,44 @@
+// Key terms for tagging a summary (`--with-keywords`).
+//
+// One extra, cheap request over the final summary rather than the transcript: the summary
+// already names the topics that matter, and it is a fraction of the tokens.
+
+pub const KEYWORDS_TEMPLATE: &str = r#"List the key topics, terms and named entities (people, products, organizations, places) of this summary.
+Give five to ten of them, most important first, as short noun phrases.
+Answer only with one line of comma-separated topics.
+Summary:
+{text}
+
+TOPICS:"#;
+
+/// Most topics kept from a reply
+const MAX_KEYWORDS: usize = 15;
+
+/// Extract the comma-separated (or listed) topics of a model reply, without duplicates
+pub fn parse(reply: &str) -> Vec<String> {
+    let mut keywords: Vec<String> = Vec::new();
+    for line in reply.lines() {
+        let line = line.trim().trim_start_matches(['-', '*', '•']).trim();
+        let line = ["Topics:", "Keywords:", "TOPICS:"]
+            .iter()
+            .find_map(|label| line.strip_prefix(label))
+            .unwrap_or(line);
+        for keyword in line.split([',', ';']) {
+            let keyword = keyword.trim().trim_matches(['"', '\'', '.', '*', '#']).trim();
+            // Preambles like "Here are the topics:" are not topics
+            if keyword.is_empty() || keyword.ends_with(':') || keyword.split_whitespace().count() > 6 {
+                continue;
+            }
+            if !keywords.iter().any(|k| k.eq_ignore_ascii_case(keyword)) {
+                keywords.push(keyword.to_string());
+            }
+        }
+    }
+    keywords.truncate(MAX_KEYWORDS);
+    keywords
+}
+
+/// `Topics: a, b, c`
+pub fn render(keywords: &[String]) -> String {
+    format!("Topics: {}", keywords.join(", "))
+}
@@ -0,0 +1,170 @@
+// Output language selection.
+//
+// `--lang` accepts an ISO 639-1 code (`ar`), an ISO 639-3 code (`ara`) or an English language
+// name (`Arabic`). Languages known to whatlang can also be verified in the model's output;
+// any other name is passed to the model as given, without verification. Without `--lang`, the
+// language of the transcript is detected so the summary is not silently translated.
+
+use std::error::Error;
+use whatlang::Lang;
+
+/// Characters of the transcript looked at by `detect`; plenty for a reliable guess
+const DETECT_SAMPLE_CHARS: usize = 10_000;
+
+/// ISO 639-1 codes of the languages whatlang can detect
+const ISO_639_1: &[(&str, Lang)] = &[
+    ("af", Lang::Afr),
+    ("ak", Lang::Aka),
+    ("am", Lang::Amh),
+    ("ar", Lang::Ara),
+    ("az", Lang::Aze),
+    ("be", Lang::Bel),
+    ("bg", Lang::Bul),
+    ("bn", Lang::Ben),
+    ("ca", Lang::Cat),
+    ("cs", Lang::Ces),
+    ("cy", Lang::Cym),
+    ("da", Lang::Dan),
+    ("de", Lang::Deu),
+    ("el", Lang::Ell),
+    ("en", Lang::Eng),
+    ("eo", Lang::Epo),
+    ("es", Lang::Spa),
+    ("et", Lang::Est),
+    ("fa", Lang::Pes),
+    ("fi", Lang::Fin),
+    ("fr", Lang::Fra),
+    ("gu", Lang::Guj),
+    ("he", Lang::Heb),
+    ("hi", Lang::Hin),
+    ("hr", Lang::Hrv),
+    ("hu", Lang::Hun),
+    ("hy", Lang::Hye),
+    ("id", Lang::Ind),
+    ("it", Lang::Ita),
+    ("ja", Lang::Jpn),
+    ("jv", Lang::Jav),
+    ("ka", Lang::Kat),
+    ("km", Lang::Khm),
+    ("kn", Lang::Kan),
+    ("ko", Lang::Kor),
+    ("la", Lang::Lat),
+    ("lt", Lang::Lit),
+    ("lv", Lang::Lav),
+    ("mk", Lang::Mkd),
+    ("ml", Lang::Mal),
+    ("mr", Lang::Mar),
+    ("my", Lang::Mya),
+    ("nb", Lang::Nob),
+    ("ne", Lang::Nep),
+    ("nl", Lang::Nld),
+    ("no", Lang::Nob),
+    ("or", Lang::Ori),
+    ("pa", Lang::Pan),
+    ("pl", Lang::Pol),
+    ("pt", Lang::Por),
+    ("ro", Lang::Ron),
+    ("ru", Lang::Rus),
+    ("si", Lang::Sin),
+    ("sk", Lang::Slk),
+    ("sl", Lang::Slv),
+    ("sn", Lang::Sna),
+    ("sr", Lang::Srp),
+    ("sv", Lang::Swe),
+    ("ta", Lang::Tam),
+    ("te", Lang::Tel),
+    ("th", Lang::Tha),
+    ("tk", Lang::Tuk),
+    ("tl", Lang::Tgl),
+    ("tr", Lang::Tur),
+    ("uk", Lang::Ukr),
+    ("ur", Lang::Urd),
+    ("uz", Lang::Uzb),
+    ("vi", Lang::Vie),
+    ("yi", Lang::Yid),
+    ("zh", Lang::Cmn),
+    ("zu", Lang::Zul),
+];
+
+/// Language the summary should be written in
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Language {
+    /// English name used in the prompts, e.g. `Arabic`
+    pub name: String,
+    /// Detectable language, used to verify the output
+    pub lang: Option<Lang>,
+    /// Line prepended to every prompt
+    pub instruction: String,
+}
+
+impl Language {
+    /// Parse `ar`, `ara` or `Arabic`; names whatlang doesn't know are accepted unverified
+    pub fn parse(s: &str) -> Result<Self, Box<dyn Error>> {
+        let s = s.trim();
+        let lower = s.to_lowercase();
+        let lang = match lower.len() {
+            2 => Some(
+                ISO_639_1
+                    .iter()
+                    .find(|(code, _)| *code == lower)
+                    .map(|(_, lang)| *lang)
+                    .ok_or_else(|| format!("Unknown language code {:?}; use e.g. ar, de or Arabic", s))?,
+            ),
+            3 => Lang::from_code(&lower),
+            _ => None,
+        }
+        .or_else(|| {
+            Lang::all()
+                .iter()
+                .copied()
+                .find(|lang| lang.eng_name().eq_ignore_ascii_case(s))
+        });
+
+        match lang {
+            Some(lang) => Ok(Self::from_lang(lang)),
+            None if !s.is_empty() && s.chars().all(|c| c.is_alphabetic() || c == ' ') => {
+                Ok(Self::named(s.to_string(), None))
+            }
+            None => Err(format!("Unknown language {:?}; use e.g. ar, de or Arabic", s).into()),
+        }
+    }
+
+    pub fn from_lang(lang: Lang) -> Self {
+        Self::named(lang.eng_name().to_string(), Some(lang))
+    }
+
+    /// The language `text` is written in, when whatlang is confident about it
+    pub fn detect(text: &str) -> Option<Self> {
+        let sample: String = text.chars().take(DETECT_SAMPLE_CHARS).collect();
+        whatlang::detect(&sample)
+            .filter(|info| info.is_reliable())
+            .map(|info| Self::from_lang(info.lang()))
+    }
+
+    fn named(name: String, lang: Option<Lang>) -> Self {
+        Self {
+            instruction: format!("Write your entire answer in {}.", name),
+            name,
+            lang,
+        }
+    }
+
+    /// Whether `text` looks like it is in this language; unverifiable cases count as a match
+    pub fn matches(&self, text: &str) -> bool {
+        let Some(expected) = self.lang else {
+            return true;
+        };
+        match whatlang::detect(text) {
+            Some(info) if info.is_reliable() => info.lang() == expected,
+            _ => true,
+        }
+    }
+
+    /// Line prepended when a reply came back in the wrong language
+    pub fn reminder(&self) -> String {
+        format!(
+            "Important: the answer must be written in {}, even though the text may be in another language.",
+            self.name
+        )
+    }
+}
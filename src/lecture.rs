@@ -0,0 +1,135 @@
+// Lecture notes written by `--mode lecture`.
+//
+// The combine reply is sorted into four fixed sections by its headings, matched loosely (`##
+// Definitions` counts as key concepts), and rewritten under the canonical headings in a fixed
+// order. Key concepts are split into a term and its one-line definition, whichever of `:`, `—`,
+// `–`, `→`, `->` or ` - ` separates them, and merged when a term comes up twice.
+
+use crate::bullets;
+
+pub const CONCEPTS_HEADING: &str = "Key Concepts";
+pub const MAIN_POINTS_HEADING: &str = "Main Points";
+pub const EXAMPLES_HEADING: &str = "Examples Mentioned";
+pub const QUESTIONS_HEADING: &str = "Further Questions";
+
+/// A term the lecture introduces
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Concept {
+    pub term: String,
+    /// One line; empty when the model gave none
+    pub definition: String,
+}
+
+/// The sections of a lecture's notes
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct LectureNotes {
+    pub concepts: Vec<Concept>,
+    pub main_points: Vec<String>,
+    pub examples: Vec<String>,
+    /// Open questions for the student to think about
+    pub questions: Vec<String>,
+}
+
+#[derive(Clone, Copy)]
+enum Section {
+    Concepts,
+    MainPoints,
+    Examples,
+    Questions,
+}
+
+/// Extract the notes of a model reply; bullets before the first heading count as main points,
+/// and `- None` placeholders and duplicates are dropped
+pub fn parse(reply: &str) -> LectureNotes {
+    let mut notes = LectureNotes::default();
+    let mut section = Section::MainPoints;
+    for line in bullets::normalize(reply).lines() {
+        let line = line.trim();
+        if let Some(heading) = line.strip_prefix('#') {
+            section = section_of(heading);
+            continue;
+        }
+        let Some(item) = line.strip_prefix("- ").map(str::trim) else {
+            continue;
+        };
+        if item.is_empty() || item.eq_ignore_ascii_case("none") {
+            continue;
+        }
+        let items = match section {
+            Section::Concepts => {
+                let concept = concept(item);
+                let key = concept.term.to_lowercase();
+                match notes.concepts.iter_mut().find(|known| known.term.to_lowercase() == key) {
+                    Some(known) if known.definition.is_empty() => known.definition = concept.definition,
+                    Some(_) => {}
+                    None => notes.concepts.push(concept),
+                }
+                continue;
+            }
+            Section::MainPoints => &mut notes.main_points,
+            Section::Examples => &mut notes.examples,
+            Section::Questions => &mut notes.questions,
+        };
+        if !items.iter().any(|known| known.eq_ignore_ascii_case(item)) {
+            items.push(item.to_string());
+        }
+    }
+    notes
+}
+
+/// The four sections under their `##` headings, `- None` under an empty one
+pub fn render(notes: &LectureNotes) -> String {
+    let concepts: Vec<String> = notes
+        .concepts
+        .iter()
+        .map(|concept| match concept.definition.as_str() {
+            "" => concept.term.clone(),
+            definition => format!("{}: {}", concept.term, definition),
+        })
+        .collect();
+    [
+        (CONCEPTS_HEADING, &concepts),
+        (MAIN_POINTS_HEADING, &notes.main_points),
+        (EXAMPLES_HEADING, &notes.examples),
+        (QUESTIONS_HEADING, &notes.questions),
+    ]
+    .iter()
+    .map(|(heading, items)| {
+        let items = match items.as_slice() {
+            [] => "- None".to_string(),
+            items => items.iter().map(|item| format!("- {}", item)).collect::<Vec<_>>().join("\n"),
+        };
+        format!("## {}\n{}", heading, items)
+    })
+    .collect::<Vec<_>>()
+    .join("\n\n")
+}
+
+fn section_of(heading: &str) -> Section {
+    let heading = heading.to_lowercase();
+    if ["concept", "term", "definition", "glossary"].iter().any(|word| heading.contains(word)) {
+        Section::Concepts
+    } else if heading.contains("example") {
+        Section::Examples
+    } else if heading.contains("question") {
+        Section::Questions
+    } else {
+        Section::MainPoints
+    }
+}
+
+/// Split a key concept into its term and definition at the first separator
+fn concept(item: &str) -> Concept {
+    let separator = [":", " — ", " – ", "→", "->", " - "]
+        .iter()
+        .filter_map(|separator| item.find(separator).map(|at| (at, separator.len())))
+        .min();
+    let (term, definition) = match separator {
+        Some((at, len)) => (&item[..at], &item[at + len..]),
+        None => (item, ""),
+    };
+    Concept {
+        term: term.trim().trim_matches('*').trim().to_string(),
+        definition: definition.trim().trim_start_matches('*').trim().to_string(),
+    }
+}
@@ -0,0 +1,67 @@
+//! Summarize `.srt`/`.vtt`/`.ass`/`.ssa` subtitle files, Whisper `.json` transcripts, PDFs, EPUB
+//! books, web pages, Markdown, plain text and (transcribed) recordings into bullet points with an
+//! LLM behind an OpenAI-compatible API (e.g. LLaMA 3.2 served by Ollama).
+//!
+//! Subtitle files are parsed into cues ([`parser`]), flattened to text, split into overlapping
+//! chunks ([`chunker`]), summarized chunk by chunk and combined into one list ([`summarizer`]),
+//! then rendered as text, Markdown or JSON ([`output`]). Timed subtitles can also be turned into
+//! YouTube chapters ([`chapters`]). LLM providers sit behind the [`backend::LlmBackend`] trait,
+//! so custom backends can be passed to [`Summarizer::with_backend`]. Failures a user can fix
+//! (a missing file, an unreachable server, a prompt over the context window) are returned as a
+//! boxed [`SummarizerError`] with a hint in its message. Progress and warnings are `tracing`
+//! events, so they only show up once the application installs a subscriber.
+//!
+//! ```no_run
+//! use srt_bullet_summarizer::{Settings, Summarizer};
+//!
+//! # async fn run() -> Result<(), Box<dyn std::error::Error>> {
+//! let summarizer = Summarizer::new(Settings::default())?;
+//! let summary = summarizer.summarize_file("lecture.srt".as_ref()).await?;
+//! println!("{}", summary.final_summary);
+//! # Ok(())
+//! # }
+//! ```
+
+pub mod action_items;
+pub mod ask;
+pub mod backend;
+pub mod batch;
+pub mod bullets;
+pub mod cache;
+pub mod chapters;
+pub mod checkpoint;
+pub mod chunker;
+pub mod compare;
+pub mod config;
+pub mod debug_dump;
+pub mod doctor;
+pub mod entities;
+pub mod error;
+pub mod headline;
+pub mod history;
+pub mod keywords;
+pub mod language;
+pub mod lecture;
+pub mod mcp;
+pub mod outline;
+pub mod output;
+pub mod parser;
+pub mod post;
+pub mod qa;
+pub mod rate_limit;
+pub mod retry;
+pub mod rollup;
+pub mod server;
+pub mod shownotes;
+pub mod summarizer;
+pub mod topics;
+pub mod transcribe;
+pub mod usage;
+pub mod watch;
+pub mod web;
+pub mod webhook;
+pub mod youtube;
+
+pub use config::{Length, Mode, OutputFormat, Settings, SettingsLayer, Split, Strategy};
+pub use error::SummarizerError;
+pub use summarizer::{Summarizer, Summary};
@@ -0,0 +1,153 @@
+// Logging setup for the binary.
+//
+// The library reports progress and warnings as `tracing` events. On stderr they read like plain
+// messages, with `Warning:`/`Error:` in front of those levels, so stdout stays free for results
+// such as `--stdout` summaries. Files summarized side by side with `--jobs` run in `job` spans,
+// whose events start with the file name. `--log-file` adds every event down to debug level as
+// JSON lines, with the input being summarized, for going through long batch runs afterwards.
+
+use std::{
+    fmt,
+    fs::OpenOptions,
+    io,
+    path::Path,
+    sync::Mutex,
+};
+use tracing::{
+    field::Field,
+    span::{Attributes, Id},
+    Event, Level, Subscriber,
+};
+use tracing_subscriber::{
+    field::Visit,
+    filter::{LevelFilter, Targets},
+    fmt::{format::Writer, FmtContext, FormatEvent, FormatFields},
+    layer::{Context, SubscriberExt},
+    registry::LookupSpan,
+    util::SubscriberInitExt,
+    Layer,
+};
+
+/// Events of this crate (the library and the binary share its name); other crates only warn
+const CRATE: &str = "srt_bullet_summarizer";
+
+/// Crates warning about the markup of the pages being read rather than anything to fix
+const NOISY: &[&str] = &["html5ever"];
+
+/// How much to log to stderr
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Verbosity {
+    /// Errors only
+    Quiet,
+    Normal,
+    /// Also per-request timings and token counts
+    Verbose,
+}
+
+/// Install the global subscriber, appending to `log_file` when given
+pub fn init(verbosity: Verbosity, log_file: Option<&Path>) -> Result<(), Box<dyn std::error::Error>> {
+    let level = match verbosity {
+        Verbosity::Quiet => LevelFilter::ERROR,
+        Verbosity::Normal => LevelFilter::INFO,
+        Verbosity::Verbose => LevelFilter::DEBUG,
+    };
+    let stderr = tracing_subscriber::fmt::layer()
+        .event_format(Plain)
+        .with_writer(io::stderr)
+        .with_filter(targets(LevelFilter::WARN.min(level)).with_target(CRATE, level));
+    let file = match log_file {
+        Some(path) => {
+            let file = OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(path)
+                .map_err(|e| format!("Cannot open log file {:?}: {}", path, e))?;
+            let layer = tracing_subscriber::fmt::layer()
+                .json()
+                .with_writer(Mutex::new(file))
+                .with_filter(targets(LevelFilter::WARN).with_target(CRATE, LevelFilter::DEBUG));
+            Some(layer)
+        }
+        None => None,
+    };
+    tracing_subscriber::registry()
+        .with(JobLabels)
+        .with(stderr)
+        .with(file)
+        .try_init()?;
+    Ok(())
+}
+
+/// Other crates at `level`, the noisy ones at errors only
+fn targets(level: LevelFilter) -> Targets {
+    NOISY
+        .iter()
+        .fold(Targets::new().with_default(level), |targets, noisy| targets.with_target(*noisy, LevelFilter::ERROR))
+}
+
+/// Just the message, as the old `println!`s printed it
+struct Plain;
+
+impl<S, N> FormatEvent<S, N> for Plain
+where
+    S: Subscriber + for<'a> LookupSpan<'a>,
+    N: for<'a> FormatFields<'a> + 'static,
+{
+    fn format_event(&self, ctx: &FmtContext<'_, S, N>, mut writer: Writer<'_>, event: &Event<'_>) -> fmt::Result {
+        if let Some(scope) = ctx.event_scope() {
+            for span in scope.from_root() {
+                if let Some(label) = span.extensions().get::<JobLabel>() {
+                    write!(writer, "[{}] ", label.0)?;
+                }
+            }
+        }
+        match *event.metadata().level() {
+            Level::ERROR => write!(writer, "Error: ")?,
+            Level::WARN => write!(writer, "Warning: ")?,
+            _ => {}
+        }
+        let mut message = FieldText("message", String::new());
+        event.record(&mut message);
+        writeln!(writer, "{}", message.1)
+    }
+}
+
+/// The `file` field of a `job` span
+struct JobLabel(String);
+
+/// Keeps the `JobLabel` of every `job` span for `Plain` to start its events with
+struct JobLabels;
+
+impl<S> Layer<S> for JobLabels
+where
+    S: Subscriber + for<'a> LookupSpan<'a>,
+{
+    fn on_new_span(&self, attrs: &Attributes<'_>, id: &Id, ctx: Context<'_, S>) {
+        if attrs.metadata().name() != "job" {
+            return;
+        }
+        let mut file = FieldText("file", String::new());
+        attrs.record(&mut file);
+        if let Some(span) = ctx.span(id) {
+            span.extensions_mut().insert(JobLabel(file.1));
+        }
+    }
+}
+
+/// Collects one field of an event or span, e.g. the `message` of an event, leaving the
+/// structured fields to the log file
+struct FieldText(&'static str, String);
+
+impl Visit for FieldText {
+    fn record_str(&mut self, field: &Field, value: &str) {
+        if field.name() == self.0 {
+            self.1 = value.to_string();
+        }
+    }
+
+    fn record_debug(&mut self, field: &Field, value: &dyn fmt::Debug) {
+        if field.name() == self.0 {
+            self.1 = format!("{:?}", value);
+        }
+    }
+}
@@ -2,170 +2,1147 @@
 // Project: srt-bullet-summarizer
 //
 // Description:
-// This Rust CLI tool processes `.srt` (subtitle) or `.txt` files and generates a concise,
+// This Rust CLI tool processes `.srt`/`.vtt`/`.ass`/`.ssa` (subtitle), `.txt`, `.md`, `.pdf` or `.epub` files, or recordings, and generates a concise,
 // bullet-point summary using a locally hosted LLM like LLaMA 3.2 via an OpenAI-compatible API.
-// For `.srt` files, it strips timestamps and sequence numbers before processing. Text is split
+// For `.srt` files, it strips timestamps and sequence numbers before processing; for `.vtt` files,
+// it also drops the WEBVTT header, NOTE/STYLE blocks, cue identifiers and cue settings; for `.ass`/`.ssa`
+// files, only the Text field of Dialogue events is kept, without override tags; Whisper `.json`
+// transcripts (openai-whisper, faster-whisper, whisper.cpp) contribute their segments. Text is split
 // into overlapping chunks, summarized individually (Map), and then combined (Reduce) into a final summary.
 //
+// Subtitle files are parsed into structured cues (see `parser`) and then flattened to plain text.
+// The summarization pipeline lives in the library crate (`src/lib.rs`); this binary only parses
+// the command line, resolves settings and writes the results.
+//
 // Dependencies:
-// - langchain_rust: For LLM chaining and prompt handling.
-// - serde_json: For dynamic input/output with the LLM.
-// - regex: For timestamp/sequence number removal from `.srt` files.
+// - async-trait: For the pluggable `LlmBackend` trait.
+// - reqwest: For the OpenAI-compatible, Ollama, Azure, Anthropic and Gemini backends, the transcription APIs, webhooks and Slack and Discord posts.
+// - serde_json: For JSON output and checkpoints.
+// - futures: For running the map step with bounded concurrency.
+// - clap: For command-line parsing.
+// - toml: For the optional configuration file.
+// - tiktoken-rs: For token-budgeted chunking.
+// - chrono: For timestamps in the Markdown output.
+// - glob: For expanding input patterns in batch mode.
+// - indicatif: For the map step progress bar.
+// - sha2: For keying map step checkpoints and cached replies to their input.
+// - hmac: For signing webhook deliveries.
+// - rand: For jitter in the retry backoff.
+// - regex: For markup and override tag removal in the subtitle parsers.
+// - whatlang: For checking the summary is in the `--lang` language.
+// - hyper, hyper-util, http-body-util: For the `serve` HTTP API.
+// - chardetng, encoding_rs: For reading subtitle files that aren't UTF-8.
+// - thiserror: For error messages with hints on how to fix them.
+// - notify: For the `watch` subcommand.
+// - rusqlite: For the opt-in history of summaries.
+// - tracing, tracing-subscriber: For progress messages, --verbose/--quiet and --log-file.
+// - pdf-extract: For reading `.pdf` inputs.
+// - dom_smoothie: For extracting the article from `.html` files and web pages.
+// - epub, dom_query: For reading the chapters of `.epub` books.
+// - candle-core, candle-transformers, tokenizers: For `--backend local` (the optional `local` feature).
 //
 // How to Use:
 // 1. Compile the code using Cargo: `cargo build --release`.
-// 2. Run the tool with the input file path as the first argument. Optionally, specify an output path.
+// 2. Run the tool with the input file path as the first argument. Optionally, pass `--output`.
+//    Directories and glob patterns summarize every subtitle file they contain.
+// 3. See `--help` for model, API, chunking and concurrency options.
 //
 // Example Usage:
 // $ ./srt-bullet-summarizer ./example.srt
-// $ ./srt-bullet-summarizer ./notes.txt ./output/summary.txt
+// $ ./srt-bullet-summarizer ./zoom_recording.vtt
+// $ ./srt-bullet-summarizer ./episode01.ass
+// $ ./srt-bullet-summarizer ./notes.txt --output ./output/summary.txt
+// $ ./srt-bullet-summarizer ./paper.pdf
+// $ ./srt-bullet-summarizer ./book.epub
+// $ ./srt-bullet-summarizer ./meeting.m4a --transcribe-api-base http://127.0.0.1:8080
+// $ cat ./lecture.srt | ./srt-bullet-summarizer - > summary.txt
+// $ ./srt-bullet-summarizer ./lecture.srt --concurrency 4 --model llama3.1:8b
+// $ ./srt-bullet-summarizer ./lecture.srt --model gpt-4o-mini --concurrency 8 --rpm 500 --tpm 200000
+// $ ./srt-bullet-summarizer ./lecture.srt --backend gemini --model gemini-2.0-flash
+// $ ./srt-bullet-summarizer ./lecture.srt --backend local --model-path ./llama3.2.gguf
+// $ ./srt-bullet-summarizer ./lecture.srt --backend azure --api-base https://my-resource.openai.azure.com --deployment prod-gpt-4o
+// $ ./srt-bullet-summarizer ./lecture.srt --map-model llama3.2:1b --combine-model llama3.3
+// $ ./srt-bullet-summarizer ./lecture.srt --backend ollama --fallback openai --fallback-model gpt-4o-mini
+// $ ./srt-bullet-summarizer ./season1/ --recursive
+// $ ./srt-bullet-summarizer ./season1/ --jobs 3 --concurrency 6
+// $ ./srt-bullet-summarizer ./standup.vtt --webhook https://hooks.example.com/summaries
+// $ ./srt-bullet-summarizer ./standup.vtt --post slack:https://hooks.slack.com/services/T000/B000/XXXX
+// $ ./srt-bullet-summarizer ./part1.srt ./part2.srt ./part3.srt --merge
+// $ ./srt-bullet-summarizer compare ./standup_week1.vtt ./standup_week2.vtt
+// $ ./srt-bullet-summarizer ask ./meeting.srt "What did they decide about pricing?"
+// $ ./srt-bullet-summarizer rollup ./season1
+// $ ./srt-bullet-summarizer ./season1/ --output-dir ./summaries --output-name "{stem}.{date}.summary.{ext}"
+// $ ./srt-bullet-summarizer ./long_lecture.srt --resume
+// $ ./srt-bullet-summarizer ./live_lecture.srt --incremental
+// $ ./srt-bullet-summarizer ./long_lecture.srt --request-timeout 300 --total-timeout 3600
+// $ ./srt-bullet-summarizer ./lecture.srt --stream
+// $ ./srt-bullet-summarizer ./long_lecture.srt --polish
+// $ ./srt-bullet-summarizer ./lecture.srt --model llama3.2:1b --samples 3
+// $ ./srt-bullet-summarizer ./lecture.srt --dry-run --chunk-size 1500
+// $ ./srt-bullet-summarizer ./lecture.srt --context-window 8192 --dry-run
+// $ ./srt-bullet-summarizer ./podcast.srt --chapters
+// $ ./srt-bullet-summarizer ./interview.srt --with-entities --format json
+// $ ./srt-bullet-summarizer ./lecture.srt --format obsidian --vault ~/Notes/Summaries
+// $ ./srt-bullet-summarizer ./lecture.srt --format anki --deck-name "Rust::Ownership"
+// $ ./srt-bullet-summarizer ./lecture.srt --mode outline --format md
+// $ ./srt-bullet-summarizer ./episode42.srt --mode shownotes --format md
+// $ ./srt-bullet-summarizer ./lecture.srt --mode lecture --format html
+// $ ./srt-bullet-summarizer ./standup.srt --mode topics --format md
+// $ ./srt-bullet-summarizer ./podcast.srt --chapters --format html --video-url "https://youtu.be/VIDEO_ID"
+// $ ./srt-bullet-summarizer ./meeting.vtt --from 00:15:00 --to 01:05:00
+// $ ./srt-bullet-summarizer ./arabic_lecture.srt --lang ar
+// $ ./srt-bullet-summarizer ./arabic_lecture.srt --translate en --translate-transcript
+// $ ./srt-bullet-summarizer "https://www.youtube.com/watch?v=VIDEO_ID" --sub-lang en
+// $ ./srt-bullet-summarizer fetch "https://www.youtube.com/playlist?list=PLAYLIST_ID" --output-dir ./lectures
+// $ ./srt-bullet-summarizer "https://example.com/blog/long-read"
+// $ ./srt-bullet-summarizer convert ./example.srt
+// $ ./srt-bullet-summarizer serve --addr 127.0.0.1:8080
+// $ ./srt-bullet-summarizer mcp --model gpt-4o-mini
+// $ ./srt-bullet-summarizer watch ~/Videos/OBS --output-dir ./summaries
+// $ ./srt-bullet-summarizer history list
+// $ ./srt-bullet-summarizer cache stats
+// $ ./srt-bullet-summarizer doctor --backend ollama --model llama3.2
 //
 // The summary will be saved in the same directory as the input file by default, using the
-// filename format: `<original_name>_summary.txt` if no output path is given.
+// filename format: `<original_name>_summary.txt` if no output path is given. With `--chapters`,
+// YouTube chapter markers are also written to `<original_name>_chapters.txt`. `--output-dir`
+// collects the outputs in one directory, and `--output-name` templates the summary file name.
+
+mod cli;
+mod logging;
 
-use regex::Regex;
-use serde_json::Value;
+use chrono::{DateTime, Local};
+use clap::Parser;
+use cli::{
+    AskArgs, CacheCommand, Cli, Command, CompareArgs, ConfigCommand, ConvertArgs, DoctorArgs, FetchArgs, HistoryCommand, McpArgs, RollupArgs, ServeArgs,
+    SummarizeArgs, WatchArgs,
+};
+use srt_bullet_summarizer::{
+    batch::{self, BatchReport},
+    cache, chapters,
+    config::{self, Settings, SettingsLayer},
+    debug_dump::DebugDump,
+    doctor::{self, Status},
+    history::{self, History},
+    mcp, output,
+    parser::{self, Transcript},
+    post, rollup, server,
+    summarizer::Plan,
+    watch::FolderWatcher,
+    web, webhook, youtube, Mode, OutputFormat, Summarizer, SummarizerError, Summary,
+};
 use std::{
-    collections::HashMap,
-    env,
     error::Error,
     fs,
-    path::Path,
-    time::Instant,
-};
-use langchain_rust::{
-    chain::{Chain, LLMChainBuilder},
-    llm::openai::{OpenAI, OpenAIConfig},
-    prompt::{HumanMessagePromptTemplate, PromptTemplate, TemplateFormat},
+    io::{self, Read, Write},
+    path::{Path, PathBuf},
+    process::ExitCode,
+    sync::Arc,
+    time::{Instant, SystemTime},
 };
+use futures::{stream, StreamExt};
+use tracing::{error, info, info_span, warn, Instrument, Span};
 
-const MAP_TEMPLATE: &str = r#"Write a detailed summary of this text section in bullet points.
-Use '-' for bullet points and answer only the bullet points.
-Text:
-{text}
+#[tokio::main]
+async fn main() -> ExitCode {
+    let cli = Cli::parse();
+    if let Err(e) = logging::init(cli.log.verbosity(), cli.log.log_file.as_deref()) {
+        eprintln!("Error: {}", e);
+        return ExitCode::FAILURE;
+    }
+    // Progress bars count as output that --quiet hides
+    let progress = !cli.log.quiet;
+    let result = match cli.into_command() {
+        Command::Summarize(args) => summarize(*args, progress).await,
+        Command::Compare(args) => compare(*args, progress).await,
+        Command::Ask(args) => ask(*args).await,
+        Command::Rollup(args) => rollup(*args, progress).await,
+        Command::Convert(args) => convert(args),
+        Command::Serve(args) => serve(*args).await,
+        Command::Mcp(args) => mcp(*args).await,
+        Command::Watch(args) => watch(*args, progress).await,
+        Command::Fetch(args) => fetch(*args, progress).await,
+        Command::Doctor(args) => run_doctor(*args).await,
+        Command::Config { command } => run_config(command),
+        Command::History { command } => run_history(command),
+        Command::Cache { command } => run_cache(command),
+    };
+    // Print errors with `Display`, so their hints read as sentences rather than debug output
+    match result {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            error!("{}", e);
+            // 128 + SIGINT, as shells report a process stopped by Ctrl+C
+            if is_interrupted(e.as_ref()) {
+                ExitCode::from(130)
+            } else {
+                ExitCode::FAILURE
+            }
+        }
+    }
+}
 
-SUMMARY:"#;
+async fn summarize(args: SummarizeArgs, progress: bool) -> Result<(), Box<dyn Error>> {
+    let settings = args.settings.resolve_sized().await?;
 
-const COMBINE_TEMPLATE: &str = r#"Combine these summaries into a final summary in bullet points.
-Use '-' for bullet points and answer only the bullet points.
-Text:
-{text}
+    // === 1. Collect input files, keeping URLs and `-` (stdin) as they are ===
+    let (urls, paths): (Vec<PathBuf>, Vec<PathBuf>) = args
+        .inputs
+        .iter()
+        .cloned()
+        .partition(|input| youtube::is_url(input) || is_stdio(input));
+    let mut inputs = if paths.is_empty() {
+        Vec::new()
+    } else {
+        batch::collect_inputs(&paths, args.recursive, args.merge)?
+    };
+    if args.merge && !urls.is_empty() {
+        return Err("--merge only takes files, not URLs or - (stdin)".into());
+    }
+    inputs.extend(urls);
+    if inputs.len() > 1 && inputs.iter().any(|input| is_stdio(input)) {
+        return Err("- (stdin) cannot be combined with other inputs".into());
+    }
+    let output = if args.stdout { Some(PathBuf::from("-")) } else { args.output };
+    if output.is_some() && inputs.len() > 1 && !args.merge {
+        return Err("--output and --stdout can only be used with a single input file".into());
+    }
+    if let Some(template) = &args.output_name {
+        output::check_template(template)?;
+        if inputs.len() > 1 && !args.merge && !template.contains("{stem}") {
+            return Err("--output-name needs {stem} when summarizing more than one input".into());
+        }
+    }
+    if let (Some(from), Some(to)) = (args.from, args.to) {
+        if from >= to {
+            return Err("--from must be before --to".into());
+        }
+    }
+    if args.merge && (args.from.is_some() || args.to.is_some()) {
+        return Err("--from and --to cannot be combined with --merge".into());
+    }
+    if args.incremental && (args.from.is_some() || args.to.is_some()) {
+        return Err("--from and --to cannot be combined with --incremental".into());
+    }
+    if args.incremental && inputs.iter().any(|input| youtube::is_url(input) || is_stdio(input)) {
+        return Err("--incremental only takes files, not URLs or - (stdin)".into());
+    }
+    let output_dir = args.output_dir.or_else(|| settings.vault_dir());
+    if let Some(dir) = &output_dir {
+        fs::create_dir_all(dir)?;
+    }
+    let layout = OutputLayout {
+        dir: output_dir,
+        name: args.output_name,
+        // Incremental runs update their summary
+        force: args.force || args.incremental,
+    };
 
-FINAL SUMMARY:"#;
+    // === 2. Configure LLM and chains once for every file ===
+    // The progress bars of files summarized side by side would draw over one another
+    let jobs = args.jobs.min(inputs.len());
+    let summarizer = Summarizer::new(settings)?
+        .with_resume(args.resume)
+        .with_incremental(args.incremental)
+        .with_stream(args.stream)
+        .with_chapters(args.chapters)
+        .with_keywords(args.with_keywords)
+        .with_entities(args.with_entities)
+        .with_headline(args.with_title, args.with_tldr)
+        .with_range(args.from, args.to)
+        .with_interrupt(true)
+        .with_progress(progress && jobs == 1)
+        .with_debug_dump(args.debug_dump.as_deref().map(DebugDump::new).transpose()?);
+    let sub_lang = args.sub_lang.as_deref();
+    if args.dry_run {
+        if args.merge {
+            let plan = summarizer.plan_files(&inputs).await?;
+            print_plan(&plan);
+            return Ok(());
+        }
+        return dry_run(&summarizer, &inputs, sub_lang).await;
+    }
+    summarizer.prepare().await?;
 
-#[tokio::main]
-async fn main() -> Result<(), Box<dyn Error>> {
-    // === 1. Get input file path ===
-    let args: Vec<String> = env::args().collect();
-    if args.len() < 2 {
-        eprintln!("Usage: {} <input_file>", args[0]);
-        std::process::exit(1);
-    }
-    let input_path = Path::new(&args[1]);
-    if !input_path.exists() {
-        return Err(format!("File not found: {:?}", input_path).into());
+    if args.merge {
+        summarize_merged(&summarizer, &inputs, output, &layout).await?;
+        return Ok(());
+    }
+
+    if let [input_path] = inputs.as_slice() {
+        summarize_input(&summarizer, input_path, output, &layout, sub_lang).await?;
+        return Ok(());
+    }
+
+    // === 3. Batch: keep going past failures and report at the end ===
+    let mut report = BatchReport::default();
+    let (summarizer, layout, total) = (&summarizer, &layout, inputs.len());
+    let mut results = stream::iter(inputs.iter().enumerate())
+        .map(|(i, input_path)| {
+            let name = input_path.file_name().unwrap_or(input_path.as_os_str()).to_string_lossy();
+            let span = if jobs > 1 {
+                info_span!("job", file = %name)
+            } else {
+                Span::none()
+            };
+            async move {
+                info!("[{}/{}]", i + 1, total);
+                (input_path, summarize_input(summarizer, input_path, None, layout, sub_lang).await)
+            }
+            .instrument(span)
+        })
+        .buffered(jobs);
+    while let Some((input_path, result)) = results.next().await {
+        match result {
+            Ok(output_path) => report.succeeded.push((input_path.clone(), output_path)),
+            Err(e) if is_interrupted(e.as_ref()) => {
+                report.print();
+                return Err(e);
+            }
+            Err(e) => {
+                error!("Failed to summarize {:?}: {}", input_path, e);
+                report.failed.push((input_path.clone(), e.to_string()));
+            }
+        }
+    }
+    report.print();
+
+    if report.failed.is_empty() {
+        Ok(())
+    } else {
+        Err(format!("{} of {} files failed", report.failed.len(), inputs.len()).into())
+    }
+}
+
+/// Where summaries go when `--output` isn't given
+struct OutputLayout {
+    /// `--output-dir`, instead of the directory of each input
+    dir: Option<PathBuf>,
+    /// `--output-name` template, instead of `<stem>_summary.<ext>`
+    name: Option<String>,
+    /// `--force`: overwrite summaries that already exist
+    force: bool,
+}
+
+impl OutputLayout {
+    /// `input_path` as if it were in the output directory
+    fn relocate(&self, input_path: &Path) -> PathBuf {
+        match &self.dir {
+            Some(dir) => dir.join(input_path.file_name().unwrap_or_default()),
+            None => input_path.to_path_buf(),
+        }
+    }
+
+    fn summary_path(&self, input_path: &Path, summary: &Summary, extension: &str) -> Result<PathBuf, Box<dyn Error>> {
+        let input_path = self.relocate(input_path);
+        match &self.name {
+            Some(template) => {
+                let name = output::template_name(template, &input_path, summary, extension)?;
+                Ok(input_path.with_file_name(name))
+            }
+            None => Ok(output::sibling_path(&input_path, "summary", extension)),
+        }
+    }
+
+    /// Refuse to overwrite an existing summary at `output_path` unless `--force` was given
+    fn check_overwrite(&self, output_path: &Path) -> Result<(), Box<dyn Error>> {
+        if !self.force && !is_stdio(output_path) && output_path.exists() {
+            return Err(format!("{:?} already exists; pass --force to overwrite it", output_path).into());
+        }
+        Ok(())
+    }
+
+    /// The summary path of `input_path` when it doesn't depend on the summary itself, to check
+    /// for an existing summary before any request is made
+    fn known_summary_path(&self, input_path: &Path, output: Option<&Path>, extension: &str) -> Option<PathBuf> {
+        match (output, &self.name) {
+            (Some(path), _) => Some(path.to_path_buf()),
+            (None, None) => Some(output::sibling_path(&self.relocate(input_path), "summary", extension)),
+            // Templates can name the date, model and mode of the summary
+            (None, Some(_)) => None,
+        }
     }
 
-    println!("Processing file: {:?}", input_path);
+    fn chapters_path(&self, input_path: &Path) -> PathBuf {
+        output::sibling_path(&self.relocate(input_path), "chapters", "txt")
+    }
+
+    /// `<stem>_partial.txt` for the chunk summaries of an interrupted run
+    fn partial_path(&self, input_path: &Path) -> PathBuf {
+        output::sibling_path(&self.relocate(input_path), "partial", "txt")
+    }
+
+    /// `<stem>_transcript_<language>.txt` for a translated transcript
+    fn translation_path(&self, input_path: &Path, language: &str) -> PathBuf {
+        let language = youtube::file_stem(&language.to_lowercase().replace(' ', "_"), "translation");
+        output::sibling_path(&self.relocate(input_path), &format!("transcript_{}", language), "txt")
+    }
+}
+
+/// Summarize a file, URL or stdin and return where the summary was written
+async fn summarize_input(
+    summarizer: &Summarizer,
+    input: &Path,
+    output: Option<PathBuf>,
+    layout: &OutputLayout,
+    sub_lang: Option<&str>,
+) -> Result<PathBuf, Box<dyn Error>> {
+    // Every event of the run carries the input in the log file
+    let span = info_span!("input", path = %input.display());
+    if is_stdio(input) {
+        summarize_stdin(summarizer, output, layout).instrument(span).await
+    } else if youtube::is_url(input) {
+        summarize_url(summarizer, input, output, layout, sub_lang).instrument(span).await
+    } else {
+        summarize_file(summarizer, input, output, layout).instrument(span).await
+    }
+}
+
+/// Summarize one file and return where the summary was written
+async fn summarize_file(
+    summarizer: &Summarizer,
+    input_path: &Path,
+    output: Option<PathBuf>,
+    layout: &OutputLayout,
+) -> Result<PathBuf, Box<dyn Error>> {
+    let extension = summarizer.settings().format.extension();
+    let known_path = layout.known_summary_path(input_path, output.as_deref(), extension);
+    if let Some(path) = &known_path {
+        layout.check_overwrite(path)?;
+    }
+    let summary = match summarizer.summarize_file(input_path).await {
+        Err(e) if is_up_to_date(e.as_ref()) => {
+            info!("{}", e);
+            return Ok(known_path.unwrap_or_else(|| input_path.to_path_buf()));
+        }
+        summary => summary.map_err(|e| save_partial(e, input_path, layout, true))?,
+    };
+    write_summary(summarizer, &summary, input_path, output, layout).await
+}
+
+/// Summarize several files as one and return where the summary was written; its outputs are
+/// named after the first file, as `<stem>_merged_summary.txt`
+async fn summarize_merged(
+    summarizer: &Summarizer,
+    input_paths: &[PathBuf],
+    output: Option<PathBuf>,
+    layout: &OutputLayout,
+) -> Result<PathBuf, Box<dyn Error>> {
+    let first = &input_paths[0];
+    let stem = first.file_stem().unwrap_or_default().to_string_lossy();
+    let named = first.with_file_name(format!("{}_merged.txt", stem));
+    let extension = summarizer.settings().format.extension();
+    if let Some(path) = layout.known_summary_path(&named, output.as_deref(), extension) {
+        layout.check_overwrite(&path)?;
+    }
+    info!("Merging {}", count(input_paths.len(), "file"));
+    let summary = summarizer
+        .summarize_files(input_paths)
+        .await
+        .map_err(|e| save_partial(e, &named, layout, false))?;
+    write_summary(summarizer, &summary, &named, output, layout).await
+}
+
+/// Summarize the subtitles of a video or the text of a web page, saving the results in the
+/// current directory (or `--output-dir`) named after its title
+async fn summarize_url(
+    summarizer: &Summarizer,
+    url: &Path,
+    output: Option<PathBuf>,
+    layout: &OutputLayout,
+    sub_lang: Option<&str>,
+) -> Result<PathBuf, Box<dyn Error>> {
+    info!("Processing URL: {}", url.display());
     let start_time = Instant::now();
 
-    // === 2. Read and clean if SRT ===
-    let raw_text = fs::read_to_string(input_path)?;
-    let cleaned_text = match input_path.extension().and_then(|e| e.to_str()).unwrap_or("").to_lowercase().as_str() {
-        "srt" => clean_srt(&raw_text),
-        _ => raw_text,
+    let (transcript, title, named) = fetch_url(url, sub_lang).await?;
+    let extension = summarizer.settings().format.extension();
+    if let Some(path) = layout.known_summary_path(&named, output.as_deref(), extension) {
+        layout.check_overwrite(&path)?;
+    }
+    let mut summary = summarizer
+        .summarize_transcript(&transcript)
+        .await
+        .map_err(|e| save_partial(e, &named, layout, false))?;
+    summary.input = Some(url.to_path_buf());
+    summary.title = Some(title);
+    summary.duration = start_time.elapsed();
+
+    write_summary(summarizer, &summary, &named, output, layout).await
+}
+
+/// The transcript of a URL with its title, and the path its outputs are named after, as if it
+/// had been saved as `<title>.vtt` (or `.html`, `.pdf`, ...)
+async fn fetch_url(url: &Path, sub_lang: Option<&str>) -> Result<(Transcript, String, PathBuf), Box<dyn Error>> {
+    let url = url.to_string_lossy();
+    if let Some(document) = web::fetch(&url).await? {
+        let named = PathBuf::from(format!("{}.{}", youtube::file_stem(&document.title, "page"), document.extension));
+        return Ok((document.transcript, document.title, named));
+    }
+    let video = youtube::fetch_subtitles(&url, sub_lang).await?;
+    let named = PathBuf::from(format!("{}.vtt", youtube::file_stem(&video.title, &video.id)));
+    Ok((video.transcript, video.title, named))
+}
+
+/// Summarize text piped to stdin, printing the summary to stdout unless `output` is a file
+async fn summarize_stdin(
+    summarizer: &Summarizer,
+    output: Option<PathBuf>,
+    layout: &OutputLayout,
+) -> Result<PathBuf, Box<dyn Error>> {
+    if let Some(path) = &output {
+        layout.check_overwrite(path)?;
+    }
+    let (transcript, named) = read_stdin()?;
+    let summary = summarizer
+        .summarize_transcript(&transcript)
+        .await
+        .map_err(|e| save_partial(e, &named, layout, false))?;
+    let output = output.unwrap_or_else(|| PathBuf::from("-"));
+    write_summary(summarizer, &summary, &named, Some(output), layout).await
+}
+
+/// Read the transcript piped to stdin, telling its subtitle format from its content; the path
+/// returned names its other outputs, e.g. `stdin_chapters.txt`
+fn read_stdin() -> Result<(Transcript, PathBuf), Box<dyn Error>> {
+    info!("Reading the transcript from stdin...");
+    let mut bytes = Vec::new();
+    io::stdin().read_to_end(&mut bytes)?;
+    if bytes.starts_with(b"%PDF-") {
+        return Ok((parser::pdf_transcript(&bytes)?, PathBuf::from("stdin.pdf")));
+    }
+    let (text, encoding) = parser::decode(&bytes);
+    if text.trim().is_empty() {
+        return Err("Nothing to summarize: stdin is empty".into());
+    }
+    let extension = parser::sniff_extension(&text);
+    let transcript = Transcript {
+        encoding,
+        ..parser::parse_transcript(extension, text)
     };
+    Ok((transcript, PathBuf::from(format!("stdin.{}", extension))))
+}
 
-    // === 3. Configure LLM ===
-    let config = OpenAIConfig::default()
-        .with_api_base("http://localhost:11434/v1");
-    let llm = OpenAI::new(config).with_model("llama3.2".to_string());
+/// Whether a path is `-`, standing for stdin as an input and stdout as the output
+fn is_stdio(path: &Path) -> bool {
+    path == Path::new("-")
+}
 
-    // === 4. Prompt templates ===
-    let map_prompt = PromptTemplate::new(
-        MAP_TEMPLATE.to_string(),
-        vec!["text".to_string()],
-        TemplateFormat::FString,
-    );
-    let combine_prompt = PromptTemplate::new(
-        COMBINE_TEMPLATE.to_string(),
-        vec!["text".to_string()],
-        TemplateFormat::FString,
+/// Print the chunk plan of every input without calling the model
+async fn dry_run(summarizer: &Summarizer, inputs: &[PathBuf], sub_lang: Option<&str>) -> Result<(), Box<dyn Error>> {
+    let mut requests = 0;
+    for (i, input) in inputs.iter().enumerate() {
+        if inputs.len() > 1 {
+            println!("\n[{}/{}]", i + 1, inputs.len());
+        }
+        let plan = if is_stdio(input) {
+            summarizer.plan_transcript(&read_stdin()?.0)?
+        } else if youtube::is_url(input) {
+            info!("Processing URL: {}", input.display());
+            summarizer.plan_transcript(&fetch_url(input, sub_lang).await?.0)?
+        } else {
+            match summarizer.plan_file(input).await {
+                Err(e) if is_up_to_date(e.as_ref()) => {
+                    info!("{}", e);
+                    continue;
+                }
+                plan => plan?,
+            }
+        };
+        print_plan(&plan);
+        requests += plan.requests;
+    }
+    if inputs.len() > 1 {
+        println!("\nWould make about {} for {} inputs", count(requests, "LLM request"), inputs.len());
+    }
+    Ok(())
+}
+
+/// Words shown from each end of a chunk in the dry-run preview
+const PREVIEW_WORDS: usize = 8;
+
+fn print_plan(plan: &Plan) {
+    for (i, chunk) in plan.chunks.iter().enumerate() {
+        let span = chunk.span.map_or_else(String::new, |(start, end)| {
+            format!(", {}–{}", parser::format_clock(start), parser::format_clock(end))
+        });
+        println!(
+            "Chunk {}: {} words, {} prompt tokens{}{}",
+            i + 1,
+            chunk.words,
+            chunk.tokens,
+            span,
+            if chunk.cached { " (cached)" } else { "" }
+        );
+        let words: Vec<&str> = chunk.text.split_whitespace().collect();
+        let head = words.len().min(PREVIEW_WORDS);
+        let tail = words.len().saturating_sub(PREVIEW_WORDS).max(head);
+        println!("  starts: {}{}", words[..head].join(" "), if head < words.len() { " …" } else { "" });
+        if tail < words.len() {
+            println!("  ends:   … {}", words[tail..].join(" "));
+        }
+    }
+    let cached = plan.chunks.iter().filter(|chunk| chunk.cached).count();
+    println!(
+        "Would make about {}{}",
+        count(plan.requests, "LLM request"),
+        if cached > 0 { format!(" ({} cached)", count(cached, "chunk")) } else { String::new() }
     );
+}
+
+/// `1 chunk`, `2 chunks`
+fn count(n: usize, noun: &str) -> String {
+    format!("{} {}{}", n, noun, if n == 1 { "" } else { "s" })
+}
 
-    // === 5. Chains ===
-    let map_chain = LLMChainBuilder::new()
-        .prompt(HumanMessagePromptTemplate::new(map_prompt))
-        .llm(llm.clone())
-        .build()?;
-    let combine_chain = LLMChainBuilder::new()
-        .prompt(HumanMessagePromptTemplate::new(combine_prompt))
-        .llm(llm)
-        .build()?;
-
-    // === 6. Split text ===
-    let chunks = split_text(&cleaned_text, 2000, 200);
-    println!("Split into {} chunks", chunks.len());
-
-    // === 7. Map step ===
-    let map_start = Instant::now();
-    let mut summaries = Vec::new();
-    for chunk in chunks {
-        let mut args = HashMap::new();
-        args.insert("text".to_string(), Value::String(chunk));
-        let gen = map_chain.call(args).await?;
-        summaries.push(gen.generation);
-    }
-    println!("Map step completed in {:?}", map_start.elapsed());
-
-    // === 8. Combine step ===
-    let combined_input = summaries.join("\n\n");
-    let mut combine_args = HashMap::new();
-    combine_args.insert("text".to_string(), Value::String(combined_input));
-    let combine_gen = combine_chain.call(combine_args).await?;
-    let final_summary = combine_gen.generation;
-
-    // === 9. Save summary ===
-    let output_path = {
-        let parent = input_path.parent().unwrap_or_else(|| Path::new("."));
-        let stem = input_path.file_stem().unwrap_or_default().to_string_lossy();
-        parent.join(format!("{}_summary.txt", stem))
+/// Write the summary (and chapters, and the translated transcript) where `layout` puts them for `input_path`, or to `output`
+/// (stdout for `-`)
+async fn write_summary(
+    summarizer: &Summarizer,
+    summary: &Summary,
+    input_path: &Path,
+    output: Option<PathBuf>,
+    layout: &OutputLayout,
+) -> Result<PathBuf, Box<dyn Error>> {
+    let format = summarizer.settings().format;
+    let output_path = match output {
+        Some(path) => path,
+        None => layout.summary_path(input_path, summary, format.extension())?,
     };
-    fs::write(&output_path, &final_summary)?;
-    println!("Summary saved to {:?}", output_path);
-    println!("Total processing time: {:?}", start_time.elapsed());
+    layout.check_overwrite(&output_path)?;
+    let rendered = output::render(format, summary);
+    let to_stdout = is_stdio(&output_path);
+    if to_stdout {
+        let mut stdout = io::stdout().lock();
+        stdout.write_all(rendered.as_bytes())?;
+        if !rendered.ends_with('\n') {
+            writeln!(stdout)?;
+        }
+        stdout.flush()?;
+    } else {
+        output::write_atomic(&output_path, &rendered)?;
+        info!("Summary saved to {:?}", output_path);
+    }
+    if let Some(chapters) = &summary.chapters {
+        let chapters_path = layout.chapters_path(input_path);
+        output::write_atomic(&chapters_path, &chapters::render(chapters))?;
+        info!("Chapters saved to {:?}", chapters_path);
+    }
+    if let (Some(transcript), Some(target)) = (&summary.translated_transcript, &summarizer.settings().translate) {
+        let translation_path = layout.translation_path(input_path, &target.name);
+        output::write_atomic(&translation_path, transcript)?;
+        info!("Translated transcript saved to {:?}", translation_path);
+    }
+    if !summary.skipped_chunks.is_empty() {
+        let numbers: Vec<String> = summary.skipped_chunks.iter().map(usize::to_string).collect();
+        warn!(
+            "Skipped {} after errors: {}; the summary doesn't cover them",
+            if numbers.len() == 1 { "chunk" } else { "chunks" },
+            numbers.join(", ")
+        );
+    }
+    info!("Total processing time: {:?}", summary.duration);
+    summary.usage.print(summarizer.settings().price_per_1k);
+    if summarizer.settings().history {
+        record_history(summary, (!to_stdout).then_some(output_path.as_path()));
+    }
+    if let Some(url) = &summarizer.settings().webhook {
+        let body = webhook::payload(summary, (!to_stdout).then_some(output_path.as_path()));
+        if let Err(e) = webhook::deliver(url, summarizer.settings().webhook_secret.as_deref(), body).await {
+            warn!("Cannot deliver the summary to the webhook: {}", e);
+        }
+    }
+    for target in &summarizer.settings().post {
+        if let Err(e) = post::post(target, summary).await {
+            warn!("Cannot post the summary to {}: {}", target.platform, e);
+        }
+    }
+
+    Ok(output_path)
+}
+
+/// Whether `error` is a run stopped by Ctrl+C
+fn is_interrupted(error: &(dyn Error + 'static)) -> bool {
+    matches!(error.downcast_ref(), Some(SummarizerError::Interrupted { .. }))
+}
+
+/// Whether `error` is an incremental run that found nothing new to summarize
+fn is_up_to_date(error: &(dyn Error + 'static)) -> bool {
+    matches!(error.downcast_ref(), Some(SummarizerError::UpToDate(_)))
+}
+
+/// Save what an interrupted or timed out run finished to `<stem>_partial.txt`, passing the
+/// error on; `resumable` inputs have a checkpoint to continue from
+fn save_partial(error: Box<dyn Error>, input_path: &Path, layout: &OutputLayout, resumable: bool) -> Box<dyn Error> {
+    if let Some(
+        SummarizerError::Interrupted { completed, partial, .. } | SummarizerError::TimedOut { completed, partial, .. },
+    ) = error.downcast_ref()
+    {
+        if !partial.is_empty() {
+            let partial_path = layout.partial_path(input_path);
+            match output::write_atomic(&partial_path, partial) {
+                Ok(()) => info!("Partial summary saved to {:?}", partial_path),
+                Err(e) => warn!("Cannot save the partial summary to {:?}: {}", partial_path, e),
+            }
+        }
+        if resumable && *completed > 0 {
+            info!("Run again with --resume to continue from the checkpoint");
+        }
+    }
+    error
+}
 
+/// Add a written summary to the history; a history that cannot be written only costs a warning
+fn record_history(summary: &Summary, output_path: Option<&Path>) {
+    // Video URLs and stdin have no file to hash
+    let content_hash = summary
+        .input
+        .as_ref()
+        .and_then(|input| history::content_hash(input).ok());
+    let recorded = History::open_default().and_then(|history| {
+        history.record(summary, output_path, content_hash.as_deref())
+    });
+    match recorded {
+        Ok(id) => info!("Recorded in history as #{}", id),
+        Err(e) => warn!("Cannot record the summary in the history: {}", e),
+    }
+}
+
+/// Summarize two files or URLs, then write a report of what only one of them covers and the
+/// decisions that changed, named `<a stem>_vs_<b stem>` after both
+async fn compare(args: CompareArgs, progress: bool) -> Result<(), Box<dyn Error>> {
+    if is_stdio(&args.a) || is_stdio(&args.b) {
+        return Err("compare takes files or URLs, not - (stdin)".into());
+    }
+    let summarizer = Summarizer::new(args.settings.resolve_sized().await?)?
+        .with_interrupt(true)
+        .with_progress(progress);
+    let format = summarizer.settings().format;
+    if !matches!(format, OutputFormat::Txt | OutputFormat::Md | OutputFormat::Json) {
+        return Err("compare writes txt, md or json reports".into());
+    }
+    let layout = OutputLayout {
+        dir: None,
+        name: None,
+        force: args.force,
+    };
+    // The report of two files can be checked for before anything is summarized; URLs are named
+    // after their titles
+    if let Some(path) = &args.output {
+        layout.check_overwrite(path)?;
+    } else if !youtube::is_url(&args.a) && !youtube::is_url(&args.b) {
+        layout.check_overwrite(&comparison_path(&args.a, &args.b, format.extension()))?;
+    }
+    summarizer.prepare().await?;
+
+    let sub_lang = args.sub_lang.as_deref();
+    let (a, named_a) = summarize_for_comparison(&summarizer, &args.a, sub_lang).await?;
+    let (b, named_b) = summarize_for_comparison(&summarizer, &args.b, sub_lang).await?;
+    let mut names = (comparison_name(&a, &named_a), comparison_name(&b, &named_b));
+    // Same-named files in different directories, e.g. week1/notes.srt and week2/notes.srt
+    if names.0 == names.1 {
+        names = (args.a.display().to_string(), args.b.display().to_string());
+    }
+    info!("Comparing {} with {}", names.0, names.1);
+    let (comparison, usage) = summarizer.compare(&a, &b, names).await?;
+
+    let output_path = args
+        .output
+        .unwrap_or_else(|| comparison_path(&named_a, &named_b, format.extension()));
+    layout.check_overwrite(&output_path)?;
+    let rendered = output::render_comparison(format, &comparison);
+    if is_stdio(&output_path) {
+        let mut stdout = io::stdout().lock();
+        stdout.write_all(rendered.as_bytes())?;
+        stdout.flush()?;
+    } else {
+        output::write_atomic(&output_path, &rendered)?;
+        info!("Comparison saved to {:?}", output_path);
+    }
+    let mut total = a.usage.clone();
+    total.merge(&b.usage);
+    total.merge(&usage);
+    total.print(summarizer.settings().price_per_1k);
     Ok(())
 }
 
-/// Remove SRT indices/timestamps and collapse to one long paragraph
-fn clean_srt(text: &str) -> String {
-    let timestamp_re = Regex::new(r"\d{2}:\d{2}:\d{2},\d{3} --> \d{2}:\d{2}:\d{2},\d{3}").unwrap();
-    let seq_re = Regex::new(r"^\d+$").unwrap();
+/// The summary of one side of `compare`, with the path its report is named after
+async fn summarize_for_comparison(
+    summarizer: &Summarizer,
+    input: &Path,
+    sub_lang: Option<&str>,
+) -> Result<(Summary, PathBuf), Box<dyn Error>> {
+    let span = info_span!("input", path = %input.display());
+    async {
+        if !youtube::is_url(input) {
+            return Ok((summarizer.summarize_file(input).await?, input.to_path_buf()));
+        }
+        info!("Processing URL: {}", input.display());
+        let (transcript, title, named) = fetch_url(input, sub_lang).await?;
+        let mut summary = summarizer.summarize_transcript(&transcript).await?;
+        summary.input = Some(input.to_path_buf());
+        summary.title = Some(title);
+        Ok((summary, named))
+    }
+    .instrument(span)
+    .await
+}
 
-    text.lines()
-        .filter(|line| {
-            let t = line.trim();
-            !t.is_empty() && !seq_re.is_match(t) && !timestamp_re.is_match(t)
-        })
-        .collect::<Vec<_>>()
-        .join(" ")
+/// How the report refers to an input: the title of a URL, else the file name
+fn comparison_name(summary: &Summary, named: &Path) -> String {
+    summary
+        .title
+        .clone()
+        .unwrap_or_else(|| named.file_name().unwrap_or_default().to_string_lossy().into_owned())
 }
 
-/// Simple word-based splitter with overlap
-fn split_text(text: &str, chunk_size: usize, chunk_overlap: usize) -> Vec<String> {
-    let words: Vec<&str> = text.split_whitespace().collect();
-    let mut chunks = Vec::new();
-    let mut start = 0;
+/// `<a stem>_vs_<b stem>.<extension>` next to `a`
+fn comparison_path(a: &Path, b: &Path, extension: &str) -> PathBuf {
+    let b_stem = b.file_stem().unwrap_or_default().to_string_lossy();
+    output::sibling_path(a, &format!("vs_{}", b_stem), extension)
+}
 
-    while start < words.len() {
-        let end = usize::min(start + chunk_size, words.len());
-        chunks.push(words[start..end].join(" "));
-        if end == words.len() {
-            break;
+/// Answer a question about a file from its chunks closest to the question, printed to stdout
+/// unless `--output` names a file
+async fn ask(args: AskArgs) -> Result<(), Box<dyn Error>> {
+    if is_stdio(&args.file) || youtube::is_url(&args.file) {
+        return Err("ask takes a file, not stdin or a URL; save the transcript first".into());
+    }
+    if args.question.trim().is_empty() {
+        return Err("The question is empty".into());
+    }
+    let summarizer = Summarizer::new(args.settings.resolve_sized().await?)?;
+    summarizer.prepare().await?;
+    let (answer, usage) = summarizer.ask(&args.file, &args.question, args.top_k).await?;
+    let rendered = output::render_answer(summarizer.settings().format, &answer);
+    match args.output {
+        Some(path) if !is_stdio(&path) => {
+            output::write_atomic(&path, &rendered)?;
+            info!("Answer saved to {:?}", path);
+        }
+        _ => {
+            let mut stdout = io::stdout().lock();
+            stdout.write_all(rendered.as_bytes())?;
+            stdout.flush()?;
         }
-        start += chunk_size.saturating_sub(chunk_overlap);
     }
+    usage.print(summarizer.settings().price_per_1k);
+    Ok(())
+}
 
-    chunks
+/// Synthesize the episode summaries of a directory into an overview, written like a summary of
+/// the directory as `<directory name>_rollup_summary.txt` in it
+async fn rollup(args: RollupArgs, progress: bool) -> Result<(), Box<dyn Error>> {
+    if !args.dir.is_dir() {
+        return Err(format!("{:?} is not a directory", args.dir).into());
+    }
+    let summarizer = Summarizer::new(args.settings.resolve_sized().await?)?
+        .with_interrupt(true)
+        .with_progress(progress);
+    if summarizer.settings().mode != Mode::Summary {
+        return Err("rollup writes a bullet overview; --mode and --format anki don't apply".into());
+    }
+    let dir = fs::canonicalize(&args.dir)?;
+    let name = dir.file_name().unwrap_or_default().to_string_lossy();
+    let named = dir.join(format!("{}_rollup.txt", name));
+    let layout = OutputLayout {
+        dir: None,
+        name: None,
+        force: args.force,
+    };
+    let extension = summarizer.settings().format.extension();
+    if let Some(path) = layout.known_summary_path(&named, args.output.as_deref(), extension) {
+        layout.check_overwrite(&path)?;
+    }
+    let episodes = rollup::collect(&dir, args.recursive)?;
+    summarizer.prepare().await?;
+    let summary = summarizer.rollup(&dir, &episodes).await?;
+    write_summary(&summarizer, &summary, &named, args.output, &layout).await?;
+    Ok(())
+}
+
+/// Answer summarize requests over HTTP with one shared summarizer
+async fn serve(args: ServeArgs) -> Result<(), Box<dyn Error>> {
+    let summarizer = Summarizer::new(args.settings.resolve_sized().await?)?
+        .with_chapters(args.chapters)
+        .with_keywords(args.with_keywords)
+        .with_entities(args.with_entities)
+        .with_headline(args.with_title, args.with_tldr)
+        .with_progress(false);
+    summarizer.prepare().await?;
+    server::serve(args.addr, Arc::new(summarizer)).await
+}
+
+/// Serve the summarize tools to an MCP client over stdin and stdout, with one shared summarizer
+async fn mcp(args: McpArgs) -> Result<(), Box<dyn Error>> {
+    let summarizer = Summarizer::new(args.settings.resolve_sized().await?)?
+        .with_chapters(args.chapters)
+        .with_keywords(args.with_keywords)
+        .with_entities(args.with_entities)
+        .with_headline(args.with_title, args.with_tldr)
+        .with_progress(false);
+    summarizer.prepare().await?;
+    mcp::serve(Arc::new(summarizer)).await
+}
+
+/// Summarize every input file that appears in a directory, until the process is stopped
+async fn watch(args: WatchArgs, progress: bool) -> Result<(), Box<dyn Error>> {
+    if !args.dir.is_dir() {
+        return Err(format!("{:?} is not a directory", args.dir).into());
+    }
+    let summarizer = Summarizer::new(args.settings.resolve_sized().await?)?
+        .with_incremental(args.incremental)
+        .with_chapters(args.chapters)
+        .with_keywords(args.with_keywords)
+        .with_entities(args.with_entities)
+        .with_headline(args.with_title, args.with_tldr)
+        .with_interrupt(true)
+        .with_progress(progress);
+    summarizer.prepare().await?;
+
+    let mut watcher = FolderWatcher::new(&args.dir, args.recursive)?;
+    let output_dir = args.output_dir.or_else(|| summarizer.settings().vault_dir());
+    if let Some(dir) = &output_dir {
+        fs::create_dir_all(dir)?;
+        // Unless summaries go into the watched directory itself, nothing in theirs is an input
+        let (watched, outputs) = (fs::canonicalize(&args.dir)?, fs::canonicalize(dir)?);
+        if !watched.starts_with(&outputs) {
+            watcher = watcher.ignore(&outputs);
+        }
+    }
+    let layout = OutputLayout {
+        dir: output_dir,
+        name: None,
+        force: args.force || args.incremental,
+    };
+
+    info!("Watching {:?} for new subtitle and text files (Ctrl+C to stop)", args.dir);
+    loop {
+        let input_path = tokio::select! {
+            input_path = watcher.next() => input_path?,
+            _ = tokio::signal::ctrl_c() => return Ok(()),
+        };
+        // One bad file shouldn't stop the watch, but Ctrl+C during a summary should
+        match summarize_input(&summarizer, &input_path, None, &layout, None).await {
+            Err(e) if is_interrupted(e.as_ref()) => return Err(e),
+            Err(e) => error!("Failed to summarize {:?}: {}", input_path, e),
+            Ok(_) => {}
+        }
+    }
+}
+
+/// Download every video of the URLs and summarize it, keeping going past failures; the
+/// subtitles (or audio) and summaries are saved in the output directory, named after each title
+async fn fetch(args: FetchArgs, progress: bool) -> Result<(), Box<dyn Error>> {
+    let summarizer = Summarizer::new(args.settings.resolve_sized().await?)?
+        .with_chapters(args.chapters)
+        .with_keywords(args.with_keywords)
+        .with_entities(args.with_entities)
+        .with_headline(args.with_title, args.with_tldr)
+        .with_interrupt(true)
+        .with_progress(progress);
+    summarizer.prepare().await?;
+    // Without --output-dir, notes go into the vault and the downloads into the current directory
+    let vault = summarizer.settings().vault_dir().filter(|_| args.output_dir.is_none());
+    let dir = args.output_dir.unwrap_or_else(|| PathBuf::from("."));
+    fs::create_dir_all(&dir)?;
+    if let Some(vault) = &vault {
+        fs::create_dir_all(vault)?;
+    }
+    let layout = OutputLayout {
+        dir: vault,
+        name: None,
+        force: args.force,
+    };
+
+    let mut report = BatchReport::default();
+    let mut videos = Vec::new();
+    for url in &args.urls {
+        match youtube::list_videos(url, args.limit).await {
+            Ok(listed) => videos.extend(listed),
+            Err(e) => {
+                error!("Cannot list the videos of {}: {}", url, e);
+                report.failed.push((PathBuf::from(url), e.to_string()));
+            }
+        }
+    }
+    // A playlist that cannot be listed counts as one failed item
+    let unlisted = report.failed.len();
+    let extension = summarizer.settings().format.extension();
+    for (i, video) in videos.iter().enumerate() {
+        info!("[{}/{}] {}", i + 1, videos.len(), video.title);
+        let url = PathBuf::from(&video.url);
+        // Saved subtitles and audio share the title's stem, and so would their summaries
+        let named = dir.join(format!("{}.vtt", youtube::file_stem(&video.title, "video")));
+        if let Some(path) = layout.known_summary_path(&named, None, extension).filter(|path| !args.force && path.exists()) {
+            info!("Skipping {:?}: {:?} already exists", video.title, path);
+            report.skipped.push((url, path));
+            continue;
+        }
+        let summarized = async {
+            let input_path = youtube::download_video(&video.url, args.sub_lang.as_deref(), &dir, args.audio).await?;
+            summarize_input(&summarizer, &input_path, None, &layout, None).await
+        };
+        match summarized.await {
+            Ok(output_path) => report.succeeded.push((url, output_path)),
+            Err(e) if is_interrupted(e.as_ref()) => {
+                report.print();
+                return Err(e);
+            }
+            Err(e) => {
+                error!("Failed to summarize {:?}: {}", video.title, e);
+                report.failed.push((url, e.to_string()));
+            }
+        }
+    }
+    report.print();
+
+    if report.failed.is_empty() {
+        Ok(())
+    } else {
+        Err(format!("{} of {} videos failed", report.failed.len(), videos.len() + unlisted).into())
+    }
+}
+
+/// Print the health checks of the configured backends, failing if any of them failed
+async fn run_doctor(args: DoctorArgs) -> Result<(), Box<dyn Error>> {
+    let checks = doctor::run(&args.settings.resolve()?).await;
+    for check in &checks {
+        let mark = match check.status {
+            Status::Ok => "✅",
+            Status::Warning => "⚠️",
+            Status::Failed => "❌",
+        };
+        println!("{} {}", mark, check.message);
+        if let Some(fix) = &check.fix {
+            println!("   → {}", fix);
+        }
+    }
+    let failed = checks.iter().filter(|check| check.status == Status::Failed).count();
+    if failed == 0 {
+        println!("Ready to summarize");
+        Ok(())
+    } else {
+        Err(format!("{} of {} checks failed", failed, checks.len()).into())
+    }
+}
+
+/// Write the cleaned transcript of a subtitle file without summarizing it
+fn convert(args: ConvertArgs) -> Result<(), Box<dyn Error>> {
+    let input_path = args.input.as_path();
+    if !input_path.exists() {
+        return Err(SummarizerError::FileNotFound(input_path.to_path_buf()).into());
+    }
+
+    let mut transcript = parser::load_transcript(input_path)?;
+    if let Some(encoding) = transcript.encoding {
+        info!("Detected encoding: {}", encoding);
+    }
+    if !args.keep_sfx {
+        transcript = parser::strip_sound_descriptions(transcript);
+    }
+    let text = transcript.text;
+    let output_path = args
+        .output
+        .unwrap_or_else(|| output::sibling_path(input_path, "transcript", "txt"));
+    output::write_atomic(&output_path, &text)?;
+    info!("Transcript saved to {:?}", output_path);
+
+    Ok(())
+}
+
+/// Handle the `config` subcommands
+fn run_config(command: ConfigCommand) -> Result<(), Box<dyn Error>> {
+    match command {
+        ConfigCommand::Init { path, force } => {
+            let path = config::init(path.as_deref(), force)?;
+            println!("Config written to {:?}", path);
+        }
+        ConfigCommand::Path => match config::default_config_path() {
+            Some(path) => println!("{}", path.display()),
+            None => return Err("Cannot determine the config directory".into()),
+        },
+    }
+
+    Ok(())
+}
+
+/// Handle the `cache` subcommands, bounded as the default config file says
+fn run_cache(command: CacheCommand) -> Result<(), Box<dyn Error>> {
+    let dir = cache::responses_dir().ok_or("Cannot determine the cache directory")?;
+    match command {
+        CacheCommand::Stats => {
+            let settings = Settings::resolve(SettingsLayer::load(None)?)?;
+            let stats = cache::stats(&dir, settings.cache_limits())?;
+            let day = |time: SystemTime| DateTime::<Local>::from(time).format("%Y-%m-%d %H:%M").to_string();
+            println!("Directory: {}", dir.display());
+            println!("Replies:   {} ({})", stats.replies, cache::format_size(stats.bytes));
+            if let (Some(oldest), Some(newest)) = (stats.oldest, stats.newest) {
+                println!("Written:   {} to {}", day(oldest), day(newest));
+            }
+            let limits: Vec<String> = [
+                settings.cache_ttl_days.map(|days| format!("replies expire after {}", count(days as usize, "day"))),
+                settings.cache_max_mb.map(|mb| format!("pruned to {} MB", mb)),
+            ]
+            .into_iter()
+            .flatten()
+            .collect();
+            if !limits.is_empty() {
+                println!("Limits:    {}", limits.join(", "));
+            }
+            if stats.expired > 0 {
+                println!("Expired:   {}, removed by the next run", stats.expired);
+            }
+        }
+        CacheCommand::Clear => {
+            let (removed, bytes) = cache::clear(&dir)?;
+            println!("Removed {} cached replies ({}) from {}", removed, cache::format_size(bytes), dir.display());
+        }
+    }
+
+    Ok(())
+}
+
+/// Handle the `history` subcommands
+fn run_history(command: HistoryCommand) -> Result<(), Box<dyn Error>> {
+    let history = History::open_default()?;
+    match command {
+        HistoryCommand::List { query, limit } => {
+            let entries = history.list(query.as_deref(), limit)?;
+            if entries.is_empty() {
+                println!("No summaries recorded yet (summarize with --history or `history = true`)");
+            }
+            for entry in entries {
+                println!(
+                    "#{:<4} {}  {:<14} {:<12} {:>7} tokens  {}",
+                    entry.id,
+                    entry.created_at.format("%Y-%m-%d %H:%M"),
+                    entry.mode,
+                    entry.model,
+                    entry.prompt_tokens + entry.completion_tokens,
+                    entry.input.as_deref().unwrap_or("(text)")
+                );
+            }
+        }
+        HistoryCommand::Show { id } => {
+            let entry = history.get(id)?.ok_or_else(|| format!("No summary #{} in the history", id))?;
+            println!("Input:    {}", entry.input.as_deref().unwrap_or("(text)"));
+            if let Some(output) = &entry.output {
+                println!("Output:   {}", output);
+            }
+            if let Some(hash) = &entry.content_hash {
+                println!("SHA-256:  {}", hash);
+            }
+            println!("Created:  {}", entry.created_at.format("%Y-%m-%d %H:%M:%S"));
+            println!("Model:    {} ({} mode)", entry.model, entry.mode);
+            println!(
+                "Tokens:   {} prompt + {} completion in {:?}",
+                entry.prompt_tokens, entry.completion_tokens, entry.duration
+            );
+            println!();
+            println!("{}", entry.summary);
+        }
+    }
+
+    Ok(())
 }
@@ -0,0 +1,208 @@
+// Model Context Protocol server for `mcp`.
+//
+// MCP clients such as Claude Desktop start the server as a subprocess and talk JSON-RPC 2.0 to
+// it over stdin and stdout, one message per line; logs go to stderr as usual. The server offers
+// two tools: `summarize_file` summarizes a file on this machine by its path, as the `summarize`
+// subcommand would without writing the summary anywhere, and `summarize_text` summarizes text
+// (or subtitles) passed in the call. Both answer with the summary in the requested format.
+// Calls run side by side, so the client can ping or call again while a long file is summarized.
+
+use serde_json::{json, Value};
+use std::{error::Error, path::PathBuf, sync::Arc};
+use tokio::{
+    io::{self, AsyncBufReadExt, AsyncWriteExt, BufReader},
+    sync::mpsc,
+};
+use tracing::{info, warn};
+
+use crate::{config::OutputFormat, output, parser, summarizer::Summarizer};
+
+/// Protocol revisions this server speaks, the newest first
+const PROTOCOL_VERSIONS: [&str; 3] = ["2025-06-18", "2025-03-26", "2024-11-05"];
+
+/// JSON-RPC error codes
+const PARSE_ERROR: i64 = -32700;
+const INVALID_REQUEST: i64 = -32600;
+const METHOD_NOT_FOUND: i64 = -32601;
+const INVALID_PARAMS: i64 = -32602;
+
+/// Answer MCP requests on stdin until the client closes it
+pub async fn serve(summarizer: Arc<Summarizer>) -> Result<(), Box<dyn Error>> {
+    info!("MCP server ready on stdin/stdout");
+    // Replies are written by one task, so those of concurrent calls don't interleave
+    let (replies, mut outgoing) = mpsc::unbounded_channel::<Value>();
+    let writer = tokio::spawn(async move {
+        let mut stdout = io::stdout();
+        while let Some(reply) = outgoing.recv().await {
+            let line = format!("{}\n", reply);
+            if stdout.write_all(line.as_bytes()).await.is_err() || stdout.flush().await.is_err() {
+                break;
+            }
+        }
+    });
+
+    let mut lines = BufReader::new(io::stdin()).lines();
+    while let Some(line) = lines.next_line().await? {
+        if line.trim().is_empty() {
+            continue;
+        }
+        let message: Value = match serde_json::from_str(&line) {
+            Ok(message) => message,
+            Err(e) => {
+                let _ = replies.send(error_reply(Value::Null, PARSE_ERROR, &format!("Invalid JSON: {}", e)));
+                continue;
+            }
+        };
+        let method = message["method"].as_str().unwrap_or("").to_string();
+        // Notifications (`notifications/initialized`, `notifications/cancelled`, ...) and
+        // replies to requests of ours have no id to answer
+        let Some(id) = message.get("id").cloned() else {
+            continue;
+        };
+        if method.is_empty() {
+            if message.get("result").is_none() && message.get("error").is_none() {
+                let _ = replies.send(error_reply(id, INVALID_REQUEST, "The request has no method"));
+            }
+            continue;
+        }
+        let params = message.get("params").cloned().unwrap_or(Value::Null);
+        let reply = match method.as_str() {
+            "initialize" => result_reply(id, initialize(&params)),
+            "ping" => result_reply(id, json!({})),
+            "tools/list" => result_reply(id, json!({ "tools": tools() })),
+            "tools/call" => {
+                let summarizer = summarizer.clone();
+                let replies = replies.clone();
+                tokio::spawn(async move {
+                    let reply = match call(&summarizer, &params).await {
+                        Ok(result) => result_reply(id, result),
+                        Err(message) => error_reply(id, INVALID_PARAMS, &message),
+                    };
+                    let _ = replies.send(reply);
+                });
+                continue;
+            }
+            _ => error_reply(id, METHOD_NOT_FOUND, &format!("Unknown method `{}`", method)),
+        };
+        let _ = replies.send(reply);
+    }
+
+    // Let the calls still running finish before stdout closes
+    drop(replies);
+    writer.await?;
+    Ok(())
+}
+
+/// The `initialize` result, in the client's protocol revision when this server speaks it
+fn initialize(params: &Value) -> Value {
+    let requested = params["protocolVersion"].as_str().unwrap_or("");
+    let version = PROTOCOL_VERSIONS
+        .iter()
+        .find(|version| **version == requested)
+        .unwrap_or(&PROTOCOL_VERSIONS[0]);
+    json!({
+        "protocolVersion": version,
+        "capabilities": { "tools": {} },
+        "serverInfo": {
+            "name": env!("CARGO_PKG_NAME"),
+            "version": env!("CARGO_PKG_VERSION"),
+        },
+    })
+}
+
+/// The tools offered, with the JSON schemas of their arguments
+fn tools() -> Value {
+    let format = json!({
+        "type": "string",
+        "enum": ["txt", "md", "json"],
+        "description": "Format of the summary: the bullet list as plain text (the default), Markdown with a title and metadata, or JSON with the per-chunk summaries",
+    });
+    json!([
+        {
+            "name": "summarize_file",
+            "description": "Summarize a subtitle (.srt, .vtt, .ass), transcript, text, Markdown, PDF, EPUB or HTML file on this machine into bullet points. Long files are split into chunks that are summarized and then combined, which can take a while.",
+            "inputSchema": {
+                "type": "object",
+                "properties": {
+                    "path": {
+                        "type": "string",
+                        "description": "Path of the file, absolute or relative to the directory the server was started in",
+                    },
+                    "format": format,
+                },
+                "required": ["path"],
+            },
+        },
+        {
+            "name": "summarize_text",
+            "description": "Summarize a transcript, article or subtitles (SRT, WebVTT, ASS or Whisper JSON) given as text into bullet points.",
+            "inputSchema": {
+                "type": "object",
+                "properties": {
+                    "text": {
+                        "type": "string",
+                        "description": "The text to summarize",
+                    },
+                    "format": format,
+                },
+                "required": ["text"],
+            },
+        },
+    ])
+}
+
+/// Run a `tools/call`; a summary that fails is a tool result with `isError`, while a call the
+/// tools cannot take (an unknown tool or missing argument) is an error of the request
+async fn call(summarizer: &Summarizer, params: &Value) -> Result<Value, String> {
+    let name = params["name"].as_str().ok_or("The call names no tool")?;
+    let arguments = &params["arguments"];
+    let format = match arguments.get("format") {
+        Some(format) => serde_json::from_value::<OutputFormat>(format.clone())
+            .ok()
+            .filter(|format| matches!(format, OutputFormat::Txt | OutputFormat::Md | OutputFormat::Json))
+            .ok_or_else(|| format!("`format` must be txt, md or json, not {}", format))?,
+        None => OutputFormat::Txt,
+    };
+    let summary = match name {
+        "summarize_file" => {
+            let path = arguments["path"].as_str().ok_or("summarize_file needs a `path`")?;
+            info!("MCP call: summarize_file {}", path);
+            summarizer.summarize_file(&PathBuf::from(path)).await
+        }
+        "summarize_text" => {
+            let text = arguments["text"].as_str().ok_or("summarize_text needs a `text`")?;
+            if text.trim().is_empty() {
+                return Ok(tool_error("The text is empty".to_string()));
+            }
+            info!("MCP call: summarize_text ({} characters)", text.chars().count());
+            let transcript = parser::parse_transcript(parser::sniff_extension(text), text.to_string());
+            summarizer.summarize_transcript(&transcript).await
+        }
+        _ => return Err(format!("Unknown tool `{}`", name)),
+    };
+    Ok(match summary {
+        Ok(summary) => json!({
+            "content": [{ "type": "text", "text": output::render(format, &summary) }],
+            "isError": false,
+        }),
+        Err(e) => {
+            warn!("MCP call {} failed: {}", name, e);
+            tool_error(e.to_string())
+        }
+    })
+}
+
+fn tool_error(message: String) -> Value {
+    json!({
+        "content": [{ "type": "text", "text": message }],
+        "isError": true,
+    })
+}
+
+fn result_reply(id: Value, result: Value) -> Value {
+    json!({ "jsonrpc": "2.0", "id": id, "result": result })
+}
+
+fn error_reply(id: Value, code: i64, message: &str) -> Value {
+    json!({ "jsonrpc": "2.0", "id": id, "error": { "code": code, "message": message } })
+}
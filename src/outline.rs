@@ -0,0 +1,119 @@
+// Hierarchical outlines written by `--mode outline`.
+//
+// The prompts ask for `- ` bullets indented by two spaces per level. Replies are parsed by
+// indentation of any width (tabs count as four spaces), with `#` headings read as topics and
+// wrapped lines joined to the item above, and rewritten with two spaces per level, at most
+// `MAX_DEPTH` levels deep. An outline whose topics have no sub-points is flat; the summarizer
+// asks the model once more for one that isn't.
+
+use crate::bullets;
+
+/// Levels of an outline: topics, their points and details; deeper items join the last level
+pub const MAX_DEPTH: usize = 3;
+
+/// One outline item with the items nested under it
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Node {
+    pub text: String,
+    pub children: Vec<Node>,
+}
+
+/// Extract the outline of a model reply, dropping preambles, closing remarks and `- None`
+/// placeholders
+pub fn parse(reply: &str) -> Vec<Node> {
+    let mut roots: Vec<Node> = Vec::new();
+    // Indentation of the open item at every level below the current heading
+    let mut indents: Vec<usize> = Vec::new();
+    let mut under_heading = false;
+    for line in bullets::normalize(reply).lines() {
+        let line = line.replace('\t', "    ");
+        let trimmed = line.trim_start();
+        if trimmed.is_empty() {
+            continue;
+        }
+        if let Some(heading) = trimmed.strip_prefix('#') {
+            let text = clean(heading.trim_start_matches('#').trim_end_matches(':'));
+            if !text.is_empty() {
+                roots.push(Node::new(text));
+                indents.clear();
+                under_heading = true;
+            }
+            continue;
+        }
+        let Some(text) = trimmed.strip_prefix("- ") else {
+            if let Some(last) = last_node(&mut roots) {
+                last.text = format!("{} {}", last.text, clean(trimmed));
+            }
+            continue;
+        };
+        let text = clean(text);
+        if text.is_empty() || text.eq_ignore_ascii_case("none") {
+            continue;
+        }
+        let indent = line.len() - trimmed.len();
+        while indents.last().is_some_and(|&open| open >= indent) {
+            indents.pop();
+        }
+        indents.push(indent);
+        let depth = (indents.len() - 1 + usize::from(under_heading)).min(MAX_DEPTH - 1);
+        insert(&mut roots, depth, Node::new(text));
+    }
+    roots
+}
+
+/// Whether some topic of `outline` has sub-points
+pub fn is_nested(outline: &[Node]) -> bool {
+    outline.iter().any(|node| !node.children.is_empty())
+}
+
+/// `- ` bullets indented by two spaces per level, or `- None` for an empty outline
+pub fn render(outline: &[Node]) -> String {
+    if outline.is_empty() {
+        return "- None".to_string();
+    }
+    let mut lines = Vec::new();
+    render_level(outline, 0, &mut lines);
+    lines.join("\n")
+}
+
+fn render_level(nodes: &[Node], depth: usize, lines: &mut Vec<String>) {
+    for node in nodes {
+        lines.push(format!("{}- {}", "  ".repeat(depth), node.text));
+        render_level(&node.children, depth + 1, lines);
+    }
+}
+
+impl Node {
+    fn new(text: String) -> Self {
+        Self {
+            text,
+            children: Vec::new(),
+        }
+    }
+}
+
+/// Add `node` `depth` levels down the last branch of `nodes`, or as deep as that branch goes
+fn insert(nodes: &mut Vec<Node>, depth: usize, node: Node) {
+    match nodes.last_mut() {
+        Some(last) if depth > 0 => insert(&mut last.children, depth - 1, node),
+        _ => nodes.push(node),
+    }
+}
+
+/// The item added last, at the end of the last branch
+fn last_node(nodes: &mut [Node]) -> Option<&mut Node> {
+    let last = nodes.last_mut()?;
+    if last.children.is_empty() {
+        return Some(last);
+    }
+    last_node(&mut last.children)
+}
+
+/// `text` without surrounding whitespace and `**bold**` markers around all of it
+fn clean(text: &str) -> String {
+    let text = text.trim();
+    match text.strip_prefix("**").and_then(|rest| rest.strip_suffix("**")) {
+        Some(inner) if !inner.contains("**") => inner.trim().to_string(),
+        _ => text.to_string(),
+    }
+}
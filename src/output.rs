@@ -0,0 +1,968 @@
+// Rendering of the final summary into the supported output formats.
+
+use clap::ValueEnum;
+use serde::Serialize;
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use crate::{
+    action_items,
+    ask::{self, Answer},
+    chapters,
+    compare::{self, Comparison},
+    entities, keywords, lecture, outline, parser, qa, shownotes, topics,
+    config::{Mode, OutputFormat},
+    summarizer::Summary,
+    usage::TokenUsage,
+    youtube,
+};
+
+/// Render the summary in the requested format
+pub fn render(format: OutputFormat, summary: &Summary) -> String {
+    match format {
+        OutputFormat::Txt => render_text(summary),
+        OutputFormat::Md => render_markdown(summary),
+        OutputFormat::Json => render_json(summary),
+        OutputFormat::Obsidian => render_obsidian(summary),
+        OutputFormat::Html => render_html(summary),
+        OutputFormat::Anki => render_anki(summary),
+    }
+}
+
+/// Render the report of `compare`: JSON, or the Markdown sections for the other formats, which
+/// read the same as plain text
+pub fn render_comparison(format: OutputFormat, comparison: &Comparison) -> String {
+    match format {
+        OutputFormat::Json => {
+            let value = JsonComparison {
+                a: &comparison.names.0,
+                b: &comparison.names.1,
+                only_in_a: &comparison.only_a,
+                only_in_b: &comparison.only_b,
+                changed_decisions: &comparison.changed,
+            };
+            serde_json::to_string_pretty(&value).unwrap() + "\n"
+        }
+        _ => compare::render(comparison),
+    }
+}
+
+/// Render the answer of `ask`: JSON, or the answer and its sources as text for the other formats
+pub fn render_answer(format: OutputFormat, answer: &Answer) -> String {
+    match format {
+        OutputFormat::Json => {
+            let sources = answer
+                .passages
+                .iter()
+                .map(|passage| JsonSource {
+                    chunk: passage.index + 1,
+                    start_seconds: passage.start.map(|start| start.as_secs()),
+                    end_seconds: passage.end.map(|end| end.as_secs()),
+                    similarity: passage.score,
+                    text: &passage.text,
+                })
+                .collect();
+            let value = JsonAnswer {
+                question: &answer.question,
+                answer: &answer.answer,
+                sources,
+            };
+            serde_json::to_string_pretty(&value).unwrap() + "\n"
+        }
+        _ => ask::render(answer),
+    }
+}
+
+/// Split a model response into bullet texts, dropping the `-`/`*`/`•` markers.
+/// Responses without any bullet markers fall back to one entry per non-empty line.
+pub fn bullets(summary: &str) -> Vec<String> {
+    let lines: Vec<&str> = summary.lines().map(str::trim).filter(|l| !l.is_empty()).collect();
+    let marked: Vec<String> = lines
+        .iter()
+        .filter_map(|l| l.strip_prefix(['-', '*', '•']))
+        .map(|l| l.trim().to_string())
+        .filter(|l| !l.is_empty())
+        .collect();
+
+    if marked.is_empty() {
+        lines.into_iter().map(str::to_string).collect()
+    } else {
+        marked
+    }
+}
+
+/// Split a sectioned response (`## Heading` lines, each followed by bullets) into headings and
+/// their bullet texts; `None` placeholders are dropped, so a section may have no items
+pub fn sections(summary: &str) -> Vec<(String, Vec<String>)> {
+    let mut sections: Vec<(String, String)> = Vec::new();
+    for line in summary.lines() {
+        match line.trim().strip_prefix('#') {
+            Some(heading) => sections.push((heading.trim_matches(['#', ' ']).to_string(), String::new())),
+            None => {
+                if let Some((_, body)) = sections.last_mut() {
+                    body.push_str(line);
+                    body.push('\n');
+                }
+            }
+        }
+    }
+
+    sections
+        .into_iter()
+        .map(|(heading, body)| {
+            let items = bullets(&body)
+                .into_iter()
+                .filter(|item| !item.eq_ignore_ascii_case("none"))
+                .collect();
+            (heading, items)
+        })
+        .collect()
+}
+
+/// `<dir>/<stem>_<suffix>.<extension>` next to the input file
+pub fn sibling_path(input_path: &Path, suffix: &str, extension: &str) -> PathBuf {
+    let parent = input_path.parent().unwrap_or_else(|| Path::new("."));
+    let stem = input_path.file_stem().unwrap_or_default().to_string_lossy();
+    parent.join(format!("{}_{}.{}", stem, suffix, extension))
+}
+
+/// Write `contents` to a hidden temporary file next to `path`, then rename it into place, so a
+/// crash mid-write leaves the previous file (or none) rather than a truncated one
+pub fn write_atomic(path: &Path, contents: &str) -> io::Result<()> {
+    let name = path.file_name().unwrap_or_default().to_string_lossy();
+    let tmp = path.with_file_name(format!(".{}.tmp", name));
+    fs::write(&tmp, contents)?;
+    fs::rename(&tmp, path).inspect_err(|_| {
+        let _ = fs::remove_file(&tmp);
+    })
+}
+
+/// Placeholders of `--output-name` templates
+pub const NAME_FIELDS: [&str; 5] = ["stem", "date", "model", "mode", "ext"];
+
+/// Output file name for `summary` of `input_path` from a template such as
+/// `{stem}.{date}.summary.{ext}`; model names are made safe for file names
+pub fn template_name(
+    template: &str,
+    input_path: &Path,
+    summary: &Summary,
+    extension: &str,
+) -> Result<String, String> {
+    expand_template(template, |field| match field {
+        "stem" => Some(input_path.file_stem().unwrap_or_default().to_string_lossy().into_owned()),
+        "date" => Some(summary.generated_at.format("%Y-%m-%d").to_string()),
+        "model" => Some(youtube::file_stem(&summary.model, "model")),
+        "mode" => summary.mode.to_possible_value().map(|value| value.get_name().to_string()),
+        "ext" => Some(extension.to_string()),
+        _ => None,
+    })
+}
+
+/// Check a `--output-name` template for unknown or unclosed placeholders before any work is done
+pub fn check_template(template: &str) -> Result<(), String> {
+    expand_template(template, |field| NAME_FIELDS.contains(&field).then(String::new)).map(|_| ())
+}
+
+fn expand_template(template: &str, value: impl Fn(&str) -> Option<String>) -> Result<String, String> {
+    let mut name = String::new();
+    let mut rest = template;
+    while let Some(open) = rest.find('{') {
+        name.push_str(&rest[..open]);
+        let Some(close) = rest[open..].find('}') else {
+            return Err(format!("Unclosed {{ in output name template {:?}", template));
+        };
+        let field = &rest[open + 1..open + close];
+        let expanded = value(field).ok_or_else(|| {
+            format!(
+                "Unknown placeholder {{{}}} in output name template {:?}; use {}",
+                field,
+                template,
+                NAME_FIELDS.map(|f| format!("{{{}}}", f)).join(", ")
+            )
+        })?;
+        name.push_str(&expanded);
+        rest = &rest[open + close + 1..];
+    }
+    name.push_str(rest);
+    Ok(name)
+}
+
+/// Readable title derived from the input filename, e.g. `intro_to_rust.srt` -> `intro to rust`
+pub fn title_from_path(path: &Path) -> String {
+    let stem = path.file_stem().unwrap_or_default().to_string_lossy();
+    let title = stem
+        .split(['_', '.'])
+        .filter(|part| !part.is_empty())
+        .collect::<Vec<_>>()
+        .join(" ");
+    if title.is_empty() {
+        "Summary".to_string()
+    } else {
+        title
+    }
+}
+
+/// Name shown for the summarized input: the file name, or the whole URL; in-memory text has none
+fn source_name(summary: &Summary) -> String {
+    match summary.input.as_deref() {
+        Some(url) if youtube::is_url(url) => url.to_string_lossy().into_owned(),
+        input => input
+            .and_then(Path::file_name)
+            .map_or_else(|| "text".to_string(), |name| name.to_string_lossy().into_owned()),
+    }
+}
+
+/// File names of the merged inputs
+fn source_names(summary: &Summary) -> Vec<String> {
+    summary
+        .sources
+        .iter()
+        .map(|path| path.file_name().unwrap_or(path.as_os_str()).to_string_lossy().into_owned())
+        .collect()
+}
+
+/// File names of the merged inputs, each wrapped by `quote`, separated by commas
+fn source_list(summary: &Summary, quote: impl Fn(String) -> String) -> String {
+    source_names(summary).into_iter().map(quote).collect::<Vec<_>>().join(", ")
+}
+
+/// The final summary, below the generated title, the merged sources and the TL;DR and above the
+/// topics when present
+fn render_text(summary: &Summary) -> String {
+    let mut text = String::new();
+    let headline = summary.headline.as_ref();
+    if let Some(title) = headline.and_then(|headline| headline.title.as_ref()) {
+        text.push_str(&format!("{}\n\n", title));
+    }
+    if !summary.sources.is_empty() {
+        text.push_str(&format!("Sources: {}\n\n", source_list(summary, |name| name)));
+    }
+    if let Some(tldr) = headline.and_then(|headline| headline.tldr.as_ref()) {
+        text.push_str(&format!("TL;DR: {}\n\n", tldr));
+    }
+    text.push_str(&text_body(summary));
+    text
+}
+
+/// The plain-text summary without the title, sources and TL;DR above it: the bullets (or show
+/// notes), then the entities and topics when present
+pub fn text_body(summary: &Summary) -> String {
+    let mut text = String::new();
+    match summary.mode {
+        // Show notes list the chapters after the description
+        Mode::Shownotes => {
+            let chapters = summary.chapters.as_deref().unwrap_or_default();
+            text.push_str(&shownotes::render(&shownotes::parse(&summary.final_summary), chapters));
+        }
+        _ => text.push_str(&summary.final_summary),
+    }
+    if let Some(entities) = &summary.entities {
+        text = format!("{}\n\n{}", text.trim_end(), entities::render(entities));
+    }
+    if let Some(topics) = &summary.keywords {
+        text = format!("{}\n\n{}\n", text.trim_end(), keywords::render(topics));
+    }
+    text
+}
+
+/// The title a summary is shown under: the generated title, the video or page title, or a title
+/// made from the file name
+pub fn title(summary: &Summary) -> String {
+    if let Some(title) = summary.headline.as_ref().and_then(|headline| headline.title.as_ref()) {
+        return title.clone();
+    }
+    if let Some(title) = &summary.title {
+        return title.clone();
+    }
+    summary
+        .input
+        .as_deref()
+        .map_or_else(|| "Summary".to_string(), title_from_path)
+}
+
+/// Which requests the fallback backend answered, e.g. `gpt-4o-mini (openai) for Chunk 3`
+fn fallback_note(summary: &Summary) -> Option<String> {
+    let fallbacks = &summary.usage.fallbacks;
+    let label = summary.fallback.as_deref().filter(|_| !fallbacks.is_empty())?;
+    Some(format!("{} for {}", label, fallbacks.join(", ")))
+}
+
+fn render_markdown(summary: &Summary) -> String {
+    let topics = match &summary.keywords {
+        Some(topics) => format!("- **Topics:** {}\n", topics.join(", ")),
+        None => String::new(),
+    };
+    let tldr = match summary.headline.as_ref().and_then(|headline| headline.tldr.as_ref()) {
+        Some(tldr) => format!("> **TL;DR:** {}\n\n", tldr),
+        None => String::new(),
+    };
+    let source = if summary.sources.is_empty() {
+        format!("- **Source:** `{}`", source_name(summary))
+    } else {
+        format!("- **Sources:** {}", source_list(summary, |name| format!("`{}`", name)))
+    };
+    let fallback = match fallback_note(summary) {
+        Some(note) => format!("- **Fallback:** {}\n", note),
+        None => String::new(),
+    };
+    let mut markdown = format!(
+        "# {}\n\n{}\n- **Generated:** {}\n- **Model:** {}\n{}{}\n{}{}",
+        title(summary),
+        source,
+        summary.generated_at.format("%Y-%m-%d %H:%M"),
+        summary.model,
+        fallback,
+        topics,
+        tldr,
+        markdown_body(summary),
+    );
+    if let Some(entities) = &summary.entities {
+        markdown.push_str(&markdown_entities(entities));
+    }
+    if let Some(chapters) = &summary.chapters {
+        markdown.push_str("\n## Chapters\n\n");
+        markdown.push_str(&chapters::render(chapters));
+    }
+    markdown
+}
+
+/// YAML front matter with the note's properties, then the Markdown summary; text the model wrote
+/// is escaped so it cannot create links, tags or comments in the vault
+fn render_obsidian(summary: &Summary) -> String {
+    let title = title(summary);
+    let mut note = format!(
+        "---\ntitle: {}\ndate: {}\n",
+        yaml_string(&title),
+        summary.generated_at.format("%Y-%m-%d")
+    );
+    if summary.sources.is_empty() {
+        note.push_str(&format!("source: {}\n", yaml_string(&source_name(summary))));
+    } else {
+        note.push_str(&format!("sources: {}\n", yaml_list(&source_names(summary))));
+    }
+    if let Some(length) = summary.media_length {
+        note.push_str(&format!("duration: {}\n", yaml_string(&chapters::format_timestamp(length))));
+    }
+    note.push_str(&format!("model: {}\n", yaml_string(&summary.model)));
+    let tags: Vec<String> = summary.keywords.iter().flatten().filter_map(|topic| tag(topic)).collect();
+    if !tags.is_empty() {
+        note.push_str(&format!("tags: {}\n", yaml_list(&tags)));
+    }
+    note.push_str(&format!("---\n\n# {}\n\n", wiki_safe(&title)));
+
+    if let Some(tldr) = summary.headline.as_ref().and_then(|headline| headline.tldr.as_ref()) {
+        note.push_str(&format!("> [!tldr]\n> {}\n\n", wiki_safe(tldr)));
+    }
+    note.push_str(&wiki_safe(&markdown_body(summary)));
+    if let Some(entities) = &summary.entities {
+        note.push_str(&wiki_safe(&markdown_entities(entities)));
+    }
+    if let Some(chapters) = &summary.chapters {
+        note.push_str("\n## Chapters\n\n");
+        note.push_str(&wiki_safe(&chapters::render(chapters)));
+    }
+    note
+}
+
+/// A double-quoted YAML scalar; JSON string syntax is a subset of it
+fn yaml_string(text: &str) -> String {
+    serde_json::to_string(text).unwrap()
+}
+
+/// A flow-style YAML list of strings
+fn yaml_list<S: AsRef<str>>(items: &[S]) -> String {
+    let items: Vec<String> = items.iter().map(|item| yaml_string(item.as_ref())).collect();
+    format!("[{}]", items.join(", "))
+}
+
+/// Obsidian tag for a topic, e.g. `Memory Safety` -> `memory-safety`; tags may only hold letters,
+/// digits, `_`, `-` and `/`, and not only digits
+fn tag(topic: &str) -> Option<String> {
+    let tag = topic
+        .split_whitespace()
+        .map(|word| {
+            word.chars()
+                .filter(|c| c.is_alphanumeric() || matches!(c, '_' | '-' | '/'))
+                .collect::<String>()
+                .to_lowercase()
+        })
+        .filter(|word| word.chars().any(char::is_alphanumeric))
+        .collect::<Vec<_>>()
+        .join("-");
+    tag.chars().any(|c| !c.is_ascii_digit()).then_some(tag)
+}
+
+/// Escape what Obsidian would read as markup of its own: `[[wiki links]]`, `#tags` and
+/// `%%comments%%`; Markdown headings (`#` followed by a space) are left alone
+fn wiki_safe(text: &str) -> String {
+    let chars: Vec<char> = text.chars().collect();
+    let mut escaped = String::with_capacity(text.len());
+    for (i, &c) in chars.iter().enumerate() {
+        let next = chars.get(i + 1).copied();
+        let previous = i.checked_sub(1).map(|i| chars[i]);
+        let doubled = |c: char| next == Some(c) || previous == Some(c);
+        let markup = match c {
+            '[' | ']' | '%' => doubled(c),
+            '#' => {
+                previous.is_none_or(char::is_whitespace) && next.is_some_and(|next| !next.is_whitespace() && next != '#')
+            }
+            _ => false,
+        };
+        if markup {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
+
+const HTML_STYLE: &str = "body { font-family: system-ui, sans-serif; line-height: 1.5; max-width: 48rem; margin: 2rem auto; padding: 0 1rem; color: #222; }
+table.meta { border-collapse: collapse; margin-bottom: 1.5rem; }
+table.meta th, table.meta td { text-align: left; padding: 0.2rem 1rem 0.2rem 0; vertical-align: top; }
+.tldr { font-size: 1.1rem; border-left: 4px solid #888; padding-left: 0.8rem; }
+details { margin: 0.4rem 0; }
+summary { cursor: pointer; }
+summary a { font-variant-numeric: tabular-nums; margin-right: 0.5rem; }";
+
+/// A standalone page with the title, a metadata table and the summary; chapters are collapsible
+/// sections holding the points of the chunks they span, their timestamps linking into the video
+fn render_html(summary: &Summary) -> String {
+    let title = title(summary);
+    let mut meta = vec![if summary.sources.is_empty() {
+        ("Source", source_name(summary))
+    } else {
+        ("Sources", source_list(summary, |name| name))
+    }];
+    meta.push(("Generated", summary.generated_at.format("%Y-%m-%d %H:%M").to_string()));
+    meta.push(("Model", summary.model.clone()));
+    if let Some(note) = fallback_note(summary) {
+        meta.push(("Fallback", note));
+    }
+    if let Some(length) = summary.media_length {
+        meta.push(("Duration", chapters::format_timestamp(length)));
+    }
+    if let Some(topics) = &summary.keywords {
+        meta.push(("Topics", topics.join(", ")));
+    }
+
+    let mut body = format!("<h1>{}</h1>\n", escape_html(&title));
+    if let Some(tldr) = summary.headline.as_ref().and_then(|headline| headline.tldr.as_ref()) {
+        body.push_str(&format!("<p class=\"tldr\"><strong>TL;DR:</strong> {}</p>\n", escape_html(tldr)));
+    }
+    body.push_str("<table class=\"meta\">\n");
+    for (name, value) in meta {
+        body.push_str(&format!("<tr><th>{}</th><td>{}</td></tr>\n", name, escape_html(&value)));
+    }
+    body.push_str("</table>\n");
+    body.push_str(&html_body(summary));
+    if let Some(entities) = &summary.entities {
+        let items: String = entities
+            .categories()
+            .iter()
+            .filter(|(_, items)| !items.is_empty())
+            .map(|(name, items)| format!("<li><strong>{}:</strong> {}</li>\n", name, escape_html(&items.join("; "))))
+            .collect();
+        body.push_str(&format!("<h2>Entities</h2>\n<ul>\n{}</ul>\n", items));
+    }
+    if let Some(chapters) = &summary.chapters {
+        body.push_str("<h2>Chapters</h2>\n");
+        body.push_str(&html_chapters(summary, chapters));
+    }
+
+    format!(
+        "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n\
+         <meta name=\"viewport\" content=\"width=device-width, initial-scale=1\">\n\
+         <title>{}</title>\n<style>\n{}\n</style>\n</head>\n<body>\n{}</body>\n</html>\n",
+        escape_html(&title),
+        HTML_STYLE,
+        body
+    )
+}
+
+/// The summary as HTML, structured like `markdown_body`
+fn html_body(summary: &Summary) -> String {
+    let list = |tag: &str, items: Vec<String>| {
+        let items: String = items.iter().map(|item| format!("<li>{}</li>\n", item)).collect();
+        format!("<{tag}>\n{}</{tag}>\n", items)
+    };
+    match summary.mode {
+        Mode::ActionItems => {
+            let items = action_items::parse(&summary.final_summary)
+                .into_iter()
+                .map(|item| match item.owner {
+                    Some(owner) => format!("<strong>{}:</strong> {}", escape_html(&owner), escape_html(&item.task)),
+                    None => escape_html(&item.task),
+                })
+                .collect();
+            return format!("<h2>Action Items</h2>\n{}", list("ul", items));
+        }
+        Mode::Qa => {
+            let pairs = qa::parse(&summary.final_summary)
+                .into_iter()
+                .map(|pair| format!("<strong>{}</strong><br>\n{}", escape_html(&pair.question), escape_html(&pair.answer)))
+                .collect();
+            return format!("<h2>Questions</h2>\n{}", list("ol", pairs));
+        }
+        Mode::Outline => return format!("<h2>Outline</h2>\n{}", html_outline(&outline::parse(&summary.final_summary))),
+        Mode::Shownotes => {
+            let notes = shownotes::parse(&summary.final_summary);
+            let or_none = |items: Vec<String>| if items.is_empty() { vec!["None".to_string()] } else { items };
+            let escaped = |items: &[String]| or_none(items.iter().map(|item| escape_html(item)).collect());
+            return format!(
+                "<h2>{}</h2>\n<p>{}</p>\n<h2>{}</h2>\n{}<h2>{}</h2>\n{}",
+                shownotes::DESCRIPTION_HEADING,
+                escape_html(&notes.description),
+                shownotes::QUOTES_HEADING,
+                list("ul", escaped(&notes.quotes)),
+                shownotes::MENTIONS_HEADING,
+                list("ul", escaped(&notes.mentions)),
+            );
+        }
+        Mode::Summary | Mode::Minutes | Mode::Lecture | Mode::Topics => {}
+    }
+    let escaped = |items: Vec<String>| items.iter().map(|item| escape_html(item)).collect();
+    let sections = if is_sectioned(summary) {
+        sections(&summary.final_summary)
+    } else {
+        Vec::new()
+    };
+    if sections.is_empty() {
+        return format!("<h2>Summary</h2>\n{}", list("ul", escaped(bullets(&summary.final_summary))));
+    }
+    sections
+        .into_iter()
+        .map(|(heading, items)| {
+            let items = if items.is_empty() { vec!["None".to_string()] } else { escaped(items) };
+            format!("<h2>{}</h2>\n{}", escape_html(&heading), list("ul", items))
+        })
+        .collect()
+}
+
+/// Nested `<ul>` lists of an outline
+fn html_outline(nodes: &[outline::Node]) -> String {
+    let items: String = nodes
+        .iter()
+        .map(|node| match node.children.as_slice() {
+            [] => format!("<li>{}</li>\n", escape_html(&node.text)),
+            children => format!("<li>{}\n{}</li>\n", escape_html(&node.text), html_outline(children)),
+        })
+        .collect();
+    format!("<ul>\n{}</ul>\n", items)
+}
+
+/// One `<details>` section per chapter, listing the points of the chunks starting within it
+fn html_chapters(summary: &Summary, chapters: &[chapters::Chapter]) -> String {
+    let video = summary
+        .video_url
+        .clone()
+        .or_else(|| summary.input.as_deref().filter(|input| youtube::is_url(input)).map(|url| url.to_string_lossy().into_owned()));
+    let mut html = String::new();
+    for (i, chapter) in chapters.iter().enumerate() {
+        let end = chapters.get(i + 1).map(|next| next.start);
+        let timestamp = chapters::format_timestamp(chapter.start);
+        let timestamp = match &video {
+            Some(url) => format!("<a href=\"{}\">{}</a>", escape_html(&time_link(url, chapter.start)), timestamp),
+            None => timestamp,
+        };
+        let points: String = summary
+            .chunk_starts
+            .iter()
+            .zip(&summary.chunk_summaries)
+            .filter(|(start, _)| **start >= chapter.start && end.is_none_or(|end| **start < end))
+            .flat_map(|(_, chunk)| bullets(chunk))
+            .map(|point| format!("<li>{}</li>\n", escape_html(&point)))
+            .collect();
+        html.push_str(&format!("<details>\n<summary>{} {}</summary>\n", timestamp, escape_html(&chapter.title)));
+        if !points.is_empty() {
+            html.push_str(&format!("<ul>\n{}</ul>\n", points));
+        }
+        html.push_str("</details>\n");
+    }
+    html
+}
+
+/// `url` opened at `time`, as a `#t=<seconds>` media fragment replacing any fragment it has
+fn time_link(url: &str, time: Duration) -> String {
+    let page = url.split('#').next().unwrap_or(url);
+    format!("{}#t={}", page, time.as_secs())
+}
+
+fn escape_html(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// Tab-separated flashcards with the header lines of Anki's text importer, so it knows the
+/// separator, the columns and the deck without asking
+fn render_anki(summary: &Summary) -> String {
+    let mut cards = String::from("#separator:tab\n#html:false\n");
+    if let Some(deck) = &summary.deck_name {
+        cards.push_str(&format!("#deck:{}\n", flatten(deck)));
+    }
+    cards.push_str("#columns:Front\tBack\n");
+    for pair in qa::parse(&summary.final_summary) {
+        cards.push_str(&format!("{}\t{}\n", anki_field(&pair.question), anki_field(&pair.answer)));
+    }
+    cards
+}
+
+/// A field on one line, quoted like CSV when it has quotes Anki would otherwise read as quoting
+fn anki_field(text: &str) -> String {
+    let text = flatten(text);
+    if text.contains('"') {
+        format!("\"{}\"", text.replace('"', "\"\""))
+    } else {
+        text
+    }
+}
+
+/// `text` with tabs and line breaks collapsed into single spaces
+fn flatten(text: &str) -> String {
+    text.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+/// Whether the final summary is made of `## Heading` sections rather than one bullet list
+fn is_sectioned(summary: &Summary) -> bool {
+    matches!(summary.mode, Mode::Minutes | Mode::Lecture | Mode::Topics) || summary.by_speaker
+}
+
+/// `## Entities` with one bullet per category that has items
+fn markdown_entities(entities: &entities::Entities) -> String {
+    let items: String = entities
+        .categories()
+        .iter()
+        .filter(|(_, items)| !items.is_empty())
+        .map(|(name, items)| format!("- **{}:** {}\n", name, items.join("; ")))
+        .collect();
+    format!("\n## Entities\n\n{}", items)
+}
+
+/// `## Summary` with the bullets, or one `##` section per heading for sectioned modes
+fn markdown_body(summary: &Summary) -> String {
+    match summary.mode {
+        Mode::ActionItems => return format!("## Action Items\n\n{}\n", summary.final_summary.trim()),
+        Mode::Qa => {
+            let pairs: String = qa::parse(&summary.final_summary)
+                .iter()
+                .enumerate()
+                .map(|(i, pair)| format!("{}. **{}**\n   {}\n", i + 1, pair.question, pair.answer))
+                .collect();
+            return format!("## Questions\n\n{}", pairs);
+        }
+        Mode::Outline => return format!("## Outline\n\n{}\n", summary.final_summary.trim()),
+        // Already in `##` sections; the chapters follow them
+        Mode::Shownotes => return format!("{}\n", summary.final_summary.trim()),
+        Mode::Summary | Mode::Minutes | Mode::Lecture | Mode::Topics => {}
+    }
+    let sections = if is_sectioned(summary) {
+        sections(&summary.final_summary)
+    } else {
+        Vec::new()
+    };
+    if sections.is_empty() {
+        return format!("## Summary\n\n{}\n", summary.final_summary.trim());
+    }
+
+    sections
+        .iter()
+        .map(|(heading, items)| {
+            let items = if items.is_empty() {
+                "- None\n".to_string()
+            } else {
+                items.iter().map(|item| format!("- {}\n", item)).collect()
+            };
+            format!("## {}\n\n{}", heading, items)
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// JSON document written by `--format json`; field order is the order in the file
+#[derive(Serialize)]
+struct JsonReport<'a> {
+    input: String,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    sources: Vec<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    title: Option<&'a str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    tldr: Option<&'a str>,
+    model: &'a str,
+    mode: Mode,
+    generated_at: String,
+    chunks: usize,
+    chunk_summaries: &'a [String],
+    #[serde(skip_serializing_if = "<[usize]>::is_empty")]
+    skipped_chunks: &'a [usize],
+    final_summary: Vec<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    sections: Option<Vec<JsonSection>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    keywords: Option<&'a [String]>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    entities: Option<JsonEntities<'a>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    action_items: Option<Vec<JsonActionItem>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    questions: Option<Vec<JsonQuestion>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    outline: Option<Vec<JsonOutlineItem>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    show_notes: Option<JsonShowNotes>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    concepts: Option<Vec<JsonConcept>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    topics: Option<Vec<JsonTopic>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    chapters: Option<Vec<JsonChapter<'a>>>,
+    duration_ms: u64,
+    usage: JsonUsage,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    fallback: Option<JsonFallback<'a>>,
+}
+
+#[derive(Serialize)]
+struct JsonFallback<'a> {
+    model: &'a str,
+    requests: &'a [String],
+}
+
+#[derive(Serialize)]
+struct JsonUsage {
+    #[serde(flatten)]
+    total: TokenUsage,
+    stages: Vec<JsonStage>,
+}
+
+#[derive(Serialize)]
+struct JsonStage {
+    stage: &'static str,
+    #[serde(flatten)]
+    usage: TokenUsage,
+}
+
+#[derive(Serialize)]
+struct JsonSection {
+    title: String,
+    items: Vec<String>,
+}
+
+#[derive(Serialize)]
+struct JsonComparison<'a> {
+    a: &'a str,
+    b: &'a str,
+    only_in_a: &'a [String],
+    only_in_b: &'a [String],
+    changed_decisions: &'a [String],
+}
+
+#[derive(Serialize)]
+struct JsonAnswer<'a> {
+    question: &'a str,
+    answer: &'a str,
+    sources: Vec<JsonSource<'a>>,
+}
+
+#[derive(Serialize)]
+struct JsonSource<'a> {
+    chunk: usize,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    start_seconds: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    end_seconds: Option<u64>,
+    similarity: f32,
+    text: &'a str,
+}
+
+#[derive(Serialize)]
+struct JsonEntities<'a> {
+    people: &'a [String],
+    organizations: &'a [String],
+    products: &'a [String],
+    numbers: &'a [String],
+}
+
+#[derive(Serialize)]
+struct JsonActionItem {
+    owner: Option<String>,
+    task: String,
+}
+
+#[derive(Serialize)]
+struct JsonQuestion {
+    question: String,
+    answer: String,
+}
+
+#[derive(Serialize)]
+struct JsonOutlineItem {
+    text: String,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    items: Vec<JsonOutlineItem>,
+}
+
+impl JsonOutlineItem {
+    fn from_nodes(nodes: Vec<outline::Node>) -> Vec<Self> {
+        nodes
+            .into_iter()
+            .map(|node| Self {
+                text: node.text,
+                items: Self::from_nodes(node.children),
+            })
+            .collect()
+    }
+}
+
+#[derive(Serialize)]
+struct JsonShowNotes {
+    description: String,
+    quotes: Vec<String>,
+    mentions: Vec<String>,
+}
+
+#[derive(Serialize)]
+struct JsonConcept {
+    term: String,
+    definition: String,
+}
+
+#[derive(Serialize)]
+struct JsonTopic {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    start: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    start_seconds: Option<u64>,
+    title: String,
+    points: Vec<String>,
+}
+
+#[derive(Serialize)]
+struct JsonChapter<'a> {
+    start: String,
+    start_seconds: u64,
+    title: &'a str,
+}
+
+fn render_json(summary: &Summary) -> String {
+    let value = JsonReport {
+        input: summary
+            .input
+            .as_deref()
+            .map_or_else(String::new, |p| p.to_string_lossy().into_owned()),
+        sources: summary.sources.iter().map(|p| p.to_string_lossy().into_owned()).collect(),
+        title: summary.headline.as_ref().and_then(|headline| headline.title.as_deref()),
+        tldr: summary.headline.as_ref().and_then(|headline| headline.tldr.as_deref()),
+        model: &summary.model,
+        mode: summary.mode,
+        generated_at: summary.generated_at.to_rfc3339(),
+        chunks: summary.chunk_summaries.len(),
+        chunk_summaries: &summary.chunk_summaries,
+        skipped_chunks: &summary.skipped_chunks,
+        final_summary: bullets(&summary.final_summary),
+        sections: is_sectioned(summary).then(|| {
+            sections(&summary.final_summary)
+                .into_iter()
+                .map(|(title, items)| JsonSection { title, items })
+                .collect()
+        }),
+        keywords: summary.keywords.as_deref(),
+        entities: summary.entities.as_ref().map(|entities| JsonEntities {
+            people: &entities.people,
+            organizations: &entities.organizations,
+            products: &entities.products,
+            numbers: &entities.numbers,
+        }),
+        action_items: (summary.mode == Mode::ActionItems).then(|| {
+            action_items::parse(&summary.final_summary)
+                .into_iter()
+                .map(|item| JsonActionItem {
+                    owner: item.owner,
+                    task: item.task,
+                })
+                .collect()
+        }),
+        questions: (summary.mode == Mode::Qa).then(|| {
+            qa::parse(&summary.final_summary)
+                .into_iter()
+                .map(|pair| JsonQuestion {
+                    question: pair.question,
+                    answer: pair.answer,
+                })
+                .collect()
+        }),
+        outline: (summary.mode == Mode::Outline)
+            .then(|| JsonOutlineItem::from_nodes(outline::parse(&summary.final_summary))),
+        show_notes: (summary.mode == Mode::Shownotes).then(|| {
+            let notes = shownotes::parse(&summary.final_summary);
+            JsonShowNotes {
+                description: notes.description,
+                quotes: notes.quotes,
+                mentions: notes.mentions,
+            }
+        }),
+        concepts: (summary.mode == Mode::Lecture).then(|| {
+            lecture::parse(&summary.final_summary)
+                .concepts
+                .into_iter()
+                .map(|concept| JsonConcept {
+                    term: concept.term,
+                    definition: concept.definition,
+                })
+                .collect()
+        }),
+        topics: (summary.mode == Mode::Topics).then(|| {
+            topics::parse(&summary.final_summary)
+                .into_iter()
+                .map(|topic| JsonTopic {
+                    start: topic.start.map(parser::format_clock),
+                    start_seconds: topic.start.map(|start| start.as_secs()),
+                    title: topic.title,
+                    points: topic.points,
+                })
+                .collect()
+        }),
+        chapters: summary.chapters.as_ref().map(|chapters| {
+            chapters
+                .iter()
+                .map(|chapter| JsonChapter {
+                    start: chapters::format_timestamp(chapter.start),
+                    start_seconds: chapter.start.as_secs(),
+                    title: &chapter.title,
+                })
+                .collect()
+        }),
+        duration_ms: summary.duration.as_millis() as u64,
+        usage: JsonUsage {
+            total: summary.usage.total(),
+            stages: summary
+                .usage
+                .stages
+                .iter()
+                .map(|&(stage, usage)| JsonStage { stage, usage })
+                .collect(),
+        },
+        fallback: summary
+            .fallback
+            .as_deref()
+            .filter(|_| !summary.usage.fallbacks.is_empty())
+            .map(|model| JsonFallback {
+                model,
+                requests: &summary.usage.fallbacks,
+            }),
+    };
+    // Plain strings and numbers always serialize
+    serde_json::to_string_pretty(&value).unwrap() + "\n"
+}
@@ -0,0 +1,75 @@
+use regex::Regex;
+
+use super::{parse_timestamp, Cue};
+
+/// Parse ASS/SSA text into cues from the `[Events]` Dialogue lines, stripping `{...}`
+/// override tags and turning `\N`/`\n` into line breaks and `\h` into spaces
+pub fn parse_ass(text: &str) -> Vec<Cue> {
+    let override_re = Regex::new(r"\{[^}]*\}").unwrap();
+
+    let mut cues = Vec::new();
+    let mut in_events = false;
+    // Default to the ASS v4+ field order if no Format line is seen; Text is always last
+    let mut field_count = 10;
+    let mut start_field = 1;
+    let mut end_field = 2;
+    let mut name_field = Some(4);
+
+    for line in text.lines() {
+        let t = line.trim();
+        if t.starts_with('[') && t.ends_with(']') {
+            in_events = t.eq_ignore_ascii_case("[events]");
+            continue;
+        }
+        if !in_events {
+            continue;
+        }
+        if let Some(format) = t.strip_prefix("Format:") {
+            let fields: Vec<&str> = format.split(',').map(str::trim).collect();
+            field_count = fields.len().max(1);
+            start_field = fields.iter().position(|f| *f == "Start").unwrap_or(1);
+            end_field = fields.iter().position(|f| *f == "End").unwrap_or(2);
+            name_field = fields.iter().position(|f| *f == "Name");
+            continue;
+        }
+        let Some(dialogue) = t.strip_prefix("Dialogue:") else {
+            continue;
+        };
+        let fields: Vec<&str> = dialogue.splitn(field_count, ',').collect();
+        if fields.len() < field_count {
+            continue;
+        }
+        let (Some(start), Some(end)) = (
+            parse_timestamp(fields[start_field]),
+            parse_timestamp(fields[end_field]),
+        ) else {
+            continue;
+        };
+
+        let stripped = override_re.replace_all(fields[field_count - 1], "");
+        let stripped = stripped
+            .replace("\\N", "\n")
+            .replace("\\n", "\n")
+            .replace("\\h", " ");
+        let stripped = stripped
+            .lines()
+            .map(|l| l.split_whitespace().collect::<Vec<_>>().join(" "))
+            .filter(|l| !l.is_empty())
+            .collect::<Vec<_>>()
+            .join("\n");
+        if !stripped.is_empty() {
+            cues.push(Cue {
+                index: cues.len() + 1,
+                start,
+                end,
+                text: stripped,
+                speaker: name_field
+                    .map(|field| fields[field].trim())
+                    .filter(|name| !name.is_empty())
+                    .map(str::to_string),
+            });
+        }
+    }
+
+    cues
+}
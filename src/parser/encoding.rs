@@ -0,0 +1,42 @@
+use chardetng::EncodingDetector;
+use encoding_rs::{Encoding, UTF_8};
+use std::{
+    fs::File,
+    io::{self, Read},
+    path::Path,
+};
+
+/// Bytes read to tell whether a file is UTF-8 without reading all of it
+const SNIFF_BYTES: u64 = 64 * 1024;
+
+/// Decode file contents to UTF-8 text. Valid UTF-8 is taken as is; a UTF-16 byte order mark
+/// wins next, and anything else is guessed by chardetng (Windows-1252, Windows-1256, GBK, ...).
+/// Returns the name of the detected encoding when it wasn't UTF-8.
+pub fn decode(bytes: &[u8]) -> (String, Option<&'static str>) {
+    if let Ok(text) = std::str::from_utf8(bytes) {
+        return (text.to_string(), None);
+    }
+    let encoding = match Encoding::for_bom(bytes) {
+        Some((encoding, _)) => encoding,
+        None => {
+            let mut detector = EncodingDetector::new();
+            detector.feed(bytes, true);
+            detector.guess(None, true)
+        }
+    };
+    // Strips the BOM it was detected from, and replaces undecodable bytes with U+FFFD
+    let (text, encoding, _) = encoding.decode(bytes);
+    let name = (encoding != UTF_8).then(|| encoding.name());
+    (text.into_owned(), name)
+}
+
+/// Whether the beginning of the file at `path` is UTF-8 (a character cut off by the sample
+/// counts), so it can be read line by line without guessing its encoding from all of it
+pub fn starts_as_utf8(path: &Path) -> io::Result<bool> {
+    let mut head = Vec::new();
+    File::open(path)?.take(SNIFF_BYTES).read_to_end(&mut head)?;
+    Ok(match std::str::from_utf8(&head) {
+        Ok(_) => true,
+        Err(e) => e.error_len().is_none(),
+    })
+}
@@ -0,0 +1,42 @@
+use dom_query::Document;
+use epub::doc::EpubDoc;
+use std::{error::Error, io::Cursor};
+
+use super::{decode, html::collapse_blank_lines, sectioned, Transcript};
+
+/// The text of an EPUB book's chapters in reading (spine) order, each chapter starting a section.
+/// Items marked non-linear (footnotes, answer keys) and the table of contents are left out, and
+/// so are chapters without text, such as the cover.
+pub fn parse_epub(bytes: &[u8]) -> Result<Transcript, Box<dyn Error>> {
+    let mut doc = EpubDoc::from_reader(Cursor::new(bytes.to_vec())).map_err(|e| format!("Cannot read the EPUB: {}", e))?;
+    let nav = doc.get_nav_id();
+    let items: Vec<String> = doc
+        .spine
+        .iter()
+        .filter(|item| item.linear && Some(&item.idref) != nav.as_ref())
+        .map(|item| item.idref.clone())
+        .collect();
+    let mut chapters = Vec::new();
+    for id in items {
+        let Some((content, _mime)) = doc.get_resource(&id) else {
+            continue;
+        };
+        let (html, _) = decode(&content);
+        let text = chapter_text(&html);
+        if !text.is_empty() {
+            chapters.push(text);
+        }
+    }
+    if chapters.is_empty() {
+        return Err("The EPUB has no chapters with text".into());
+    }
+    Ok(sectioned(chapters))
+}
+
+/// The text of a chapter's XHTML body, with a blank line after every paragraph and heading
+fn chapter_text(html: &str) -> String {
+    let document = Document::from(html);
+    document.select("script, style").remove();
+    let text = document.select("body").formatted_text();
+    collapse_blank_lines(&text)
+}
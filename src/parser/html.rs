@@ -0,0 +1,94 @@
+// Article text of `.html` pages.
+//
+// dom_smoothie, a port of Mozilla's Readability, scores the page's blocks to find the article
+// body and drops navigation, sidebars, ads, comment sections and footers around it. Its text
+// keeps paragraph breaks, which the sentence splitter uses like subtitle cue ends.
+
+use dom_smoothie::{Config, Readability, TextMode};
+use std::error::Error;
+
+/// The readable part of a web page
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Article {
+    /// The page title without the site name, when it has one
+    pub title: Option<String>,
+    pub text: String,
+}
+
+/// Extract the article from `html`; `url` resolves its relative links
+pub fn extract_article(html: &str, url: Option<&str>) -> Result<Article, Box<dyn Error>> {
+    let config = Config {
+        text_mode: TextMode::Formatted,
+        ..Config::default()
+    };
+    let article = Readability::new(html, url, Some(config))
+        .and_then(|mut readability| readability.parse())
+        .map_err(|e| format!("Cannot find the article in the page: {}", e))?;
+    let title = article.title.trim();
+    Ok(Article {
+        title: (!title.is_empty()).then(|| title.to_string()),
+        text: collapse_blank_lines(&article.text_content),
+    })
+}
+
+/// Whether the page embeds a video (an `og:video` meta tag), so its subtitles are wanted rather
+/// than its text
+pub fn has_video(html: &str) -> bool {
+    let html = html.to_ascii_lowercase();
+    ["property=\"og:video", "property='og:video", "property=og:video"]
+        .iter()
+        .any(|tag| html.contains(tag))
+}
+
+/// Trim every line; runs of empty lines left by removed blocks become one paragraph break
+pub(super) fn collapse_blank_lines(text: &str) -> String {
+    let text = text.lines().map(str::trim).collect::<Vec<_>>().join("\n");
+    let mut collapsed = String::with_capacity(text.len());
+    for paragraph in text.split("\n\n").map(str::trim).filter(|p| !p.is_empty()) {
+        if !collapsed.is_empty() {
+            collapsed.push_str("\n\n");
+        }
+        collapsed.push_str(paragraph);
+    }
+    collapsed
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PAGE: &str = r#"<!DOCTYPE html>
+<html><head><title>Why Rust Works | Example Blog</title>
+<meta property="og:title" content="Why Rust Works"></head>
+<body>
+<nav><a href="/">Home</a> <a href="/about">About</a> <a href="/subscribe">Subscribe now</a></nav>
+<div class="sidebar ad">Buy our course! Limited offer, click here.</div>
+<article>
+<h1>Why Rust Works</h1>
+<p>Rust gives systems programmers memory safety without a garbage collector. The borrow checker
+enforces ownership rules at compile time, so whole classes of bugs never reach production.</p>
+<p>Teams adopting it report fewer crashes and more confident refactoring, although the learning
+curve is steep at first and compile times can be long for large projects.</p>
+<p>The ecosystem has matured quickly, with Cargo making it easy to share libraries and tools,
+and with editors offering good support through rust-analyzer.</p>
+</article>
+<footer>Copyright 2024 Example Blog. All rights reserved. Privacy policy.</footer>
+</body></html>"#;
+
+    #[test]
+    fn article_body_is_kept_without_boilerplate() {
+        let article = extract_article(PAGE, Some("https://example.com/rust")).unwrap();
+        assert_eq!(article.title.as_deref(), Some("Why Rust Works"));
+        assert!(article.text.contains("memory safety without a garbage collector"), "{}", article.text);
+        assert!(article.text.contains("rust-analyzer"), "{}", article.text);
+        for boilerplate in ["Subscribe now", "Buy our course", "All rights reserved"] {
+            assert!(!article.text.contains(boilerplate), "{}", article.text);
+        }
+    }
+
+    #[test]
+    fn video_pages_are_recognized() {
+        assert!(has_video(r#"<meta property="og:video:url" content="https://example.com/v.mp4">"#));
+        assert!(!has_video(PAGE));
+    }
+}
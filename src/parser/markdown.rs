@@ -0,0 +1,144 @@
+use regex::Regex;
+
+use super::{sectioned, Transcript};
+
+/// Strip Markdown down to its text: front matter, fences, list and quote markers, emphasis,
+/// link targets, images, HTML tags and table rules go, while code block contents and link texts
+/// stay. Every ATX (`## Title`) or setext (`Title` over `===`) heading starts a section, which
+/// chunks prefer to end before.
+pub fn parse_markdown(text: &str) -> Transcript {
+    let stripper = Stripper::new();
+    let mut sections: Vec<Vec<String>> = vec![Vec::new()];
+    let mut in_fence = false;
+
+    for line in front_matter_stripped(text).lines() {
+        let trimmed = line.trim();
+        if trimmed.starts_with("```") || trimmed.starts_with("~~~") {
+            in_fence = !in_fence;
+            continue;
+        }
+        if in_fence {
+            sections.last_mut().unwrap().push(line.to_string());
+            continue;
+        }
+        if let Some(heading) = stripper.heading.captures(line) {
+            sections.push(vec![stripper.inline(&heading[1])]);
+            continue;
+        }
+        let current = sections.last_mut().unwrap();
+        if stripper.setext.is_match(line) {
+            // Only a line of text right above makes `===`/`---` an underline; otherwise `---` is a rule
+            match current.pop() {
+                Some(title) if !title.is_empty() => sections.push(vec![title]),
+                previous => current.extend(previous),
+            }
+            continue;
+        }
+        if stripper.rule.is_match(line) || stripper.definition.is_match(line) || stripper.table_rule.is_match(line) {
+            continue;
+        }
+        current.push(stripper.line(line));
+    }
+
+    let sections: Vec<String> = sections
+        .into_iter()
+        .map(|mut lines| {
+            lines.dedup_by(|line, previous| line.is_empty() && previous.is_empty());
+            lines.join("\n").trim().to_string()
+        })
+        .filter(|section| !section.is_empty())
+        .collect();
+    sectioned(sections)
+}
+
+/// `text` without a leading `---` YAML (or `+++` TOML) front matter block
+fn front_matter_stripped(text: &str) -> &str {
+    for fence in ["---", "+++"] {
+        if let Some(rest) = text.strip_prefix(fence).and_then(|rest| rest.strip_prefix('\n')) {
+            let end = format!("\n{}", fence);
+            if let Some(at) = rest.find(&end) {
+                let after = &rest[at + end.len()..];
+                return after.split_once('\n').map_or("", |(_, body)| body);
+            }
+        }
+    }
+    text
+}
+
+struct Stripper {
+    heading: Regex,
+    setext: Regex,
+    rule: Regex,
+    definition: Regex,
+    table_rule: Regex,
+    block_markers: Regex,
+    inline_rules: Vec<(Regex, &'static str)>,
+}
+
+impl Stripper {
+    fn new() -> Self {
+        let rule = |pattern: &str, replacement| (Regex::new(pattern).unwrap(), replacement);
+        Self {
+            heading: Regex::new(r"^ {0,3}#{1,6}\s+(.*?)(?:\s+#+)?\s*$").unwrap(),
+            setext: Regex::new(r"^ {0,3}(?:=+|-+)\s*$").unwrap(),
+            rule: Regex::new(r"^ {0,3}(?:(?:\*\s*){3,}|(?:_\s*){3,}|(?:-\s*){3,})$").unwrap(),
+            definition: Regex::new(r"^ {0,3}\[[^\]]+\]:\s*\S").unwrap(),
+            table_rule: Regex::new(r"^\s*\|?\s*:?-+:?\s*(?:\|\s*:?-+:?\s*)+\|?\s*$").unwrap(),
+            // Quote markers, then a list marker and a task box
+            block_markers: Regex::new(r"^\s*(?:>\s?)*(?:(?:[-*+]|\d+[.)])\s+(?:\[[ xX]\]\s+)?)?").unwrap(),
+            inline_rules: vec![
+                rule(r"!\[([^\]]*)\]\([^)]*\)", "$1"),
+                rule(r"\[([^\]]+)\]\([^)]*\)", "$1"),
+                rule(r"\[([^\]]+)\]\[[^\]]*\]", "$1"),
+                rule(r"<(https?://[^>\s]+)>", "$1"),
+                rule(r"</?[A-Za-z][^>]*>", ""),
+                rule(r"`([^`]+)`", "$1"),
+                rule(r"\*\*([^*]+)\*\*", "$1"),
+                rule(r"\b__([^_\n]+)__\b", "$1"),
+                rule(r"\*([^*\s][^*]*)\*", "$1"),
+                rule(r"\b_([^_\n]+)_\b", "$1"),
+                rule(r"~~([^~]+)~~", "$1"),
+            ],
+        }
+    }
+
+    fn line(&self, line: &str) -> String {
+        let line = self.block_markers.replace(line, "");
+        // Table cells
+        let line = if line.trim_start().starts_with('|') {
+            line.replace('|', " ")
+        } else {
+            line.into_owned()
+        };
+        self.inline(&line)
+    }
+
+    fn inline(&self, text: &str) -> String {
+        let mut text = text.to_string();
+        for (pattern, replacement) in &self.inline_rules {
+            text = pattern.replace_all(&text, *replacement).into_owned();
+        }
+        text.trim().to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn formatting_is_stripped_and_headings_start_sections() {
+        let text = "---\ntitle: Notes\n---\nIntro with **bold**, _em_ and a [link](https://example.com).\n\n\
+                    # First Part\n\n- item one\n- [x] item `two`\n\n> quoted snake_case_name\n\n\
+                    Second Part\n===========\n\n| a | b |\n|---|---|\n| 1 | 2 |\n\n---\n\n```rust\nlet x = 1;\n```\n";
+        let transcript = parse_markdown(text);
+        assert_eq!(
+            transcript.text,
+            "Intro with bold, em and a link.\n\nFirst Part\n\nitem one\nitem two\n\nquoted snake_case_name\n\n\
+             Second Part\n\na   b\n1   2\n\nlet x = 1;"
+        );
+        let words: Vec<&str> = transcript.text.split_whitespace().collect();
+        let starts: Vec<&str> = transcript.sections.iter().map(|&i| words[i]).collect();
+        assert_eq!(starts, ["First", "Second"]);
+    }
+}
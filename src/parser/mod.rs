@@ -0,0 +1,499 @@
+// Subtitle parsers that turn `.srt`, `.vtt`, `.ass`/`.ssa` and Whisper `.json` files into
+// structured cues, and text extraction for `.pdf`, `.html`, `.epub` and Markdown files.
+//
+// Every parser is lenient: malformed blocks are skipped rather than aborting the whole file,
+// so a single broken cue in a long transcript does not prevent summarization.
+
+mod ass;
+mod encoding;
+mod epub;
+mod html;
+mod markdown;
+mod pdf;
+mod srt;
+mod sanitize;
+mod vtt;
+mod whisper;
+
+use regex::Regex;
+use std::{error::Error, fs, path::Path, time::Duration};
+use tracing::warn;
+
+/// Fewer words per page than this is taken as a scanned PDF without a text layer
+const MIN_PDF_WORDS_PER_PAGE: usize = 10;
+
+pub use ass::parse_ass;
+pub use encoding::{decode, starts_as_utf8};
+pub use epub::parse_epub;
+pub use html::{extract_article, has_video, Article};
+pub use markdown::parse_markdown;
+pub use pdf::extract_pdf;
+pub use sanitize::strip_sound_descriptions;
+pub use srt::parse_srt;
+pub use vtt::parse_vtt;
+pub use whisper::parse_whisper;
+
+/// A single timed subtitle entry
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Cue {
+    /// 1-based position of the cue (the SRT index when the file provides one)
+    pub index: usize,
+    pub start: Duration,
+    pub end: Duration,
+    /// Cue text with markup and speaker label removed; multi-line cues keep their `\n` separators
+    pub text: String,
+    /// Who is speaking, from a VTT `<v Name>` tag, the ASS `Name` field or a `NAME:` prefix;
+    /// unlabelled cues inherit the speaker of the cue before them
+    pub speaker: Option<String>,
+}
+
+/// Parse subtitle text according to the file extension; `None` means "not a subtitle format"
+/// (including `.json` files that aren't Whisper transcripts)
+pub fn parse_cues(extension: &str, text: &str) -> Option<Vec<Cue>> {
+    let mut cues = match extension.to_lowercase().as_str() {
+        "srt" => parse_srt(text),
+        "vtt" => parse_vtt(text),
+        "ass" | "ssa" => parse_ass(text),
+        "json" => parse_whisper(text)?,
+        _ => return None,
+    };
+    label_speakers(&mut cues);
+    collapse_repeats(&mut cues);
+    Some(cues)
+}
+
+/// Move `NAME:` prefixes (also `>> NAME:` and `Speaker 2:`) from the cue text to `speaker`, and
+/// let unlabelled cues inherit the previous speaker
+fn label_speakers(cues: &mut [Cue]) {
+    let label_re = Regex::new(
+        r"^(?:>>\s*)?(\p{Lu}[\p{L}\p{M}'.-]*(?: (?:\p{Lu}[\p{L}\p{M}'.-]*|\d+)){0,2}):(?:\s+|$)",
+    )
+    .unwrap();
+
+    let mut current: Option<String> = None;
+    for cue in cues.iter_mut() {
+        if cue.speaker.is_none() {
+            if let Some(label) = label_re.captures(&cue.text) {
+                cue.speaker = Some(label[1].to_string());
+                cue.text = cue.text[label[0].len()..].trim_start().to_string();
+            }
+        }
+        match &cue.speaker {
+            Some(speaker) => current = Some(speaker.clone()),
+            None => cue.speaker = current.clone(),
+        }
+    }
+}
+
+/// Cleaned text of an input file, with its cues when it is a subtitle file
+#[derive(Debug, Clone)]
+pub struct Transcript {
+    pub text: String,
+    pub cues: Option<Vec<Cue>>,
+    /// Encoding the file was transcoded from, when it wasn't UTF-8
+    pub encoding: Option<&'static str>,
+    /// Index of the first word of every section after the first (Markdown headings, EPUB
+    /// chapters), where chunks prefer to end
+    pub sections: Vec<usize>,
+}
+
+/// Read a file and, for subtitle formats, flatten its cues to plain text
+pub fn load_text(path: &Path) -> Result<String, Box<dyn Error>> {
+    Ok(load_transcript(path)?.text)
+}
+
+/// Like `load_text`, but keep the cues of subtitle formats for their timing. Files that aren't
+/// UTF-8 are transcoded from their detected encoding.
+pub fn load_transcript(path: &Path) -> Result<Transcript, Box<dyn Error>> {
+    let extension = path.extension().and_then(|e| e.to_str()).unwrap_or("");
+    if is_audio(extension) {
+        return Err(format!("{:?} is a recording; only summarizing transcribes audio", path).into());
+    }
+    let bytes = fs::read(path)?;
+    parse_bytes(extension, &bytes).map_err(|e| format!("{:?}: {}", path, e).into())
+}
+
+/// Parse the contents of a file with `extension`: the text of PDF, EPUB, HTML and Markdown files
+/// is extracted, and anything else is decoded and parsed by `parse_transcript`
+pub fn parse_bytes(extension: &str, bytes: &[u8]) -> Result<Transcript, Box<dyn Error>> {
+    if extension.eq_ignore_ascii_case("pdf") {
+        return pdf_transcript(bytes);
+    }
+    if extension.eq_ignore_ascii_case("epub") {
+        return parse_epub(bytes);
+    }
+    let (raw_text, encoding) = decode(bytes);
+    if is_html(extension) {
+        let article = extract_article(&raw_text, None)?;
+        return Ok(Transcript {
+            encoding,
+            ..parse_transcript("txt", article.text)
+        });
+    }
+    if ["md", "markdown"].iter().any(|md| extension.eq_ignore_ascii_case(md)) {
+        return Ok(Transcript {
+            encoding,
+            ..parse_markdown(&normalize(&raw_text))
+        });
+    }
+    Ok(Transcript {
+        encoding,
+        ..parse_transcript(extension, raw_text)
+    })
+}
+
+/// Join the texts of consecutive sections, remembering the word each one starts at
+fn sectioned(sections: Vec<String>) -> Transcript {
+    let mut starts = Vec::new();
+    let mut words = 0;
+    for section in &sections {
+        if words > 0 {
+            starts.push(words);
+        }
+        words += section.split_whitespace().count();
+    }
+    Transcript {
+        text: sections.join("\n\n"),
+        cues: None,
+        encoding: None,
+        sections: starts,
+    }
+}
+
+/// Transcripts of several files as one text, in order, every file starting a section. The cues
+/// are dropped: the timestamps of different recordings don't make up one timeline.
+pub fn concatenate(parts: Vec<Transcript>) -> Transcript {
+    let mut text = String::new();
+    let mut sections = Vec::new();
+    let mut words = 0;
+    for part in parts {
+        let count = part.text.split_whitespace().count();
+        if count == 0 {
+            continue;
+        }
+        if words > 0 {
+            sections.push(words);
+            text.push_str("\n\n");
+        }
+        sections.extend(part.sections.iter().map(|start| start + words));
+        text.push_str(part.text.trim());
+        words += count;
+    }
+    Transcript {
+        text,
+        cues: None,
+        encoding: None,
+        sections,
+    }
+}
+
+/// Extensions of the recordings that are transcribed before they are summarized
+pub const AUDIO_EXTENSIONS: &[&str] = &["mp3", "wav", "m4a", "flac", "ogg"];
+
+/// Whether `extension` is that of a recording
+pub fn is_audio(extension: &str) -> bool {
+    AUDIO_EXTENSIONS.iter().any(|audio| extension.eq_ignore_ascii_case(audio))
+}
+
+/// Whether `extension` is that of a saved web page
+pub fn is_html(extension: &str) -> bool {
+    extension.eq_ignore_ascii_case("html") || extension.eq_ignore_ascii_case("htm")
+}
+
+/// Whether files with `extension` are read as plain text, without cues, sections or markup to
+/// extract
+pub fn is_plain_text(extension: &str) -> bool {
+    const STRUCTURED: [&str; 9] = ["srt", "vtt", "ass", "ssa", "json", "pdf", "epub", "md", "markdown"];
+    !is_audio(extension) && !is_html(extension) && !STRUCTURED.iter().any(|s| extension.eq_ignore_ascii_case(s))
+}
+
+/// The text of a PDF's pages, warning when there is hardly any, as in scanned documents
+pub fn pdf_transcript(bytes: &[u8]) -> Result<Transcript, Box<dyn Error>> {
+    let pages = extract_pdf(bytes)?;
+    let text = pages.join("\n\n");
+    let words = text.split_whitespace().count();
+    if words < pages.len().max(1) * MIN_PDF_WORDS_PER_PAGE {
+        warn!(
+            "Only {} words could be extracted from {} PDF pages; scanned pages need OCR first",
+            words,
+            pages.len()
+        );
+    }
+    Ok(Transcript {
+        text,
+        cues: None,
+        encoding: None,
+        sections: Vec::new(),
+    })
+}
+
+/// Parse `raw_text` as the subtitle format of `extension`, or keep it as plain text
+pub fn parse_transcript(extension: &str, raw_text: String) -> Transcript {
+    let raw_text = normalize(&raw_text);
+    match parse_cues(extension, &raw_text) {
+        Some(cues) => Transcript {
+            text: cues_to_text(&cues),
+            cues: Some(cues),
+            encoding: None,
+            sections: Vec::new(),
+        },
+        None => Transcript {
+            text: raw_text,
+            cues: None,
+            encoding: None,
+            sections: Vec::new(),
+        },
+    }
+}
+
+/// Subtitle extension implied by text without a file name, such as piped input: `vtt`, `ass`,
+/// Whisper `json`, `srt`, or else `txt`
+pub fn sniff_extension(text: &str) -> &'static str {
+    match text.trim_start_matches('\u{feff}') {
+        text if text.starts_with("WEBVTT") => "vtt",
+        text if text.contains("[Events]") => "ass",
+        // Whisper JSON; anything else that fails to parse as it falls back to plain text
+        text if text.trim_start().starts_with(['{', '[']) => "json",
+        text if text.contains("-->") => "srt",
+        _ => "txt",
+    }
+}
+
+/// Drop a leading byte order mark, which would otherwise hide the first SRT index or the
+/// `WEBVTT` header, and turn `\r\n` and lone `\r` line endings into `\n`
+fn normalize(text: &str) -> String {
+    text.strip_prefix('\u{feff}')
+        .unwrap_or(text)
+        .replace("\r\n", "\n")
+        .replace('\r', "\n")
+}
+
+/// Remove the rolling-caption repetition of auto-generated subtitles, where every cue repeats the
+/// line before it (and short in-between cues repeat it once more). Leading words of a cue that
+/// repeat the end of the previous cue of the same speaker are dropped; a cue left empty is merged
+/// into the previous one by extending its end time. Overlaps of a single word only count when
+/// they make up the whole cue, so an ordinary "the" / "the" at a cue boundary survives.
+fn collapse_repeats(cues: &mut Vec<Cue>) {
+    let mut kept: Vec<Cue> = Vec::with_capacity(cues.len());
+    // Words of the last kept cue before its own repetition was removed
+    let mut previous: Vec<String> = Vec::new();
+    for mut cue in cues.drain(..) {
+        let words: Vec<String> = cue.text.split_whitespace().map(str::to_string).collect();
+        let same_speaker = kept.last().is_some_and(|last| last.speaker == cue.speaker);
+        let overlap = if same_speaker {
+            (1..=previous.len().min(words.len()))
+                .rev()
+                .find(|&k| previous[previous.len() - k..] == words[..k])
+                .filter(|&k| k >= 2 || k == words.len())
+                .unwrap_or(0)
+        } else {
+            0
+        };
+        match kept.last_mut() {
+            Some(last) if overlap == words.len() => last.end = last.end.max(cue.end),
+            _ => {
+                if overlap > 0 {
+                    cue.text = words[overlap..].join(" ");
+                }
+                previous = words;
+                kept.push(cue);
+            }
+        }
+    }
+    *cues = kept;
+}
+
+/// Keep only the cues of a subtitle transcript that overlap `from..to` (either end may be open);
+/// `None` for plain text, which has no timings to clip by
+pub fn clip(transcript: Transcript, from: Option<Duration>, to: Option<Duration>) -> Option<Transcript> {
+    let mut cues = transcript.cues?;
+    cues.retain(|cue| from.is_none_or(|from| cue.end > from) && to.is_none_or(|to| cue.start < to));
+    Some(Transcript {
+        text: cues_to_text(&cues),
+        cues: Some(cues),
+        encoding: transcript.encoding,
+        sections: Vec::new(),
+    })
+}
+
+/// Drop the first `count` cues of a subtitle transcript, e.g. those an earlier run summarized;
+/// plain text is returned as it is
+pub fn skip_cues(transcript: Transcript, count: usize) -> Transcript {
+    let Some(mut cues) = transcript.cues else {
+        return transcript;
+    };
+    cues.drain(..count.min(cues.len()));
+    Transcript {
+        text: cues_to_text(&cues),
+        cues: Some(cues),
+        encoding: transcript.encoding,
+        sections: Vec::new(),
+    }
+}
+
+/// Join cue text into one long paragraph, with a `Name:` label wherever the speaker changes
+pub fn cues_to_text(cues: &[Cue]) -> String {
+    labelled_texts(cues)
+        .iter()
+        .flat_map(|text| text.lines())
+        .map(str::trim)
+        .filter(|line| !line.is_empty())
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Index into `cues` of every whitespace-separated word of `cues_to_text(cues)`, in order
+pub fn word_cues(cues: &[Cue]) -> Vec<usize> {
+    labelled_texts(cues)
+        .iter()
+        .enumerate()
+        .flat_map(|(i, text)| text.split_whitespace().map(move |_| i))
+        .collect()
+}
+
+/// Whether any cue carries a speaker label
+pub fn has_speakers(cues: &[Cue]) -> bool {
+    cues.iter().any(|cue| cue.speaker.is_some())
+}
+
+/// Text of every cue, prefixed with `Name: ` when its speaker differs from the previous cue's
+fn labelled_texts(cues: &[Cue]) -> Vec<String> {
+    let mut previous: Option<&str> = None;
+    cues.iter()
+        .map(|cue| match cue.speaker.as_deref() {
+            Some(speaker) if previous != Some(speaker) && !cue.text.trim().is_empty() => {
+                previous = Some(speaker);
+                format!("{}: {}", speaker, cue.text)
+            }
+            _ => cue.text.clone(),
+        })
+        .collect()
+}
+
+/// `HH:MM:SS`, the precision shown in timestamped bullets
+pub fn format_clock(time: Duration) -> String {
+    let seconds = time.as_secs();
+    format!("{:02}:{:02}:{:02}", seconds / 3600, seconds / 60 % 60, seconds % 60)
+}
+
+/// Parse a time given on the command line, `[HH:]MM:SS` with optional fractions
+pub fn parse_clock(s: &str) -> Option<Duration> {
+    parse_timestamp(s)
+}
+
+/// Parse `[HH:]MM:SS(,|.)fff` timestamps (`fff` may be 1-3 digits, as in ASS centiseconds)
+pub(crate) fn parse_timestamp(s: &str) -> Option<Duration> {
+    let s = s.trim();
+    let (clock, frac) = s.split_once([',', '.']).unwrap_or((s, "0"));
+    let parts = clock
+        .split(':')
+        .map(|p| p.parse::<u64>().ok())
+        .collect::<Option<Vec<_>>>()?;
+    let (hours, minutes, seconds) = match parts.as_slice() {
+        [h, m, s] => (*h, *m, *s),
+        [m, s] => (0, *m, *s),
+        _ => return None,
+    };
+    if frac.is_empty() || frac.len() > 3 || !frac.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+    let millis = frac.parse::<u64>().ok()? * 10u64.pow(3 - frac.len() as u32);
+
+    Some(Duration::from_millis(
+        ((hours * 60 + minutes) * 60 + seconds) * 1000 + millis,
+    ))
+}
+
+/// Parse a `start --> end` timing line, ignoring any trailing cue settings
+fn parse_timing(line: &str) -> Option<(Duration, Duration)> {
+    let (start, rest) = line.split_once("-->")?;
+    let end = rest.split_whitespace().next()?;
+    Some((parse_timestamp(start)?, parse_timestamp(end)?))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Starts past 1, so a lost index cannot pass for the fallback numbering
+    const SRT: &str = "7\n00:00:01,000 --> 00:00:02,000\nHello there\n\n8\n00:00:03,000 --> 00:00:04,000\nGeneral Kenobi\n";
+
+    #[test]
+    fn bom_does_not_leak_the_first_index() {
+        let transcript = parse_transcript("srt", format!("\u{feff}{}", SRT));
+        let cues = transcript.cues.unwrap();
+        assert_eq!(cues.len(), 2);
+        assert_eq!(cues[0].index, 7);
+        assert_eq!(cues[0].text, "Hello there");
+        assert_eq!(transcript.text, "Hello there General Kenobi");
+    }
+
+    #[test]
+    fn bom_does_not_hide_the_vtt_header() {
+        let vtt = "\u{feff}WEBVTT\n\n00:00:01.000 --> 00:00:02.000\nHello there\n";
+        let transcript = parse_transcript("vtt", vtt.to_string());
+        assert_eq!(transcript.text, "Hello there");
+    }
+
+    #[test]
+    fn crlf_line_endings_leave_no_carriage_returns() {
+        let crlf = SRT.replace('\n', "\r\n");
+        let transcript = parse_transcript("srt", crlf);
+        let cues = transcript.cues.unwrap();
+        assert_eq!(cues.len(), 2);
+        assert!(cues.iter().all(|cue| !cue.text.contains('\r')));
+        assert_eq!(transcript.text, "Hello there General Kenobi");
+    }
+
+    #[test]
+    fn lone_carriage_returns_split_lines() {
+        let transcript = parse_transcript("srt", SRT.replace('\n', "\r"));
+        let cues = transcript.cues.unwrap();
+        assert_eq!(cues.iter().map(|cue| cue.index).collect::<Vec<_>>(), [7, 8]);
+        assert_eq!(transcript.text, "Hello there General Kenobi");
+    }
+
+    #[test]
+    fn piped_text_is_sniffed() {
+        assert_eq!(sniff_extension(SRT), "srt");
+        assert_eq!(sniff_extension("\u{feff}WEBVTT\n\n00:01.000 --> 00:02.000\nHi\n"), "vtt");
+        assert_eq!(sniff_extension("[Script Info]\n\n[Events]\nDialogue: 0,..."), "ass");
+        assert_eq!(sniff_extension("  {\"segments\": []}"), "json");
+        assert_eq!(sniff_extension("Just some notes -> not a cue"), "txt");
+    }
+
+    #[test]
+    fn plain_text_is_normalized_too() {
+        let transcript = parse_transcript("txt", "\u{feff}first line\r\nsecond line\rthird".to_string());
+        assert_eq!(transcript.text, "first line\nsecond line\nthird");
+    }
+
+    #[test]
+    fn only_unstructured_files_are_plain_text() {
+        assert!(is_plain_text("txt"));
+        assert!(is_plain_text("log"));
+        assert!(is_plain_text(""));
+        assert!(!is_plain_text("SRT"));
+        assert!(!is_plain_text("md"));
+        assert!(!is_plain_text("htm"));
+        assert!(!is_plain_text("mp3"));
+    }
+
+    #[test]
+    fn skipped_cues_leave_the_rest_of_the_text() {
+        let rest = skip_cues(parse_transcript("srt", SRT.to_string()), 1);
+        assert_eq!(rest.text, "General Kenobi");
+        assert_eq!(rest.cues.unwrap()[0].index, 8);
+        let plain = skip_cues(parse_transcript("txt", "some notes".to_string()), 1);
+        assert_eq!(plain.text, "some notes");
+    }
+
+    #[test]
+    fn concatenated_files_start_sections() {
+        let notes = parse_markdown("Intro words\n\n# Heading\n\nMore words");
+        let merged = concatenate(vec![parse_transcript("srt", SRT.to_string()), parse_transcript("txt", " ".to_string()), notes]);
+        assert_eq!(merged.text, "Hello there General Kenobi\n\nIntro words\n\nHeading\n\nMore words");
+        assert_eq!(merged.sections, [4, 6]);
+        assert!(merged.cues.is_none());
+    }
+}
@@ -0,0 +1,299 @@
+// Text extraction from `.pdf` files such as lecture slides and papers.
+//
+// pdf-extract reports every glyph with its position. Glyphs on one baseline are joined into line
+// fragments, and a page whose fragments leave a vertical gutter free is read column by column,
+// with full-width lines such as titles and footers splitting it into bands, so two-column papers
+// don't come out with the lines of both columns interleaved. Words hyphenated across a line
+// break are joined again, keeping the hyphen before a capital or digit, and lone page numbers
+// are dropped.
+
+use pdf_extract::{Document, MediaBox, OutputDev, OutputError, Transform};
+use std::{
+    error::Error,
+    panic::{self, AssertUnwindSafe},
+};
+
+/// Fewest lines on each side of a gutter for a page to count as having columns
+const MIN_COLUMN_LINES: usize = 4;
+
+/// How many times a page is split at a gutter, enough for three- and four-column layouts
+const MAX_COLUMN_DEPTH: usize = 3;
+
+/// Text of every page of a PDF, in reading order
+pub fn extract_pdf(bytes: &[u8]) -> Result<Vec<String>, Box<dyn Error>> {
+    let mut doc = Document::load_mem(bytes).map_err(|e| format!("Cannot read the PDF: {}", e))?;
+    if doc.is_encrypted() && doc.decrypt("").is_err() {
+        return Err("The PDF is password-protected".into());
+    }
+    let mut collector = Collector::default();
+    // pdf-extract panics on some unsupported fonts and encodings instead of failing
+    panic::catch_unwind(AssertUnwindSafe(|| pdf_extract::output_doc(&doc, &mut collector)))
+        .map_err(|_| "Cannot extract text from the PDF: unsupported font or encoding")?
+        .map_err(|e| format!("Cannot extract text from the PDF: {}", e))?;
+    collector.flush();
+    Ok(collector.pages.into_iter().map(layout).collect())
+}
+
+/// A run of glyphs on one baseline without a gap wide enough to be a column gutter
+#[derive(Debug, Clone, PartialEq)]
+struct Fragment {
+    x0: f64,
+    x1: f64,
+    /// Baseline, growing upwards as in PDF coordinates
+    y: f64,
+    size: f64,
+    text: String,
+}
+
+/// Free space between two columns
+#[derive(Debug, Clone, Copy)]
+struct Gutter {
+    left: f64,
+    right: f64,
+}
+
+/// Collects the fragments of every page
+#[derive(Default)]
+struct Collector {
+    pages: Vec<Vec<Fragment>>,
+    current: Option<Fragment>,
+    word_start: bool,
+}
+
+impl Collector {
+    fn flush(&mut self) {
+        if let Some(fragment) = self.current.take() {
+            if !fragment.text.trim().is_empty() {
+                if let Some(page) = self.pages.last_mut() {
+                    page.push(fragment);
+                }
+            }
+        }
+    }
+}
+
+impl OutputDev for Collector {
+    fn begin_page(&mut self, _page: u32, _media_box: &MediaBox, _art_box: Option<(f64, f64, f64, f64)>) -> Result<(), OutputError> {
+        self.flush();
+        self.pages.push(Vec::new());
+        Ok(())
+    }
+
+    fn end_page(&mut self) -> Result<(), OutputError> {
+        self.flush();
+        Ok(())
+    }
+
+    fn output_character(&mut self, trm: &Transform, width: f64, _spacing: f64, font_size: f64, text: &str) -> Result<(), OutputError> {
+        // The font size scaled by the text matrix, as pdf-extract's own plain text output does
+        let scaled = ((font_size * (trm.m11 + trm.m21)) * (font_size * (trm.m12 + trm.m22))).abs().sqrt();
+        let size = if scaled > 0.0 { scaled } else { font_size.max(1.0) };
+        let (x, y) = (trm.m31, trm.m32);
+        let end = x + width * size;
+        let word_start = std::mem::take(&mut self.word_start);
+
+        let continues = self.current.as_ref().is_some_and(|current| {
+            (y - current.y).abs() < current.size * 0.5 && x > current.x1 - current.size && x < current.x1 + current.size * 1.5
+        });
+        if !continues {
+            self.flush();
+            self.current = Some(Fragment {
+                x0: x,
+                x1: end,
+                y,
+                size,
+                text: String::new(),
+            });
+        }
+        let Some(current) = self.current.as_mut() else {
+            return Ok(());
+        };
+        if continues && word_start && x > current.x1 + size * 0.1 && !current.text.ends_with(' ') {
+            current.text.push(' ');
+        }
+        if text.trim().is_empty() {
+            if !current.text.ends_with(' ') {
+                current.text.push(' ');
+            }
+        } else {
+            current.text.push_str(text);
+        }
+        current.x1 = current.x1.max(end);
+        Ok(())
+    }
+
+    fn begin_word(&mut self) -> Result<(), OutputError> {
+        self.word_start = true;
+        Ok(())
+    }
+
+    fn end_word(&mut self) -> Result<(), OutputError> {
+        Ok(())
+    }
+
+    fn end_line(&mut self) -> Result<(), OutputError> {
+        Ok(())
+    }
+}
+
+/// A page's fragments as text: columns read one after the other, lines joined into paragraphs
+fn layout(fragments: Vec<Fragment>) -> String {
+    let lines = lines(reading_order(fragments, 0));
+    let is_page_number = |line: &(Fragment, bool)| {
+        let text = line.0.text.trim();
+        text.len() <= 4 && text.bytes().all(|b| b.is_ascii_digit())
+    };
+    let first = usize::from(lines.first().is_some_and(is_page_number));
+    let last = lines.len() - usize::from(lines.len() > first && lines.last().is_some_and(is_page_number));
+
+    let mut text = String::new();
+    for (line, paragraph) in &lines[first..last] {
+        let line_text = line.text.trim();
+        if !text.is_empty() {
+            if *paragraph {
+                text.push_str("\n\n");
+            } else if text.ends_with('-') && text[..text.len() - 1].ends_with(char::is_alphabetic) {
+                // A word broken across the lines loses its hyphen; a compound such as
+                // `well-Known` or `COVID-19` keeps it, and neither gets a space
+                if line_text.starts_with(char::is_lowercase) {
+                    text.pop();
+                }
+            } else {
+                text.push(' ');
+            }
+        }
+        text.push_str(line_text);
+    }
+    text
+}
+
+/// Fragments sorted top to bottom, taking each column of a band at a time when there is a gutter
+fn reading_order(mut fragments: Vec<Fragment>, depth: usize) -> Vec<Fragment> {
+    fragments.sort_by(|a, b| b.y.total_cmp(&a.y).then(a.x0.total_cmp(&b.x0)));
+    let Some(gutter) = (depth < MAX_COLUMN_DEPTH).then(|| gutter(&fragments)).flatten() else {
+        return fragments;
+    };
+    let mut ordered = Vec::with_capacity(fragments.len());
+    let mut band: Vec<Fragment> = Vec::new();
+    let flush = |band: &mut Vec<Fragment>, ordered: &mut Vec<Fragment>| {
+        let (left, right): (Vec<Fragment>, Vec<Fragment>) = band.drain(..).partition(|f| f.x1 <= gutter.left);
+        ordered.extend(reading_order(left, depth + 1));
+        ordered.extend(reading_order(right, depth + 1));
+    };
+    for fragment in fragments {
+        if fragment.x1 > gutter.left && fragment.x0 < gutter.right {
+            flush(&mut band, &mut ordered);
+            ordered.push(fragment);
+        } else {
+            band.push(fragment);
+        }
+    }
+    flush(&mut band, &mut ordered);
+    ordered
+}
+
+/// A strip at least a character wide left free by all but a few full-width fragments (titles,
+/// centered page numbers), with enough text on either side of it to be columns rather than, say,
+/// a slide's bullet markers
+fn gutter(fragments: &[Fragment]) -> Option<Gutter> {
+    if fragments.is_empty() {
+        return None;
+    }
+    let size = fragments.iter().map(|f| f.size).sum::<f64>() / fragments.len() as f64;
+    let chars = |f: &Fragment| f.text.chars().count();
+    let total_chars: usize = fragments.iter().map(chars).sum();
+    let mut best: Option<(usize, Gutter)> = None;
+    for right_edge in fragments.iter().map(|f| f.x0) {
+        let gutter = Gutter {
+            left: right_edge - size,
+            right: right_edge,
+        };
+        let left: Vec<&Fragment> = fragments.iter().filter(|f| f.x1 <= gutter.left).collect();
+        let right: Vec<&Fragment> = fragments.iter().filter(|f| f.x0 >= gutter.right).collect();
+        let crossing = fragments.len() - left.len() - right.len();
+        if left.len() < MIN_COLUMN_LINES || right.len() < MIN_COLUMN_LINES || crossing * 5 > fragments.len() {
+            continue;
+        }
+        let balance = left.iter().map(|f| chars(f)).sum::<usize>().min(right.iter().map(|f| chars(f)).sum());
+        if balance * 4 < total_chars {
+            continue;
+        }
+        if best.is_none_or(|(best, _)| balance > best) {
+            best = Some((balance, gutter));
+        }
+    }
+    best.map(|(_, gutter)| gutter)
+}
+
+/// Merge fragments on the same baseline into lines, each marked when it starts a paragraph: after
+/// a vertical gap of more than a line and a half, or when reading moves up to the next column
+fn lines(fragments: Vec<Fragment>) -> Vec<(Fragment, bool)> {
+    let mut lines: Vec<(Fragment, bool)> = Vec::new();
+    for fragment in fragments {
+        match lines.last_mut() {
+            Some((line, _)) if (fragment.y - line.y).abs() < line.size * 0.5 && fragment.x0 >= line.x1 => {
+                line.text = format!("{} {}", line.text.trim_end(), fragment.text.trim_start());
+                line.x1 = fragment.x1;
+            }
+            Some((line, _)) => {
+                let gap = line.y - fragment.y;
+                let paragraph = gap > line.size.max(fragment.size) * 1.8 || gap < 0.0;
+                lines.push((fragment, paragraph));
+            }
+            None => lines.push((fragment, false)),
+        }
+    }
+    lines
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fragment(x0: f64, x1: f64, y: f64, text: &str) -> Fragment {
+        Fragment {
+            x0,
+            x1,
+            y,
+            size: 10.0,
+            text: text.to_string(),
+        }
+    }
+
+    #[test]
+    fn columns_are_read_one_after_the_other() {
+        let mut fragments = vec![fragment(50.0, 550.0, 800.0, "A Title Spanning The Page")];
+        for i in 0..5 {
+            let y = 760.0 - 12.0 * i as f64;
+            fragments.push(fragment(50.0, 290.0, y, &format!("left line {} of the first column", i)));
+            fragments.push(fragment(310.0, 550.0, y, &format!("right line {} of the second column", i)));
+        }
+        fragments.push(fragment(296.0, 304.0, 40.0, "7"));
+        let text = layout(fragments);
+        assert!(text.starts_with("A Title Spanning The Page\n\nleft line 0"), "{}", text);
+        assert!(text.contains("left line 4 of the first column\n\nright line 0"), "{}", text);
+        assert!(text.ends_with("right line 4 of the second column"), "{}", text);
+    }
+
+    #[test]
+    fn slides_are_not_split_at_bullets() {
+        let fragments: Vec<Fragment> = (0..5)
+            .flat_map(|i| {
+                let y = 500.0 - 30.0 * i as f64;
+                [fragment(40.0, 46.0, y, "•"), fragment(60.0, 400.0, y, &format!("Point number {}", i))]
+            })
+            .collect();
+        let text = layout(fragments);
+        assert!(text.starts_with("• Point number 0\n\n• Point number 1"), "{}", text);
+    }
+
+    #[test]
+    fn hyphenated_words_are_joined() {
+        let fragments = vec![
+            fragment(50.0, 290.0, 700.0, "a summary of the recor-"),
+            fragment(50.0, 290.0, 688.0, "ding and a well-"),
+            fragment(50.0, 290.0, 676.0, "Known name"),
+        ];
+        assert_eq!(layout(fragments), "a summary of the recording and a well-Known name");
+    }
+}
@@ -0,0 +1,53 @@
+use regex::Regex;
+
+use super::{cues_to_text, Transcript};
+
+/// Remove sound descriptions from a transcript: `[music]`, `[APPLAUSE]`, parenthesized cues like
+/// `(laughter)` or `(DOOR SLAMS)`, and lyric lines marked with `♪`/`♫`. Cues left without text
+/// are dropped. Longer lowercase asides in parentheses are kept, since they are usually speech.
+/// Plain text is returned unchanged: brackets there are more often references than sound cues.
+pub fn strip_sound_descriptions(transcript: Transcript) -> Transcript {
+    let Some(mut cues) = transcript.cues else {
+        return transcript;
+    };
+    let cleaner = Cleaner::new();
+    for cue in &mut cues {
+        cue.text = cleaner.clean(&cue.text);
+    }
+    cues.retain(|cue| !cue.text.is_empty());
+    Transcript {
+        text: cues_to_text(&cues),
+        cues: Some(cues),
+        encoding: transcript.encoding,
+        sections: Vec::new(),
+    }
+}
+
+struct Cleaner {
+    brackets: Regex,
+    parens: Regex,
+}
+
+impl Cleaner {
+    fn new() -> Self {
+        Self {
+            brackets: Regex::new(r"\[[^\]\n]*\]").unwrap(),
+            // All-caps descriptions of any length, or up to three lowercase words
+            parens: Regex::new(r"\((?:[^\p{Ll}()\n]*\p{Lu}[^\p{Ll}()\n]*|[\p{Ll}']+(?: [\p{Ll}']+){0,2})\)").unwrap(),
+        }
+    }
+
+    /// Clean every line of `text`, dropping lyric lines and lines left without any words
+    fn clean(&self, text: &str) -> String {
+        text.lines()
+            .filter(|line| !line.contains(['♪', '♫']))
+            .map(|line| {
+                let line = self.brackets.replace_all(line, "");
+                let line = self.parens.replace_all(&line, "");
+                line.split_whitespace().collect::<Vec<_>>().join(" ")
+            })
+            .filter(|line| line.chars().any(char::is_alphanumeric))
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+}
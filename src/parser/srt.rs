@@ -0,0 +1,61 @@
+use regex::Regex;
+
+use super::{parse_timing, Cue};
+
+/// Parse SRT text into cues.
+///
+/// Cues are delimited by their timing lines rather than by blank lines, so subtitle text that
+/// itself contains blank lines is kept with its cue. A number directly above a timing line is
+/// taken as that cue's index. Formatting tags (`<i>`, `<font color=...>`) and ASS-style
+/// `{\an8}` position tags are removed from the text.
+pub fn parse_srt(text: &str) -> Vec<Cue> {
+    let markup_re = Regex::new(r"</?[a-zA-Z][^>]*>|\{\\[^}]*\}").unwrap();
+    let mut cues: Vec<Cue> = Vec::new();
+    let mut lines: Vec<&str> = Vec::new();
+
+    for line in text.lines() {
+        let t = line.trim();
+        let Some((start, end)) = parse_timing(t) else {
+            lines.push(t);
+            continue;
+        };
+
+        while lines.last().is_some_and(|l| l.is_empty()) {
+            lines.pop();
+        }
+        let index = match lines.last().and_then(|l| l.parse::<usize>().ok()) {
+            Some(index) => {
+                lines.pop();
+                index
+            }
+            None => cues.last().map_or(1, |c| c.index + 1),
+        };
+        if let Some(prev) = cues.last_mut() {
+            prev.text = join_lines(&lines, &markup_re);
+        }
+        lines.clear();
+
+        cues.push(Cue {
+            index,
+            start,
+            end,
+            text: String::new(),
+            speaker: None,
+        });
+    }
+    if let Some(prev) = cues.last_mut() {
+        prev.text = join_lines(&lines, &markup_re);
+    }
+
+    cues.retain(|c| !c.text.is_empty());
+    cues
+}
+
+fn join_lines(lines: &[&str], markup_re: &Regex) -> String {
+    lines
+        .iter()
+        .map(|l| markup_re.replace_all(l, "").trim().to_string())
+        .filter(|l| !l.is_empty())
+        .collect::<Vec<_>>()
+        .join("\n")
+}
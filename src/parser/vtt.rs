@@ -0,0 +1,76 @@
+use regex::Regex;
+
+use super::{parse_timing, Cue};
+
+/// Parse WebVTT text into cues, dropping the WEBVTT header, NOTE/STYLE/REGION blocks,
+/// cue identifiers, cue settings, and inline timestamp/class tags; the first `<v Name>` voice
+/// tag of a cue becomes its speaker
+pub fn parse_vtt(text: &str) -> Vec<Cue> {
+    let tag_re = Regex::new(r"<[^>]*>").unwrap();
+    let voice_re = Regex::new(r"<v(?:\.[^\s>]*)?\s+([^>]+)>").unwrap();
+
+    let mut cues: Vec<Cue> = Vec::new();
+    let mut block_start = true;
+    let mut skip_block = false;
+    let mut in_cue = false;
+
+    for line in text.lines() {
+        let t = line.trim();
+        if t.is_empty() {
+            block_start = true;
+            skip_block = false;
+            in_cue = false;
+            continue;
+        }
+        if block_start {
+            block_start = false;
+            let is_meta = t.starts_with("WEBVTT")
+                || t == "NOTE"
+                || t.starts_with("NOTE ")
+                || t == "STYLE"
+                || t == "REGION";
+            if is_meta {
+                skip_block = true;
+                continue;
+            }
+        }
+        if skip_block {
+            continue;
+        }
+        if let Some((start, end)) = parse_timing(t) {
+            // Cue settings (align:, position:, ...) live on the timing line and go with it
+            in_cue = true;
+            cues.push(Cue {
+                index: cues.len() + 1,
+                start,
+                end,
+                text: String::new(),
+                speaker: None,
+            });
+            continue;
+        }
+        if !in_cue {
+            // Anything before the timing line is a cue identifier
+            continue;
+        }
+        if let Some(cue) = cues.last_mut().filter(|cue| cue.speaker.is_none()) {
+            cue.speaker = voice_re.captures(t).map(|voice| voice[1].trim().to_string());
+        }
+        let stripped = tag_re.replace_all(t, "");
+        let stripped = stripped
+            .replace("&nbsp;", " ")
+            .replace("&lt;", "<")
+            .replace("&gt;", ">")
+            .replace("&amp;", "&");
+        let stripped = stripped.trim();
+        if let (Some(cue), false) = (cues.last_mut(), stripped.is_empty()) {
+            if !cue.text.is_empty() {
+                cue.text.push('\n');
+            }
+            cue.text.push_str(stripped);
+        }
+    }
+
+    cues.retain(|c| !c.text.is_empty());
+    cues
+}
@@ -0,0 +1,73 @@
+use serde::Deserialize;
+use serde_json::Value;
+use std::time::Duration;
+
+use super::{parse_timestamp, Cue};
+
+/// `segments` entry of openai-whisper, faster-whisper and WhisperX output; times in seconds
+#[derive(Deserialize)]
+struct Segment {
+    start: f64,
+    end: f64,
+    text: String,
+    /// Set by diarizing tools such as WhisperX
+    #[serde(default)]
+    speaker: Option<String>,
+}
+
+/// `transcription` entry of whisper.cpp `-oj` output
+#[derive(Deserialize)]
+struct CppSegment {
+    timestamps: CppTimestamps,
+    text: String,
+}
+
+#[derive(Deserialize)]
+struct CppTimestamps {
+    from: String,
+    to: String,
+}
+
+/// Parse a Whisper JSON transcript into cues: an object with `segments` (openai-whisper,
+/// faster-whisper, WhisperX), an object with `transcription` (whisper.cpp), or a bare array
+/// of segments. Returns `None` for JSON in any other shape, so it can be read as plain text.
+pub fn parse_whisper(text: &str) -> Option<Vec<Cue>> {
+    let json: Value = serde_json::from_str(text.trim_start_matches('\u{feff}')).ok()?;
+    let mut cues: Vec<Cue> = match &json {
+        Value::Array(segments) => segments.iter().filter_map(segment).collect(),
+        Value::Object(object) => match (object.get("segments"), object.get("transcription")) {
+            (Some(Value::Array(segments)), _) => segments.iter().filter_map(segment).collect(),
+            (_, Some(Value::Array(segments))) => segments.iter().filter_map(cpp_segment).collect(),
+            _ => return None,
+        },
+        _ => return None,
+    };
+
+    cues.retain(|cue| !cue.text.is_empty());
+    for (i, cue) in cues.iter_mut().enumerate() {
+        cue.index = i + 1;
+    }
+    Some(cues)
+}
+
+fn segment(value: &Value) -> Option<Cue> {
+    let segment = Segment::deserialize(value).ok()?;
+    Some(Cue {
+        index: 0,
+        start: Duration::try_from_secs_f64(segment.start).ok()?,
+        end: Duration::try_from_secs_f64(segment.end).ok()?,
+        text: segment.text.trim().to_string(),
+        speaker: segment.speaker.filter(|speaker| !speaker.is_empty()),
+    })
+}
+
+fn cpp_segment(value: &Value) -> Option<Cue> {
+    let segment = CppSegment::deserialize(value).ok()?;
+    Some(Cue {
+        index: 0,
+        start: parse_timestamp(&segment.timestamps.from)?,
+        end: parse_timestamp(&segment.timestamps.to)?,
+        text: segment.text.trim().to_string(),
+        speaker: None,
+    })
+}
@@ -0,0 +1,164 @@
+// Posting finished summaries to Slack and Discord channels, for `--post`.
+//
+// Each target is an incoming webhook of one platform, written `slack:<url>` or `discord:<url>`.
+// The summary is formatted for the platform: Slack's mrkdwn has `*bold*` and no headings, so
+// section headings become bold lines and bullets `•`, while Discord renders Markdown as is.
+// A summary longer than one message allows (4000 characters on Slack, 2000 on Discord) is split
+// at line breaks into several messages, posted in order. Like webhook deliveries, a post that
+// fails is retried, then only warned about.
+
+use serde_json::json;
+use std::{error::Error, fmt};
+use tracing::info;
+
+use crate::{output, summarizer::Summary, webhook};
+
+/// Characters of one Slack message before Slack truncates it
+const SLACK_LIMIT: usize = 4000;
+
+/// Characters of the content of one Discord message
+const DISCORD_LIMIT: usize = 2000;
+
+/// Chat platform of a `--post` target
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Platform {
+    Slack,
+    Discord,
+}
+
+impl fmt::Display for Platform {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Platform::Slack => "Slack",
+            Platform::Discord => "Discord",
+        })
+    }
+}
+
+/// An incoming webhook summaries are posted to
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Target {
+    pub platform: Platform,
+    pub url: String,
+}
+
+impl Target {
+    /// Parse `slack:<url>` or `discord:<url>`
+    pub fn parse(s: &str) -> Result<Self, String> {
+        let (platform, url) = s
+            .split_once(':')
+            .ok_or_else(|| "expected slack:<webhook URL> or discord:<webhook URL>".to_string())?;
+        let platform = match platform.to_ascii_lowercase().as_str() {
+            "slack" => Platform::Slack,
+            "discord" => Platform::Discord,
+            other => return Err(format!("unknown platform `{}`, expected slack or discord", other)),
+        };
+        if !(url.starts_with("https://") || url.starts_with("http://")) {
+            return Err(format!("expected an http(s) webhook URL after {}:", platform.to_string().to_lowercase()));
+        }
+        Ok(Self {
+            platform,
+            url: url.to_string(),
+        })
+    }
+}
+
+/// Post `summary` to `target`, one message after the other
+pub async fn post(target: &Target, summary: &Summary) -> Result<(), Box<dyn Error>> {
+    let messages = messages(target.platform, summary);
+    for message in &messages {
+        let body = match target.platform {
+            Platform::Slack => json!({ "text": message, "unfurl_links": false }),
+            // Nothing the model wrote may ping @everyone or a role
+            Platform::Discord => json!({ "content": message, "allowed_mentions": { "parse": [] } }),
+        };
+        webhook::post_json(&target.url, &[], body.to_string()).await?;
+    }
+    info!("Summary posted to {} in {} message(s)", target.platform, messages.len());
+    Ok(())
+}
+
+/// The summary formatted for `platform`, split into messages it accepts
+pub fn messages(platform: Platform, summary: &Summary) -> Vec<String> {
+    let tldr = summary.headline.as_ref().and_then(|headline| headline.tldr.as_ref());
+    let body = output::text_body(summary);
+    let text = match platform {
+        Platform::Slack => {
+            let mut text = format!("*{}*\n\n", slack_escape(&output::title(summary)));
+            if let Some(tldr) = tldr {
+                text.push_str(&format!("> *TL;DR:* {}\n\n", slack_escape(tldr)));
+            }
+            text.push_str(&body.lines().map(slack_line).collect::<Vec<_>>().join("\n"));
+            text
+        }
+        Platform::Discord => {
+            let mut text = format!("**{}**\n\n", output::title(summary));
+            if let Some(tldr) = tldr {
+                text.push_str(&format!("> **TL;DR:** {}\n\n", tldr));
+            }
+            text.push_str(&body);
+            text
+        }
+    };
+    let limit = match platform {
+        Platform::Slack => SLACK_LIMIT,
+        Platform::Discord => DISCORD_LIMIT,
+    };
+    split(text.trim_end(), limit)
+}
+
+/// A line of the plain-text summary in Slack mrkdwn: headings in bold, `•` bullets and
+/// `**bold**` as `*bold*`
+fn slack_line(line: &str) -> String {
+    let line = slack_escape(line);
+    let content = line.trim_start();
+    let indent = &line[..line.len() - content.len()];
+    if let Some(heading) = content.strip_prefix('#') {
+        return format!("*{}*", heading.trim_matches(['#', ' ']).replace("**", ""));
+    }
+    let content = match content.strip_prefix("- ").or_else(|| content.strip_prefix("* ")) {
+        Some(item) => format!("• {}", item),
+        None => content.to_string(),
+    };
+    format!("{}{}", indent, content.replace("**", "*"))
+}
+
+/// Escape the characters Slack reads as links and mentions (`<@U123>`, `<!channel>`)
+fn slack_escape(text: &str) -> String {
+    text.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;")
+}
+
+/// Split `text` into messages of at most `limit` characters, at line breaks where possible and
+/// else at the last space that fits
+fn split(text: &str, limit: usize) -> Vec<String> {
+    let mut messages = Vec::new();
+    let mut current = String::new();
+    let mut current_len = 0;
+    for line in text.lines() {
+        let line_len = line.chars().count();
+        if current_len > 0 && current_len + 1 + line_len > limit {
+            messages.push(current.trim_end().to_string());
+            current.clear();
+            current_len = 0;
+        }
+        let mut rest = line;
+        let mut rest_len = line_len;
+        while rest_len > limit {
+            let cut = rest.char_indices().nth(limit).map_or(rest.len(), |(i, _)| i);
+            let cut = rest[..cut].rfind(' ').filter(|&i| i > 0).unwrap_or(cut);
+            messages.push(rest[..cut].trim_end().to_string());
+            rest = rest[cut..].trim_start();
+            rest_len = rest.chars().count();
+        }
+        if current_len > 0 {
+            current.push('\n');
+            current_len += 1;
+        }
+        current.push_str(rest);
+        current_len += rest_len;
+    }
+    if !current.trim().is_empty() {
+        messages.push(current.trim_end().to_string());
+    }
+    messages
+}
@@ -0,0 +1,64 @@
+// Study questions generated by `--mode qa`.
+//
+// Chunks yield candidate questions with their answers; the combine step picks the requested
+// number across the whole transcript. Replies are parsed leniently (`Q:`, `Q1.`, `**Question
+// 1:**`, ...) and rewritten as numbered `Q1.` / `A:` pairs.
+
+use regex::Regex;
+
+/// Questions asked when `questions` is not configured
+pub const DEFAULT_QUESTIONS: usize = 10;
+
+/// One study question and its answer
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct QaPair {
+    pub question: String,
+    pub answer: String,
+}
+
+/// Extract question and answer pairs from a model reply; questions without an answer are dropped
+pub fn parse(reply: &str) -> Vec<QaPair> {
+    let question_re = Regex::new(r"(?i)^(?:question|q)\s*\d*\s*[.:)]\s*(?:\*\*)?\s*(.*)$").unwrap();
+    let answer_re = Regex::new(r"(?i)^(?:answer|a)\s*\d*\s*[.:)]\s*(?:\*\*)?\s*(.*)$").unwrap();
+
+    let mut pairs: Vec<QaPair> = Vec::new();
+    // Whether the lines that follow continue an answer
+    let mut in_answer = false;
+    for line in reply.lines() {
+        let line = line
+            .trim()
+            .trim_start_matches(['-', '*', '•', '#'])
+            .trim_start_matches(|c: char| c.is_ascii_digit())
+            .trim_start_matches(['.', ')', '*'])
+            .trim();
+        if let Some(captures) = question_re.captures(line) {
+            pairs.push(QaPair {
+                question: clean(&captures[1]),
+                answer: String::new(),
+            });
+            in_answer = false;
+        } else if let (Some(captures), Some(pair)) = (answer_re.captures(line), pairs.last_mut()) {
+            pair.answer = clean(&captures[1]);
+            in_answer = true;
+        } else if let (true, Some(pair)) = (in_answer && !line.is_empty(), pairs.last_mut()) {
+            pair.answer = format!("{} {}", pair.answer, clean(line)).trim().to_string();
+        }
+    }
+    pairs.retain(|pair| !pair.question.is_empty() && !pair.answer.is_empty());
+    pairs
+}
+
+/// Drop the Markdown emphasis models like to wrap questions in
+fn clean(text: &str) -> String {
+    text.trim().trim_matches('*').trim().to_string()
+}
+
+/// Numbered `Q1. question` / `A: answer` pairs separated by blank lines
+pub fn render(pairs: &[QaPair]) -> String {
+    pairs
+        .iter()
+        .enumerate()
+        .map(|(i, pair)| format!("Q{}. {}\nA: {}", i + 1, pair.question, pair.answer))
+        .collect::<Vec<_>>()
+        .join("\n\n")
+}
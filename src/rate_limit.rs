@@ -0,0 +1,122 @@
+// Client-side rate limiting of LLM requests (`--rpm`, `--tpm`).
+//
+// Each limit is a token bucket holding one minute's allowance that refills continuously, so a
+// burst up to the limit goes out at once and a long run settles at the limit's pace. A request
+// that doesn't fit reserves its share anyway and sleeps until the bucket has refilled it, which
+// queues concurrent map requests instead of letting them all run into the provider's 429s.
+
+use indicatif::ProgressBar;
+use std::{
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Mutex,
+    },
+    time::{Duration, Instant},
+};
+use tracing::debug;
+
+pub struct RateLimiter {
+    requests: Option<Mutex<Bucket>>,
+    tokens: Option<Mutex<Bucket>>,
+    /// Progress bar that shows the wait while requests are held back
+    progress: Mutex<Option<ProgressBar>>,
+    waiting: AtomicUsize,
+}
+
+struct Bucket {
+    per_minute: f64,
+    /// Negative while reserved requests are waiting for it to refill
+    available: f64,
+    refilled: Instant,
+}
+
+impl Bucket {
+    fn new(per_minute: u32) -> Self {
+        Self {
+            per_minute: f64::from(per_minute),
+            available: f64::from(per_minute),
+            refilled: Instant::now(),
+        }
+    }
+
+    /// Take `amount` out of the bucket, returning how long until it has refilled enough to
+    /// cover it; more than a minute's allowance only waits for a full bucket
+    fn take(&mut self, amount: f64) -> Duration {
+        let now = Instant::now();
+        let per_second = self.per_minute / 60.0;
+        let refill = now.duration_since(self.refilled).as_secs_f64() * per_second;
+        self.available = (self.available + refill).min(self.per_minute);
+        self.refilled = now;
+        self.available -= amount.min(self.per_minute);
+        if self.available >= 0.0 {
+            Duration::ZERO
+        } else {
+            Duration::from_secs_f64(-self.available / per_second)
+        }
+    }
+}
+
+impl RateLimiter {
+    /// A limiter for `rpm` requests and `tpm` tokens per minute, or `None` without limits
+    pub fn new(rpm: Option<u32>, tpm: Option<u32>) -> Option<Self> {
+        if rpm.is_none() && tpm.is_none() {
+            return None;
+        }
+        Some(Self {
+            requests: rpm.map(|rpm| Mutex::new(Bucket::new(rpm))),
+            tokens: tpm.map(|tpm| Mutex::new(Bucket::new(tpm))),
+            progress: Mutex::new(None),
+            waiting: AtomicUsize::new(0),
+        })
+    }
+
+    /// Wait until a request with a prompt of `prompt_tokens` tokens fits both limits
+    pub async fn acquire(&self, prompt_tokens: usize) {
+        let take = |bucket: &Option<Mutex<Bucket>>, amount: f64| {
+            bucket
+                .as_ref()
+                .map_or(Duration::ZERO, |bucket| bucket.lock().unwrap().take(amount))
+        };
+        let wait = take(&self.requests, 1.0).max(take(&self.tokens, prompt_tokens as f64));
+        if wait.is_zero() {
+            return;
+        }
+
+        debug!("Rate limit reached; waiting {:.1}s", wait.as_secs_f64());
+        self.waiting.fetch_add(1, Ordering::Relaxed);
+        self.set_message(format!(" (throttled, waiting {:.0}s)", wait.as_secs_f64().ceil()));
+        // Clears the message once the last waiting request goes, even if it is cancelled
+        let _waited = Waited(self);
+        tokio::time::sleep(wait).await;
+    }
+
+    fn set_message(&self, message: String) {
+        match &*self.progress.lock().unwrap() {
+            Some(progress) if !progress.is_finished() => progress.set_message(message),
+            _ => {}
+        }
+    }
+
+    /// Count the `completion_tokens` of a reply against the token limit; later requests wait
+    /// for them
+    pub fn record(&self, completion_tokens: u64) {
+        if let Some(bucket) = &self.tokens {
+            bucket.lock().unwrap().take(completion_tokens as f64);
+        }
+    }
+
+    /// Show waits on `progress` until it finishes or another one replaces it
+    pub fn show_on(&self, progress: &ProgressBar) {
+        *self.progress.lock().unwrap() = Some(progress.clone());
+    }
+}
+
+struct Waited<'a>(&'a RateLimiter);
+
+impl Drop for Waited<'_> {
+    fn drop(&mut self) {
+        if self.0.waiting.fetch_sub(1, Ordering::Relaxed) == 1 {
+            self.0.set_message(String::new());
+        }
+    }
+}
@@ -0,0 +1,62 @@
+// Retrying of LLM calls with exponential backoff and jitter, so a single transient connection
+// error or overloaded server doesn't abort a long run.
+
+use std::{error::Error, fmt::Display, future::Future, time::Duration};
+use tracing::warn;
+
+use crate::error;
+
+/// Upper bound for a single backoff delay
+const MAX_DELAY: Duration = Duration::from_secs(60);
+
+#[derive(Debug, Clone, Copy)]
+pub struct RetryPolicy {
+    /// Attempts after the first one; 0 disables retrying
+    pub max_retries: u32,
+    /// Delay before the first retry; doubles on every further retry
+    pub base_delay: Duration,
+}
+
+impl RetryPolicy {
+    /// Run `op` until it succeeds or the retries are used up, returning the last error; a prompt
+    /// over the context window or blocked by safety filters fails straight away
+    pub async fn run<T, E, F, Fut>(&self, what: &str, mut op: F) -> Result<T, E>
+    where
+        E: Display + AsRef<dyn Error + Send + Sync>,
+        F: FnMut() -> Fut,
+        Fut: Future<Output = Result<T, E>>,
+    {
+        let mut attempt = 0;
+        loop {
+            match op().await {
+                Ok(value) => return Ok(value),
+                Err(e) if attempt < self.max_retries && !error::is_overflow(e.as_ref())
+                    && !error::is_blocked(e.as_ref())
+                    && !error::is_unsupported(e.as_ref()) => {
+                    let delay = self.delay(attempt);
+                    attempt += 1;
+                    warn!(
+                        "{} failed: {}; retrying in {:.1}s ({}/{})",
+                        what,
+                        e,
+                        delay.as_secs_f64(),
+                        attempt,
+                        self.max_retries
+                    );
+                    tokio::time::sleep(delay).await;
+                }
+                Err(e) => return Err(e),
+            }
+        }
+    }
+
+    /// Exponential step with jitter: a random point between 50% and 100% of the step, so
+    /// concurrent chunks that failed together don't retry in lockstep
+    fn delay(&self, attempt: u32) -> Duration {
+        let step = self
+            .base_delay
+            .saturating_mul(2u32.saturating_pow(attempt))
+            .min(MAX_DELAY);
+        step.mul_f64(0.5 + rand::random::<f64>() * 0.5)
+    }
+}
@@ -0,0 +1,136 @@
+// Series overviews written by `rollup`.
+//
+// The summaries this tool wrote for the episodes of a series (`<stem>_summary.txt`, `.md` or
+// `.json`) are read from a directory in file name order, one per episode, preferring the JSON
+// summary when an episode has several. Each keeps its bullets under the episode's name, and the
+// reduce and combine steps turn them into one overview with `ROLLUP_TEMPLATE`.
+
+use serde_json::Value;
+use std::{
+    collections::BTreeMap,
+    error::Error,
+    fs,
+    path::{Path, PathBuf},
+};
+use tracing::warn;
+
+pub const ROLLUP_TEMPLATE: &str = r#"Below are the summaries of the episodes of a series, e.g. the lectures of a course or a podcast season, in order, each under the episode's name.
+Synthesize across them into an overview of the whole series in bullet points: the themes that run through several episodes, how they develop from one episode to the next, and the main takeaways.
+Name the episodes a point comes from when it is specific to them, and merge what several episodes repeat into one point.
+Use '-' for bullet points and answer only the bullet points.
+Text:
+{text}
+
+SERIES OVERVIEW:"#;
+
+/// Suffix of the overview's own file name, `<directory>_rollup_summary.<ext>`, which is never read
+/// back as an episode
+pub const ROLLUP_SUFFIX: &str = "rollup_summary";
+
+/// Extensions of episode summaries, the most structured first
+const EXTENSIONS: [&str; 3] = ["json", "md", "txt"];
+
+/// One episode's summary
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Episode {
+    /// Its title when the summary has one, else the file name without `_summary`
+    pub name: String,
+    pub path: PathBuf,
+    pub summary: String,
+}
+
+/// The episode summaries in `dir` (and its subdirectories when `recursive` is set), by file name
+pub fn collect(dir: &Path, recursive: bool) -> Result<Vec<Episode>, Box<dyn Error>> {
+    let mut found = Vec::new();
+    scan_dir(dir, recursive, &mut found)?;
+    // One summary per episode, the first of `EXTENSIONS`
+    let mut by_stem: BTreeMap<PathBuf, PathBuf> = BTreeMap::new();
+    for path in found {
+        let stem = path.with_extension("");
+        let rank = |path: &Path| EXTENSIONS.iter().position(|e| path.extension().is_some_and(|ext| ext == *e));
+        match by_stem.get(&stem) {
+            Some(known) if rank(known) <= rank(&path) => {}
+            _ => {
+                by_stem.insert(stem, path);
+            }
+        }
+    }
+    let mut episodes = Vec::new();
+    for path in by_stem.into_values() {
+        match read(&path) {
+            Ok(episode) if episode.summary.trim().is_empty() => warn!("{:?} has no summary; leaving it out", path),
+            Ok(episode) => episodes.push(episode),
+            Err(e) => warn!("Cannot read {:?}: {}; leaving it out", path, e),
+        }
+    }
+    Ok(episodes)
+}
+
+/// Read a summary file written in any of the text, Markdown, Obsidian or JSON formats
+pub fn read(path: &Path) -> Result<Episode, Box<dyn Error>> {
+    let text = fs::read_to_string(path)?;
+    let stem = path.file_stem().unwrap_or_default().to_string_lossy();
+    let mut name = stem.strip_suffix("_summary").unwrap_or(&stem).to_string();
+    let summary = match path.extension().and_then(|e| e.to_str()) {
+        Some("json") => {
+            let report: Value = serde_json::from_str(&text)?;
+            if let Some(title) = report["title"].as_str() {
+                name = title.to_string();
+            }
+            let bullets = report["final_summary"].as_array().ok_or("no final_summary in the JSON summary")?;
+            bullets
+                .iter()
+                .filter_map(Value::as_str)
+                .map(|bullet| format!("- {}", bullet))
+                .collect::<Vec<_>>()
+                .join("\n")
+        }
+        Some("md") => {
+            let body = strip_front_matter(&text);
+            if let Some(title) = body.lines().next().and_then(|line| line.strip_prefix("# ")) {
+                name = title.trim().to_string();
+            }
+            // The sections from `## Summary` on, below the title and metadata list, without that
+            // first heading
+            let sections = match body.find("\n## ") {
+                Some(at) => &body[at + 1..],
+                None => body,
+            };
+            sections.strip_prefix("## Summary").unwrap_or(sections).trim().to_string()
+        }
+        _ => text.trim().to_string(),
+    };
+    Ok(Episode {
+        name,
+        path: path.to_path_buf(),
+        summary,
+    })
+}
+
+fn scan_dir(dir: &Path, recursive: bool, files: &mut Vec<PathBuf>) -> Result<(), Box<dyn Error>> {
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+        if path.is_dir() {
+            if recursive {
+                scan_dir(&path, recursive, files)?;
+            }
+        } else if is_summary(&path) {
+            files.push(path);
+        }
+    }
+    Ok(())
+}
+
+/// Whether `path` is a `<stem>_summary` file of this tool, and not an overview
+fn is_summary(path: &Path) -> bool {
+    let stem = path.file_stem().unwrap_or_default().to_string_lossy();
+    let extension = path.extension().and_then(|e| e.to_str()).unwrap_or("");
+    stem.ends_with("_summary") && !stem.ends_with(ROLLUP_SUFFIX) && EXTENSIONS.contains(&extension)
+}
+
+/// An Obsidian note without its YAML front matter
+fn strip_front_matter(text: &str) -> &str {
+    text.strip_prefix("---\n")
+        .and_then(|rest| rest.find("\n---\n").map(|end| rest[end + 5..].trim_start()))
+        .unwrap_or(text)
+}
@@ -0,0 +1,221 @@
+// HTTP API for summarizing without shelling out.
+//
+// `POST /summarize` takes a subtitle, text, PDF, EPUB, HTML or Markdown file, either as the raw
+// request body or as the `file` field of a multipart/form-data upload, parsed as the same file
+// on disk would be, and answers with the same JSON document as `--format json`. The input
+// format comes from the `?format=` query parameter, the uploaded file name, the Content-Type,
+// or finally the body itself. Errors are `{"error": "..."}`.
+
+use http_body_util::{BodyExt, Full, LengthLimitError, Limited};
+use hyper::{
+    body::{Bytes, Incoming},
+    header,
+    server::conn::http1,
+    service::service_fn,
+    Method, Request, Response, StatusCode,
+};
+use hyper_util::rt::TokioIo;
+use std::{convert::Infallible, error::Error, net::SocketAddr, path::PathBuf, sync::Arc};
+use tokio::net::TcpListener;
+use tracing::{info, warn};
+
+use crate::{config::OutputFormat, error::SummarizerError, output, parser, summarizer::Summarizer};
+
+/// Largest accepted request body; a 10-hour transcript is a few megabytes
+const MAX_BODY_BYTES: usize = 64 * 1024 * 1024;
+
+/// Serve `POST /summarize` on `addr` until the process is stopped
+pub async fn serve(addr: SocketAddr, summarizer: Arc<Summarizer>) -> Result<(), Box<dyn Error>> {
+    let listener = TcpListener::bind(addr).await?;
+    info!("Listening on http://{}", listener.local_addr()?);
+
+    loop {
+        let (stream, _) = listener.accept().await?;
+        let summarizer = summarizer.clone();
+        tokio::spawn(async move {
+            let service = service_fn(move |request| handle(summarizer.clone(), request));
+            if let Err(e) = http1::Builder::new().serve_connection(TokioIo::new(stream), service).await {
+                warn!("Connection error: {}", e);
+            }
+        });
+    }
+}
+
+async fn handle(
+    summarizer: Arc<Summarizer>,
+    request: Request<Incoming>,
+) -> Result<Response<Full<Bytes>>, Infallible> {
+    let response = match (request.method(), request.uri().path()) {
+        (&Method::POST, "/summarize") => match summarize(&summarizer, request).await {
+            Ok(json) => json_response(StatusCode::OK, json),
+            Err((status, message)) => error_response(status, &message),
+        },
+        (_, "/summarize") => error_response(StatusCode::METHOD_NOT_ALLOWED, "Use POST /summarize"),
+        _ => error_response(StatusCode::NOT_FOUND, "Not found; use POST /summarize"),
+    };
+    Ok(response)
+}
+
+/// Read the upload and summarize it, returning the JSON report
+async fn summarize(
+    summarizer: &Summarizer,
+    request: Request<Incoming>,
+) -> Result<String, (StatusCode, String)> {
+    let format = query_param(request.uri().query().unwrap_or(""), "format");
+    let content_type = request
+        .headers()
+        .get(header::CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .unwrap_or("")
+        .to_string();
+
+    let body = Limited::new(request.into_body(), MAX_BODY_BYTES)
+        .collect()
+        .await
+        .map_err(|e| match e.downcast_ref::<LengthLimitError>() {
+            Some(_) => (
+                StatusCode::PAYLOAD_TOO_LARGE,
+                format!("The request body is larger than {} bytes", MAX_BODY_BYTES),
+            ),
+            None => (StatusCode::BAD_REQUEST, format!("Cannot read the request body: {}", e)),
+        })?
+        .to_bytes();
+    let (file_name, bytes) = if content_type.starts_with("multipart/form-data") {
+        multipart_file(&content_type, &body).ok_or_else(|| {
+            (
+                StatusCode::BAD_REQUEST,
+                "The multipart upload has no `file` field".to_string(),
+            )
+        })?
+    } else {
+        (None, &body[..])
+    };
+    if bytes.iter().all(u8::is_ascii_whitespace) {
+        return Err((StatusCode::BAD_REQUEST, "The upload is empty".to_string()));
+    }
+
+    let extension = format
+        .or_else(|| {
+            file_name
+                .as_deref()
+                .and_then(|name| name.rsplit_once('.'))
+                .map(|(_, extension)| extension.to_string())
+        })
+        .unwrap_or_else(|| sniff_extension(&content_type, bytes).to_string());
+    let transcript = parser::parse_bytes(&extension, bytes).map_err(|e| (StatusCode::BAD_REQUEST, e.to_string()))?;
+    summarize_upload(summarizer, &transcript, file_name).await
+}
+
+/// Summarize an uploaded transcript into the JSON response
+async fn summarize_upload(
+    summarizer: &Summarizer,
+    transcript: &parser::Transcript,
+    file_name: Option<String>,
+) -> Result<String, (StatusCode, String)> {
+    let mut summary = summarizer
+        .summarize_transcript(transcript)
+        .await
+        .map_err(|e| {
+            // A backend that is down or refuses the key is the gateway's fault, not ours
+            let status = match e.downcast_ref::<SummarizerError>() {
+                Some(SummarizerError::BackendUnreachable { .. } | SummarizerError::Unauthorized { .. }) => {
+                    StatusCode::BAD_GATEWAY
+                }
+                Some(SummarizerError::ContextOverflow { .. }) => StatusCode::UNPROCESSABLE_ENTITY,
+                _ => StatusCode::INTERNAL_SERVER_ERROR,
+            };
+            (status, e.to_string())
+        })?;
+    summary.input = file_name.map(PathBuf::from);
+    Ok(output::render(OutputFormat::Json, &summary))
+}
+
+/// Extension implied by the Content-Type, or else by the upload itself
+fn sniff_extension(content_type: &str, bytes: &[u8]) -> &'static str {
+    let mime = content_type.split(';').next().unwrap_or("").trim();
+    match mime {
+        "text/vtt" => "vtt",
+        "application/x-subrip" | "text/srt" => "srt",
+        "application/json" => "json",
+        "application/pdf" => "pdf",
+        "application/epub+zip" => "epub",
+        "text/html" => "html",
+        "text/markdown" => "md",
+        _ if bytes.starts_with(b"%PDF-") => "pdf",
+        _ => parser::sniff_extension(&parser::decode(bytes).0),
+    }
+}
+
+/// File name and content of the `file` part (or else the first part with a file name)
+fn multipart_file<'a>(content_type: &str, body: &'a [u8]) -> Option<(Option<String>, &'a [u8])> {
+    let boundary = content_type
+        .split(';')
+        .find_map(|param| param.trim().strip_prefix("boundary="))?
+        .trim_matches('"');
+    let delimiter = format!("--{}", boundary);
+
+    let mut fallback = None;
+    for part in split_bytes(body, delimiter.as_bytes()).into_iter().skip(1) {
+        let Some(end) = find_bytes(part, b"\r\n\r\n") else {
+            continue;
+        };
+        let (headers, content) = (String::from_utf8_lossy(&part[..end]), &part[end + 4..]);
+        let content = content.strip_suffix(b"\r\n").unwrap_or(content);
+        let disposition = headers
+            .lines()
+            .find(|line| line.to_ascii_lowercase().starts_with("content-disposition:"))
+            .unwrap_or("");
+        let file_name = disposition_param(disposition, "filename");
+        if disposition_param(disposition, "name").as_deref() == Some("file") {
+            return Some((file_name, content));
+        }
+        if fallback.is_none() && file_name.is_some() {
+            fallback = Some((file_name, content));
+        }
+    }
+    fallback
+}
+
+/// The pieces of `bytes` between occurrences of `delimiter`
+fn split_bytes<'a>(mut bytes: &'a [u8], delimiter: &[u8]) -> Vec<&'a [u8]> {
+    let mut pieces = Vec::new();
+    while let Some(at) = find_bytes(bytes, delimiter) {
+        pieces.push(&bytes[..at]);
+        bytes = &bytes[at + delimiter.len()..];
+    }
+    pieces.push(bytes);
+    pieces
+}
+
+/// Offset of the first `needle` in `haystack`
+fn find_bytes(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+    haystack.windows(needle.len()).position(|window| window == needle)
+}
+
+/// `value` of `key="value"` in a Content-Disposition header
+fn disposition_param(disposition: &str, key: &str) -> Option<String> {
+    disposition.split(';').find_map(|param| {
+        let (name, value) = param.trim().split_once('=')?;
+        (name == key).then(|| value.trim_matches('"').to_string())
+    })
+}
+
+fn query_param(query: &str, key: &str) -> Option<String> {
+    query.split('&').find_map(|pair| {
+        let (name, value) = pair.split_once('=')?;
+        (name == key && !value.is_empty()).then(|| value.to_string())
+    })
+}
+
+fn json_response(status: StatusCode, json: String) -> Response<Full<Bytes>> {
+    let mut response = Response::new(Full::new(Bytes::from(json)));
+    *response.status_mut() = status;
+    response
+        .headers_mut()
+        .insert(header::CONTENT_TYPE, header::HeaderValue::from_static("application/json"));
+    response
+}
+
+fn error_response(status: StatusCode, message: &str) -> Response<Full<Bytes>> {
+    json_response(status, serde_json::json!({ "error": message }).to_string())
+}
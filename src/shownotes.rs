@@ -0,0 +1,120 @@
+// Podcast show notes written by `--mode shownotes`.
+//
+// The combine reply has a description paragraph, key quotes and the links and people mentioned,
+// each under its own heading; headings are matched loosely, so `## About this episode` or
+// `**Quotes:**` still land in the right section. The chapter list comes from the chapters
+// request and is placed after the description when the notes are written as text.
+
+use crate::chapters::{self, Chapter};
+
+pub const DESCRIPTION_HEADING: &str = "Description";
+pub const QUOTES_HEADING: &str = "Key Quotes";
+pub const MENTIONS_HEADING: &str = "Links and Mentions";
+
+/// The parts of an episode's show notes besides its chapters
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ShowNotes {
+    /// One paragraph on what the episode covers
+    pub description: String,
+    /// Quotes as the model wrote them, usually `"Quote" — Speaker`
+    pub quotes: Vec<String>,
+    /// People, books, products, sites and links brought up in the episode
+    pub mentions: Vec<String>,
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum Section {
+    Description,
+    Quotes,
+    Mentions,
+    /// Chapters the model wrote itself; the chapters request makes the ones used
+    Chapters,
+}
+
+/// Extract the show notes of a model reply; text before the first heading counts as
+/// description, and `- None` placeholders are dropped
+pub fn parse(reply: &str) -> ShowNotes {
+    let mut notes = ShowNotes::default();
+    let mut description: Vec<&str> = Vec::new();
+    let mut section = Section::Description;
+    for line in reply.lines() {
+        let line = line.trim();
+        if line.is_empty() {
+            continue;
+        }
+        if let Some(found) = heading(line) {
+            section = found;
+            continue;
+        }
+        let item = line.strip_prefix(['-', '*', '•']).map(str::trim);
+        match (section, item) {
+            (Section::Chapters, _) => {}
+            (Section::Description, _) => description.push(item.unwrap_or(line)),
+            (_, Some(item)) if item.is_empty() || item.eq_ignore_ascii_case("none") => {}
+            (Section::Quotes, Some(item)) => notes.quotes.push(item.to_string()),
+            (Section::Mentions, Some(item)) => notes.mentions.push(item.to_string()),
+            // A wrapped line continues the item above it
+            (Section::Quotes, None) => append(&mut notes.quotes, line),
+            (Section::Mentions, None) => append(&mut notes.mentions, line),
+        }
+    }
+    notes.description = description
+        .into_iter()
+        .filter(|line| !is_preamble(line))
+        .collect::<Vec<_>>()
+        .join(" ");
+    notes
+}
+
+/// The notes under `##` headings, with `chapters` as `MM:SS Title` lines after the description
+/// when there are any
+pub fn render(notes: &ShowNotes, chapters: &[Chapter]) -> String {
+    let list = |items: &[String]| match items {
+        [] => "- None".to_string(),
+        items => items.iter().map(|item| format!("- {}", item)).collect::<Vec<_>>().join("\n"),
+    };
+    let mut text = format!("## {}\n{}\n", DESCRIPTION_HEADING, notes.description);
+    if !chapters.is_empty() {
+        text.push_str(&format!("\n## Chapters\n{}", chapters::render(chapters)));
+    }
+    text.push_str(&format!("\n## {}\n{}\n", QUOTES_HEADING, list(&notes.quotes)));
+    text.push_str(&format!("\n## {}\n{}", MENTIONS_HEADING, list(&notes.mentions)));
+    text
+}
+
+/// The section a `## Heading` or `**Heading:**` line starts
+fn heading(line: &str) -> Option<Section> {
+    let text = if let Some(heading) = line.strip_prefix('#') {
+        heading.trim_start_matches('#')
+    } else if (line.starts_with("**") && line.ends_with("**") || line.ends_with(':')) && line.split_whitespace().count() <= 4 {
+        line
+    } else {
+        return None;
+    };
+    let text = text.trim_matches(['*', ':', ' ']).to_lowercase();
+    Some(if text.contains("chapter") {
+        Section::Chapters
+    } else if text.contains("quote") {
+        Section::Quotes
+    } else if text.contains("link") || text.contains("mention") || text.contains("resource") {
+        Section::Mentions
+    } else {
+        Section::Description
+    })
+}
+
+fn append(items: &mut [String], line: &str) {
+    if let Some(last) = items.last_mut() {
+        last.push(' ');
+        last.push_str(line);
+    }
+}
+
+/// Whether `line` is a "Here are the show notes:" kind of opener
+fn is_preamble(line: &str) -> bool {
+    let line = line.to_lowercase();
+    ["here are", "here is", "here's", "sure", "certainly"]
+        .iter()
+        .any(|opener| line.starts_with(opener))
+        && line.ends_with(':')
+}
@@ -0,0 +1,2524 @@
+// Map-reduce and refine summarization over any `LlmBackend`.
+//
+// Text is split into overlapping chunks, each chunk is summarized on its own (Map), and the
+// chunk summaries are combined into the final bullet list (Reduce). Map output too long for one
+// combine request is first reduced in batches, level by level, until it fits; a chunk or batch
+// the backend rejects as too long for its context window is split or batched smaller. The refine
+// strategy instead walks the chunks in order, revising one running summary as it goes.
+
+use chrono::{DateTime, Local};
+use futures::{stream, StreamExt, TryStreamExt};
+use indicatif::{ProgressBar, ProgressDrawTarget, ProgressStyle};
+use std::{
+    error::Error,
+    fs::{self, File},
+    future::Future,
+    io::{self, BufReader},
+    iter,
+    ops::Range,
+    path::{Path, PathBuf},
+    pin::Pin,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    time::{Duration, Instant},
+};
+use tokio::sync::Semaphore;
+use tracing::{debug, info, warn};
+
+use crate::{
+    action_items,
+    ask::{self, Answer, Passage},
+    backend::{self, BackendError, LlmBackend},
+    bullets,
+    cache::{self, ResponseCache},
+    chapters::{self, Chapter},
+    checkpoint::{self, Checkpoint, Incremental, RunKey},
+    chunker,
+    compare::{self, Comparison},
+    debug_dump::DebugDump,
+    entities::{self, Entities},
+    headline::{self, Headline},
+    keywords,
+    config::{Mode, OutputFormat, Settings, Split, Strategy},
+    error::{self, SummarizerError},
+    language::Language,
+    lecture, outline,
+    parser::{self, Cue, Transcript},
+    qa,
+    rate_limit::RateLimiter,
+    rollup::{self, Episode},
+    shownotes, topics,
+    transcribe::Transcriber,
+    usage::{TokenUsage, UsageMeter, UsageReport},
+};
+
+pub const MAP_TEMPLATE: &str = r#"Write a detailed summary of this text section in bullet points.
+Use '-' for bullet points and answer only the bullet points.
+Text:
+{text}
+
+SUMMARY:"#;
+
+pub const COMBINE_TEMPLATE: &str = r#"Combine these summaries into a final summary in bullet points.
+Use '-' for bullet points and answer only the bullet points.
+Text:
+{text}
+
+FINAL SUMMARY:"#;
+
+/// Prepended to a final prompt whose reply had no bullet points
+const BULLETS_REMINDER: &str = "Answer only with bullet points, one per line, each starting with '- '.";
+
+/// Asks for a summary of at most `{max}` bullets when the final one has too many
+pub const CONDENSE_TEMPLATE: &str = r#"Condense this summary into at most {max} bullet points.
+Merge related points and drop minor details; keep any headings, [time ranges] and [owners].
+Use '-' for bullet points and answer only the bullet points.
+Summary:
+{text}
+
+CONDENSED SUMMARY:"#;
+
+/// Revises a final summary with `--polish`
+pub const POLISH_TEMPLATE: &str = r#"This summary was combined from summaries of overlapping sections of a text, so some points repeat.
+Merge bullet points that say the same or nearly the same thing, put the points in the order they come up in the text with related points next to each other, and tighten the wording without dropping any facts.
+Keep any headings, the nesting of indented sub-points, [time ranges] and [owners].
+Use '-' for bullet points and answer only the bullet points.
+Summary:
+{text}
+
+POLISHED SUMMARY:"#;
+
+/// Merges the `--samples` replies of the final step; `{count}` is their number
+pub const CONSENSUS_TEMPLATE: &str = r#"Below are {count} versions of the same summary, written independently from the same text.
+Write one final version in the same format: keep the points that most versions agree on, merge points they phrase differently, and leave out details that only one version has unless they matter.
+Keep any headings, [time ranges] and [owners].
+Answer only with the final version.
+Versions:
+{text}
+
+FINAL VERSION:"#;
+
+/// Fewest words of a passage `--split semantic` compares with the next one
+const SEMANTIC_PASSAGE_WORDS: usize = 40;
+
+/// Texts embedded in one request
+const EMBED_BATCH: usize = 64;
+
+/// Temperature of the `--samples` replies when `temperature` is unset or 0, so they differ
+const SAMPLE_TEMPERATURE: f64 = 0.7;
+
+/// Translates a final summary or transcript chunk; `{language}` is the target language's name
+pub const TRANSLATE_TEMPLATE: &str = r#"Translate this text into {language}.
+Keep the layout exactly as it is: line breaks, '-' bullets, '##' headings, '[ ]' checkboxes, "Q1." and "A:" labels, [HH:MM:SS] time ranges and [Owner] tags stay unchanged, and names of people, products and organizations are not translated.
+Answer only with the translation.
+Text:
+{text}
+
+TRANSLATION:"#;
+
+/// Result of summarizing one input
+#[derive(Debug, Clone)]
+pub struct Summary {
+    /// The summarized file, or `None` for in-memory text
+    pub input: Option<PathBuf>,
+    /// Title of the source when it has one, e.g. a video title; otherwise derived from `input`
+    pub title: Option<String>,
+    /// The files summarized together by `summarize_files`, in order; empty for a single input
+    pub sources: Vec<PathBuf>,
+    pub model: String,
+    pub mode: Mode,
+    /// One section per speaker instead of one bullet list
+    pub by_speaker: bool,
+    pub chunk_summaries: Vec<String>,
+    /// Start time of every chunk of timed input summarized with map-reduce; empty otherwise
+    pub chunk_starts: Vec<Duration>,
+    pub final_summary: String,
+    /// Chapter markers, when requested and the input has timestamps
+    pub chapters: Option<Vec<Chapter>>,
+    /// Key topics of the summary, when requested
+    pub keywords: Option<Vec<String>>,
+    /// People, organizations, products, and numbers and dates mentioned, when requested
+    pub entities: Option<Entities>,
+    /// Generated title and TL;DR, when requested
+    pub headline: Option<Headline>,
+    /// The cleaned transcript in the `translate` language, when `translate_transcript` is set
+    pub translated_transcript: Option<String>,
+    /// Page of the video that chapter timestamps link to, from `video_url`
+    pub video_url: Option<String>,
+    /// Anki deck of the flashcard export, from `deck_name`
+    pub deck_name: Option<String>,
+    /// Length of the recording, from the end of the last subtitle cue; `None` for untimed text
+    pub media_length: Option<Duration>,
+    pub duration: Duration,
+    pub generated_at: DateTime<Local>,
+    /// Tokens spent per stage; cached chunk summaries cost none
+    pub usage: UsageReport,
+    /// The fallback backend's model, e.g. `gpt-4o-mini (openai)`, when one is configured;
+    /// `usage.fallbacks` lists the requests it answered
+    pub fallback: Option<String>,
+    /// Numbers (from 1) of the chunks left out after failing with `skip_failed_chunks`, or after
+    /// safety filters blocked them
+    pub skipped_chunks: Vec<usize>,
+}
+
+pub const REFINE_TEMPLATE: &str = r#"Here is a summary of the beginning of a text in bullet points:
+{summary}
+
+Revise the summary so that it also covers the next section of the text below.
+Keep the points that are still accurate, and add or merge points for the new material.
+Use '-' for bullet points and answer only the bullet points.
+Text:
+{text}
+
+REVISED SUMMARY:"#;
+
+pub const TIMESTAMPED_MAP_TEMPLATE: &str = r#"Write a detailed summary of this text section in bullet points.
+The text contains [HH:MM:SS] markers giving the time at which the following words are spoken.
+Start every bullet with the time range of the text it summarizes, e.g. "- [00:14:10–00:18:42] The speaker explains the training loop".
+Use '-' for bullet points and answer only the bullet points.
+Text:
+{text}
+
+SUMMARY:"#;
+
+pub const TIMESTAMPED_COMBINE_TEMPLATE: &str = r#"Combine these summaries into a final summary in bullet points.
+Every bullet starts with a [HH:MM:SS–HH:MM:SS] time range; keep it, and when you merge bullets give the merged bullet a range spanning all of them.
+Use '-' for bullet points and answer only the bullet points.
+Text:
+{text}
+
+FINAL SUMMARY:"#;
+
+pub const MINUTES_MAP_TEMPLATE: &str = r#"Extract meeting minutes from this section of a meeting transcript.
+List the items under these headings, leaving out a heading when the section has nothing for it:
+## Decisions
+## Action Items
+## Open Questions
+## Key Discussion Points
+Use '-' for items. When the transcript labels speakers (e.g. "Name: ..."), start each action item with its owner in square brackets, e.g. "- [Sarah] Send the revised budget".
+Answer only with the headings and items.
+Transcript:
+{text}
+
+MINUTES:"#;
+
+pub const MINUTES_COMBINE_TEMPLATE: &str = r#"Combine these partial meeting minutes into the minutes of the whole meeting.
+Use exactly these headings, in this order, and write "- None" under a heading without items:
+## Decisions
+## Action Items
+## Open Questions
+## Key Discussion Points
+Merge duplicate items, keep the [Owner] at the start of action items, and use '-' for items.
+Answer only with the headings and items.
+Minutes:
+{text}
+
+FINAL MINUTES:"#;
+
+pub const SPEAKER_MAP_TEMPLATE: &str = r#"Summarize this section of a transcript separately for every speaker.
+Speaker turns start with the speaker's name and a colon, e.g. "Sarah: ...".
+For every speaker, write a '## Name' heading followed by bullet points on what they said and anything they committed to do.
+Use '-' for bullet points and answer only the headings and bullet points.
+Transcript:
+{text}
+
+SUMMARY BY SPEAKER:"#;
+
+pub const SPEAKER_COMBINE_TEMPLATE: &str = r#"Combine these per-speaker summaries into one summary per speaker.
+Use one '## Name' heading per speaker, in order of first appearance, and merge the points of the same speaker.
+Keep what each speaker said and anything they committed to do.
+Use '-' for bullet points and answer only the headings and bullet points.
+Summaries:
+{text}
+
+FINAL SUMMARY BY SPEAKER:"#;
+
+pub const ACTION_ITEMS_MAP_TEMPLATE: &str = r#"List the action items in this section of a transcript: concrete follow-ups that someone agreed or was asked to do.
+Leave out decisions, opinions, general discussion and anything that was only suggested without being taken on.
+Write every action item on its own line in the format "- [ ] owner: task (due date)". Use the name of the person responsible as the owner, or "Unassigned" when the transcript doesn't say; add the due date in parentheses only when one is mentioned.
+Answer only with the action items, or "- None" when there are none.
+Transcript:
+{text}
+
+ACTION ITEMS:"#;
+
+pub const ACTION_ITEMS_COMBINE_TEMPLATE: &str = r#"Combine these lists of action items from consecutive sections of a transcript into one list.
+Merge duplicates, keeping the most specific owner and due date, and keep the order in which the items first come up.
+Write every action item on its own line in the format "- [ ] owner: task (due date)", with "Unassigned" as the owner when none is known.
+Answer only with the action items, or "- None" when there are none.
+Action items:
+{text}
+
+FINAL ACTION ITEMS:"#;
+
+pub const QA_MAP_TEMPLATE: &str = r#"Write study questions with their answers about this section of a transcript, for a student revising the material.
+Ask about the key ideas, definitions, facts and reasoning rather than trivia, and answer each question in one to three sentences using only the text.
+Write every pair as a "Q:" line followed by an "A:" line.
+Answer only with the questions and answers.
+Text:
+{text}
+
+QUESTIONS:"#;
+
+pub const QA_COMBINE_TEMPLATE: &str = r#"Below are study questions with answers from consecutive sections of a transcript.
+Choose the questions that best cover the whole transcript, merging duplicates and improving the answers where the sections complement each other.
+Write every pair as a "Q:" line followed by an "A:" line.
+Answer only with the questions and answers.
+Questions:
+{text}
+
+FINAL QUESTIONS:"#;
+
+pub const OUTLINE_MAP_TEMPLATE: &str = r#"Write an outline of this text section: its main topics as top-level bullet points, each with indented sub-bullets for the points made about it, and one more level of sub-bullets for details where needed.
+Start every bullet with '- ' and indent sub-bullets by two spaces per level, e.g.
+- Topic
+  - Point about the topic
+    - Detail of the point
+Answer only with the outline.
+Text:
+{text}
+
+OUTLINE:"#;
+
+pub const OUTLINE_COMBINE_TEMPLATE: &str = r#"Combine these outlines of consecutive sections of a text into one outline of the whole text.
+Merge topics that come up more than once into one top-level bullet, keep the topics in the order they first come up, and put every point under the topic it belongs to.
+Start every bullet with '- ' and indent sub-bullets by two spaces per level, at most three levels deep, e.g.
+- Topic
+  - Point about the topic
+    - Detail of the point
+Answer only with the outline.
+Outlines:
+{text}
+
+FINAL OUTLINE:"#;
+
+/// Prepended to a final outline prompt whose reply had no sub-points
+const OUTLINE_REMINDER: &str = "Every top-level bullet is a topic and needs indented sub-bullets, two spaces deeper, for the points made about it.";
+
+pub const SHOWNOTES_MAP_TEMPLATE: &str = r#"Take notes on this section of a podcast episode transcript for the episode's show notes.
+Write them under these headings, leaving out a heading when the section has nothing for it:
+## Topics
+## Quotes
+## Mentions
+Under Topics, list what is discussed. Under Quotes, copy up to three memorable sentences word for word as '- "Quote" — Speaker', naming the speaker when the transcript does. Under Mentions, list the people, books, products, companies, websites and links brought up, one per item with a few words on the context.
+Use '-' for items and answer only with the headings and items.
+Transcript:
+{text}
+
+NOTES:"#;
+
+pub const SHOWNOTES_COMBINE_TEMPLATE: &str = r#"Write the show notes of a podcast episode from these notes on its consecutive sections.
+Use exactly these headings, in this order:
+## Description
+## Key Quotes
+## Links and Mentions
+Under Description, write one paragraph of two to four sentences telling listeners what the episode covers and why it is worth a listen, without bullet points. Under Key Quotes, pick the three to five best quotes, word for word, as '- "Quote" — Speaker'. Under Links and Mentions, merge the mentions into one list without duplicates, keeping any URLs, or write "- None" when there are none.
+Answer only with the headings and their content.
+Notes:
+{text}
+
+SHOW NOTES:"#;
+
+pub const LECTURE_MAP_TEMPLATE: &str = r#"Take study notes on this section of a lecture transcript.
+Write them under these headings, leaving out a heading when the section has nothing for it:
+## Key Concepts
+## Main Points
+## Examples Mentioned
+## Further Questions
+Under Key Concepts, write every term the section introduces or explains as "- Term: one-line definition". Under Main Points, list the ideas and arguments. Under Examples Mentioned, list the examples, cases and demonstrations used. Under Further Questions, list questions the section raises or leaves open, for a student to think about.
+Use '-' for items and answer only with the headings and items.
+Transcript:
+{text}
+
+LECTURE NOTES:"#;
+
+pub const LECTURE_COMBINE_TEMPLATE: &str = r#"Combine these study notes on consecutive sections of a lecture into the notes of the whole lecture.
+Use exactly these headings, in this order, and write "- None" under a heading without items:
+## Key Concepts
+## Main Points
+## Examples Mentioned
+## Further Questions
+Merge duplicate items, and write every key concept once as "- Term: one-line definition". Keep the main points in the order they come up.
+Use '-' for items and answer only with the headings and items.
+Notes:
+{text}
+
+FINAL LECTURE NOTES:"#;
+
+pub const TOPICS_MAP_TEMPLATE: &str = r#"Split this section of a transcript into the topics it discusses, in order, and summarize each one.
+The text contains [HH:MM:SS] markers giving the time at which the following words are spoken.
+Write every topic as a '## [HH:MM:SS] Topic' heading, with the marker where the topic starts and a title of at most six words, followed by '-' bullet points on what is said about it. Leave out the marker when the text has none.
+Answer only with the headings and bullet points.
+Transcript:
+{text}
+
+TOPICS:"#;
+
+pub const TOPICS_COMBINE_TEMPLATE: &str = r#"Below are the topics of consecutive sections of a transcript, each under a '## [HH:MM:SS] Topic' heading with the time it starts.
+Combine them into the topics of the whole transcript: merge neighbouring topics that are about the same subject under one heading with the earliest start time, keep the topics in the order they come up, and merge duplicate bullet points.
+Write every topic as a '## [HH:MM:SS] Topic' heading followed by '-' bullet points.
+Answer only with the headings and bullet points.
+Topics:
+{text}
+
+FINAL TOPICS:"#;
+
+/// Default map and combine templates for a mode; `by_speaker` and `timestamps` only change the
+/// summary mode's
+pub fn default_templates(mode: Mode, timestamps: bool, by_speaker: bool) -> (&'static str, &'static str) {
+    match mode {
+        Mode::Summary if by_speaker => (SPEAKER_MAP_TEMPLATE, SPEAKER_COMBINE_TEMPLATE),
+        Mode::Summary if timestamps => (TIMESTAMPED_MAP_TEMPLATE, TIMESTAMPED_COMBINE_TEMPLATE),
+        Mode::Summary => (MAP_TEMPLATE, COMBINE_TEMPLATE),
+        Mode::Minutes => (MINUTES_MAP_TEMPLATE, MINUTES_COMBINE_TEMPLATE),
+        Mode::ActionItems => (ACTION_ITEMS_MAP_TEMPLATE, ACTION_ITEMS_COMBINE_TEMPLATE),
+        Mode::Qa => (QA_MAP_TEMPLATE, QA_COMBINE_TEMPLATE),
+        Mode::Outline => (OUTLINE_MAP_TEMPLATE, OUTLINE_COMBINE_TEMPLATE),
+        Mode::Shownotes => (SHOWNOTES_MAP_TEMPLATE, SHOWNOTES_COMBINE_TEMPLATE),
+        Mode::Lecture => (LECTURE_MAP_TEMPLATE, LECTURE_COMBINE_TEMPLATE),
+        Mode::Topics => (TOPICS_MAP_TEMPLATE, TOPICS_COMBINE_TEMPLATE),
+    }
+}
+
+/// Minimum time between two markers in a timestamped chunk; a marker per cue would roughly
+/// double the prompt size for short cues
+const MARKER_SPACING: Duration = Duration::from_secs(15);
+
+/// Chunk texts and the range of words each one covers
+type Chunks = (Vec<String>, Vec<Range<usize>>);
+
+/// What summarizing an input would send to the model, without sending it (`--dry-run`)
+#[derive(Debug, Clone)]
+pub struct Plan {
+    pub chunks: Vec<PlannedChunk>,
+    /// Requests a run would make: one per chunk not in the cache, the combine (map-reduce with
+    /// more than one chunk), polish and chapters requests; reduce batches and language retries
+    /// can add more
+    pub requests: usize,
+}
+
+/// One chunk of a `Plan`
+#[derive(Debug, Clone)]
+pub struct PlannedChunk {
+    /// Chunk text as sent, with any time markers or speaker label
+    pub text: String,
+    pub words: usize,
+    /// Tokens of the rendered map prompt
+    pub tokens: usize,
+    /// Start of the first and end of the last cue of the chunk, for timed subtitles
+    pub span: Option<(Duration, Duration)>,
+    /// Whether the chunk cache already holds its summary
+    pub cached: bool,
+}
+
+/// Stands in for the summary of a chunk that failed with `skip_failed_chunks`, or was blocked
+fn skipped_placeholder(index: usize) -> String {
+    format!("- (Chunk {} could not be summarized and was skipped)", index + 1)
+}
+
+/// Fill the `{text}` placeholder of a prompt template
+pub fn render_prompt(template: &str, text: &str) -> String {
+    template.replace("{text}", text)
+}
+
+/// Fill the `{summary}` and `{text}` placeholders of a refine template
+pub fn render_refine_prompt(template: &str, summary: &str, text: &str) -> String {
+    template.replace("{summary}", summary).replace("{text}", text)
+}
+
+/// Prepend the language instruction to a rendered prompt
+fn localize(lang: Option<&Language>, prompt: String) -> String {
+    match lang {
+        Some(lang) => format!("{}\n\n{}", lang.instruction, prompt),
+        None => prompt,
+    }
+}
+
+/// Rebuild the chunk covering `range` of `words` with `[HH:MM:SS]` markers at cue starts and
+/// the end time of its last cue appended
+fn timestamped_chunk(words: &[&str], cues: &[Cue], word_cues: &[usize], range: Range<usize>) -> String {
+    let mut text = String::new();
+    let mut last_marker: Option<Duration> = None;
+    let mut last_cue = None;
+    for i in range {
+        let cue = &cues[word_cues[i]];
+        let due = last_marker.is_none_or(|marker| cue.start >= marker + MARKER_SPACING);
+        if last_cue != Some(word_cues[i]) && due {
+            text.push_str(&format!("[{}] ", parser::format_clock(cue.start)));
+            last_marker = Some(cue.start);
+        }
+        last_cue = Some(word_cues[i]);
+        text.push_str(words[i]);
+        text.push(' ');
+    }
+    if let Some(cue) = last_cue {
+        text.push_str(&format!("[{}]", parser::format_clock(cues[cue].end)));
+    }
+    text.trim_end().to_string()
+}
+
+/// "between 00:15:00 and 01:05:00", "after 00:15:00" or "before 01:05:00"
+fn describe_range(from: Option<Duration>, to: Option<Duration>) -> String {
+    match (from, to) {
+        (Some(from), Some(to)) => format!("between {} and {}", parser::format_clock(from), parser::format_clock(to)),
+        (Some(from), None) => format!("after {}", parser::format_clock(from)),
+        (None, Some(to)) => format!("before {}", parser::format_clock(to)),
+        (None, None) => String::new(),
+    }
+}
+
+/// Chunk a subtitle transcript into windows of `minutes`, by the start time of each word's cue
+fn split_by_time(text: &str, cues: &[Cue], word_cues: &[usize], minutes: usize) -> Chunks {
+    let words: Vec<&str> = text.split_whitespace().collect();
+    let starts: Vec<Duration> = word_cues.iter().map(|&cue| cues[cue].start).collect();
+    let windows = chunker::time_windows(&starts, Duration::from_secs(minutes as u64 * 60));
+    let chunks: Vec<String> = windows.iter().map(|range| words[range.clone()].join(" ")).collect();
+    info!("Split into {} chunks of up to {} minutes", chunks.len(), minutes);
+    (chunks, windows)
+}
+
+/// Start a chunk that begins mid-turn with the label of its first cue's speaker
+fn speaker_chunk(chunk: &str, first_cue: &Cue) -> String {
+    match &first_cue.speaker {
+        Some(speaker) if !chunk.starts_with(&format!("{}:", speaker)) => format!("{}: {}", speaker, chunk),
+        _ => chunk.to_string(),
+    }
+}
+
+/// Why a run stopped before it finished
+enum Stop {
+    Interrupted,
+    TimedOut,
+}
+
+/// Backend asked when the primary one fails
+struct Fallback {
+    backend: Arc<dyn LlmBackend>,
+    /// Its model and kind, e.g. `gpt-4o-mini (openai)`
+    label: String,
+}
+
+/// Reusable summarizer holding the configured backend
+pub struct Summarizer {
+    settings: Settings,
+    /// Backend of the steps after the chunk summaries
+    backend: Arc<dyn LlmBackend>,
+    /// Backend of the chunk summaries; the same client as `backend` unless `map_model` differs
+    map_backend: Arc<dyn LlmBackend>,
+    /// Backend of the `samples` replies, sampling at a temperature above 0; the same client as
+    /// `backend` unless that one samples at 0
+    sample_backend: Arc<dyn LlmBackend>,
+    /// Backend embedding the chunks of `ask`, with the embedding model
+    embed_backend: Arc<dyn LlmBackend>,
+    fallback: Option<Fallback>,
+    /// Set once the primary backend is found down, so later requests go straight to the fallback
+    primary_down: AtomicBool,
+    cache: ResponseCache,
+    /// Model names the cache is keyed by, including non-default sampling parameters, for the
+    /// chunk summaries and for the steps after them
+    map_cache_model: String,
+    cache_model: String,
+    resume: bool,
+    /// Summarize only the cues of files added since their last run, merged into its summary
+    incremental: bool,
+    stream: bool,
+    chapters: bool,
+    keywords: bool,
+    entities: bool,
+    /// Generate a title and a TL;DR
+    headline: (bool, bool),
+    /// Part of timed transcripts to summarize
+    range: (Option<Duration>, Option<Duration>),
+    /// Stop cleanly on Ctrl+C
+    interrupt: bool,
+    progress: bool,
+    debug_dump: Option<DebugDump>,
+    rate_limit: Option<RateLimiter>,
+    /// `--concurrency` permits, so files summarized side by side (`--jobs`) share it
+    in_flight: Semaphore,
+}
+
+impl Summarizer {
+    /// Build the backends described by `settings`, one per model when the map step uses its own
+    pub fn new(settings: Settings) -> Result<Self, Box<dyn Error>> {
+        let backend = backend::for_model(&settings, settings.final_model())?;
+        let map_backend = if settings.chunk_model() == settings.final_model() {
+            backend.clone()
+        } else {
+            backend::for_model(&settings, settings.chunk_model())?
+        };
+        let fallback = match settings.fallback_settings() {
+            Some(fallback) => Some((backend::from_settings(&fallback)?, format!("{} ({})", fallback.model, fallback.backend))),
+            None => None,
+        };
+        let sample_backend = match settings.temperature {
+            _ if settings.samples == 1 => None,
+            Some(temperature) if temperature > 0.0 => None,
+            _ => Some(backend::for_model(
+                &Settings {
+                    temperature: Some(SAMPLE_TEMPERATURE),
+                    ..settings.clone()
+                },
+                settings.final_model(),
+            )?),
+        };
+        // Embeddings don't sample, so a backend that doesn't support --seed needn't warn twice
+        let embed_settings = Settings {
+            seed: None,
+            ..settings.clone()
+        };
+        let embed_backend = backend::for_model(&embed_settings, settings.embedding_model())?;
+        let mut summarizer = Self::with_backends(settings, map_backend, backend);
+        if let Some(sample_backend) = sample_backend {
+            summarizer.sample_backend = sample_backend;
+        }
+        summarizer.embed_backend = embed_backend;
+        Ok(match fallback {
+            Some((backend, label)) => summarizer.with_fallback(backend, label),
+            None => summarizer,
+        })
+    }
+
+    /// Use a custom backend; the backend-related fields of `settings` are then ignored
+    pub fn with_backend(settings: Settings, backend: Arc<dyn LlmBackend>) -> Self {
+        Self::with_backends(settings, backend.clone(), backend)
+    }
+
+    /// Use custom backends for the chunk summaries (`map_backend`) and for every step after them,
+    /// which also embeds the chunks of `ask`
+    pub fn with_backends(settings: Settings, map_backend: Arc<dyn LlmBackend>, backend: Arc<dyn LlmBackend>) -> Self {
+        Self {
+            cache: ResponseCache::new(settings.cache, settings.cache_limits()),
+            map_cache_model: settings.cache_model(settings.chunk_model()),
+            cache_model: settings.cache_model(settings.final_model()),
+            rate_limit: RateLimiter::new(settings.rpm, settings.tpm),
+            in_flight: Semaphore::new(settings.concurrency),
+            settings,
+            sample_backend: backend.clone(),
+            embed_backend: backend.clone(),
+            backend,
+            map_backend,
+            fallback: None,
+            primary_down: AtomicBool::new(false),
+            resume: false,
+            incremental: false,
+            stream: false,
+            chapters: false,
+            keywords: false,
+            entities: false,
+            headline: (false, false),
+            range: (None, None),
+            interrupt: false,
+            progress: true,
+            debug_dump: None,
+        }
+    }
+
+    /// Ask `backend` (described by `label` in warnings and outputs) whenever a request still fails
+    /// after its retries, or every time once the primary backend is found down
+    pub fn with_fallback(mut self, backend: Arc<dyn LlmBackend>, label: impl Into<String>) -> Self {
+        self.fallback = Some(Fallback {
+            backend,
+            label: label.into(),
+        });
+        self
+    }
+
+    /// Continue interrupted file runs from their checkpoint instead of starting over
+    pub fn with_resume(mut self, resume: bool) -> Self {
+        self.resume = resume;
+        self
+    }
+
+    /// Summarize only the cues a subtitle file gained since its last incremental run, merging
+    /// their summary into that run's, which the file's state file keeps
+    pub fn with_incremental(mut self, incremental: bool) -> Self {
+        self.incremental = incremental;
+        self
+    }
+
+    /// Print the final summary to stderr as the backend streams it in
+    pub fn with_stream(mut self, stream: bool) -> Self {
+        self.stream = stream;
+        self
+    }
+
+    /// Also generate chapter markers for subtitle files
+    pub fn with_chapters(mut self, chapters: bool) -> Self {
+        self.chapters = chapters;
+        self
+    }
+
+    /// Also extract the key topics of every summary
+    pub fn with_keywords(mut self, keywords: bool) -> Self {
+        self.keywords = keywords;
+        self
+    }
+
+    /// Also list the people, organizations, products, and numbers and dates every input mentions
+    pub fn with_entities(mut self, entities: bool) -> Self {
+        self.entities = entities;
+        self
+    }
+
+    /// Also generate a one-line title and a one-sentence TL;DR for every summary
+    pub fn with_headline(mut self, title: bool, tldr: bool) -> Self {
+        self.headline = (title, tldr);
+        self
+    }
+
+    /// Only summarize the cues of subtitle files between `from` and `to`; plain text is rejected
+    pub fn with_range(mut self, from: Option<Duration>, to: Option<Duration>) -> Self {
+        self.range = (from, to);
+        self
+    }
+
+    /// On Ctrl+C, stop with a `SummarizerError::Interrupted` holding the finished chunk
+    /// summaries instead of being killed; a file's checkpoint is kept for `--resume`
+    pub fn with_interrupt(mut self, interrupt: bool) -> Self {
+        self.interrupt = interrupt;
+        self
+    }
+
+    /// Write every prompt and raw reply to a directory, numbered in the order they are sent
+    pub fn with_debug_dump(mut self, debug_dump: Option<DebugDump>) -> Self {
+        self.debug_dump = debug_dump;
+        self
+    }
+
+    /// Draw progress bars for the map and refine steps (on by default)
+    pub fn with_progress(mut self, progress: bool) -> Self {
+        self.progress = progress;
+        self
+    }
+
+    /// Chapters are generated on request, and always for show notes, which list them
+    fn wants_chapters(&self) -> bool {
+        self.chapters || self.settings.mode == Mode::Shownotes
+    }
+
+    /// Key topics are extracted on request, and always for Obsidian notes, which use them as tags
+    fn wants_keywords(&self) -> bool {
+        self.keywords || self.settings.format == OutputFormat::Obsidian
+    }
+
+    pub fn settings(&self) -> &Settings {
+        &self.settings
+    }
+
+    /// Let the backends verify they are ready (e.g. the models are installed) before any file is
+    /// read; when the primary one isn't, every request goes to the fallback, if there is one
+    pub async fn prepare(&self) -> Result<(), Box<dyn Error>> {
+        let primary = async {
+            if !Arc::ptr_eq(&self.map_backend, &self.backend) {
+                self.map_backend.prepare().await?;
+            }
+            self.backend.prepare().await
+        };
+        match (primary.await, &self.fallback) {
+            (Ok(()), _) => Ok(()),
+            (Err(e), Some(fallback)) => {
+                warn!("{}; sending every request to the fallback {}", e, fallback.label);
+                self.primary_down.store(true, Ordering::Relaxed);
+                fallback.backend.prepare().await.map_err(|e| e as Box<dyn Error>)
+            }
+            (Err(e), None) => Err(e as Box<dyn Error>),
+        }
+    }
+
+    /// The backend answering requests of `stage`
+    fn backend_for(&self, stage: &str) -> &Arc<dyn LlmBackend> {
+        match stage {
+            "map" => &self.map_backend,
+            "sample" => &self.sample_backend,
+            _ => &self.backend,
+        }
+    }
+
+    /// Run `op` against the backend of `stage` with retries, then against the fallback backend if
+    /// it still fails (unless the prompt is over the context window, which callers split
+    /// instead); also returns whether the fallback answered. Every attempt is cancelled after
+    /// `--request-timeout`, which counts as a failure to retry, and waits for `--rpm`/`--tpm`
+    /// to allow sending `prompt`.
+    async fn run_with_fallback<T, F, Fut>(
+        &self,
+        stage: &str,
+        what: &str,
+        prompt: &str,
+        usage: &UsageMeter,
+        op: F,
+    ) -> Result<(T, bool), BackendError>
+    where
+        F: Fn(Arc<dyn LlmBackend>) -> Fut,
+        Fut: Future<Output = Result<T, BackendError>>,
+    {
+        let prompt_tokens = self.rate_limit.as_ref().map_or(0, |_| chunker::count_tokens(prompt));
+        let retry = self.settings.retry_policy();
+        let Some(fallback) = &self.fallback else {
+            return retry
+                .run(what, || self.attempt(prompt_tokens, op(self.backend_for(stage).clone())))
+                .await
+                .map(|reply| (reply, false));
+        };
+        if !self.primary_down.load(Ordering::Relaxed) {
+            match retry.run(what, || self.attempt(prompt_tokens, op(self.backend_for(stage).clone()))).await {
+                Ok(reply) => return Ok((reply, false)),
+                Err(e) if error::is_overflow(e.as_ref()) => return Err(e),
+                Err(e) => {
+                    warn!("{} failed: {}; asking the fallback {}", what, e, fallback.label);
+                    if error::is_unreachable(e.as_ref()) && !self.primary_down.swap(true, Ordering::Relaxed) {
+                        warn!("Sending the remaining requests to the fallback {}", fallback.label);
+                    }
+                }
+            }
+        }
+        let reply = retry.run(what, || self.attempt(prompt_tokens, op(fallback.backend.clone()))).await?;
+        usage.record_fallback(what);
+        Ok((reply, true))
+    }
+
+    /// `request` once fewer than `--concurrency` others are in flight and the rate limit allows
+    /// it, failing with `SummarizerError::RequestTimeout` after `--request-timeout`
+    async fn attempt<T>(
+        &self,
+        prompt_tokens: usize,
+        request: impl Future<Output = Result<T, BackendError>>,
+    ) -> Result<T, BackendError> {
+        let _permit = self.in_flight.acquire().await?;
+        if let Some(rate_limit) = &self.rate_limit {
+            rate_limit.acquire(prompt_tokens).await;
+        }
+        let Some(seconds) = self.settings.request_timeout else {
+            return request.await;
+        };
+        tokio::time::timeout(Duration::from_secs(seconds), request)
+            .await
+            .unwrap_or_else(|_| Err(SummarizerError::RequestTimeout(seconds).into()))
+    }
+
+    /// Read, clean and summarize a file, checkpointing the map step next to it
+    pub async fn summarize_file(&self, input_path: &Path) -> Result<Summary, Box<dyn Error>> {
+        if !input_path.exists() {
+            return Err(SummarizerError::FileNotFound(input_path.to_path_buf()).into());
+        }
+        info!("Processing file: {:?}", input_path);
+        let start_time = Instant::now();
+
+        let streamed = self.streamed_size(input_path)?;
+        let mut summary = match streamed {
+            Some(size) => self.summarize_streamed(input_path, size).await?,
+            None => {
+                let transcript = self.load(input_path).await?;
+                self.summarize_loaded(transcript, Some(input_path)).await?
+            }
+        };
+        summary.input = Some(input_path.to_path_buf());
+        summary.duration = start_time.elapsed();
+        Ok(summary)
+    }
+
+    /// Summarize a parsed transcript that doesn't come from a file, without checkpointing;
+    /// subtitle cues still provide timestamps, speakers and chapters
+    pub async fn summarize_transcript(&self, transcript: &Transcript) -> Result<Summary, Box<dyn Error>> {
+        let start_time = Instant::now();
+        let mut summary = self.summarize_loaded(transcript.clone(), None).await?;
+        summary.duration = start_time.elapsed();
+        Ok(summary)
+    }
+
+    /// Summarize several files as one text, in order, such as the parts of a lecture series.
+    /// Every file starts a section that chunks prefer to end before; timed subtitles are read as
+    /// plain text and nothing is checkpointed.
+    pub async fn summarize_files(&self, input_paths: &[PathBuf]) -> Result<Summary, Box<dyn Error>> {
+        let start_time = Instant::now();
+        let transcript = self.load_merged(input_paths).await?;
+        let mut summary = self.summarize_loaded(transcript, None).await?;
+        summary.sources = input_paths.to_vec();
+        summary.duration = start_time.elapsed();
+        Ok(summary)
+    }
+
+    /// Compare the final summaries of two inputs in one more request, for what only one of them
+    /// covers and the decisions that changed between them; `names` label the inputs in the report
+    pub async fn compare(
+        &self,
+        a: &Summary,
+        b: &Summary,
+        names: (String, String),
+    ) -> Result<(Comparison, UsageReport), Box<dyn Error>> {
+        let lang = self
+            .settings
+            .translate
+            .clone()
+            .or_else(|| self.settings.lang.clone())
+            .or_else(|| Language::detect(&a.final_summary));
+        let prompt = localize(lang.as_ref(), compare::prompt(&a.final_summary, &b.final_summary, &names));
+        let usage = UsageMeter::default();
+        let reply = self
+            .request("compare", "Comparison", &prompt, &usage)
+            .await
+            .map_err(|e| e as Box<dyn Error>)?;
+        let comparison = compare::parse(&reply, names);
+        if comparison.is_empty() {
+            warn!("The model's reply listed no differences");
+        }
+        Ok((comparison, usage.into_report()))
+    }
+
+    /// Synthesize the summaries of the episodes of a series into an overview of all of them, with
+    /// the reduce and combine steps of the map-reduce strategy and the rollup prompt; `dir` is
+    /// where the episode summaries were found
+    pub async fn rollup(&self, dir: &Path, episodes: &[Episode]) -> Result<Summary, Box<dyn Error>> {
+        if episodes.is_empty() {
+            return Err(format!("No episode summaries (<stem>_summary.txt, .md or .json) found in {:?}", dir).into());
+        }
+        let start = Instant::now();
+        let summaries: Vec<String> = episodes
+            .iter()
+            .map(|episode| format!("{}:\n{}", episode.name, episode.summary))
+            .collect();
+        let lang = self
+            .settings
+            .lang
+            .clone()
+            .or_else(|| Language::detect(&summaries.join("\n\n")));
+        info!("Synthesizing {} episode summaries", summaries.len());
+        let usage = UsageMeter::default();
+        let final_summary = self
+            .combine(summaries.clone(), rollup::ROLLUP_TEMPLATE, lang.as_ref(), &usage)
+            .await?;
+        Ok(Summary {
+            input: Some(dir.to_path_buf()),
+            title: dir.file_name().map(|name| name.to_string_lossy().into_owned()),
+            sources: episodes.iter().map(|episode| episode.path.clone()).collect(),
+            model: self.settings.model_label(),
+            mode: self.settings.mode,
+            by_speaker: false,
+            chunk_summaries: summaries,
+            chunk_starts: Vec::new(),
+            final_summary,
+            chapters: None,
+            keywords: None,
+            entities: None,
+            headline: None,
+            translated_transcript: None,
+            video_url: None,
+            deck_name: None,
+            media_length: None,
+            duration: start.elapsed(),
+            generated_at: Local::now(),
+            usage: usage.into_report(),
+            fallback: self.fallback.as_ref().map(|fallback| fallback.label.clone()),
+            skipped_chunks: Vec::new(),
+        })
+    }
+
+    /// Answer `question` about a file from the `top_k` chunks whose embeddings are closest to it,
+    /// citing their time ranges
+    pub async fn ask(&self, input_path: &Path, question: &str, top_k: usize) -> Result<(Answer, UsageReport), Box<dyn Error>> {
+        if !input_path.exists() {
+            return Err(SummarizerError::FileNotFound(input_path.to_path_buf()).into());
+        }
+        info!("Processing file: {:?}", input_path);
+        let transcript = self.load(input_path).await?;
+        let transcript = self.clean(transcript, &display_name(Some(input_path)))?;
+        let timing = transcript.cues.as_ref().map(|cues| (cues, parser::word_cues(cues)));
+        let word_cues = timing.as_ref().map(|(_, word_cues)| word_cues.as_slice());
+        let usage = UsageMeter::default();
+        let boundaries = self.semantic_boundaries(&transcript.text, word_cues, &transcript.sections, &usage).await?;
+        let (chunks, windows) = self.split(&transcript.text, word_cues, &transcript.sections, boundaries.as_deref())?;
+        if chunks.is_empty() {
+            return Err(format!("{:?} has no text to answer from", input_path).into());
+        }
+
+        let mut texts = chunks.clone();
+        texts.push(question.to_string());
+        let mut vectors = self.embed(&texts, &usage).await?;
+        let query = vectors.pop().unwrap_or_default();
+        let mut passages: Vec<Passage> = ask::nearest(&query, &vectors, top_k)
+            .into_iter()
+            .map(|(index, score)| {
+                let range = timing.as_ref().map(|(cues, word_cues)| {
+                    (cues[word_cues[windows[index].start]].start, cues[word_cues[windows[index].end - 1]].end)
+                });
+                Passage {
+                    index,
+                    start: range.map(|(start, _)| start),
+                    end: range.map(|(_, end)| end),
+                    score,
+                    text: chunks[index].clone(),
+                }
+            })
+            .collect();
+        passages.sort_by_key(|passage| passage.index);
+        info!("Answering from {} of {} chunks", passages.len(), chunks.len());
+
+        // In the language of the question, which may differ from the transcript's
+        let lang = self.settings.lang.clone().or_else(|| Language::detect(question));
+        let prompt = localize(lang.as_ref(), ask::prompt(question, &passages));
+        let reply = self
+            .request("ask", "Answer", &prompt, &usage)
+            .await
+            .map_err(|e| e as Box<dyn Error>)?;
+        let answer = Answer {
+            question: question.to_string(),
+            answer: reply.trim().to_string(),
+            passages,
+        };
+        Ok((answer, usage.into_report()))
+    }
+
+    /// Embeddings of `texts` with the embedding model, in batches, from the cache where an
+    /// earlier `ask` stored them
+    async fn embed(&self, texts: &[String], usage: &UsageMeter) -> Result<Vec<Vec<f32>>, Box<dyn Error>> {
+        let cache_model = format!("{} (embeddings)", self.settings.embedding_model());
+        let mut vectors: Vec<Option<Vec<f32>>> = texts
+            .iter()
+            .map(|text| self.cache.get(&cache_model, text).and_then(|cached| serde_json::from_str(&cached).ok()))
+            .collect();
+        let missing: Vec<usize> = (0..texts.len()).filter(|&i| vectors[i].is_none()).collect();
+        if missing.len() < texts.len() {
+            info!("{} of {} embeddings from the cache", texts.len() - missing.len(), texts.len());
+        }
+        let retry = self.settings.retry_policy();
+        for batch in missing.chunks(EMBED_BATCH) {
+            let batch_texts: Vec<String> = batch.iter().map(|&i| texts[i].clone()).collect();
+            let prompt_tokens = match &self.rate_limit {
+                Some(_) => batch_texts.iter().map(|text| chunker::count_tokens(text)).sum(),
+                None => 0,
+            };
+            let embeddings = retry
+                .run("Embedding", || self.attempt(prompt_tokens, self.embed_backend.embed(&batch_texts)))
+                .await
+                .map_err(|e| e as Box<dyn Error>)?;
+            if embeddings.vectors.len() != batch.len() {
+                return Err(format!(
+                    "The embeddings API returned {} vectors for {} texts",
+                    embeddings.vectors.len(),
+                    batch.len()
+                )
+                .into());
+            }
+            let tokens = embeddings
+                .usage
+                .unwrap_or_else(|| TokenUsage::estimate(&batch_texts.join("\n"), ""));
+            self.record("embed", tokens, usage);
+            for (&i, vector) in batch.iter().zip(embeddings.vectors) {
+                self.cache.put(&cache_model, &texts[i], &serde_json::to_string(&vector)?);
+                vectors[i] = Some(vector);
+            }
+        }
+        Ok(vectors.into_iter().flatten().collect())
+    }
+
+    /// Read a file, transcribing recordings first
+    async fn load(&self, input_path: &Path) -> Result<Transcript, Box<dyn Error>> {
+        let extension = input_path.extension().and_then(|e| e.to_str()).unwrap_or("");
+        if parser::is_audio(extension) {
+            Transcriber::new(&self.settings).transcribe(input_path).await
+        } else {
+            parser::load_transcript(input_path)
+        }
+    }
+
+    /// Cleaned transcripts of `input_paths`, concatenated for `summarize_files`
+    async fn load_merged(&self, input_paths: &[PathBuf]) -> Result<Transcript, Box<dyn Error>> {
+        let mut parts = Vec::with_capacity(input_paths.len());
+        for input_path in input_paths {
+            if !input_path.exists() {
+                return Err(SummarizerError::FileNotFound(input_path.clone()).into());
+            }
+            info!("Processing file: {:?}", input_path);
+            let transcript = self.load(input_path).await?;
+            parts.push(self.clean(transcript, &display_name(Some(input_path)))?);
+        }
+        Ok(parser::concatenate(parts))
+    }
+
+    /// Shared body of `summarize_file` and `summarize_transcript`; only file input is checkpointed
+    async fn summarize_loaded(
+        &self,
+        transcript: Transcript,
+        input_path: Option<&Path>,
+    ) -> Result<Summary, Box<dyn Error>> {
+        let name = display_name(input_path);
+        let transcript = self.clean(transcript, &name)?;
+        let lang = self.language_for(&transcript.text);
+        let media_length = transcript.cues.as_ref().and_then(|cues| cues.iter().map(|cue| cue.end).max());
+        let cue_count = transcript.cues.as_ref().map_or(0, Vec::len);
+        let key = self.incremental_key(lang.as_ref());
+        let (transcript, previous) = match input_path.filter(|_| self.incremental) {
+            Some(input_path) => self.new_cues(transcript, input_path, &name, &key)?,
+            None => (transcript, None),
+        };
+        let usage = UsageMeter::default();
+        let boundaries = match self.settings.chunk_minutes.filter(|_| transcript.cues.is_some()) {
+            // Time windows don't move to topic changes
+            Some(_) => None,
+            None => {
+                let word_cues = transcript.cues.as_deref().map(parser::word_cues);
+                self.semantic_boundaries(&transcript.text, word_cues.as_deref(), &transcript.sections, &usage)
+                    .await?
+            }
+        };
+        let (chunks, windows) = self.chunk_transcript(&transcript, &name, boundaries.as_deref())?;
+        let timing = transcript.cues.as_ref().map(|cues| (cues, parser::word_cues(cues)));
+        let mut checkpoint = match input_path {
+            Some(input_path) => Checkpoint::open(
+                input_path,
+                &chunks,
+                &self.settings.model_label(),
+                &self.chunk_prompts(lang.as_ref()),
+                self.resume,
+            )?,
+            None => Checkpoint::in_memory(chunks.len()),
+        };
+
+        let finished = async {
+            let mut summary = self
+                .run(chunks.into_iter().map(Ok), &mut checkpoint, previous.as_ref(), lang.as_ref(), &usage)
+                .await?;
+            // Refine keeps running summaries, not one summary per chunk
+            if let (Some((cues, word_cues)), Strategy::MapReduce) = (&timing, self.settings.strategy) {
+                let starts = windows.iter().map(|range| cues[word_cues[range.start]].start);
+                let earlier = previous.iter().flat_map(|previous| previous.chunk_starts.iter().copied());
+                summary.chunk_starts = earlier.chain(starts).collect();
+            }
+            if self.wants_chapters() {
+                match &timing {
+                    Some(_) if self.settings.strategy == Strategy::Refine => {
+                        warn!("Chapters need the map-reduce strategy; skipping chapters")
+                    }
+                    Some(_) => {
+                        let chapters = self
+                            .chapters(&summary.chunk_starts, &summary.chunk_summaries, lang.as_ref(), &usage)
+                            .await?;
+                        summary.chapters = Some(chapters);
+                    }
+                    None => warn!("{} has no timestamps; skipping chapters", name),
+                }
+            }
+            if let (Some(target), true) = (&self.settings.translate, self.settings.translate_transcript) {
+                summary.translated_transcript = Some(self.translate_transcript(&transcript.text, target, &usage).await?);
+            }
+            Ok::<_, Box<dyn Error>>(summary)
+        };
+        let mut summary = match self.unless_stopped(finished).await {
+            Ok(summary) => summary?,
+            Err(Stop::Interrupted) => return Err(self.interrupted(&checkpoint).into()),
+            Err(Stop::TimedOut) => return Err(self.timed_out(&checkpoint).into()),
+        };
+        // Plain text has no cues to continue from next time
+        match input_path.filter(|_| self.incremental && cue_count > 0) {
+            Some(_) => checkpoint.keep_incremental(Incremental {
+                key,
+                cues: cue_count,
+                chunk_summaries: summary.chunk_summaries.clone(),
+                chunk_starts: summary.chunk_starts.clone(),
+                final_summary: summary.final_summary.clone(),
+            })?,
+            None => checkpoint.remove()?,
+        }
+        summary.media_length = media_length;
+        summary.usage = usage.into_report();
+        Ok(summary)
+    }
+
+    /// The size of `input_path` when it is plain text above `stream_above_mb`, to be read a chunk
+    /// at a time; with settings that need all of its text at once it is loaded whole after a
+    /// warning
+    fn streamed_size(&self, input_path: &Path) -> Result<Option<u64>, Box<dyn Error>> {
+        let extension = input_path.extension().and_then(|e| e.to_str()).unwrap_or("");
+        let size = fs::metadata(input_path)?.len();
+        if !parser::is_plain_text(extension) || size <= self.settings.stream_above_mb * 1_000_000 {
+            return Ok(None);
+        }
+        let whole = if self.splits_by_topic() {
+            Some("splitting it by topic")
+        } else if self.settings.translate.is_some() && self.settings.translate_transcript {
+            Some("translating the transcript")
+        } else if !parser::starts_as_utf8(input_path)? {
+            Some("detecting its encoding")
+        } else {
+            None
+        };
+        match whole {
+            Some(reason) => {
+                warn!(
+                    "{} is {}, but {} needs all of it in memory; loading it whole",
+                    display_name(Some(input_path)),
+                    cache::format_size(size),
+                    reason
+                );
+                Ok(None)
+            }
+            None => Ok(Some(size)),
+        }
+    }
+
+    /// Summarize a plain-text file of `size` bytes a chunk at a time, so memory stays
+    /// proportional to a chunk rather than to the file: a first pass counts the chunks and keys
+    /// the checkpoint, and the per-chunk step reads them again as it gets to them
+    async fn summarize_streamed(&self, input_path: &Path, size: u64) -> Result<Summary, Box<dyn Error>> {
+        let name = display_name(Some(input_path));
+        if self.range != (None, None) {
+            return Err(format!("{} has no timestamps to apply --from/--to to", name).into());
+        }
+        info!("{} is {}; reading it a chunk at a time", name, cache::format_size(size));
+        self.warn_untimed(&name);
+        let mut chunks = self.stream_chunks(input_path)?;
+        let first = chunks.next().transpose()?;
+        let lang = self.language_for(first.as_deref().unwrap_or(""));
+        let mut key = RunKey::new(&self.settings.model_label(), &self.chunk_prompts(lang.as_ref()));
+        let mut total = 0;
+        for chunk in first.map(Ok).into_iter().chain(chunks) {
+            key.add(&chunk?);
+            total += 1;
+        }
+        info!("Split into {} chunks", total);
+        let mut checkpoint = Checkpoint::open_with_key(input_path, key.finish(), total, self.resume)?;
+
+        let usage = UsageMeter::default();
+        let chunks = self.stream_chunks(input_path)?;
+        let finished = self.run(chunks, &mut checkpoint, None, lang.as_ref(), &usage);
+        let mut summary = match self.unless_stopped(finished).await {
+            Ok(summary) => summary?,
+            Err(Stop::Interrupted) => return Err(self.interrupted(&checkpoint).into()),
+            Err(Stop::TimedOut) => return Err(self.timed_out(&checkpoint).into()),
+        };
+        checkpoint.remove()?;
+        summary.usage = usage.into_report();
+        Ok(summary)
+    }
+
+    /// The chunks `split` cuts the plain text of `input_path` into, read as they are needed
+    fn stream_chunks(&self, input_path: &Path) -> io::Result<impl Iterator<Item = io::Result<String>> + Send + '_> {
+        let reader = BufReader::new(File::open(input_path)?);
+        // Every word costs at least one token, so no chunk holds more words than its budget
+        let look_ahead = self.settings.max_tokens_per_chunk.unwrap_or(self.settings.chunk_size) + 1;
+        Ok(chunker::StreamedChunks::new(reader, look_ahead, |words: &[&str]| {
+            self.windows(words, None, &[], None)
+        }))
+    }
+
+    /// The warnings `summarize_loaded` gives plain text for the settings that need timestamps or
+    /// cues, for streamed text
+    fn warn_untimed(&self, name: &str) {
+        if self.settings.chunk_minutes.is_some() {
+            warn!("{} has no timestamps; splitting by chunk size instead of --chunk-minutes", name);
+        }
+        if self.settings.timestamps {
+            warn!("{} has no timestamps; bullets will not carry time ranges", name);
+        } else if self.settings.mode == Mode::Topics {
+            warn!("{} has no timestamps; topics will not carry start times", name);
+        }
+        if self.incremental {
+            warn!("{} has no subtitle cues to continue from; summarizing all of it", name);
+        }
+        if self.wants_chapters() {
+            warn!("{} has no timestamps; skipping chapters", name);
+        }
+    }
+
+    /// Await `work` unless Ctrl+C (when handled) or `--total-timeout` stops it first
+    async fn unless_stopped<T>(&self, work: impl Future<Output = T>) -> Result<T, Stop> {
+        let interrupted = async {
+            match self.interrupt {
+                true => tokio::signal::ctrl_c().await,
+                false => std::future::pending().await,
+            }
+        };
+        let deadline = async {
+            match self.settings.total_timeout {
+                Some(seconds) => tokio::time::sleep(Duration::from_secs(seconds)).await,
+                None => std::future::pending().await,
+            }
+        };
+        tokio::select! {
+            done = work => Ok(done),
+            _ = interrupted => Err(Stop::Interrupted),
+            _ = deadline => Err(Stop::TimedOut),
+        }
+    }
+
+    /// The error for a run stopped by Ctrl+C, with what the finished chunks produced
+    fn interrupted(&self, checkpoint: &Checkpoint) -> SummarizerError {
+        SummarizerError::Interrupted {
+            completed: checkpoint.completed(),
+            total: checkpoint.total(),
+            partial: self.partial(checkpoint),
+        }
+    }
+
+    /// The error for a run stopped by `--total-timeout`, like `interrupted`
+    fn timed_out(&self, checkpoint: &Checkpoint) -> SummarizerError {
+        SummarizerError::TimedOut {
+            seconds: self.settings.total_timeout.unwrap_or_default(),
+            completed: checkpoint.completed(),
+            total: checkpoint.total(),
+            partial: self.partial(checkpoint),
+        }
+    }
+
+    /// What the finished chunks of a stopped run produced
+    fn partial(&self, checkpoint: &Checkpoint) -> String {
+        let summaries = checkpoint.summaries();
+        match self.settings.strategy {
+            // Every refine step revises the one before, so the last covers all finished chunks
+            Strategy::Refine => summaries.last().cloned().unwrap_or_default(),
+            Strategy::MapReduce => summaries.join("\n\n"),
+        }
+    }
+
+    /// Read, clean and chunk a file like `summarize_file` would, without calling the model;
+    /// recordings are still transcribed
+    pub async fn plan_file(&self, input_path: &Path) -> Result<Plan, Box<dyn Error>> {
+        if !input_path.exists() {
+            return Err(SummarizerError::FileNotFound(input_path.to_path_buf()).into());
+        }
+        info!("Processing file: {:?}", input_path);
+        self.plan_loaded(self.load(input_path).await?, Some(input_path))
+    }
+
+    /// Clean and chunk a transcript like `summarize_transcript` would, without calling the model
+    pub fn plan_transcript(&self, transcript: &Transcript) -> Result<Plan, Box<dyn Error>> {
+        self.plan_loaded(transcript.clone(), None)
+    }
+
+    /// Load and chunk several files like `summarize_files` would, without calling the model
+    pub async fn plan_files(&self, input_paths: &[PathBuf]) -> Result<Plan, Box<dyn Error>> {
+        self.plan_loaded(self.load_merged(input_paths).await?, None)
+    }
+
+    fn plan_loaded(&self, transcript: Transcript, input_path: Option<&Path>) -> Result<Plan, Box<dyn Error>> {
+        let name = display_name(input_path);
+        let transcript = self.clean(transcript, &name)?;
+        let lang = self.language_for(&transcript.text);
+        let (transcript, previous) = match input_path.filter(|_| self.incremental) {
+            Some(input_path) => self.new_cues(transcript, input_path, &name, &self.incremental_key(lang.as_ref()))?,
+            None => (transcript, None),
+        };
+        // Without embeddings, semantic chunks are planned as the sentence chunks they fall back to
+        if self.splits_by_topic() {
+            info!("The plan shows sentence chunks; splitting by topic moves their ends to where the topic changes");
+        }
+        let (chunks, windows) = self.chunk_transcript(&transcript, &name, None)?;
+        let timing = transcript.cues.as_ref().map(|cues| (cues, parser::word_cues(cues)));
+
+        let count = chunks.len();
+        let planned: Vec<PlannedChunk> = chunks
+            .into_iter()
+            .zip(&windows)
+            .enumerate()
+            .map(|(i, (text, range))| {
+                // Refine prompts depend on the running summary, so only its first step can be
+                // cached; a lone chunk is the final step of either strategy and never is
+                let cacheable = match self.settings.strategy {
+                    Strategy::MapReduce => count > 1 || self.settings.always_reduce,
+                    Strategy::Refine => i == 0 && count > 1,
+                };
+                let prompt = localize(lang.as_ref(), self.focus(render_prompt(&self.settings.map_prompt, &text)));
+                PlannedChunk {
+                    words: range.len(),
+                    tokens: chunker::count_tokens(&prompt),
+                    span: timing.as_ref().map(|(cues, word_cues)| {
+                        (cues[word_cues[range.start]].start, cues[word_cues[range.end - 1]].end)
+                    }),
+                    cached: cacheable && self.cache.get(&self.map_cache_model, &prompt).is_some(),
+                    text,
+                }
+            })
+            .collect();
+
+        let uncached = planned.iter().filter(|chunk| !chunk.cached).count();
+        // The final request, or its samples and the consensus request
+        let finals = match self.settings.samples {
+            1 => 1,
+            samples => samples + 1,
+        };
+        let requests = match self.settings.strategy {
+            // Every chunk, then the combine request unless there is only one chunk
+            Strategy::MapReduce => {
+                let combined = count > 1 || (count == 1 && (self.settings.always_reduce || self.settings.samples > 1));
+                uncached + if combined { finals } else { 0 }
+            }
+            Strategy::Refine => uncached + finals - 1,
+        } + usize::from(previous.is_some()) * finals
+            + usize::from(self.wants_chapters() && timing.is_some() && self.settings.strategy == Strategy::MapReduce)
+            + usize::from(self.settings.polish && self.settings.mode.is_bulleted())
+            + usize::from(self.wants_keywords())
+            + usize::from(self.entities)
+            + usize::from(self.headline.0 || self.headline.1)
+            + match (&self.settings.translate, self.settings.translate_transcript) {
+                (Some(_), true) => 1 + self.translation_chunks(&transcript.text).len(),
+                (Some(_), false) => 1,
+                (None, _) => 0,
+            };
+        Ok(Plan {
+            chunks: planned,
+            requests,
+        })
+    }
+
+    /// Report the encoding of a transcoded file, clip it to the requested time range and drop
+    /// sound descriptions unless kept; `name` identifies it in errors
+    fn clean(&self, transcript: Transcript, name: &str) -> Result<Transcript, Box<dyn Error>> {
+        if let Some(encoding) = transcript.encoding {
+            info!("Detected encoding: {}", encoding);
+        }
+        let transcript = match self.range {
+            (None, None) => transcript,
+            (from, to) => {
+                let clipped = parser::clip(transcript, from, to)
+                    .ok_or_else(|| format!("{} has no timestamps to apply --from/--to to", name))?;
+                if clipped.text.is_empty() {
+                    return Err(format!("{} has no cues {}", name, describe_range(from, to)).into());
+                }
+                clipped
+            }
+        };
+        Ok(if self.settings.keep_sfx {
+            transcript
+        } else {
+            parser::strip_sound_descriptions(transcript)
+        })
+    }
+
+    /// Split a cleaned transcript into the chunks sent to the model, with time markers or a
+    /// speaker label added when the settings ask for them; `name` identifies it in warnings, and
+    /// `boundaries` are the passage boundaries of `--split semantic`
+    fn chunk_transcript(
+        &self,
+        transcript: &Transcript,
+        name: &str,
+        boundaries: Option<&[(usize, f32)]>,
+    ) -> Result<Chunks, Box<dyn Error>> {
+        // Index of the cue every word came from, for subtitle files
+        let timing = transcript.cues.as_ref().map(|cues| (cues, parser::word_cues(cues)));
+        let by_time = self.settings.chunk_minutes.filter(|_| timing.is_some());
+        let (mut chunks, windows) = match (by_time, &timing) {
+            (Some(minutes), Some((cues, word_cues))) => split_by_time(&transcript.text, cues, word_cues, minutes),
+            _ => {
+                if self.settings.chunk_minutes.is_some() {
+                    warn!("{} has no timestamps; splitting by chunk size instead of --chunk-minutes", name);
+                }
+                let word_cues = timing.as_ref().map(|(_, w)| w.as_slice());
+                self.split(&transcript.text, word_cues, &transcript.sections, boundaries)?
+            }
+        };
+        // Topics are headed with the marker where they start
+        let timestamped = self.settings.timestamps || self.settings.mode == Mode::Topics;
+        if timestamped {
+            match &timing {
+                Some((cues, word_cues)) => {
+                    let words: Vec<&str> = transcript.text.split_whitespace().collect();
+                    chunks = windows
+                        .iter()
+                        .map(|range| timestamped_chunk(&words, cues, word_cues, range.clone()))
+                        .collect();
+                }
+                None if self.settings.timestamps => warn!("{} has no timestamps; bullets will not carry time ranges", name),
+                None => warn!("{} has no timestamps; topics will not carry start times", name),
+            }
+        }
+        if self.settings.by_speaker {
+            match &timing {
+                Some((cues, word_cues)) if parser::has_speakers(cues) => {
+                    for (chunk, range) in chunks.iter_mut().zip(&windows) {
+                        *chunk = speaker_chunk(chunk, &cues[word_cues[range.start]]);
+                    }
+                }
+                Some(_) => warn!("{} has no speaker labels; the summary cannot tell speakers apart", name),
+                None => {}
+            }
+        }
+        // Time markers already show the range
+        if let (Some(_), Some((cues, word_cues)), false) = (by_time, &timing, timestamped) {
+            for (chunk, range) in chunks.iter_mut().zip(&windows) {
+                let (start, end) = (cues[word_cues[range.start]].start, cues[word_cues[range.end - 1]].end);
+                *chunk = format!("[{}–{}] {}", parser::format_clock(start), parser::format_clock(end), chunk);
+            }
+        }
+        Ok((chunks, windows))
+    }
+
+    /// Summarize text that is already clean, without checkpointing
+    pub async fn summarize_text(&self, text: &str) -> Result<Summary, Box<dyn Error>> {
+        let start_time = Instant::now();
+        let usage = UsageMeter::default();
+        let boundaries = self.semantic_boundaries(text, None, &[], &usage).await?;
+        let (chunks, _) = self.split(text, None, &[], boundaries.as_deref())?;
+        let lang = self.language_for(text);
+        let mut checkpoint = Checkpoint::in_memory(chunks.len());
+
+        let mut summary = self
+            .run(chunks.into_iter().map(Ok), &mut checkpoint, None, lang.as_ref(), &usage)
+            .await?;
+        summary.usage = usage.into_report();
+        summary.duration = start_time.elapsed();
+        Ok(summary)
+    }
+
+    /// Summarize `chunks`, merged into the summary of the `previous` incremental run when there
+    /// is one, then add the requested extras; the chunks are read as they are summarized
+    async fn run(
+        &self,
+        chunks: impl Iterator<Item = io::Result<String>> + Send,
+        checkpoint: &mut Checkpoint,
+        previous: Option<&Incremental>,
+        lang: Option<&Language>,
+        usage: &UsageMeter,
+    ) -> Result<Summary, Box<dyn Error>> {
+        let mut summary = match self.settings.strategy {
+            Strategy::MapReduce => self.map_reduce(chunks, checkpoint, lang, usage).await?,
+            Strategy::Refine => self.refine(chunks, checkpoint, lang, usage).await?,
+        };
+        if let Some(previous) = previous {
+            summary = self.merge_previous(previous, summary, lang, usage).await?;
+        }
+        // Everything generated from here on is in the translation's language
+        let lang = match &self.settings.translate {
+            Some(target) => {
+                let translated = self.translate(&summary.final_summary, target, usage).await?;
+                summary.final_summary = self.structure(translated);
+                Some(target)
+            }
+            None => lang,
+        };
+        if self.wants_keywords() {
+            summary.keywords = Some(self.keywords(&summary.final_summary, lang, usage).await?);
+        }
+        if self.entities {
+            summary.entities = Some(self.entities(&summary, lang, usage).await?);
+        }
+        let (title, tldr) = self.headline;
+        if title || tldr {
+            summary.headline = Some(self.headline(&summary.final_summary, title, tldr, lang, usage).await?);
+        }
+        Ok(summary)
+    }
+
+    /// Combine the summary of a file's new cues with the summary of the cues before them, as the
+    /// combine step combines chunk summaries
+    async fn merge_previous(
+        &self,
+        previous: &Incremental,
+        mut summary: Summary,
+        lang: Option<&Language>,
+        usage: &UsageMeter,
+    ) -> Result<Summary, Box<dyn Error>> {
+        let joined = format!("{}\n\n{}", previous.final_summary, summary.final_summary);
+        let prompt = localize(lang, self.focus(render_prompt(&self.settings.combine_prompt, &joined)));
+        summary.final_summary = self.final_step("combine", "Merge step", &prompt, lang, usage).await?;
+        // Chunks are numbered across every run
+        let earlier = previous.chunk_summaries.len();
+        summary.skipped_chunks = summary.skipped_chunks.iter().map(|i| i + earlier).collect();
+        summary.chunk_summaries = previous
+            .chunk_summaries
+            .iter()
+            .cloned()
+            .chain(summary.chunk_summaries)
+            .collect();
+        Ok(summary)
+    }
+
+    /// The cues of a growing file that its last incremental run didn't summarize, with what that
+    /// run left in the state file; all of them when there was none, or other settings made it
+    fn new_cues(
+        &self,
+        transcript: Transcript,
+        input_path: &Path,
+        name: &str,
+        key: &str,
+    ) -> Result<(Transcript, Option<Incremental>), Box<dyn Error>> {
+        let Some(cues) = &transcript.cues else {
+            warn!("{} has no subtitle cues to continue from; summarizing all of it", name);
+            return Ok((transcript, None));
+        };
+        let previous = match Checkpoint::incremental(input_path) {
+            Some(previous) if previous.key != key => {
+                info!("The last summary of {} was made with other settings; summarizing all of it", name);
+                None
+            }
+            Some(previous) if previous.cues > cues.len() => {
+                info!("{} has fewer cues than at its last summary; summarizing all of it", name);
+                None
+            }
+            Some(previous) if previous.cues == cues.len() => {
+                return Err(SummarizerError::UpToDate(input_path.to_path_buf()).into());
+            }
+            previous => previous,
+        };
+        let Some(previous) = previous else {
+            return Ok((transcript, None));
+        };
+        info!("{} new cues after the {} summarized before", cues.len() - previous.cues, previous.cues);
+        let transcript = parser::skip_cues(transcript, previous.cues);
+        Ok((transcript, Some(previous)))
+    }
+
+    /// Extract the entities of the chunk summaries (or of the final summary, when it is all
+    /// there is) in batches of `max_reduce_tokens`, merging the lists of every batch
+    async fn entities(&self, summary: &Summary, lang: Option<&Language>, usage: &UsageMeter) -> Result<Entities, Box<dyn Error>> {
+        // Refine's chunk summaries are running summaries, the last of them the final one
+        let sources = match self.settings.strategy {
+            Strategy::MapReduce if summary.chunk_summaries.len() > 1 => summary.chunk_summaries.clone(),
+            _ => vec![summary.final_summary.clone()],
+        };
+        let batches = chunker::token_batches(&sources, self.settings.max_reduce_tokens)?;
+        let count = batches.len();
+        let mut entities = Entities::default();
+        for (i, batch) in batches.into_iter().enumerate() {
+            let prompt = localize(lang, render_prompt(entities::ENTITIES_TEMPLATE, &sources[batch].join("\n\n")));
+            let what = match count {
+                1 => "Entities".to_string(),
+                _ => format!("Entities batch {}", i + 1),
+            };
+            let reply = self
+                .request("entities", &what, &prompt, usage)
+                .await
+                .map_err(|e| e as Box<dyn Error>)?;
+            entities.merge(entities::parse(&reply));
+        }
+        if entities.is_empty() {
+            warn!("The model's reply listed no entities");
+        }
+        Ok(entities)
+    }
+
+    /// Generate the requested title and TL;DR of a final summary in one extra request
+    async fn headline(
+        &self,
+        final_summary: &str,
+        title: bool,
+        tldr: bool,
+        lang: Option<&Language>,
+        usage: &UsageMeter,
+    ) -> Result<Headline, Box<dyn Error>> {
+        let prompt = localize(lang, headline::prompt(title, tldr, final_summary));
+        let reply = self
+            .request("headline", "Title and TL;DR", &prompt, usage)
+            .await
+            .map_err(|e| e as Box<dyn Error>)?;
+        let headline = headline::parse(&reply, title, tldr);
+        if (title && headline.title.is_none()) || (tldr && headline.tldr.is_none()) {
+            warn!("The model's reply lacked the requested title or TL;DR");
+        }
+        Ok(headline)
+    }
+
+    /// Translate `text` into `target`, warning when the reply is in another language
+    async fn translate(&self, text: &str, target: &Language, usage: &UsageMeter) -> Result<String, Box<dyn Error>> {
+        let prompt = render_prompt(&TRANSLATE_TEMPLATE.replace("{language}", &target.name), text);
+        let translated = self
+            .request("translate", "Translation", &prompt, usage)
+            .await
+            .map_err(|e| e as Box<dyn Error>)?;
+        if !target.matches(&translated) {
+            warn!("The translation does not look like {}; keeping it anyway", target.name);
+        }
+        Ok(translated)
+    }
+
+    /// Translate a cleaned transcript chunk by chunk, without the overlap of map chunks, caching
+    /// every translated chunk
+    async fn translate_transcript(
+        &self,
+        text: &str,
+        target: &Language,
+        usage: &UsageMeter,
+    ) -> Result<String, Box<dyn Error>> {
+        let template = TRANSLATE_TEMPLATE.replace("{language}", &target.name);
+        let template = template.as_str();
+        let chunks = self.translation_chunks(text);
+        info!("Translating the transcript in {} chunks", chunks.len());
+        let translated: Vec<String> = stream::iter(chunks.into_iter().enumerate())
+            .map(|(i, chunk)| async move {
+                let prompt = render_prompt(template, &chunk);
+                let what = format!("Transcript chunk {}", i + 1);
+                self.request("translate", &what, &prompt, usage).await
+            })
+            .buffered(self.settings.concurrency)
+            .try_collect()
+            .await
+            .map_err(|e| e as Box<dyn Error>)?;
+        Ok(translated.join("\n\n"))
+    }
+
+    /// Sentence-aligned chunks of `chunk_size` words that don't overlap
+    fn translation_chunks(&self, text: &str) -> Vec<String> {
+        let words: Vec<&str> = text.split_whitespace().collect();
+        let breaks = chunker::breaks(&words, None, &[]);
+        chunker::sentence_windows(&vec![1; words.len()], &breaks, self.settings.chunk_size, 0)
+            .into_iter()
+            .map(|range| words[range].join(" "))
+            .collect()
+    }
+
+    /// Extract the key topics of a final summary in one extra request
+    async fn keywords(
+        &self,
+        final_summary: &str,
+        lang: Option<&Language>,
+        usage: &UsageMeter,
+    ) -> Result<Vec<String>, Box<dyn Error>> {
+        let prompt = localize(lang, render_prompt(keywords::KEYWORDS_TEMPLATE, final_summary));
+        let reply = self
+            .request("keywords", "Keywords", &prompt, usage)
+            .await
+            .map_err(|e| e as Box<dyn Error>)?;
+        let keywords = keywords::parse(&reply);
+        if keywords.is_empty() {
+            warn!("The model's reply contained no topics");
+        }
+        Ok(keywords)
+    }
+
+    /// One request with retries, its tokens counted under `stage`; see `run_with_fallback`. A
+    /// reply cached by an earlier run answers it instead, except for `--samples`.
+    async fn request(
+        &self,
+        stage: &'static str,
+        what: &str,
+        prompt: &str,
+        usage: &UsageMeter,
+    ) -> Result<String, BackendError> {
+        let cache_model = match stage {
+            "map" => Some(&self.map_cache_model),
+            "sample" => None,
+            _ => Some(&self.cache_model),
+        };
+        if let Some(reply) = cache_model.and_then(|model| self.cache.get(model, prompt)) {
+            debug!(stage, "{} answered from the cache", what);
+            return Ok(reply);
+        }
+        let start = Instant::now();
+        let dumped = self.debug_dump.as_ref().map(|dump| dump.prompt(what, prompt));
+        let completion = self
+            .run_with_fallback(stage, what, prompt, usage, |backend| async move { backend.complete_with_usage(prompt).await })
+            .await;
+        self.dump_reply(dumped.as_deref(), completion.as_ref().map(|(completion, _)| completion.text.as_str()));
+        let (completion, fell_back) = completion?;
+        let model = match &self.fallback {
+            Some(fallback) if fell_back => fallback.label.as_str(),
+            _ => self.settings.stage_model(stage),
+        };
+        let tokens = completion
+            .usage
+            .unwrap_or_else(|| TokenUsage::estimate(prompt, &completion.text));
+        debug!(
+            stage,
+            model = %model,
+            prompt_tokens = tokens.prompt_tokens,
+            completion_tokens = tokens.completion_tokens,
+            estimated = tokens.estimated,
+            elapsed_ms = start.elapsed().as_millis() as u64,
+            "{} took {:?}",
+            what,
+            start.elapsed()
+        );
+        self.record(stage, tokens, usage);
+        // The cache holds the replies of the configured models only
+        if let (Some(model), false) = (cache_model, fell_back) {
+            self.cache.put(model, prompt, &completion.text);
+        }
+        Ok(completion.text)
+    }
+
+    /// Show `--rpm`/`--tpm` waits on `progress` while it runs
+    fn show_throttling(&self, progress: &ProgressBar) {
+        if let Some(rate_limit) = &self.rate_limit {
+            rate_limit.show_on(progress);
+        }
+    }
+
+    /// Count the tokens of a reply in the usage report and against `--tpm`
+    fn record(&self, stage: &'static str, tokens: TokenUsage, usage: &UsageMeter) {
+        if let Some(rate_limit) = &self.rate_limit {
+            rate_limit.record(tokens.completion_tokens);
+        }
+        usage.record(stage, tokens);
+    }
+
+    /// Write the reply (or the error) of a request whose prompt was dumped as `dumped`
+    fn dump_reply(&self, dumped: Option<&str>, reply: Result<&str, &BackendError>) {
+        let (Some(dump), Some(name)) = (&self.debug_dump, dumped) else {
+            return;
+        };
+        match reply {
+            Ok(text) => dump.response(name, text),
+            Err(e) => dump.error(name, e.as_ref()),
+        }
+    }
+
+    /// `--lang`, or else the language detected in `text`
+    fn language_for(&self, text: &str) -> Option<Language> {
+        if let Some(lang) = &self.settings.lang {
+            return Some(lang.clone());
+        }
+        let detected = Language::detect(text);
+        if let Some(lang) = &detected {
+            info!("Detected language: {}", lang.name);
+        }
+        detected
+    }
+
+    /// Key of the summaries an incremental run can merge into: the model and the prompts of the
+    /// chunk summaries and of combining them
+    fn incremental_key(&self, lang: Option<&Language>) -> String {
+        let mut prompts = self.chunk_prompts(lang);
+        prompts.push(&self.settings.combine_prompt);
+        checkpoint::settings_key(&self.settings.model_label(), &prompts)
+    }
+
+    /// Prompts whose output the checkpoint stores, so a changed prompt, strategy, language or
+    /// focus starts over
+    fn chunk_prompts<'a>(&'a self, lang: Option<&'a Language>) -> Vec<&'a str> {
+        let settings = &self.settings;
+        let mut prompts: Vec<&str> = match settings.strategy {
+            Strategy::MapReduce => vec![&settings.map_prompt],
+            Strategy::Refine => vec![&settings.map_prompt, &settings.refine_prompt],
+        };
+        if let Some(lang) = lang {
+            prompts.push(&lang.instruction);
+        }
+        if let Some(focus) = &settings.focus {
+            prompts.push(focus);
+        }
+        prompts
+    }
+
+    /// Prepend the `--focus` instruction to a rendered map, reduce or combine prompt
+    fn focus(&self, prompt: String) -> String {
+        match &self.settings.focus {
+            Some(focus) => format!(
+                "Focus on {}: keep only what is relevant to it and leave out unrelated material. \
+                 If nothing in the text is relevant, answer \"- Nothing relevant\".\n\n{}",
+                focus, prompt
+            ),
+            None => prompt,
+        }
+    }
+
+    /// Chunk `text`, also returning the word range each chunk covers; `word_cues` (the cue of
+    /// every word of a subtitle file) lets chunks end at cue boundaries, `sections` before
+    /// headings, and `boundaries` (from `semantic_boundaries`) where the topic changes
+    fn split(
+        &self,
+        text: &str,
+        word_cues: Option<&[usize]>,
+        sections: &[usize],
+        boundaries: Option<&[(usize, f32)]>,
+    ) -> Result<Chunks, Box<dyn Error>> {
+        let words: Vec<&str> = text.split_whitespace().collect();
+        let windows = self.windows(&words, word_cues, sections, boundaries)?;
+        let chunks: Vec<String> = windows.iter().map(|range| words[range.clone()].join(" ")).collect();
+        info!("Split into {} chunks", chunks.len());
+        Ok((chunks, windows))
+    }
+
+    /// Word index ranges of the chunks `split` cuts `words` into
+    fn windows(
+        &self,
+        words: &[&str],
+        word_cues: Option<&[usize]>,
+        sections: &[usize],
+        boundaries: Option<&[(usize, f32)]>,
+    ) -> Result<Vec<Range<usize>>, Box<dyn Error>> {
+        let settings = &self.settings;
+        Ok(match (settings.split, settings.max_tokens_per_chunk) {
+            (Split::Word, Some(max_tokens)) => chunker::token_windows(words, max_tokens, settings.chunk_overlap)?,
+            (Split::Word, None) => chunker::word_windows(words.len(), settings.chunk_size, settings.chunk_overlap),
+            (Split::Sentence | Split::Semantic, max_tokens) => {
+                let breaks = chunker::breaks(words, word_cues, sections);
+                let (costs, budget) = match max_tokens {
+                    Some(max_tokens) => (chunker::word_token_counts(words)?, max_tokens),
+                    None => (vec![1; words.len()], settings.chunk_size),
+                };
+                match boundaries {
+                    Some(boundaries) => chunker::semantic_windows(&costs, &breaks, boundaries, budget, settings.chunk_overlap),
+                    None => chunker::sentence_windows(&costs, &breaks, budget, settings.chunk_overlap),
+                }
+            }
+        })
+    }
+
+    /// Whether chunks end where the topic changes: with `--split semantic`, and in topics mode
+    /// unless `--split word` is given
+    fn splits_by_topic(&self) -> bool {
+        match self.settings.split {
+            Split::Semantic => true,
+            Split::Sentence => self.settings.mode == Mode::Topics,
+            Split::Word => false,
+        }
+    }
+
+    /// For `--split semantic` and topics mode, the end of every passage of `text` but the last (its sentences or
+    /// subtitle cues, grouped to at least `SEMANTIC_PASSAGE_WORDS` words) with the similarity of
+    /// the embeddings of the passages either side of it; `None` for the other splits, or when
+    /// topics mode cannot embed
+    async fn semantic_boundaries(
+        &self,
+        text: &str,
+        word_cues: Option<&[usize]>,
+        sections: &[usize],
+        usage: &UsageMeter,
+    ) -> Result<Option<Vec<(usize, f32)>>, Box<dyn Error>> {
+        if !self.splits_by_topic() {
+            return Ok(None);
+        }
+        let words: Vec<&str> = text.split_whitespace().collect();
+        let breaks = chunker::breaks(&words, word_cues, sections);
+        let passages = chunker::passages(&breaks, SEMANTIC_PASSAGE_WORDS);
+        if passages.len() < 2 {
+            return Ok(Some(Vec::new()));
+        }
+        info!("Embedding {} passages to find where the topic changes", passages.len());
+        let texts: Vec<String> = passages.iter().map(|range| words[range.clone()].join(" ")).collect();
+        let vectors = match self.embed(&texts, usage).await {
+            Ok(vectors) => vectors,
+            // The combine step of topics mode still groups sentence chunks by topic
+            Err(e) if self.settings.split != Split::Semantic => {
+                warn!("Cannot embed the transcript: {}; leaving the topics to the model alone", e);
+                return Ok(None);
+            }
+            Err(e) => return Err(e),
+        };
+        Ok(Some(
+            passages
+                .iter()
+                .zip(vectors.windows(2))
+                .map(|(passage, pair)| (passage.end, chunker::similarity(&pair[0], &pair[1])))
+                .collect(),
+        ))
+    }
+
+    /// Turn the chunk summaries, labelled with their chunk's start time, into chapters
+    async fn chapters(
+        &self,
+        starts: &[Duration],
+        summaries: &[String],
+        lang: Option<&Language>,
+        usage: &UsageMeter,
+    ) -> Result<Vec<Chapter>, Box<dyn Error>> {
+        let sections = chapters::sections(starts, summaries);
+        let prompt = localize(lang, render_prompt(chapters::CHAPTERS_TEMPLATE, &sections));
+        let reply = self
+            .request("chapters", "Chapters", &prompt, usage)
+            .await
+            .map_err(|e| e as Box<dyn Error>)?;
+        let chapters = chapters::parse(&reply);
+        if chapters.is_empty() {
+            warn!("The model's reply contained no `MM:SS Title` chapter lines");
+        }
+        Ok(chapters)
+    }
+
+    async fn map_reduce(
+        &self,
+        mut chunks: impl Iterator<Item = io::Result<String>> + Send,
+        checkpoint: &mut Checkpoint,
+        lang: Option<&Language>,
+        usage: &UsageMeter,
+    ) -> Result<Summary, Box<dyn Error>> {
+        let start = Instant::now();
+        let (chunk_summaries, final_summary, skipped_chunks) = match checkpoint.total() {
+            // A single chunk needs no combining: its map reply is the final summary, unless the
+            // combine step is sampled
+            1 if !self.settings.always_reduce && self.settings.samples == 1 => {
+                let chunk = chunks.next().transpose()?.unwrap_or_default();
+                let prompt = localize(lang, self.focus(render_prompt(&self.settings.map_prompt, &chunk)));
+                let overflowed = match self.final_step("map", "Chunk 1", &prompt, lang, usage).await {
+                    Ok(final_summary) => Ok((vec![final_summary.clone()], final_summary, Vec::new())),
+                    Err(e) if error::is_overflow(e.as_ref()) => Err(()),
+                    Err(e) => return Err(e),
+                };
+                match overflowed {
+                    Ok(result) => result,
+                    // Too long for one request after all: split it and combine the halves
+                    Err(()) => {
+                        warn!("The transcript does not fit the model's context window; summarizing it in parts");
+                        self.map_and_combine(iter::once(Ok(chunk)), checkpoint, lang, usage).await?
+                    }
+                }
+            }
+            _ => self.map_and_combine(chunks, checkpoint, lang, usage).await?,
+        };
+
+        Ok(Summary {
+            input: None,
+            title: None,
+            sources: Vec::new(),
+            model: self.settings.model_label(),
+            mode: self.settings.mode,
+            by_speaker: self.settings.by_speaker,
+            chunk_summaries,
+            chunk_starts: Vec::new(),
+            final_summary,
+            chapters: None,
+            keywords: None,
+            entities: None,
+            headline: None,
+            translated_transcript: None,
+            video_url: self.settings.video_url.clone(),
+            deck_name: self.settings.deck_name.clone(),
+            media_length: None,
+            duration: start.elapsed(),
+            generated_at: Local::now(),
+            usage: UsageReport::default(),
+            fallback: self.fallback.as_ref().map(|fallback| fallback.label.clone()),
+            skipped_chunks,
+        })
+    }
+
+    /// Summarize every chunk, then combine the chunk summaries, returning both and the numbers
+    /// of the chunks skipped after failing
+    async fn map_and_combine(
+        &self,
+        chunks: impl Iterator<Item = io::Result<String>> + Send,
+        checkpoint: &mut Checkpoint,
+        lang: Option<&Language>,
+        usage: &UsageMeter,
+    ) -> Result<(Vec<String>, String, Vec<usize>), Box<dyn Error>> {
+        // === 1. Map step, checkpointing each finished chunk ===
+        let map_start = Instant::now();
+        let progress = progress_bar("Map", checkpoint.total(), checkpoint.completed(), self.progress)?;
+        self.show_throttling(&progress);
+
+        // Chunks are only read once a request is free for them
+        let done: Vec<bool> = (0..checkpoint.total()).map(|i| checkpoint.is_done(i)).collect();
+        let pending = chunks
+            .enumerate()
+            .filter(move |(i, _)| !done.get(*i).copied().unwrap_or(false));
+        let map_prompt = self.settings.map_prompt.as_str();
+        let mut results = stream::iter(pending)
+            .map(|(i, chunk)| async move {
+                let chunk = chunk?;
+                let prompt = localize(lang, self.focus(render_prompt(map_prompt, &chunk)));
+                if let Some(summary) = self.cache.get(&self.map_cache_model, &prompt) {
+                    return Ok((i, Some(summary), true));
+                }
+                let what = format!("Chunk {}", i + 1);
+                let summary = match self.summarize_chunk(what.clone(), chunk, lang, usage).await {
+                    Ok(summary) => summary,
+                    Err(e) if self.settings.skip_failed_chunks || error::is_blocked(e.as_ref()) => {
+                        warn!("{} failed: {}; skipping it", what, e);
+                        return Ok((i, None, false));
+                    }
+                    Err(e) => return Err(e),
+                };
+                Ok::<_, BackendError>((i, Some(summary), false))
+            })
+            .buffer_unordered(self.settings.concurrency);
+        // Chunks finish out of order; the checkpoint keeps them in transcript order. Skipped
+        // chunks stay out of it, so resuming an interrupted run tries them again
+        let mut cached = 0;
+        let mut skipped = Vec::new();
+        while let Some((i, summary, hit)) = results.try_next().await.map_err(|e| e as Box<dyn Error>)? {
+            match summary {
+                Some(summary) => checkpoint.record(i, summary)?,
+                None => skipped.push(i),
+            }
+            cached += usize::from(hit);
+            progress.inc(1);
+        }
+        progress.finish_and_clear();
+        let mut summaries = checkpoint.summaries();
+        skipped.sort_unstable();
+        for &i in &skipped {
+            summaries.insert(i, skipped_placeholder(i));
+        }
+        if cached > 0 {
+            info!("Reused {} chunk summaries from the cache", cached);
+        }
+        info!("Map step completed in {:?}", map_start.elapsed());
+
+        // === 2. Reduce and combine the summaries of the chunks that didn't fail ===
+        let level_summaries: Vec<String> = summaries
+            .iter()
+            .enumerate()
+            .filter(|(i, _)| skipped.binary_search(i).is_err())
+            .map(|(_, summary)| summary.clone())
+            .collect();
+        if level_summaries.is_empty() && !summaries.is_empty() {
+            return Err("Every chunk failed; nothing to combine".into());
+        }
+        // Overlapping chunks often yield the same bullet twice, which only lengthens the prompt
+        let level_summaries = if self.settings.dedup {
+            let (deduped, dropped) = bullets::dedup_neighbours(&level_summaries);
+            if dropped > 0 {
+                info!("Dropped {} bullets repeated from the chunk before them", dropped);
+            }
+            deduped
+        } else {
+            level_summaries
+        };
+        let final_summary = self
+            .combine(level_summaries, &self.settings.combine_prompt, lang, usage)
+            .await?;
+        Ok((summaries, final_summary, skipped.iter().map(|i| i + 1).collect()))
+    }
+
+    /// Reduce batches of `summaries` with `combine_template` until they fit in one request, then
+    /// combine them into the final summary
+    async fn combine(
+        &self,
+        mut level_summaries: Vec<String>,
+        combine_template: &str,
+        lang: Option<&Language>,
+        usage: &UsageMeter,
+    ) -> Result<String, Box<dyn Error>> {
+        // Lowered whenever a reduce batch or the combine request overflows the context window
+        let mut budget = self.settings.max_reduce_tokens;
+        let mut level = 1;
+        loop {
+            let batches = chunker::token_batches(&level_summaries, budget)?;
+            if batches.len() == level_summaries.len() && batches.len() > 1 {
+                warn!("Every summary exceeds max_reduce_tokens on its own; combining them in one request");
+            } else if batches.len() > 1 {
+                info!(
+                    "Reduce level {}: {} summaries in {} batches",
+                    level,
+                    level_summaries.len(),
+                    batches.len()
+                );
+                let current = &level_summaries;
+                let reduced: Result<Vec<String>, _> = stream::iter(batches.into_iter().enumerate())
+                    .map(|(i, batch)| async move {
+                        let joined = current[batch].join("\n\n");
+                        let prompt = localize(lang, self.focus(render_prompt(combine_template, &joined)));
+                        let what = format!("Reduce level {} batch {}", level, i + 1);
+                        self.request("reduce", &what, &prompt, usage).await
+                    })
+                    .buffered(self.settings.concurrency)
+                    .try_collect()
+                    .await;
+                match reduced.map_err(|e| e as Box<dyn Error>) {
+                    Ok(reduced) => {
+                        level_summaries = reduced;
+                        level += 1;
+                    }
+                    Err(e) if error::is_overflow(e.as_ref()) => {
+                        budget = self.smaller_budget(&level_summaries, budget / 2).ok_or(e)?;
+                        warn!(
+                            "A reduce batch does not fit the model's context window; reducing in batches of {} tokens",
+                            budget
+                        );
+                    }
+                    Err(e) => return Err(e),
+                }
+                continue;
+            }
+
+            // Combine step
+            let combined_input = level_summaries.join("\n\n");
+            let combine_prompt = localize(lang, self.focus(render_prompt(combine_template, &combined_input)));
+            match self.final_step("combine", "Combine step", &combine_prompt, lang, usage).await {
+                Ok(final_summary) => return Ok(final_summary),
+                // Fall back to reducing the summaries in batches that do fit
+                Err(e) if error::is_overflow(e.as_ref()) => {
+                    let tokens = chunker::count_tokens(&combined_input);
+                    budget = self.smaller_budget(&level_summaries, budget.min(tokens / 2)).ok_or(e)?;
+                    warn!(
+                        "The combined summaries do not fit the model's context window; reducing them in batches of {} tokens",
+                        budget
+                    );
+                }
+                Err(e) => return Err(e),
+            }
+        }
+    }
+
+    /// `budget` if batching `summaries` by it still groups some of them together, so reducing
+    /// makes progress
+    fn smaller_budget(&self, summaries: &[String], budget: usize) -> Option<usize> {
+        let batches = chunker::token_batches(summaries, budget).ok()?;
+        (batches.len() < summaries.len()).then_some(budget)
+    }
+
+    /// Summarize one chunk for the map step. A chunk too long for the model's context window is
+    /// split in half and each half summarized on its own, down to single words
+    fn summarize_chunk<'a>(
+        &'a self,
+        what: String,
+        chunk: String,
+        lang: Option<&'a Language>,
+        usage: &'a UsageMeter,
+    ) -> Pin<Box<dyn Future<Output = Result<String, BackendError>> + Send + 'a>> {
+        Box::pin(async move {
+            let prompt = localize(lang, self.focus(render_prompt(&self.settings.map_prompt, &chunk)));
+            match self.request("map", &what, &prompt, usage).await {
+                Err(e) if error::is_overflow(e.as_ref()) => {
+                    let words: Vec<&str> = chunk.split_whitespace().collect();
+                    if words.len() < 2 {
+                        return Err(e);
+                    }
+                    warn!("{} does not fit the model's context window; splitting it in half", what);
+                    let (first, second) = words.split_at(words.len() / 2);
+                    let first = self
+                        .summarize_chunk(format!("{} part 1", what), first.join(" "), lang, usage)
+                        .await?;
+                    let second = self
+                        .summarize_chunk(format!("{} part 2", what), second.join(" "), lang, usage)
+                        .await?;
+                    let summary = format!("{}\n{}", first.trim_end(), second.trim_start());
+                    // Cached under the whole chunk too, so a re-run doesn't overflow again
+                    if !usage.fell_back(&what) {
+                        self.cache.put(&self.map_cache_model, &prompt, &summary);
+                    }
+                    Ok(summary)
+                }
+                reply => reply,
+            }
+        })
+    }
+
+    /// Summarize the first chunk, then revise the running summary with each following chunk,
+    /// checkpointing the running summary after every step
+    async fn refine(
+        &self,
+        chunks: impl Iterator<Item = io::Result<String>> + Send,
+        checkpoint: &mut Checkpoint,
+        lang: Option<&Language>,
+        usage: &UsageMeter,
+    ) -> Result<Summary, Box<dyn Error>> {
+        if checkpoint.total() == 0 {
+            return Err("The input contains no text to summarize".into());
+        }
+        let refine_start = Instant::now();
+        let progress = progress_bar("Refine", checkpoint.total(), checkpoint.completed(), self.progress)?;
+        self.show_throttling(&progress);
+        let last = checkpoint.total() - 1;
+
+        // Steps run in order, so the checkpoint always holds a prefix of them; a skipped step
+        // records the running summary unchanged (empty before the first one)
+        let mut summary = checkpoint.summaries().pop().filter(|summary| !summary.is_empty());
+        let mut skipped_chunks = Vec::new();
+        for (i, chunk) in chunks.enumerate().skip(checkpoint.completed()) {
+            let chunk = chunk?;
+            let prompt = match &summary {
+                None => render_prompt(&self.settings.map_prompt, &chunk),
+                Some(summary) => render_refine_prompt(&self.settings.refine_prompt, summary, &chunk),
+            };
+            let prompt = localize(lang, self.focus(prompt));
+            let next = if i == last {
+                progress.finish_and_clear();
+                self.final_step("refine", "Refine step", &prompt, lang, usage).await?
+            } else {
+                let what = format!("Chunk {}", i + 1);
+                match self.request("refine", &what, &prompt, usage).await {
+                    Ok(next) => next,
+                    Err(e) if self.settings.skip_failed_chunks || error::is_blocked(e.as_ref()) => {
+                        warn!("{} failed: {}; skipping it", what, e);
+                        skipped_chunks.push(i + 1);
+                        checkpoint.record(i, summary.clone().unwrap_or_default())?;
+                        progress.inc(1);
+                        continue;
+                    }
+                    Err(e) => return Err(e),
+                }
+            };
+            checkpoint.record(i, next.clone())?;
+            summary = Some(next);
+            progress.inc(1);
+        }
+        progress.finish_and_clear();
+        info!("Refine completed in {:?}", refine_start.elapsed());
+
+        Ok(Summary {
+            input: None,
+            title: None,
+            sources: Vec::new(),
+            model: self.settings.model_label(),
+            mode: self.settings.mode,
+            by_speaker: self.settings.by_speaker,
+            chunk_summaries: checkpoint.summaries(),
+            chunk_starts: Vec::new(),
+            final_summary: summary.unwrap_or_default(),
+            chapters: None,
+            keywords: None,
+            entities: None,
+            headline: None,
+            translated_transcript: None,
+            video_url: self.settings.video_url.clone(),
+            deck_name: self.settings.deck_name.clone(),
+            media_length: None,
+            duration: refine_start.elapsed(),
+            generated_at: Local::now(),
+            usage: UsageReport::default(),
+            fallback: self.fallback.as_ref().map(|fallback| fallback.label.clone()),
+            skipped_chunks,
+        })
+    }
+
+    /// The request producing the final summary, retried once with a reminder when it comes back
+    /// in another language than `lang`
+    async fn final_step(
+        &self,
+        stage: &'static str,
+        what: &str,
+        prompt: &str,
+        lang: Option<&Language>,
+        usage: &UsageMeter,
+    ) -> Result<String, Box<dyn Error>> {
+        let prompt = match self.length_instruction() {
+            Some(instruction) => format!("{}\n\n{}", instruction, prompt),
+            None => prompt.to_string(),
+        };
+        let prompt = match self.settings.mode {
+            Mode::Qa => format!("Write exactly {} questions.\n\n{}", self.settings.questions, prompt),
+            _ => prompt,
+        };
+        let mut final_summary = match self.settings.samples {
+            1 => self.complete_final(stage, what, &prompt, usage).await?,
+            _ => self.consensus(what, &prompt, lang, usage).await?,
+        };
+        if let Some(lang) = lang.filter(|lang| !lang.matches(&final_summary)) {
+            warn!("The summary is not in {}; retrying once", lang.name);
+            final_summary = self
+                .complete_final(stage, what, &format!("{}\n\n{}", lang.reminder(), prompt), usage)
+                .await?;
+            if !lang.matches(&final_summary) {
+                warn!("The summary is still not in {}; keeping it anyway", lang.name);
+            }
+        }
+        let bulleted = self.settings.mode.is_bulleted();
+        if bulleted && self.settings.bullet_retry && bullets::count(&bullets::normalize(&final_summary)) == 0 {
+            warn!("The summary has no bullet points; retrying once");
+            final_summary = self
+                .complete_final(stage, what, &format!("{}\n\n{}", BULLETS_REMINDER, prompt), usage)
+                .await?;
+        }
+        let flat = |summary: &str| !outline::is_nested(&outline::parse(summary));
+        if self.settings.mode == Mode::Outline && self.settings.bullet_retry && flat(&final_summary) {
+            warn!("The outline has no sub-points; retrying once");
+            final_summary = self
+                .complete_final(stage, what, &format!("{}\n\n{}", OUTLINE_REMINDER, prompt), usage)
+                .await?;
+            if flat(&final_summary) {
+                warn!("The outline still has no sub-points; keeping it anyway");
+            }
+        }
+        if bulleted && self.settings.polish {
+            final_summary = self.polish(final_summary, lang, usage).await?;
+        }
+        let final_summary = self.limit_bullets(final_summary, lang, usage).await?;
+        Ok(self.structure(final_summary))
+    }
+
+    /// Send the final prompt `samples` times and ask the model for the version the replies agree
+    /// on; replies that are all the same need no consensus request
+    async fn consensus(
+        &self,
+        what: &str,
+        prompt: &str,
+        lang: Option<&Language>,
+        usage: &UsageMeter,
+    ) -> Result<String, Box<dyn Error>> {
+        let samples = self.settings.samples;
+        info!("{}: sampling {} replies", what, samples);
+        let mut replies: Vec<String> = stream::iter(1..=samples)
+            .map(|i| async move {
+                let what = format!("{} sample {}", what, i);
+                self.request("sample", &what, prompt, usage).await
+            })
+            .buffered(self.settings.concurrency)
+            .try_collect()
+            .await
+            .map_err(|e| e as Box<dyn Error>)?;
+        if replies.iter().all(|reply| reply.trim() == replies[0].trim()) {
+            info!("All {} samples agree", samples);
+            if self.stream {
+                eprintln!("Final summary:\n{}", replies[0].trim());
+            }
+            return Ok(replies.swap_remove(0));
+        }
+
+        let versions = replies
+            .iter()
+            .enumerate()
+            .map(|(i, reply)| format!("Version {}:\n{}", i + 1, reply.trim()))
+            .collect::<Vec<_>>()
+            .join("\n\n");
+        let template = CONSENSUS_TEMPLATE.replace("{count}", &samples.to_string());
+        let prompt = localize(lang, render_prompt(&template, &versions));
+        match self.complete_final("consensus", "Consensus step", &prompt, usage).await {
+            // Keep a sample rather than letting the caller reduce the input again
+            Err(e) if error::is_overflow(e.as_ref()) => {
+                warn!("The {} samples do not fit the model's context window together; keeping the first one", samples);
+                Ok(replies.swap_remove(0))
+            }
+            reply => reply,
+        }
+    }
+
+    /// Ask the model to deduplicate, order and tighten the bullets of a final summary, keeping
+    /// the summary as it was when the reply has no bullet points
+    async fn polish(&self, summary: String, lang: Option<&Language>, usage: &UsageMeter) -> Result<String, Box<dyn Error>> {
+        let summary = bullets::normalize(&summary);
+        let prompt = localize(lang, render_prompt(POLISH_TEMPLATE, &summary));
+        let polished = self
+            .request("polish", "Polish step", &prompt, usage)
+            .await
+            .map_err(|e| e as Box<dyn Error>)?;
+        let count = bullets::count(&bullets::normalize(&polished));
+        if count == 0 {
+            warn!("The polished summary has no bullet points; keeping the summary as it was");
+            return Ok(summary);
+        }
+        info!("Polish step: {} bullets became {}", bullets::count(&summary), count);
+        Ok(polished)
+    }
+
+    /// Rewrite the final reply of the action-items, qa, outline, shownotes, lecture and topics
+    /// modes in their strict output format
+    fn structure(&self, final_summary: String) -> String {
+        match self.settings.mode {
+            Mode::ActionItems => {
+                let items = action_items::parse(&final_summary);
+                if items.is_empty() {
+                    warn!("The model's reply contained no action items");
+                }
+                action_items::render(&items)
+            }
+            Mode::Qa => {
+                let mut pairs = qa::parse(&final_summary);
+                let wanted = self.settings.questions;
+                if pairs.len() < wanted {
+                    warn!("The model wrote {} of the {} questions asked for", pairs.len(), wanted);
+                }
+                pairs.truncate(wanted);
+                qa::render(&pairs)
+            }
+            Mode::Outline => {
+                let outline = outline::parse(&final_summary);
+                if outline.is_empty() {
+                    warn!("The model's reply contained no outline");
+                }
+                outline::render(&outline)
+            }
+            Mode::Shownotes => {
+                let notes = shownotes::parse(&final_summary);
+                if notes.description.is_empty() {
+                    warn!("The model's reply had no episode description");
+                }
+                shownotes::render(&notes, &[])
+            }
+            Mode::Lecture => {
+                let notes = lecture::parse(&final_summary);
+                if notes.concepts.is_empty() && notes.main_points.is_empty() {
+                    warn!("The model's reply had no key concepts or main points");
+                }
+                lecture::render(&notes)
+            }
+            Mode::Topics => {
+                let topics = topics::parse(&final_summary);
+                if topics.is_empty() {
+                    warn!("The model's reply contained no topics with bullet points");
+                    return bullets::normalize(&final_summary);
+                }
+                topics::render(&topics)
+            }
+            Mode::Summary | Mode::Minutes => bullets::normalize(&final_summary),
+        }
+    }
+
+    /// `--length` and `--max-bullets` as an instruction for the final prompt
+    fn length_instruction(&self) -> Option<String> {
+        let limit = self.settings.bullet_limit()?;
+        let limit = format!("Use at most {} bullet points.", limit);
+        Some(match self.settings.length {
+            Some(length) => format!("{} {}", length.instruction(), limit),
+            None => limit,
+        })
+    }
+
+    /// Ask the model once to condense a final summary over the bullet limit, then truncate
+    /// whatever is still over it
+    async fn limit_bullets(
+        &self,
+        summary: String,
+        lang: Option<&Language>,
+        usage: &UsageMeter,
+    ) -> Result<String, Box<dyn Error>> {
+        let Some(max) = self.settings.bullet_limit() else {
+            return Ok(summary);
+        };
+        let count = bullets::count(&summary);
+        if count <= max {
+            return Ok(summary);
+        }
+
+        warn!("The summary has {} bullets, more than {}; asking the model to condense it", count, max);
+        let template = CONDENSE_TEMPLATE.replace("{max}", &max.to_string());
+        let prompt = localize(lang, render_prompt(&template, &summary));
+        let condensed = self
+            .request("condense", "Condense step", &prompt, usage)
+            .await
+            .map_err(|e| e as Box<dyn Error>)?;
+        let count = bullets::count(&condensed);
+        if count <= max {
+            return Ok(condensed);
+        }
+        warn!("The condensed summary still has {} bullets; keeping the first {}", count, max);
+        Ok(bullets::truncate(&condensed, max))
+    }
+
+    /// Send the final request, streaming the reply to stderr with `--stream`
+    async fn complete_final(
+        &self,
+        stage: &'static str,
+        what: &str,
+        prompt: &str,
+        usage: &UsageMeter,
+    ) -> Result<String, Box<dyn Error>> {
+        if !self.stream {
+            return self
+                .request(stage, what, prompt, usage)
+                .await
+                .map_err(|e| e as Box<dyn Error>);
+        }
+        eprintln!("Final summary:");
+        let print_token = &|token: &str| eprint!("{}", token);
+        let dumped = self.debug_dump.as_ref().map(|dump| dump.prompt(what, prompt));
+        let final_summary = self
+            .run_with_fallback(stage, what, prompt, usage, |backend| async move {
+                backend.complete_streaming(prompt, print_token).await
+            })
+            .await
+            .map(|(reply, _)| reply);
+        eprintln!();
+        self.dump_reply(dumped.as_deref(), final_summary.as_deref());
+        let final_summary = final_summary.map_err(|e| e as Box<dyn Error>)?;
+        // Streaming replies carry no usage fields
+        self.record(stage, TokenUsage::estimate(prompt, &final_summary), usage);
+        Ok(final_summary)
+    }
+}
+
+/// How an input is named in warnings
+fn display_name(input_path: Option<&Path>) -> String {
+    input_path.map_or_else(|| "The input".to_string(), |path| format!("{:?}", path))
+}
+
+/// Progress bar over `len` chunks with `done` of them already finished (resumed), drawn only
+/// when `visible`
+fn progress_bar(label: &str, len: usize, done: usize, visible: bool) -> Result<ProgressBar, Box<dyn Error>> {
+    let progress = ProgressBar::new(len as u64).with_style(
+        ProgressStyle::with_template(&format!(
+            "{} [{{bar:30}}] {{pos}}/{{len}} chunks, {{elapsed_precise}} elapsed, ETA {{eta_precise}}{{msg}}",
+            label
+        ))?
+        .progress_chars("=> "),
+    );
+    // Hidden before the first draw, which setting the position already does
+    if !visible {
+        progress.set_draw_target(ProgressDrawTarget::hidden());
+    }
+    progress.set_position(done as u64);
+    // Resumed chunks are already counted; keep them out of the ETA estimate
+    progress.reset_eta();
+    Ok(progress)
+}
@@ -0,0 +1,123 @@
+// Topic sections written by `--mode topics`.
+//
+// The transcript is chunked where the topic changes (see `--split semantic`), and every chunk
+// carries `[HH:MM:SS]` markers, so the model can head each topic with the time it starts. The
+// combine reply is read back as `## [HH:MM:SS] Topic` headings with bullets under them, headings
+// matched loosely (`**00:14:10 – Budget**` counts too), and neighbouring sections the model left
+// on the same topic are merged.
+
+use std::time::Duration;
+
+use crate::{bullets, parser};
+
+/// One topic of the transcript
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Topic {
+    /// When the topic starts, for timed transcripts
+    pub start: Option<Duration>,
+    pub title: String,
+    pub points: Vec<String>,
+}
+
+/// Extract the topics of a model reply; bullets before the first heading go under an
+/// `Overview` topic, and topics without bullets are dropped
+pub fn parse(reply: &str) -> Vec<Topic> {
+    let mut topics: Vec<Topic> = Vec::new();
+    for line in bullets::normalize(reply).lines() {
+        let line = line.trim();
+        if let Some(heading) = heading(line) {
+            let (start, title) = split_start(heading);
+            if !title.is_empty() {
+                topics.push(Topic {
+                    start,
+                    title,
+                    points: Vec::new(),
+                });
+            }
+            continue;
+        }
+        let Some(point) = line.strip_prefix("- ").map(str::trim) else {
+            continue;
+        };
+        if point.is_empty() || point.eq_ignore_ascii_case("none") {
+            continue;
+        }
+        if topics.is_empty() {
+            topics.push(Topic {
+                start: None,
+                title: "Overview".to_string(),
+                points: Vec::new(),
+            });
+        }
+        let points = &mut topics.last_mut().unwrap().points;
+        if !points.iter().any(|known| known.eq_ignore_ascii_case(point)) {
+            points.push(point.to_string());
+        }
+    }
+    topics.retain(|topic| !topic.points.is_empty());
+    merge_repeated(topics)
+}
+
+/// A `## [HH:MM:SS] Topic` heading with the bullets below, per topic
+pub fn render(topics: &[Topic]) -> String {
+    topics
+        .iter()
+        .map(|topic| {
+            let heading = match topic.start {
+                Some(start) => format!("## [{}] {}", parser::format_clock(start), topic.title),
+                None => format!("## {}", topic.title),
+            };
+            let points: Vec<String> = topic.points.iter().map(|point| format!("- {}", point)).collect();
+            format!("{}\n{}", heading, points.join("\n"))
+        })
+        .collect::<Vec<_>>()
+        .join("\n\n")
+}
+
+/// The text of a `## Heading` or `**Heading**` line
+fn heading(line: &str) -> Option<&str> {
+    if let Some(heading) = line.strip_prefix('#') {
+        return Some(heading.trim_start_matches('#').trim().trim_matches('*').trim());
+    }
+    let bold = line.strip_prefix("**")?.strip_suffix("**")?;
+    Some(bold.trim().trim_end_matches(':'))
+}
+
+/// The start time of a heading, `[00:14:10] Budget`, `00:14:10 – Budget` or `Budget (00:14:10)`,
+/// and its title
+fn split_start(heading: &str) -> (Option<Duration>, String) {
+    let clean = |title: &str| title.trim().trim_matches(['-', '–', '—', ':', '*', ' ']).to_string();
+    if let Some((time, title)) = heading.split_once(char::is_whitespace) {
+        // A time range counts from its start
+        let time = time.trim_matches(['[', ']', '(', ')']);
+        let time = time.split(['–', '-']).next().unwrap_or(time);
+        if let Some(start) = parser::parse_clock(time) {
+            return (Some(start), clean(title));
+        }
+    }
+    if let Some((title, time)) = heading.rsplit_once(['(', '[']) {
+        let time = time.trim_end_matches([')', ']']);
+        if let Some(start) = parser::parse_clock(time.split(['–', '-']).next().unwrap_or(time)) {
+            return (Some(start), clean(title));
+        }
+    }
+    (None, clean(heading))
+}
+
+/// Merge each topic into the one before it when they have the same title
+fn merge_repeated(topics: Vec<Topic>) -> Vec<Topic> {
+    let mut merged: Vec<Topic> = Vec::new();
+    for topic in topics {
+        match merged.last_mut() {
+            Some(last) if last.title.eq_ignore_ascii_case(&topic.title) => {
+                for point in topic.points {
+                    if !last.points.iter().any(|known| known.eq_ignore_ascii_case(&point)) {
+                        last.points.push(point);
+                    }
+                }
+            }
+            _ => merged.push(topic),
+        }
+    }
+    merged
+}
@@ -0,0 +1,162 @@
+// Transcription of recordings (`.mp3`, `.wav`, `.m4a`, ...) before they are summarized.
+//
+// The audio is uploaded to a speech-to-text server: whisper.cpp's `whisper-server`, at its
+// `/inference` endpoint, or any OpenAI-compatible `/audio/transcriptions` endpoint. Both are
+// asked for `verbose_json`, whose segments read like a Whisper `.json` transcript, so the cues
+// keep their timings for `--timestamps`, `--chapters` and `--from`/`--to`. Replies are cached by
+// the sha256 of the audio, so summarizing a recording again doesn't transcribe it again.
+
+use reqwest::multipart::{Form, Part};
+use sha2::{Digest, Sha256};
+use std::{
+    error::Error,
+    fs,
+    path::{Path, PathBuf},
+    time::Instant,
+};
+use tracing::{info, warn};
+
+use crate::{
+    cache,
+    config::{Settings, TranscriberKind},
+    error::SummarizerError,
+    parser::{self, Transcript},
+};
+
+/// Uploads recordings to the configured speech-to-text API
+#[derive(Debug, Clone)]
+pub struct Transcriber {
+    client: reqwest::Client,
+    kind: TranscriberKind,
+    api_base: String,
+    model: String,
+    api_key: Option<String>,
+    /// `<cache dir>/transcripts`, unless the cache is disabled
+    cache_dir: Option<PathBuf>,
+}
+
+impl Transcriber {
+    pub fn new(settings: &Settings) -> Self {
+        Self {
+            client: reqwest::Client::new(),
+            kind: settings.transcriber,
+            api_base: settings.transcribe_api_base.trim_end_matches('/').to_string(),
+            model: settings.transcribe_model.clone(),
+            api_key: settings.transcribe_api_key.clone(),
+            cache_dir: settings
+                .cache
+                .then(cache::default_cache_dir)
+                .flatten()
+                .map(|dir| dir.join("transcripts")),
+        }
+    }
+
+    /// The timed transcript of the recording at `path`
+    pub async fn transcribe(&self, path: &Path) -> Result<Transcript, Box<dyn Error>> {
+        let audio = fs::read(path)?;
+        let cache_path = self.cache_path(&audio);
+        if let Some(reply) = cache_path.as_ref().and_then(|cached| fs::read_to_string(cached).ok()) {
+            info!("Using the cached transcript of {:?}", path);
+            return reply_transcript(&reply).map_err(|e| format!("{:?}: {}", path, e).into());
+        }
+
+        info!("Transcribing {:?}...", path);
+        let start = Instant::now();
+        let reply = self.request(path, audio).await?;
+        let transcript = reply_transcript(&reply).map_err(|e| format!("{:?}: {}", path, e))?;
+        info!(
+            "Transcribed {:?} in {:?} ({} words)",
+            path,
+            start.elapsed(),
+            transcript.text.split_whitespace().count()
+        );
+        if let Some(cache_path) = cache_path {
+            let written = cache_path
+                .parent()
+                .map_or(Ok(()), fs::create_dir_all)
+                .and_then(|()| fs::write(&cache_path, &reply));
+            if let Err(e) = written {
+                warn!("Cannot write transcript cache {:?}: {}", cache_path, e);
+            }
+        }
+        Ok(transcript)
+    }
+
+    /// Upload the audio and return the `verbose_json` reply
+    async fn request(&self, path: &Path, audio: Vec<u8>) -> Result<String, Box<dyn Error>> {
+        let file_name = path.file_name().unwrap_or_default().to_string_lossy().into_owned();
+        let mime = match path.extension().and_then(|e| e.to_str()).unwrap_or("").to_lowercase().as_str() {
+            "mp3" => "audio/mpeg",
+            "wav" => "audio/wav",
+            "m4a" => "audio/mp4",
+            "flac" => "audio/flac",
+            "ogg" => "audio/ogg",
+            _ => "application/octet-stream",
+        };
+        let mut form = Form::new()
+            .part("file", Part::bytes(audio).file_name(file_name).mime_str(mime)?)
+            .text("response_format", "verbose_json");
+        let url = match self.kind {
+            TranscriberKind::WhisperCpp => format!("{}/inference", self.api_base),
+            TranscriberKind::Openai => {
+                form = form.text("model", self.model.clone());
+                format!("{}/audio/transcriptions", self.api_base)
+            }
+        };
+
+        let mut request = self.client.post(url).multipart(form);
+        if let Some(api_key) = &self.api_key {
+            request = request.bearer_auth(api_key);
+        }
+        let response = request.send().await.map_err(|e| -> Box<dyn Error> {
+            if e.is_connect() || e.is_timeout() {
+                Box::new(SummarizerError::TranscriberUnreachable {
+                    transcriber: self.kind,
+                    api_base: self.api_base.clone(),
+                    message: e.to_string(),
+                })
+            } else {
+                Box::new(e)
+            }
+        })?;
+        let status = response.status();
+        let body = response.text().await?;
+        if !status.is_success() {
+            let message = serde_json::from_str::<serde_json::Value>(&body)
+                .ok()
+                .and_then(|v| v["error"]["message"].as_str().or(v["error"].as_str()).map(str::to_string))
+                .unwrap_or(body);
+            return Err(format!("The transcription server returned {}: {}", status, message.trim()).into());
+        }
+        Ok(body)
+    }
+
+    /// `<cache dir>/transcripts/<sha256>.json`, keyed by the API, the model and the audio
+    fn cache_path(&self, audio: &[u8]) -> Option<PathBuf> {
+        let mut hasher = Sha256::new();
+        hasher.update(format!("{:?}", self.kind).as_bytes());
+        hasher.update([0]);
+        hasher.update(self.model.as_bytes());
+        hasher.update([0]);
+        hasher.update(audio);
+        Some(self.cache_dir.as_ref()?.join(format!("{:x}.json", hasher.finalize())))
+    }
+}
+
+/// The cues of a `verbose_json` reply, or its text alone when it has no segments
+fn reply_transcript(reply: &str) -> Result<Transcript, Box<dyn Error>> {
+    let transcript = if parser::parse_whisper(reply).is_some() {
+        parser::parse_transcript("json", reply.to_string())
+    } else {
+        let json: serde_json::Value =
+            serde_json::from_str(reply).map_err(|e| format!("Unexpected reply from the transcription server: {}", e))?;
+        let text = json["text"]
+            .as_str()
+            .ok_or("Unexpected reply from the transcription server: no segments or text")?;
+        parser::parse_transcript("txt", text.to_string())
+    };
+    if transcript.text.trim().is_empty() {
+        return Err("The transcription is empty; does the recording contain speech?".into());
+    }
+    Ok(transcript)
+}
@@ -0,0 +1,139 @@
+// Token accounting for the usage report printed after every summary.
+//
+// Backends report the prompt and completion tokens of a request when their API returns them;
+// otherwise (streamed replies, APIs without usage fields) both sides are estimated with the
+// chunker's tokenizer and the stage is marked as estimated. Cache hits cost nothing and are not
+// counted.
+
+use serde::Serialize;
+use std::sync::Mutex;
+use tracing::info;
+
+use crate::chunker;
+
+/// Prompt and completion tokens of one or more requests
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+pub struct TokenUsage {
+    pub prompt_tokens: u64,
+    pub completion_tokens: u64,
+    /// Whether any of the counts were estimated locally rather than reported by the API
+    pub estimated: bool,
+}
+
+impl TokenUsage {
+    /// Local estimate for a request whose API reported no usage
+    pub fn estimate(prompt: &str, reply: &str) -> Self {
+        Self {
+            prompt_tokens: chunker::count_tokens(prompt) as u64,
+            completion_tokens: chunker::count_tokens(reply) as u64,
+            estimated: true,
+        }
+    }
+
+    pub fn total(&self) -> u64 {
+        self.prompt_tokens + self.completion_tokens
+    }
+
+    fn add(&mut self, other: TokenUsage) {
+        self.prompt_tokens += other.prompt_tokens;
+        self.completion_tokens += other.completion_tokens;
+        self.estimated |= other.estimated;
+    }
+}
+
+/// Token usage per stage (`map`, `reduce`, `combine`, `refine`, `sample`, `consensus`,
+/// `polish`, `condense`, `chapters`, `keywords`, `entities`, `headline`, `translate`,
+/// `compare`, `embed`, `ask`), in the order the stages first ran
+#[derive(Debug, Clone, Default)]
+pub struct UsageReport {
+    pub stages: Vec<(&'static str, TokenUsage)>,
+    /// The requests the fallback backend answered, e.g. `Chunk 3`, in the order they finished
+    pub fallbacks: Vec<String>,
+}
+
+impl UsageReport {
+    pub fn total(&self) -> TokenUsage {
+        let mut total = TokenUsage::default();
+        for (_, usage) in &self.stages {
+            total.add(*usage);
+        }
+        total
+    }
+
+    /// Add the counts and fallbacks of `other`, e.g. to report several summaries as one
+    pub fn merge(&mut self, other: &UsageReport) {
+        for (stage, usage) in &other.stages {
+            match self.stages.iter_mut().find(|(name, _)| name == stage) {
+                Some((_, total)) => total.add(*usage),
+                None => self.stages.push((stage, *usage)),
+            }
+        }
+        self.fallbacks.extend(other.fallbacks.iter().cloned());
+    }
+
+    /// Print the per-stage and total token counts, and the cost at `price_per_1k` tokens
+    pub fn print(&self, price_per_1k: Option<f64>) {
+        if self.stages.is_empty() {
+            return;
+        }
+        info!("Token usage:");
+        let total = self.total();
+        for (stage, usage) in self.stages.iter().chain([&("total", total)]) {
+            info!(
+                "  {:<9} {:>9} prompt + {:>7} completion = {:>9} tokens{}",
+                stage,
+                usage.prompt_tokens,
+                usage.completion_tokens,
+                usage.total(),
+                if usage.estimated { " (estimated)" } else { "" }
+            );
+        }
+        if !self.fallbacks.is_empty() {
+            info!("Answered by the fallback backend: {}", self.fallbacks.join(", "));
+        }
+        if let Some(price) = price_per_1k {
+            info!(
+                "Estimated cost: ${:.4} at ${} per 1k tokens",
+                total.total() as f64 / 1000.0 * price,
+                price
+            );
+        }
+    }
+}
+
+/// Usage collected across the concurrent requests of one summary
+#[derive(Debug, Default)]
+pub struct UsageMeter {
+    report: Mutex<UsageReport>,
+}
+
+impl UsageMeter {
+    pub fn record(&self, stage: &'static str, usage: TokenUsage) {
+        let mut report = self.report.lock().unwrap();
+        match report.stages.iter_mut().find(|(name, _)| *name == stage) {
+            Some((_, total)) => total.add(usage),
+            None => report.stages.push((stage, usage)),
+        }
+    }
+
+    /// Note that the fallback backend answered the request `what`
+    pub fn record_fallback(&self, what: &str) {
+        self.report.lock().unwrap().fallbacks.push(what.to_string());
+    }
+
+    /// Whether the fallback backend answered the request `what`, or a part of it (`Chunk 3
+    /// part 1` of `Chunk 3`, split for the context window)
+    pub fn fell_back(&self, what: &str) -> bool {
+        let parts = format!("{} ", what);
+        self.report
+            .lock()
+            .unwrap()
+            .fallbacks
+            .iter()
+            .any(|request| request == what || request.starts_with(&parts))
+    }
+
+    pub fn into_report(self) -> UsageReport {
+        self.report.into_inner().unwrap()
+    }
+}
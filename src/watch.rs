@@ -0,0 +1,147 @@
+// Watch-folder support for the `watch` subcommand: reporting files dropped into a directory
+// once they have finished being written.
+//
+// notify reports raw file-system events from its own thread; they are forwarded over a channel
+// and a file is only handed out after its size has stopped changing for `SETTLE`, so recordings
+// and transcripts that are still being written aren't summarized half-way.
+
+use notify::{event::ModifyKind, Event, EventKind, RecommendedWatcher, RecursiveMode, Watcher};
+use std::{
+    collections::HashMap,
+    error::Error,
+    fs,
+    path::{Path, PathBuf},
+    time::{Duration, Instant},
+};
+use tokio::sync::mpsc;
+
+use crate::parser;
+
+/// Extensions of the files picked up besides recordings; like batch scanning, Whisper `.json` is
+/// left out so `_summary.json` files aren't summarized again
+const WATCH_EXTENSIONS: &[&str] = &["srt", "vtt", "ass", "ssa", "txt", "pdf", "epub", "md"];
+
+/// Name endings of the files this tool writes next to its inputs; translated transcripts are
+/// `<stem>_transcript_<language>`
+const OUTPUT_SUFFIXES: &[&str] = &["_summary", "_chapters", "_partial", "_transcript"];
+
+/// How long a file's size must stay the same before it counts as written
+const SETTLE: Duration = Duration::from_secs(2);
+
+/// How often files waiting to settle are checked
+const POLL: Duration = Duration::from_millis(500);
+
+/// A directory being watched for new and changed input files
+pub struct FolderWatcher {
+    // Kept alive for as long as events are wanted
+    _watcher: RecommendedWatcher,
+    events: mpsc::UnboundedReceiver<notify::Result<Event>>,
+    /// Directories whose files are never reported, such as the output directory
+    ignored: Vec<PathBuf>,
+    /// Files seen changing, with their last size and when it last changed
+    pending: HashMap<PathBuf, (u64, Instant)>,
+}
+
+impl FolderWatcher {
+    /// Start watching `dir`, and its subdirectories when `recursive` is set
+    pub fn new(dir: &Path, recursive: bool) -> Result<Self, Box<dyn Error>> {
+        let (sender, events) = mpsc::unbounded_channel();
+        let mut watcher = notify::recommended_watcher(move |event| {
+            // The receiver is only gone once the watcher is being dropped
+            let _ = sender.send(event);
+        })?;
+        let mode = if recursive {
+            RecursiveMode::Recursive
+        } else {
+            RecursiveMode::NonRecursive
+        };
+        watcher
+            .watch(dir, mode)
+            .map_err(|e| format!("Cannot watch {:?}: {}", dir, e))?;
+        Ok(Self {
+            _watcher: watcher,
+            events,
+            ignored: Vec::new(),
+            pending: HashMap::new(),
+        })
+    }
+
+    /// Never report files inside `dir`
+    pub fn ignore(mut self, dir: &Path) -> Self {
+        self.ignored.push(fs::canonicalize(dir).unwrap_or_else(|_| dir.to_path_buf()));
+        self
+    }
+
+    /// Wait for the next input file that was created or changed and has finished being written
+    pub async fn next(&mut self) -> Result<PathBuf, Box<dyn Error>> {
+        loop {
+            if let Some(path) = self.settled() {
+                return Ok(path);
+            }
+            match tokio::time::timeout(POLL, self.events.recv()).await {
+                Ok(Some(event)) => {
+                    let event = event.map_err(|e| format!("Watching failed: {}", e))?;
+                    self.record(event);
+                }
+                Ok(None) => return Err("The file watcher stopped".into()),
+                Err(_) => {}
+            }
+        }
+    }
+
+    fn record(&mut self, event: Event) {
+        let relevant = match event.kind {
+            EventKind::Create(_) => true,
+            EventKind::Modify(ModifyKind::Metadata(_)) => false,
+            EventKind::Modify(_) => true,
+            _ => false,
+        };
+        if !relevant {
+            return;
+        }
+        for path in event.paths {
+            if self.is_input(&path) {
+                let size = fs::metadata(&path).map(|m| m.len()).unwrap_or(0);
+                self.pending.insert(path, (size, Instant::now()));
+            }
+        }
+    }
+
+    /// Take a pending file whose size hasn't changed for `SETTLE`, forgetting deleted ones
+    fn settled(&mut self) -> Option<PathBuf> {
+        let mut ready = None;
+        self.pending.retain(|path, (size, changed)| {
+            let Ok(metadata) = fs::metadata(path) else {
+                return false;
+            };
+            if metadata.len() != *size {
+                *size = metadata.len();
+                *changed = Instant::now();
+            } else if ready.is_none() && metadata.len() > 0 && changed.elapsed() >= SETTLE {
+                ready = Some(path.clone());
+                return false;
+            }
+            true
+        });
+        ready
+    }
+
+    fn is_input(&self, path: &Path) -> bool {
+        let extension = path.extension().and_then(|e| e.to_str()).map(str::to_lowercase);
+        if !extension.is_some_and(|e| WATCH_EXTENSIONS.contains(&e.as_str()) || parser::is_audio(&e)) {
+            return false;
+        }
+        let stem = path.file_stem().and_then(|s| s.to_str()).unwrap_or_default();
+        // Hidden files are editor swap files and partial downloads
+        if stem.starts_with('.') || is_output(stem) {
+            return false;
+        }
+        let dir = path.parent().and_then(|p| fs::canonicalize(p).ok());
+        !dir.is_some_and(|dir| self.ignored.iter().any(|ignored| dir.starts_with(ignored)))
+    }
+}
+
+/// Whether a file stem looks like one of this tool's outputs
+fn is_output(stem: &str) -> bool {
+    OUTPUT_SUFFIXES.iter().any(|suffix| stem.ends_with(suffix)) || stem.contains("_transcript_")
+}
@@ -0,0 +1,156 @@
+// Documents fetched by URL: web articles, and subtitle, text or PDF files linked directly.
+//
+// Video sites are left to yt-dlp (see `youtube`) without fetching anything. Any other URL is
+// downloaded once: an HTML page is reduced to its article with the readability extractor, unless
+// it embeds a video (`og:video`), in which case yt-dlp is asked for its subtitles after all;
+// anything else is read like a local file of the type its Content-Type or path names.
+
+use std::{error::Error, time::Duration};
+use tracing::info;
+
+use crate::{
+    parser::{self, Transcript},
+    youtube,
+};
+
+/// Sent with every request; some sites refuse clients without a user agent
+const USER_AGENT: &str = concat!("srt-bullet-summarizer/", env!("CARGO_PKG_VERSION"));
+
+/// How long a page may take to download
+const TIMEOUT: Duration = Duration::from_secs(60);
+
+/// Hosts whose pages are videos, passed straight to yt-dlp
+const VIDEO_HOSTS: &[&str] = &[
+    "youtube.com",
+    "youtu.be",
+    "vimeo.com",
+    "dailymotion.com",
+    "twitch.tv",
+    "tiktok.com",
+    "ted.com",
+];
+
+/// A document fetched by URL
+#[derive(Debug, Clone)]
+pub struct Document {
+    /// The article title, or else the last segment of the URL's path
+    pub title: String,
+    /// Extension of the format it was read as, naming its outputs like a local file would be
+    pub extension: &'static str,
+    pub transcript: Transcript,
+}
+
+/// Whether `url` is on a video site, whose subtitles yt-dlp fetches
+pub fn is_video_url(url: &str) -> bool {
+    let host = host(url).to_ascii_lowercase();
+    VIDEO_HOSTS
+        .iter()
+        .any(|video| host == *video || host.ends_with(&format!(".{}", video)))
+}
+
+/// Fetch and read `url`; `None` means it is a video page, to be handed to yt-dlp
+pub async fn fetch(url: &str) -> Result<Option<Document>, Box<dyn Error>> {
+    if is_video_url(url) {
+        return Ok(None);
+    }
+    let client = reqwest::Client::builder().user_agent(USER_AGENT).timeout(TIMEOUT).build()?;
+    let response = client
+        .get(url)
+        .send()
+        .await
+        .map_err(|e| format!("Cannot fetch {}: {}", url, e))?;
+    let status = response.status();
+    if !status.is_success() {
+        return Err(format!("Cannot fetch {}: HTTP {}", url, status).into());
+    }
+    // Redirects may have moved the page; relative links and the name come from where it ended up
+    let final_url = response.url().to_string();
+    let mime = response
+        .headers()
+        .get(reqwest::header::CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.split(';').next())
+        .unwrap_or("")
+        .trim()
+        .to_ascii_lowercase();
+    let bytes = response.bytes().await.map_err(|e| format!("Cannot fetch {}: {}", url, e))?;
+    let name = youtube::file_stem(path_stem(&final_url), "page");
+
+    if mime == "application/pdf" || bytes.starts_with(b"%PDF-") {
+        info!("Fetched a PDF from {}", final_url);
+        return Ok(Some(Document {
+            title: name,
+            extension: "pdf",
+            transcript: parser::pdf_transcript(&bytes)?,
+        }));
+    }
+    let (text, encoding) = parser::decode(&bytes);
+    let extension = path_extension(&final_url);
+    if mime == "text/html" || mime == "application/xhtml+xml" || extension.is_some_and(parser::is_html) {
+        if parser::has_video(&text) {
+            return Ok(None);
+        }
+        let article = parser::extract_article(&text, Some(&final_url)).map_err(|e| format!("{}: {}", url, e))?;
+        let title = article.title.unwrap_or(name);
+        info!("Fetched {:?} ({} words)", title, article.text.split_whitespace().count());
+        return Ok(Some(Document {
+            title,
+            extension: "html",
+            transcript: Transcript {
+                encoding,
+                ..parser::parse_transcript("txt", article.text)
+            },
+        }));
+    }
+
+    let extension = match (mime.as_str(), extension) {
+        ("text/vtt", _) => "vtt",
+        ("application/x-subrip" | "text/srt", _) => "srt",
+        (_, Some(extension)) => match extension.to_ascii_lowercase().as_str() {
+            "srt" => "srt",
+            "vtt" => "vtt",
+            "ass" => "ass",
+            "ssa" => "ssa",
+            "json" => "json",
+            _ => parser::sniff_extension(&text),
+        },
+        _ => parser::sniff_extension(&text),
+    };
+    info!("Fetched {}", final_url);
+    Ok(Some(Document {
+        title: name,
+        extension,
+        transcript: Transcript {
+            encoding,
+            ..parser::parse_transcript(extension, text)
+        },
+    }))
+}
+
+/// `example.com` of `https://user@example.com:8080/a/b?q`
+fn host(url: &str) -> &str {
+    let rest = url.split_once("://").map_or(url, |(_, rest)| rest);
+    let authority = rest.split(['/', '?', '#']).next().unwrap_or("");
+    let authority = authority.rsplit_once('@').map_or(authority, |(_, host)| host);
+    authority.split(':').next().unwrap_or("")
+}
+
+/// Last segment of the URL's path, without query, fragment or extension
+fn path_stem(url: &str) -> &str {
+    let segment = last_segment(url);
+    segment.rsplit_once('.').map_or(segment, |(stem, _)| stem)
+}
+
+/// Extension of the last segment of the URL's path
+fn path_extension(url: &str) -> Option<&str> {
+    last_segment(url).rsplit_once('.').map(|(_, extension)| extension)
+}
+
+fn last_segment(url: &str) -> &str {
+    let rest = url.split_once("://").map_or(url, |(_, rest)| rest);
+    let path = rest.split(['?', '#']).next().unwrap_or("");
+    match path.split_once('/') {
+        Some((_, path)) => path.trim_end_matches('/').rsplit('/').next().unwrap_or(""),
+        None => "",
+    }
+}
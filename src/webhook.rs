@@ -0,0 +1,117 @@
+// Delivery of finished summaries to a webhook, for `--webhook`.
+//
+// Once a summary is written, a JSON document with the event, the input, where the summary was
+// saved, the summary as plain text and the full `--format json` report (model, mode, timings,
+// token usage, ...) is POSTed to the URL, so automations such as n8n or Zapier can pick it up.
+// With `webhook_secret` set, the body is signed with HMAC-SHA256 and the hex digest sent as
+// `X-Summarizer-Signature-256: sha256=<hex>`, which the receiver recomputes from the raw body to
+// check the request came from this tool. A delivery that fails is retried twice, then only
+// warned about, since the summary has been saved either way.
+
+use serde_json::{json, Value};
+use hmac::{Hmac, Mac};
+use sha2::Sha256;
+use std::{error::Error, path::Path, time::Duration};
+use tracing::{info, warn};
+
+use crate::{config::OutputFormat, output, summarizer::Summary};
+
+/// Header carrying the signature of the body
+pub const SIGNATURE_HEADER: &str = "X-Summarizer-Signature-256";
+
+const USER_AGENT: &str = concat!("srt-bullet-summarizer/", env!("CARGO_PKG_VERSION"));
+
+/// How long the receiver may take to answer one delivery
+const TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Deliveries tried before giving up, the first one included
+const ATTEMPTS: u32 = 3;
+
+/// The body POSTed for `summary`, saved to `output_path` (`None` when it went to stdout)
+pub fn payload(summary: &Summary, output_path: Option<&Path>) -> String {
+    let report: Value = serde_json::from_str(&output::render(OutputFormat::Json, summary)).unwrap_or(Value::Null);
+    json!({
+        "event": "summary.completed",
+        "input": summary.input.as_ref().map(|input| input.display().to_string()),
+        "title": summary.title,
+        "output": output_path.map(|path| path.display().to_string()),
+        "text": output::render(OutputFormat::Txt, summary),
+        "summary": report,
+    })
+    .to_string()
+}
+
+/// `sha256=<hex>` of the HMAC-SHA256 of `body` under `secret`
+pub fn signature(secret: &str, body: &str) -> String {
+    let mut mac = Hmac::<Sha256>::new_from_slice(secret.as_bytes()).expect("HMAC takes keys of any length");
+    mac.update(body.as_bytes());
+    let hex: String = mac.finalize().into_bytes().iter().map(|byte| format!("{:02x}", byte)).collect();
+    format!("sha256={}", hex)
+}
+
+/// POST `body` to `url`, signed when there is a `secret`
+pub async fn deliver(url: &str, secret: Option<&str>, body: String) -> Result<(), Box<dyn Error>> {
+    let signature = secret.map(|secret| signature(secret, &body));
+    let headers: Vec<(&str, &str)> = signature.iter().map(|signature| (SIGNATURE_HEADER, signature.as_str())).collect();
+    post_json(url, &headers, body).await?;
+    info!("Summary delivered to the webhook");
+    Ok(())
+}
+
+/// POST the JSON `body` to `url` with the extra `headers`, retrying network errors, 429s and
+/// server errors with a growing delay
+pub(crate) async fn post_json(url: &str, headers: &[(&str, &str)], body: String) -> Result<(), Box<dyn Error>> {
+    let client = reqwest::Client::builder().user_agent(USER_AGENT).timeout(TIMEOUT).build()?;
+    let mut attempt = 1;
+    loop {
+        let mut request = client
+            .post(url)
+            .header(reqwest::header::CONTENT_TYPE, "application/json")
+            .body(body.clone());
+        for (name, value) in headers {
+            request = request.header(*name, *value);
+        }
+        let failure = match request.send().await {
+            Ok(response) if response.status().is_success() => return Ok(()),
+            Ok(response) => {
+                let status = response.status();
+                let failure = format!("the webhook answered {}", status);
+                if !(status.is_server_error() || status == reqwest::StatusCode::TOO_MANY_REQUESTS) {
+                    return Err(failure.into());
+                }
+                failure
+            }
+            Err(e) if e.is_builder() => return Err(format!("{} is not a valid webhook URL", url).into()),
+            Err(e) => format!("cannot reach the webhook: {}", e),
+        };
+        if attempt == ATTEMPTS {
+            return Err(failure.into());
+        }
+        let delay = Duration::from_secs(1 << (attempt - 1));
+        warn!("Webhook delivery {} of {} failed: {}; retrying in {:?}", attempt, ATTEMPTS, failure, delay);
+        tokio::time::sleep(delay).await;
+        attempt += 1;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn signature_matches_rfc_4231() {
+        // Test case 2 of RFC 4231
+        assert_eq!(
+            signature("Jefe", "what do ya want for nothing?"),
+            "sha256=5bdcc146bf60754e6a042426089575c75a003f089d2739839dec58b964ec3843"
+        );
+    }
+
+    #[test]
+    fn signature_is_lowercase_hex_after_the_scheme() {
+        let signature = signature("secret", "{}");
+        let hex = signature.strip_prefix("sha256=").unwrap();
+        assert_eq!(hex.len(), 64);
+        assert!(hex.chars().all(|c| c.is_ascii_digit() || ('a'..='f').contains(&c)), "{}", signature);
+    }
+}
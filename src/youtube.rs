@@ -0,0 +1,246 @@
+// Subtitles of online videos (YouTube and anything else yt-dlp supports).
+//
+// `yt-dlp -J` lists a video's subtitle tracks without downloading anything; a manual track in
+// the requested language is preferred over an auto-generated one, and its WebVTT version is
+// downloaded and parsed like a local `.vtt` file. The `fetch` subcommand also lists the videos
+// of playlists and channels (`--flat-playlist`) and saves every video's subtitles, or its audio
+// when it has none, for the batch loop to summarize.
+
+use serde::Deserialize;
+use std::{
+    collections::HashMap,
+    error::Error,
+    fs,
+    path::{Path, PathBuf},
+};
+use tokio::process::Command;
+use tracing::info;
+
+use crate::parser::{self, Transcript};
+
+/// Language tried when neither `--sub-lang` nor the video metadata names one
+const DEFAULT_SUB_LANG: &str = "en";
+
+/// Longest file name stem derived from a video title
+const MAX_STEM_CHARS: usize = 100;
+
+/// Subtitles of one video
+#[derive(Debug, Clone)]
+pub struct Video {
+    pub id: String,
+    pub title: String,
+    /// Language key of the chosen track, e.g. `en` or `en-US`
+    pub sub_lang: String,
+    /// Whether the track was generated by automatic speech recognition
+    pub automatic: bool,
+    pub transcript: Transcript,
+}
+
+/// A video of a playlist, or the video a URL points to
+#[derive(Debug, Clone)]
+pub struct Entry {
+    pub url: String,
+    pub title: String,
+}
+
+#[derive(Deserialize)]
+struct Metadata {
+    id: String,
+    title: String,
+    #[serde(default)]
+    language: Option<String>,
+    #[serde(default)]
+    subtitles: HashMap<String, Vec<Track>>,
+    #[serde(default)]
+    automatic_captions: HashMap<String, Vec<Track>>,
+}
+
+#[derive(Deserialize)]
+struct Track {
+    ext: String,
+    url: String,
+}
+
+/// Whether an input argument is a URL rather than a path
+pub fn is_url(input: &Path) -> bool {
+    input
+        .to_str()
+        .is_some_and(|s| s.starts_with("https://") || s.starts_with("http://"))
+}
+
+/// Fetch the subtitles of `url` in `sub_lang` (default: the video's language, else English)
+pub async fn fetch_subtitles(url: &str, sub_lang: Option<&str>) -> Result<Video, Box<dyn Error>> {
+    let metadata = metadata(url).await?;
+    let (key, track, automatic) = choose_track(&metadata, sub_lang)?;
+    let vtt = download_track(&metadata, key, track, automatic).await?;
+    Ok(Video {
+        id: metadata.id.clone(),
+        title: metadata.title.clone(),
+        sub_lang: key.to_string(),
+        automatic,
+        transcript: parser::parse_transcript("vtt", vtt),
+    })
+}
+
+/// The videos of a playlist or channel, at most `limit` of them, or the video `url` points to
+pub async fn list_videos(url: &str, limit: Option<usize>) -> Result<Vec<Entry>, Box<dyn Error>> {
+    let limit = limit.map(|limit| limit.to_string());
+    let mut args = vec!["-J", "--flat-playlist", "--no-warnings"];
+    if let Some(limit) = &limit {
+        args.extend(["--playlist-end", limit]);
+    }
+    args.push(url);
+    let json: serde_json::Value = serde_json::from_slice(&yt_dlp(&args, url).await?)
+        .map_err(|e| format!("Unexpected yt-dlp output for {}: {}", url, e))?;
+
+    let entry = |value: &serde_json::Value| {
+        let url = value["url"].as_str().or(value["webpage_url"].as_str())?;
+        let title = value["title"].as_str().or(value["id"].as_str()).unwrap_or(url);
+        Some(Entry {
+            url: url.to_string(),
+            title: title.to_string(),
+        })
+    };
+    match json["entries"].as_array() {
+        Some(entries) => {
+            let videos: Vec<Entry> = entries.iter().filter_map(entry).collect();
+            info!(
+                "{:?} lists {} videos",
+                json["title"].as_str().unwrap_or(url),
+                videos.len()
+            );
+            Ok(videos)
+        }
+        None => Ok(vec![Entry {
+            url: json["webpage_url"].as_str().unwrap_or(url).to_string(),
+            title: json["title"].as_str().unwrap_or(url).to_string(),
+        }]),
+    }
+}
+
+/// Save the subtitles of `url` to `<dir>/<title>.vtt`, or with `audio` (or when it has no
+/// subtitles in `sub_lang`) its audio to `<dir>/<title>.mp3`, and return the file's path
+pub async fn download_video(url: &str, sub_lang: Option<&str>, dir: &Path, audio: bool) -> Result<PathBuf, Box<dyn Error>> {
+    let metadata = metadata(url).await?;
+    let stem = file_stem(&metadata.title, &metadata.id);
+    if !audio {
+        match choose_track(&metadata, sub_lang) {
+            Ok((key, track, automatic)) => {
+                let vtt = download_track(&metadata, key, track, automatic).await?;
+                let path = dir.join(format!("{}.vtt", stem));
+                fs::write(&path, vtt)?;
+                return Ok(path);
+            }
+            Err(reason) => info!("{}; downloading the audio to transcribe instead", reason),
+        }
+    }
+
+    let template = dir.join(format!("{}.%(ext)s", stem));
+    let template = template.to_string_lossy();
+    let args = [
+        "-x",
+        "--audio-format",
+        "mp3",
+        "--no-playlist",
+        "--no-warnings",
+        "--no-progress",
+        "-o",
+        &template,
+        "--print",
+        "after_move:filepath",
+        url,
+    ];
+    let output = yt_dlp(&args, url).await?;
+    let path = String::from_utf8_lossy(&output).lines().last().unwrap_or("").trim().to_string();
+    if path.is_empty() {
+        return Err(format!("yt-dlp did not save the audio of {}", url).into());
+    }
+    info!("Downloaded the audio of {:?}", metadata.title);
+    Ok(PathBuf::from(path))
+}
+
+/// Run yt-dlp and return its stdout
+async fn yt_dlp(args: &[&str], url: &str) -> Result<Vec<u8>, Box<dyn Error>> {
+    let output = Command::new("yt-dlp")
+        .args(args)
+        .output()
+        .await
+        .map_err(|e| format!("Summarizing video URLs needs yt-dlp on the PATH (https://github.com/yt-dlp/yt-dlp): {}", e))?;
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(format!("yt-dlp failed for {}: {}", url, stderr.trim()).into());
+    }
+    Ok(output.stdout)
+}
+
+/// Title and subtitle tracks of a single video
+async fn metadata(url: &str) -> Result<Metadata, Box<dyn Error>> {
+    let stdout = yt_dlp(&["-J", "--no-playlist", "--no-warnings", url], url).await?;
+    serde_json::from_slice(&stdout)
+        .map_err(|e| format!("Unexpected yt-dlp output for {}: {}", url, e).into())
+}
+
+/// The WebVTT track to summarize, its language key and whether it is auto-generated; the error
+/// says which languages there are instead
+fn choose_track<'a>(metadata: &'a Metadata, sub_lang: Option<&str>) -> Result<(&'a str, &'a Track, bool), String> {
+    let wanted = sub_lang
+        .or(metadata.language.as_deref())
+        .unwrap_or(DEFAULT_SUB_LANG);
+    let (key, tracks, automatic) = find_track(&metadata.subtitles, wanted)
+        .map(|(key, tracks)| (key, tracks, false))
+        .or_else(|| find_track(&metadata.automatic_captions, wanted).map(|(key, tracks)| (key, tracks, true)))
+        .ok_or_else(|| {
+            let mut available: Vec<&str> = metadata.subtitles.keys().map(String::as_str).collect();
+            available.sort();
+            format!(
+                "{:?} has no {} subtitles (manual: {}); pick another with --sub-lang",
+                metadata.title,
+                wanted,
+                if available.is_empty() { "none".to_string() } else { available.join(", ") }
+            )
+        })?;
+    let track = tracks
+        .iter()
+        .find(|track| track.ext == "vtt")
+        .ok_or_else(|| format!("The {} subtitles of {:?} have no WebVTT version", key, metadata.title))?;
+    Ok((key, track, automatic))
+}
+
+async fn download_track(metadata: &Metadata, key: &str, track: &Track, automatic: bool) -> Result<String, Box<dyn Error>> {
+    let vtt = reqwest::get(&track.url).await?.error_for_status()?.text().await?;
+    info!(
+        "Fetched {} {} subtitles of {:?}",
+        if automatic { "auto-generated" } else { "manual" },
+        key,
+        metadata.title
+    );
+    Ok(vtt)
+}
+
+/// The track for `lang` exactly, or else a regional variant such as `en-US` (or `en-orig`)
+fn find_track<'a>(tracks: &'a HashMap<String, Vec<Track>>, lang: &str) -> Option<(&'a str, &'a [Track])> {
+    let prefix = format!("{}-", lang);
+    tracks
+        .get_key_value(lang)
+        .or_else(|| {
+            let mut variants: Vec<_> = tracks.iter().filter(|(key, _)| key.starts_with(&prefix)).collect();
+            variants.sort_by_key(|(key, _)| key.as_str());
+            variants.into_iter().next()
+        })
+        .map(|(key, tracks)| (key.as_str(), tracks.as_slice()))
+}
+
+/// A file name stem for `title` that is valid on every platform, or `fallback` for an empty one
+pub fn file_stem(title: &str, fallback: &str) -> String {
+    let stem: String = title
+        .chars()
+        .map(|c| if c.is_control() || r#"/\:*?"<>|"#.contains(c) { '_' } else { c })
+        .take(MAX_STEM_CHARS)
+        .collect();
+    let stem = stem.trim().trim_matches('.').trim();
+    if stem.is_empty() {
+        fallback.to_string()
+    } else {
+        stem.to_string()
+    }
+}